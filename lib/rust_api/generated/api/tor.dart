@@ -12,18 +12,65 @@ Future<String> torHelloFrb() => RustLib.instance.api.crateApiTorTorHelloFrb();
 
 /// Start Tor service
 ///
-/// If use_system_proxy is true, Tor will use the proxy set via tor_set_proxy_frb().
-/// If false or no proxy is set, direct connections will be used.
+/// A socks_port of 0 listens on a free port chosen by the OS. Returns the
+/// port actually listened on. If a client from tor_start_client_only_frb()
+/// is running, the SOCKS listener is added to it and the options that shape
+/// the client are ignored. See StartOptions for the rest.
+///
+/// Fails with a "feature not enabled" error in builds without the `socks` feature.
 Future<int> torStartFrb(
         {required int socksPort,
         required String stateDir,
         required String cacheDir,
-        required bool useSystemProxy}) =>
+        required StartOptions options}) =>
     RustLib.instance.api.crateApiTorTorStartFrb(
         socksPort: socksPort,
         stateDir: stateDir,
         cacheDir: cacheDir,
-        useSystemProxy: useSystemProxy);
+        options: options);
+
+/// tor_start_frb() that reports bootstrap progress
+///
+/// Takes the same parameters and sends progress events to the returned
+/// stream while Tor starts, each with the fraction done and arti's summary
+/// of the current phase; the stream ends once the start has finished. A
+/// successful start ends with fraction 1.0, and then the SOCKS port is in
+/// tor_status_frb(). If the client is already running only that last event
+/// is sent.
+Stream<BootstrapStatus> torStartWithProgressFrb(
+        {required int socksPort,
+        required String stateDir,
+        required String cacheDir,
+        required StartOptions options}) =>
+    RustLib.instance.api.crateApiTorTorStartWithProgressFrb(
+        socksPort: socksPort,
+        stateDir: stateDir,
+        cacheDir: cacheDir,
+        options: options);
+
+/// Start Tor without the local SOCKS listener
+///
+/// Bootstraps a client for in-process use only, so builds without the
+/// `socks` feature can still run Tor. A later tor_start_frb() call adds the
+/// SOCKS listener to this client. Takes the same options as tor_start_frb();
+/// the SOCKS idle timeout and bind address are left for that call.
+Future<void> torStartClientOnlyFrb(
+        {required String stateDir,
+        required String cacheDir,
+        required StartOptions options}) =>
+    RustLib.instance.api.crateApiTorTorStartClientOnlyFrb(
+        stateDir: stateDir, cacheDir: cacheDir, options: options);
+
+/// Move the SOCKS listener to another port without restarting Tor
+///
+/// Keeps the bootstrapped client, e.g. when another app has taken the port.
+/// The new port is opened before the old one is closed, so if it cannot be
+/// listened on this fails and the old port stays in use. Returns the new
+/// port; a new_port of 0 picks a free one. Fails if Tor is not running with
+/// a SOCKS listener, and with a "feature not enabled" error in builds
+/// without the `socks` feature.
+Future<int> torRebindSocksFrb({required int newPort}) =>
+    RustLib.instance.api.crateApiTorTorRebindSocksFrb(newPort: newPort);
 
 /// Update current proxy configuration
 ///
@@ -31,12 +78,410 @@ Future<int> torStartFrb(
 /// Pass Some(ProxyInfo) to set/update proxy.
 ///
 /// This can be called while Tor is running to update proxy dynamically.
+///
+/// Fails with an "Invalid proxy: <reason>" error, keeping the previous proxy,
+/// if the address is empty or not an IP, is reserved (0.0.0.0,
+/// 255.255.255.255, multicast) or the port is 0, or if an HTTPS proxy has
+/// neither a TLS server name nor a valid certificate pin. IPv6 addresses may
+/// be given with or without brackets.
 Future<void> torSetProxyFrb({ProxyInfo? proxy}) =>
     RustLib.instance.api.crateApiTorTorSetProxyFrb(proxy: proxy);
 
+/// Update current proxy configuration with several weighted proxies
+///
+/// Each new connection picks a proxy at random in proportion to its weight,
+/// skipping proxies that failed within the last minute. An empty list clears
+/// the proxy (use direct connection).
+///
+/// This can be called while Tor is running to update proxies dynamically.
+/// Fails as tor_set_proxy_frb() does if any proxy is invalid, keeping the
+/// previous proxies.
+Future<void> torSetProxiesFrb({required List<WeightedProxy> proxies}) =>
+    RustLib.instance.api.crateApiTorTorSetProxiesFrb(proxies: proxies);
+
+/// Route connections through several proxies in series
+///
+/// The first hop is dialled, each hop connects to the next and the last one
+/// to the target, so only the last hop learns the target. Hops must be
+/// SOCKS5, SOCKS4 or HTTP CONNECT proxies. The chain replaces the proxies
+/// from tor_set_proxy_frb() and tor_set_proxies_frb() until either is called
+/// again.
+///
+/// Fails as tor_set_proxy_frb() does if any hop is invalid, and also if the
+/// chain is empty or has an HTTPS hop, keeping the previous proxies.
+Future<void> torSetProxyChainFrb({required List<ProxyInfo> hops}) =>
+    RustLib.instance.api.crateApiTorTorSetProxyChainFrb(hops: hops);
+
+/// Connect through the first of several proxies that works
+///
+/// Proxies are tried highest priority first, at most `max_attempts` of them
+/// per connection (all if None), moving on when one cannot be connected to
+/// or fails its handshake. With `sticky`, the proxy that worked last is
+/// tried first from then on, so a dead primary does not slow down every
+/// connection. Replaces the proxies from tor_set_proxy_frb(),
+/// tor_set_proxies_frb() and tor_set_proxy_chain_frb() until one of them is
+/// called again.
+///
+/// Fails as tor_set_proxy_frb() does if any proxy is invalid, and also if
+/// the list is empty, keeping the previous proxies.
+Future<void> torSetProxyFailoverFrb(
+        {required List<ProxyInfo> proxies,
+        int? maxAttempts,
+        required bool sticky}) =>
+    RustLib.instance.api.crateApiTorTorSetProxyFailoverFrb(
+        proxies: proxies, maxAttempts: maxAttempts, sticky: sticky);
+
+/// Switch proxy use on or off without restarting Tor
+///
+/// Overrides use_system_proxy from tor_start_frb(). When off, connections go
+/// direct even if a proxy is set; when on, they use the proxies set via
+/// tor_set_proxy_frb() or tor_set_proxies_frb(). Takes effect for new
+/// connections only.
+Future<void> torSetUseProxyFrb({required bool useProxy}) =>
+    RustLib.instance.api.crateApiTorTorSetUseProxyFrb(useProxy: useProxy);
+
+/// Check that an address is a well-formed v3 .onion address
+///
+/// Fails with an "Invalid onion address: <reason>" error naming the problem
+/// (missing .onion, wrong length, v2 address, bad characters, bad checksum),
+/// suitable for showing to a user who pasted the address.
+Future<void> torValidateOnionAddressFrb({required String address}) =>
+    RustLib.instance.api
+        .crateApiTorTorValidateOnionAddressFrb(address: address);
+
+/// Resolve a host name through Tor
+///
+/// Returns the host's IP addresses as strings. The lookup is done by the
+/// exit relay, not the local resolver. Fails with "Host not found: <name>"
+/// if the name does not exist (NXDOMAIN), which is not worth retrying, or
+/// with "Could not resolve <name>: <reason>" for everything else, such as
+/// Tor not running or not bootstrapped yet, or a failed circuit.
+Future<List<String>> torResolveFrb({required String name}) =>
+    RustLib.instance.api.crateApiTorTorResolveFrb(name: name);
+
+/// Build a circuit for several streams to share
+///
+/// target_hint is a "host:port" the circuit must be able to reach, such as
+/// the peer the streams are for; one connection to it is opened and closed
+/// to build the circuit. Use the handle with tor_open_stream_on_circuit_frb()
+/// and release it with tor_release_circuit_frb(). Stopping Tor releases all
+/// handles. Fails if Tor is not running or target_hint cannot be reached,
+/// and with "Invalid onion address: <reason>" if it is a malformed .onion
+/// address.
+///
+/// Normally every destination gets its own circuit, so exit relays cannot
+/// link them. Streams on one circuit are seen by its exit relay as coming
+/// from one user: only put streams on a circuit that belong to the same
+/// session anyway, e.g. all traffic with one peer. The streams stay on the
+/// circuit while it is usable; arti retires circuits about ten minutes
+/// after their first use, after which they move together to a new one.
+Future<CircuitHandle> torBuildCircuitFrb({required String targetHint}) =>
+    RustLib.instance.api.crateApiTorTorBuildCircuitFrb(targetHint: targetHint);
+
+/// Open a stream to host:port on a circuit from tor_build_circuit_frb()
+///
+/// Returns a port on 127.0.0.1 to connect a socket to and a token to send on
+/// it first; whatever is sent after the token goes over the stream. Any
+/// local app can connect to the port, so only a connection that sends the
+/// token is relayed, see LocalStream. The port takes a single connection
+/// and is closed if none arrives within a minute. Fails if the handle was
+/// released or the stream cannot be opened, and as
+/// tor_validate_onion_address_frb() does if host is a malformed .onion
+/// address.
+Future<LocalStream> torOpenStreamOnCircuitFrb(
+        {required CircuitHandle handle,
+        required String host,
+        required int port}) =>
+    RustLib.instance.api.crateApiTorTorOpenStreamOnCircuitFrb(
+        handle: handle, host: host, port: port);
+
+/// Release a circuit from tor_build_circuit_frb()
+///
+/// Closes the streams opened on it. Releasing an unknown handle does nothing.
+Future<void> torReleaseCircuitFrb({required CircuitHandle handle}) =>
+    RustLib.instance.api.crateApiTorTorReleaseCircuitFrb(handle: handle);
+
+/// Open a stream to host:port isolated by isolation_key
+///
+/// Streams opened with the same key may share circuits; streams with
+/// different keys never do, nor with the SOCKS port. Use one key per
+/// activity that must not be linked, e.g. per chat account. The key can be
+/// any string and stays valid until Tor stops.
+///
+/// Returns a port on 127.0.0.1 and the token to send on it first, like
+/// tor_open_stream_on_circuit_frb(): it takes a single connection and is
+/// closed if none arrives within a minute. Fails if Tor is not running or
+/// the stream cannot be opened, and as tor_validate_onion_address_frb()
+/// does if host is a malformed .onion address.
+Future<LocalStream> torConnectIsolatedFrb(
+        {required String host,
+        required int port,
+        required String isolationKey}) =>
+    RustLib.instance.api.crateApiTorTorConnectIsolatedFrb(
+        host: host, port: port, isolationKey: isolationKey);
+
+/// Open another SOCKS listener whose connections get circuits of their own
+///
+/// Connections through the returned port never share a circuit with those
+/// through the main SOCKS port or other isolated ports, e.g. for a private
+/// tab or one sensitive request. The running client is reused, so this
+/// needs no new bootstrap. The port stays open until
+/// tor_close_isolated_listener_frb() or until Tor stops. Fails if Tor is not
+/// running, and with a "feature not enabled" error in builds without the
+/// `socks` feature.
+Future<int> torOpenIsolatedListenerFrb() =>
+    RustLib.instance.api.crateApiTorTorOpenIsolatedListenerFrb();
+
+/// Close a port from tor_open_isolated_listener_frb()
+///
+/// Connections already made through it stay open. Returns false if no
+/// isolated listener is open on that port.
+Future<bool> torCloseIsolatedListenerFrb({required int port}) =>
+    RustLib.instance.api.crateApiTorTorCloseIsolatedListenerFrb(port: port);
+
+/// Switch to new circuits for new connections, like Tor Browser's
+/// "New Identity"
+///
+/// Only connections made afterwards are affected: streams that are already
+/// open keep their circuits, as do circuits from tor_build_circuit_frb()
+/// and ports from tor_open_isolated_listener_frb(). The client stays
+/// bootstrapped and the SOCKS port stays the same. Fails if Tor is not
+/// running.
+Future<void> torNewIdentityFrb() =>
+    RustLib.instance.api.crateApiTorTorNewIdentityFrb();
+
+/// Choose the countries circuits from tor_build_circuit_frb() exit in
+///
+/// country_codes are two-letter ISO 3166-1 codes such as "us" or "de"; an
+/// empty list allows any country again. arti can only ask for one exit
+/// country per circuit, so each new circuit exits in one of them picked at
+/// random. Circuits already built keep their exit, and traffic through the
+/// SOCKS port is not affected, as arti's SOCKS listener picks its own
+/// circuits. A country with no usable exits makes circuit building fail.
+///
+/// Fails on a code that is not two letters, and with a "feature not
+/// enabled" error for a non-empty list in builds without the `exit-country`
+/// feature.
+Future<void> torSetExitCountriesFrb({required List<String> countryCodes}) =>
+    RustLib.instance.api.crateApiTorTorSetExitCountriesFrb(
+        countryCodes: countryCodes);
+
+/// Isolate SOCKS connections that use contact_id as username by token
+///
+/// Pass a stable contact id as the SOCKS5 username (any password) and the
+/// listener swaps it for the contact's token before Tor sees it. Contacts
+/// mapped to the same token share circuits; different tokens never do.
+/// Mapping a contact again replaces its token, which moves its new
+/// connections to new circuits. Open connections keep their circuits.
+/// Usernames without a mapping are still isolated from each other.
+///
+/// Fails if contact_id is empty or longer than 255 bytes, or if token is
+/// empty or too long to fit a SOCKS username with its prefix. Mappings
+/// survive stopping and starting Tor.
+Future<void> torAddIsolationMappingFrb(
+        {required String contactId, required String token}) =>
+    RustLib.instance.api.crateApiTorTorAddIsolationMappingFrb(
+        contactId: contactId, token: token);
+
+/// Remove the isolation mapping of contact_id
+///
+/// Returns whether there was one.
+Future<bool> torRemoveIsolationMappingFrb({required String contactId}) =>
+    RustLib.instance.api.crateApiTorTorRemoveIsolationMappingFrb(
+        contactId: contactId);
+
+/// List the isolation mappings, sorted by contact id
+Future<List<IsolationMapping>> torListIsolationMappingsFrb() =>
+    RustLib.instance.api.crateApiTorTorListIsolationMappingsFrb();
+
+/// Tell Tor whether the device currently has network connectivity
+///
+/// While offline, tor_start_frb() and tor_start_client_only_frb() return
+/// without bootstrapping, so no battery is spent on attempts that cannot
+/// succeed; connections fail until then. Reporting online again starts the
+/// deferred bootstrap in the background.
+Future<void> torNotifyNetworkChangedFrb({required bool online}) =>
+    RustLib.instance.api.crateApiTorTorNotifyNetworkChangedFrb(online: online);
+
+/// Restrict which IP addresses Tor may connect to
+///
+/// Both lists take CIDR notation ("10.0.0.0/8", "fe80::/10") or bare
+/// addresses. Targets in deny are always refused; if allow is non-empty,
+/// only targets in it are permitted. Refused connections fail with a
+/// "blocked by policy" error. Pass two empty lists to remove the policy.
+///
+/// This applies to connections to relays and bridges (or to the proxy's
+/// target), and can be changed while Tor is running.
+Future<void> torSetTargetPolicyFrb(
+        {required List<String> allow, required List<String> deny}) =>
+    RustLib.instance.api
+        .crateApiTorTorSetTargetPolicyFrb(allow: allow, deny: deny);
+
+/// Choose how connection targets are written to logs
+///
+/// Covers the debug log and the connection log (see tor_start_frb()). The
+/// default, Hashed, lets a log be shared without revealing which relays or
+/// bridges were used while still telling connections apart. Takes effect
+/// for new connections.
+Future<void> torSetTargetRedactionFrb({required TargetRedaction redaction}) =>
+    RustLib.instance.api
+        .crateApiTorTorSetTargetRedactionFrb(redaction: redaction);
+
+/// Choose whether Tor restarts itself when it stops working
+///
+/// A running client is checked every ten minutes by resolving a name through
+/// Tor. After three failures in a row the status reports a health_error and,
+/// if enabled here, Tor is stopped and started again with the same
+/// parameters; observers see it stop and start. Off by default.
+Future<void> torSetAutoRestartFrb({required bool enabled}) =>
+    RustLib.instance.api.crateApiTorTorSetAutoRestartFrb(enabled: enabled);
+
+/// Tell Tor whether the device's network is metered
+///
+/// While metered, Tor stops the background work it does to be ready ahead
+/// of time, such as building spare circuits, and skips its periodic health
+/// checks (see tor_set_auto_restart_frb()). Unlike dormant mode Tor stays
+/// usable: connections work as before, though the first one may wait for a
+/// circuit, and the directory is still refreshed as far as circuits need
+/// it. Off by default; applies to a running client and to later starts.
+Future<void> torSetMeteredFrb({required bool metered}) =>
+    RustLib.instance.api.crateApiTorTorSetMeteredFrb(metered: metered);
+
 /// Stop Tor service
+///
+/// Also cancels a tor_start_frb() that is still bootstrapping; that call
+/// then fails. Dart cannot cancel the start future itself.
 Future<void> torStopFrb() => RustLib.instance.api.crateApiTorTorStopFrb();
 
+/// Stop Tor service, letting open SOCKS connections finish first
+///
+/// The SOCKS listener stops accepting at once; connections it already
+/// accepted get up to timeout_ms to close on their own before Tor is shut
+/// down, which cuts off any still open. Returns whether all of them
+/// finished in time. Like tor_stop_frb() this cancels a pending start.
+Future<bool> torStopGracefulFrb({required BigInt timeoutMs}) =>
+    RustLib.instance.api.crateApiTorTorStopGracefulFrb(timeoutMs: timeoutMs);
+
+/// Forget the selected guards so that new ones are picked on the next start
+///
+/// Removes only the guard state in state_dir. Fails unless Tor is stopped.
+Future<void> torResetGuardsFrb({required String stateDir}) =>
+    RustLib.instance.api.crateApiTorTorResetGuardsFrb(stateDir: stateDir);
+
+/// Drop the cached consensus so that it is downloaded on the next start
+///
+/// The microdescriptors and certificates cached with it in cache_dir are
+/// dropped too; guards and other state are kept. Fails unless Tor is stopped.
+Future<void> torResetConsensusFrb({required String cacheDir}) =>
+    RustLib.instance.api.crateApiTorTorResetConsensusFrb(cacheDir: cacheDir);
+
+/// Empty cache_dir, for a clean slate on the next start
+///
+/// Removes everything Tor cached there, including the consensus, so the next
+/// start downloads the directory afresh; for when Tor will not bootstrap,
+/// e.g. after an OS update. Guards and keys live in state_dir and are kept.
+/// Tor also clears the directory cache by itself, once, if bootstrap from a
+/// cached consensus fails or takes over two minutes. Fails unless Tor is
+/// stopped.
+Future<void> torClearCacheFrb({required String cacheDir}) =>
+    RustLib.instance.api.crateApiTorTorClearCacheFrb(cacheDir: cacheDir);
+
+/// Seed cache_dir with a directory cache shipped with the app
+///
+/// bundle_dir must hold a copy of another client's directory cache
+/// (`dir.sqlite3` and `dir_blobs`, e.g. extracted from the app's assets).
+/// It is copied into cache_dir if its consensus has not expired and
+/// cache_dir has no unexpired one, so the first start skips downloading the
+/// directory. Returns whether it was copied; a stale or missing bundle is
+/// not an error, Tor then bootstraps as usual. arti checks the consensus
+/// signatures when it loads the cache and downloads a fresh one if they fail.
+/// Fails unless Tor is stopped.
+Future<bool> torSeedDirectoryCacheFrb(
+        {required String bundleDir, required String cacheDir}) =>
+    RustLib.instance.api.crateApiTorTorSeedDirectoryCacheFrb(
+        bundleDir: bundleDir, cacheDir: cacheDir);
+
 /// Set dormant mode
+///
+/// With soft_mode the client suspends its background work, e.g. while the
+/// app is in the background, but keeps its circuits and SOCKS listener and
+/// still serves connections. Calling this with false wakes it up again
+/// without a restart; it stays in soft mode while metered (see
+/// tor_set_metered_frb()). Also applies to clients started later.
+///
+/// There is no hard mode that also tears down circuits: arti's only
+/// dormant mode is the soft one. To free everything, stop Tor instead.
 Future<void> torSetDormantFrb({required bool softMode}) =>
     RustLib.instance.api.crateApiTorTorSetDormantFrb(softMode: softMode);
+
+/// Query current file descriptor usage
+///
+/// Returns the number of open descriptors together with the soft and hard
+/// NOFILE limits. Not supported on Windows.
+Future<FdUsage> torFdUsageFrb() =>
+    RustLib.instance.api.crateApiTorTorFdUsageFrb();
+
+/// List the optional features compiled into this build
+///
+/// Functions backed by a feature that is missing here fail with a
+/// "feature not enabled" error instead of doing nothing.
+Future<List<String>> torEnabledFeaturesFrb() =>
+    RustLib.instance.api.crateApiTorTorEnabledFeaturesFrb();
+
+/// Get the config of the running client as TOML
+///
+/// Shows what arti actually runs with after start() applied its parameters,
+/// for display or support logs. Settings left at arti's defaults are
+/// omitted and bridge lines are redacted. Fails if Tor is not running.
+Future<String> torEffectiveConfigFrb() =>
+    RustLib.instance.api.crateApiTorTorEffectiveConfigFrb();
+
+/// Get the current status of the Tor service
+///
+/// Read-only: safe to call from any isolate, including ones that do not own
+/// the Tor lifecycle.
+Future<TorStatus> torStatusFrb() =>
+    RustLib.instance.api.crateApiTorTorStatusFrb();
+
+/// Observe status changes of the Tor service
+///
+/// Emits the current status immediately, then every change (start, stop,
+/// bootstrap progress). Observers cannot stop or reconfigure Tor, so a
+/// background isolate can monitor connectivity while the main isolate owns
+/// the lifecycle.
+Stream<TorStatus> torObserveStatusFrb() =>
+    RustLib.instance.api.crateApiTorTorObserveStatusFrb();
+
+/// Observe bootstrap progress, e.g. for a progress bar
+///
+/// Emits the current progress immediately, then every change, each with the
+/// fraction done and arti's summary of the current phase. Unlike
+/// tor_start_with_progress_frb() this can be called before or while
+/// tor_start_frb() or tor_start_client_only_frb() runs, and keeps going
+/// across restarts. Progress is back at 0 while Tor is stopped.
+Stream<BootstrapStatus> torObserveBootstrapFrb() =>
+    RustLib.instance.api.crateApiTorTorObserveBootstrapFrb();
+
+/// Observe outgoing connection attempts, e.g. to show proxy health
+///
+/// Emits an event when an attempt starts and when it connects or fails, with
+/// the target redacted as set by tor_set_target_redaction_frb() and the
+/// proxy it went through. Each proxy tried from a failover list and each
+/// retry is an attempt of its own. Earlier attempts are not replayed, and a
+/// listener that falls far behind misses the oldest events.
+Stream<ConnectionEvent> torObserveConnectionsFrb() =>
+    RustLib.instance.api.crateApiTorTorObserveConnectionsFrb();
+
+/// Observe Tor's log lines, for a debug console
+///
+/// Emits arti's log output and this library's debug log (targets
+/// "tor::manager" and "tor::proxy") from error down to debug level as it
+/// happens; earlier lines are not replayed. Each listener has a queue of
+/// 1024 lines: if Dart falls behind, newer lines are dropped and a warning
+/// with the number dropped follows. Cancelling the subscription is enough
+/// to stop it.
+///
+/// Only one tracing subscriber can exist per process, so this delivers
+/// nothing but a warning if the app installed its own first.
+Stream<LogEntry> torSubscribeLogsFrb() =>
+    RustLib.instance.api.crateApiTorTorSubscribeLogsFrb();
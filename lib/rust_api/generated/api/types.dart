@@ -6,7 +6,291 @@
 import '../frb_generated.dart';
 import 'package:flutter_rust_bridge/flutter_rust_bridge_for_generated.dart';
 
-// These function are ignored because they are on traits that is not defined in current crate (put an empty `#[frb]` on it to unignore): `assert_receiver_is_total_eq`, `clone`, `clone`, `eq`, `fmt`, `fmt`
+// These function are ignored because they are on traits that is not defined in current crate (put an empty `#[frb]` on it to unignore): `assert_receiver_is_total_eq`, `assert_receiver_is_total_eq`, `assert_receiver_is_total_eq`, `assert_receiver_is_total_eq`, `assert_receiver_is_total_eq`, `clone`, `clone`, `clone`, `clone`, `clone`, `clone`, `clone`, `clone`, `eq`, `eq`, `eq`, `eq`, `eq`, `fmt`, `fmt`, `fmt`, `fmt`, `fmt`, `fmt`, `fmt`, `fmt`
+
+/// Bootstrap that has made no progress for a while
+class BootstrapStalled {
+  final String lastPhase;
+  final String suggestion;
+
+  const BootstrapStalled({
+    required this.lastPhase,
+    required this.suggestion,
+  });
+
+  @override
+  int get hashCode => lastPhase.hashCode ^ suggestion.hashCode;
+
+  @override
+  bool operator ==(Object other) =>
+      identical(this, other) ||
+      other is BootstrapStalled &&
+          runtimeType == other.runtimeType &&
+          lastPhase == other.lastPhase &&
+          suggestion == other.suggestion;
+}
+
+/// Bootstrap progress, as reported by tor_start_with_progress_frb()
+class BootstrapStatus {
+  final double fraction;
+  final String summary;
+
+  const BootstrapStatus({
+    required this.fraction,
+    required this.summary,
+  });
+
+  @override
+  int get hashCode => fraction.hashCode ^ summary.hashCode;
+
+  @override
+  bool operator ==(Object other) =>
+      identical(this, other) ||
+      other is BootstrapStatus &&
+          runtimeType == other.runtimeType &&
+          fraction == other.fraction &&
+          summary == other.summary;
+}
+
+/// Bridge to reach the Tor network through, for where Tor is blocked
+///
+/// `line` is a bridge line as handed out by BridgeDB, e.g.
+/// "obfs4 192.0.2.3:443 <fingerprint> cert=... iat-mode=0". Only obfs4
+/// bridges are supported; they need the obfs4proxy or lyrebird binary. For
+/// Snowflake see SnowflakeConfig.
+class BridgeLine {
+  final String line;
+
+  const BridgeLine({
+    required this.line,
+  });
+
+  @override
+  int get hashCode => line.hashCode;
+
+  @override
+  bool operator ==(Object other) =>
+      identical(this, other) ||
+      other is BridgeLine &&
+          runtimeType == other.runtimeType &&
+          line == other.line;
+}
+
+/// Circuit built with tor_build_circuit_frb(), for opening several
+/// streams on it
+class CircuitHandle {
+  final BigInt id;
+
+  const CircuitHandle({
+    required this.id,
+  });
+
+  @override
+  int get hashCode => id.hashCode;
+
+  @override
+  bool operator ==(Object other) =>
+      identical(this, other) ||
+      other is CircuitHandle &&
+          runtimeType == other.runtimeType &&
+          id == other.id;
+}
+
+/// Outgoing connection attempt, as sent by tor_observe_connections_frb()
+class ConnectionEvent {
+  final ConnectionStage stage;
+
+  /// Where the connection goes, redacted as in the logs
+  final String target;
+
+  /// How it gets there, as in the connection log, e.g.
+  /// "socks5:127.0.0.1:1080" or "direct"
+  final String via;
+
+  /// Connected: how long the attempt took, in milliseconds
+  final int? elapsedMs;
+
+  /// Failed: why; "Proxy failed: ..." if the proxy failed rather than the
+  /// target
+  final String? error;
+
+  const ConnectionEvent({
+    required this.stage,
+    required this.target,
+    required this.via,
+    this.elapsedMs,
+    this.error,
+  });
+
+  @override
+  int get hashCode =>
+      stage.hashCode ^
+      target.hashCode ^
+      via.hashCode ^
+      elapsedMs.hashCode ^
+      error.hashCode;
+
+  @override
+  bool operator ==(Object other) =>
+      identical(this, other) ||
+      other is ConnectionEvent &&
+          runtimeType == other.runtimeType &&
+          stage == other.stage &&
+          target == other.target &&
+          via == other.via &&
+          elapsedMs == other.elapsedMs &&
+          error == other.error;
+}
+
+/// Stage of an outgoing connection attempt, see ConnectionEvent
+enum ConnectionStage {
+  /// The attempt started
+  started,
+  /// The stream is ready for use
+  connected,
+  /// The attempt failed
+  failed,
+  ;
+}
+
+/// Directory mirror to bootstrap from
+///
+/// A mirror is a relay that caches directory documents; it is what arti
+/// calls a fallback directory. It only serves copies, which are still
+/// checked against the directory authorities' signatures, so a bad mirror
+/// can stall bootstrap but cannot forge the network view.
+///
+/// `rsa_identity` is the relay's hex fingerprint, `ed_identity` its base64
+/// Ed25519 identity, and `addresses` its ORPorts as "ip:port".
+class DirectoryMirror {
+  final String rsaIdentity;
+  final String edIdentity;
+  final List<String> addresses;
+
+  const DirectoryMirror({
+    required this.rsaIdentity,
+    required this.edIdentity,
+    required this.addresses,
+  });
+
+  @override
+  int get hashCode =>
+      rsaIdentity.hashCode ^ edIdentity.hashCode ^ addresses.hashCode;
+
+  @override
+  bool operator ==(Object other) =>
+      identical(this, other) ||
+      other is DirectoryMirror &&
+          runtimeType == other.runtimeType &&
+          rsaIdentity == other.rsaIdentity &&
+          edIdentity == other.edIdentity &&
+          addresses == other.addresses;
+}
+
+/// File descriptor usage of the current process
+class FdUsage {
+  final BigInt open;
+  final BigInt softLimit;
+  final BigInt hardLimit;
+
+  const FdUsage({
+    required this.open,
+    required this.softLimit,
+    required this.hardLimit,
+  });
+
+  @override
+  int get hashCode => open.hashCode ^ softLimit.hashCode ^ hardLimit.hashCode;
+
+  @override
+  bool operator ==(Object other) =>
+      identical(this, other) ||
+      other is FdUsage &&
+          runtimeType == other.runtimeType &&
+          open == other.open &&
+          softLimit == other.softLimit &&
+          hardLimit == other.hardLimit;
+}
+
+/// SOCKS username mapped to an isolation token, see
+/// tor_add_isolation_mapping_frb()
+class IsolationMapping {
+  final String contactId;
+  final String token;
+
+  const IsolationMapping({
+    required this.contactId,
+    required this.token,
+  });
+
+  @override
+  int get hashCode => contactId.hashCode ^ token.hashCode;
+
+  @override
+  bool operator ==(Object other) =>
+      identical(this, other) ||
+      other is IsolationMapping &&
+          runtimeType == other.runtimeType &&
+          contactId == other.contactId &&
+          token == other.token;
+}
+
+/// Local port relaying a stream, from tor_open_stream_on_circuit_frb() or
+/// tor_connect_isolated_frb()
+///
+/// Connect a socket to `port` on 127.0.0.1 and send `token`, as ASCII,
+/// before anything else. Other local apps can connect to the port too; the
+/// token is how the relay tells the app apart from them, so keep it
+/// private. Connections that do not send it within 5 seconds are closed.
+/// The port relays a single connection and is closed if none sends the
+/// token within a minute.
+class LocalStream {
+  final int port;
+
+  /// 32 hex digits
+  final String token;
+
+  const LocalStream({
+    required this.port,
+    required this.token,
+  });
+
+  @override
+  int get hashCode => port.hashCode ^ token.hashCode;
+
+  @override
+  bool operator ==(Object other) =>
+      identical(this, other) ||
+      other is LocalStream &&
+          runtimeType == other.runtimeType &&
+          port == other.port &&
+          token == other.token;
+}
+
+/// Log line from Tor, as sent by tor_subscribe_logs_frb()
+class LogEntry {
+  final String level;
+  final String target;
+  final String message;
+
+  const LogEntry({
+    required this.level,
+    required this.target,
+    required this.message,
+  });
+
+  @override
+  int get hashCode => level.hashCode ^ target.hashCode ^ message.hashCode;
+
+  @override
+  bool operator ==(Object other) =>
+      identical(this, other) ||
+      other is LogEntry &&
+          runtimeType == other.runtimeType &&
+          level == other.level &&
+          target == other.target &&
+          message == other.message;
+}
 
 /// Proxy information passed from Dart
 class ProxyInfo {
@@ -16,12 +300,24 @@ class ProxyInfo {
   final String? username;
   final String? password;
 
+  /// HTTPS proxies: the host name the proxy's certificate must be issued
+  /// for, also sent as SNI
+  final String? tlsServerName;
+
+  /// HTTPS proxies: SHA-256 of the proxy's certificate (DER) as 64 hex
+  /// digits, optionally separated by colons as openssl prints it. If set, the
+  /// certificate only has to match it and is not checked against CAs or
+  /// tls_server_name, for proxies with self-signed certificates.
+  final String? tlsCertSha256;
+
   const ProxyInfo({
     required this.address,
     required this.port,
     required this.proxyType,
     this.username,
     this.password,
+    this.tlsServerName,
+    this.tlsCertSha256,
   });
 
   @override
@@ -30,7 +326,9 @@ class ProxyInfo {
       port.hashCode ^
       proxyType.hashCode ^
       username.hashCode ^
-      password.hashCode;
+      password.hashCode ^
+      tlsServerName.hashCode ^
+      tlsCertSha256.hashCode;
 
   @override
   bool operator ==(Object other) =>
@@ -41,12 +339,316 @@ class ProxyInfo {
           port == other.port &&
           proxyType == other.proxyType &&
           username == other.username &&
-          password == other.password;
+          password == other.password &&
+          tlsServerName == other.tlsServerName &&
+          tlsCertSha256 == other.tlsCertSha256;
 }
 
 /// Proxy type enumeration
 enum ProxyType {
   socks5,
   httpConnect,
+  /// SOCKS4, or SOCKS4a for domain names; the username is sent as user id
+  socks4,
+  /// HTTP CONNECT inside TLS, so that neither the credentials nor the
+  /// target cross the network in the clear; needs the `https-proxy`
+  /// feature and tls_server_name or tls_cert_sha256
+  httpsConnect,
+  ;
+}
+
+/// Snowflake bridge to reach the Tor network through, for where obfs4
+/// bridges are blocked too
+///
+/// `bridge_line` is a snowflake bridge line as shipped with Tor Browser,
+/// e.g. "snowflake 192.0.2.3:80 <fingerprint> fingerprint=<fingerprint>
+/// url=... fronts=... ice=...". `broker_url`, `front_domain` and
+/// `stun_servers` replace its url=, fronts= and ice= settings if given;
+/// STUN servers are written as "stun:host:port". `binary_path` is the
+/// snowflake client (snowflake-client or lyrebird), run with `arguments`.
+class SnowflakeConfig {
+  final String bridgeLine;
+  final String binaryPath;
+  final String? brokerUrl;
+  final String? frontDomain;
+  final List<String> stunServers;
+  final List<String> arguments;
+
+  const SnowflakeConfig({
+    required this.bridgeLine,
+    required this.binaryPath,
+    this.brokerUrl,
+    this.frontDomain,
+    required this.stunServers,
+    required this.arguments,
+  });
+
+  @override
+  int get hashCode =>
+      bridgeLine.hashCode ^
+      binaryPath.hashCode ^
+      brokerUrl.hashCode ^
+      frontDomain.hashCode ^
+      stunServers.hashCode ^
+      arguments.hashCode;
+
+  @override
+  bool operator ==(Object other) =>
+      identical(this, other) ||
+      other is SnowflakeConfig &&
+          runtimeType == other.runtimeType &&
+          bridgeLine == other.bridgeLine &&
+          binaryPath == other.binaryPath &&
+          brokerUrl == other.brokerUrl &&
+          frontDomain == other.frontDomain &&
+          stunServers == other.stunServers &&
+          arguments == other.arguments;
+}
+
+/// Settings for tor_start_frb() and tor_start_client_only_frb()
+///
+/// Everything about starting Tor except the SOCKS port and the directories,
+/// so that both calls take the same settings. Settings that shape the client
+/// only apply if the call creates it, not when tor_start_frb() adds a SOCKS
+/// listener to a client from tor_start_client_only_frb(). Timeouts are in
+/// whole seconds; 0 is rejected.
+class StartOptions {
+  /// Connect through the proxy from tor_set_proxy_frb(); direct if false
+  /// or no proxy is set
+  final bool useSystemProxy;
+
+  /// Append every outgoing connection (time, target, proxy, bytes,
+  /// duration) to `connections.log` in the state directory, rotated at
+  /// 1 MiB. This is an audit trail of when and how Tor was used that
+  /// persists on disk, so only enable it where that is wanted.
+  final bool connectionLog;
+
+  /// Replace arti's built-in fallback directories for the first download
+  /// of the directory. Mirrors are relays that cache directory documents,
+  /// not directory authorities. If bootstrap through them fails or takes
+  /// over a minute, the built-in fallback directories are used instead.
+  final List<DirectoryMirror> directoryMirrors;
+
+  /// Connect only through these obfs4 bridges, for networks where Tor
+  /// relays are blocked; each is checked before Tor starts. They need the
+  /// `bridges` feature and obfs4_binary_path.
+  final List<BridgeLine> bridges;
+
+  /// The obfs4proxy or lyrebird executable that arti runs to speak obfs4.
+  /// It connects to the bridges itself, so the proxy from
+  /// tor_set_proxy_frb() is not used for them.
+  final String? obfs4BinaryPath;
+
+  /// Snowflake bridge, for networks that also block obfs4 bridges; alone
+  /// or next to `bridges`, with the same requirements
+  final SnowflakeConfig? snowflake;
+
+  /// Exit circuits built ahead of time for each port Tor expects to be
+  /// used. None keeps arti's default of 2; 0 turns this off, which saves
+  /// battery and bandwidth at the cost of slower first connections. At
+  /// most 8.
+  final int? preemptiveCircuits;
+
+  /// For devices whose clock is off and cannot be corrected: a consensus
+  /// is accepted this long before it becomes valid and after it expires.
+  /// None keeps arti's defaults (1 day before, 3 days after); at most 7
+  /// days. Every bit of extra tolerance also lets an attacker feed the
+  /// device an old consensus for that much longer, so only raise it as far
+  /// as the clock needs.
+  final int? consensusTimeToleranceSecs;
+
+  /// How long a stream may take to open through Tor; on timeout a SOCKS
+  /// client gets an error reply. None keeps arti's 10 seconds.
+  final int? socksConnectTimeoutSecs;
+
+  /// Close SOCKS connections that carry no traffic for this long; None
+  /// never does. Only used by tor_start_frb().
+  final int? socksIdleTimeoutSecs;
+
+  /// IP address the SOCKS listener listens on, e.g. 0.0.0.0 for all
+  /// interfaces; None keeps it on localhost. Anything but localhost
+  /// exposes the listener, which has no authentication, to every device
+  /// that can reach that address. Only used by tor_start_frb(), and
+  /// ignored if the SOCKS listener is already running.
+  final String? bindAddr;
+
+  /// Give up on bootstrap after this long, failing with "Bootstrap timed
+  /// out after ...". The half-started client is dropped, so starting again
+  /// starts over. None waits as long as bootstrap takes.
+  final int? bootstrapTimeoutSecs;
+
+  /// Give up on a proxy that has not finished connecting and its
+  /// handshake this long after it was dialled, and try the next one. None
+  /// keeps 30 seconds.
+  final int? proxyHandshakeTimeoutSecs;
+
+  /// SOCKS5 authentication methods to offer proxies, most preferred
+  /// first, e.g. [0] to never offer username/password to proxies that reject
+  /// clients offering a method they do not use. Username/password (2) is
+  /// only offered with credentials; unknown methods are skipped. None offers
+  /// every method the credentials allow.
+  final Uint8List? socks5MethodOrder;
+
+  const StartOptions({
+    this.useSystemProxy = true,
+    this.connectionLog = false,
+    this.directoryMirrors = const [],
+    this.bridges = const [],
+    this.obfs4BinaryPath,
+    this.snowflake,
+    this.preemptiveCircuits,
+    this.consensusTimeToleranceSecs,
+    this.socksConnectTimeoutSecs,
+    this.socksIdleTimeoutSecs,
+    this.bindAddr,
+    this.bootstrapTimeoutSecs,
+    this.proxyHandshakeTimeoutSecs,
+    this.socks5MethodOrder,
+  });
+
+  @override
+  int get hashCode =>
+      useSystemProxy.hashCode ^
+      connectionLog.hashCode ^
+      directoryMirrors.hashCode ^
+      bridges.hashCode ^
+      obfs4BinaryPath.hashCode ^
+      snowflake.hashCode ^
+      preemptiveCircuits.hashCode ^
+      consensusTimeToleranceSecs.hashCode ^
+      socksConnectTimeoutSecs.hashCode ^
+      socksIdleTimeoutSecs.hashCode ^
+      bindAddr.hashCode ^
+      bootstrapTimeoutSecs.hashCode ^
+      proxyHandshakeTimeoutSecs.hashCode ^
+      socks5MethodOrder.hashCode;
+
+  @override
+  bool operator ==(Object other) =>
+      identical(this, other) ||
+      other is StartOptions &&
+          runtimeType == other.runtimeType &&
+          useSystemProxy == other.useSystemProxy &&
+          connectionLog == other.connectionLog &&
+          directoryMirrors == other.directoryMirrors &&
+          bridges == other.bridges &&
+          obfs4BinaryPath == other.obfs4BinaryPath &&
+          snowflake == other.snowflake &&
+          preemptiveCircuits == other.preemptiveCircuits &&
+          consensusTimeToleranceSecs == other.consensusTimeToleranceSecs &&
+          socksConnectTimeoutSecs == other.socksConnectTimeoutSecs &&
+          socksIdleTimeoutSecs == other.socksIdleTimeoutSecs &&
+          bindAddr == other.bindAddr &&
+          bootstrapTimeoutSecs == other.bootstrapTimeoutSecs &&
+          proxyHandshakeTimeoutSecs == other.proxyHandshakeTimeoutSecs &&
+          socks5MethodOrder == other.socks5MethodOrder;
+}
+
+/// How connection targets appear in logs
+///
+/// Applies to the debug log and the connection log. `Hashed` keeps targets
+/// apart without revealing them; the hash key is random per process, so
+/// hashes only match within one run.
+enum TargetRedaction {
+  /// The full address, e.g. "203.0.113.5:9001"
+  full,
+  /// The IP without the port
+  hostOnly,
+  /// A keyed hash of the address
+  hashed,
+  /// Nothing; targets are logged as "redacted"
+  none,
   ;
 }
+
+/// Snapshot of the Tor service, as seen by observers
+class TorStatus {
+  /// A client exists (it may still be bootstrapping)
+  final bool running;
+
+  /// Port of the local SOCKS listener, if one is running
+  final int? socksPort;
+
+  /// Bootstrap progress, 0 to 100
+  final int bootstrapPercent;
+
+  /// Bootstrapped far enough to carry traffic
+  final bool readyForTraffic;
+
+  /// Why bootstrap appears to be stuck, if it is
+  final String? blockage;
+
+  /// Set while bootstrap has not progressed for a while, with a hint
+  final BootstrapStalled? bootstrapStalled;
+
+  /// Why the client failed its periodic health checks, if it did
+  final String? healthError;
+
+  /// The running client is in soft dormant mode, see tor_set_dormant_frb()
+  /// and tor_set_metered_frb()
+  final bool dormant;
+
+  const TorStatus({
+    required this.running,
+    this.socksPort,
+    required this.bootstrapPercent,
+    required this.readyForTraffic,
+    this.blockage,
+    this.bootstrapStalled,
+    this.healthError,
+    required this.dormant,
+  });
+
+  static Future<TorStatus> default_() =>
+      RustLib.instance.api.crateApiTypesTorStatusDefault();
+
+  @override
+  int get hashCode =>
+      running.hashCode ^
+      socksPort.hashCode ^
+      bootstrapPercent.hashCode ^
+      readyForTraffic.hashCode ^
+      blockage.hashCode ^
+      bootstrapStalled.hashCode ^
+      healthError.hashCode ^
+      dormant.hashCode;
+
+  @override
+  bool operator ==(Object other) =>
+      identical(this, other) ||
+      other is TorStatus &&
+          runtimeType == other.runtimeType &&
+          running == other.running &&
+          socksPort == other.socksPort &&
+          bootstrapPercent == other.bootstrapPercent &&
+          readyForTraffic == other.readyForTraffic &&
+          blockage == other.blockage &&
+          bootstrapStalled == other.bootstrapStalled &&
+          healthError == other.healthError &&
+          dormant == other.dormant;
+}
+
+/// Proxy with a relative weight for load balancing
+///
+/// New connections are spread across proxies in proportion to their weights.
+/// A weight of 0 disables the proxy.
+class WeightedProxy {
+  final ProxyInfo proxy;
+  final int weight;
+
+  const WeightedProxy({
+    required this.proxy,
+    required this.weight,
+  });
+
+  @override
+  int get hashCode => proxy.hashCode ^ weight.hashCode;
+
+  @override
+  bool operator ==(Object other) =>
+      identical(this, other) ||
+      other is WeightedProxy &&
+          runtimeType == other.runtimeType &&
+          proxy == other.proxy &&
+          weight == other.weight;
+}
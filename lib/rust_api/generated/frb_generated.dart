@@ -67,7 +67,7 @@ class RustLib extends BaseEntrypoint<RustLibApi, RustLibApiImpl, RustLibWire> {
   String get codegenVersion => '2.7.0';
 
   @override
-  int get rustContentHash => -2027089074;
+  int get rustContentHash => 335660611;
 
   static const kDefaultExternalLibraryLoaderConfig =
       ExternalLibraryLoaderConfig(
@@ -78,19 +78,117 @@ class RustLib extends BaseEntrypoint<RustLibApi, RustLibApiImpl, RustLibWire> {
 }
 
 abstract class RustLibApi extends BaseApi {
+  Future<void> crateApiTorTorAddIsolationMappingFrb(
+      {required String contactId, required String token});
+
+  Future<CircuitHandle> crateApiTorTorBuildCircuitFrb(
+      {required String targetHint});
+
+  Future<void> crateApiTorTorClearCacheFrb({required String cacheDir});
+
+  Future<bool> crateApiTorTorCloseIsolatedListenerFrb({required int port});
+
+  Future<LocalStream> crateApiTorTorConnectIsolatedFrb(
+      {required String host, required int port, required String isolationKey});
+
+  Future<String> crateApiTorTorEffectiveConfigFrb();
+
+  Future<List<String>> crateApiTorTorEnabledFeaturesFrb();
+
+  Future<FdUsage> crateApiTorTorFdUsageFrb();
+
   Future<String> crateApiTorTorHelloFrb();
 
+  Future<List<IsolationMapping>> crateApiTorTorListIsolationMappingsFrb();
+
+  Future<void> crateApiTorTorNewIdentityFrb();
+
+  Future<void> crateApiTorTorNotifyNetworkChangedFrb({required bool online});
+
+  Stream<BootstrapStatus> crateApiTorTorObserveBootstrapFrb();
+
+  Stream<ConnectionEvent> crateApiTorTorObserveConnectionsFrb();
+
+  Stream<TorStatus> crateApiTorTorObserveStatusFrb();
+
+  Future<int> crateApiTorTorOpenIsolatedListenerFrb();
+
+  Future<LocalStream> crateApiTorTorOpenStreamOnCircuitFrb(
+      {required CircuitHandle handle, required String host, required int port});
+
+  Future<int> crateApiTorTorRebindSocksFrb({required int newPort});
+
+  Future<void> crateApiTorTorReleaseCircuitFrb({required CircuitHandle handle});
+
+  Future<bool> crateApiTorTorRemoveIsolationMappingFrb(
+      {required String contactId});
+
+  Future<void> crateApiTorTorResetConsensusFrb({required String cacheDir});
+
+  Future<void> crateApiTorTorResetGuardsFrb({required String stateDir});
+
+  Future<List<String>> crateApiTorTorResolveFrb({required String name});
+
+  Future<bool> crateApiTorTorSeedDirectoryCacheFrb(
+      {required String bundleDir, required String cacheDir});
+
+  Future<void> crateApiTorTorSetAutoRestartFrb({required bool enabled});
+
   Future<void> crateApiTorTorSetDormantFrb({required bool softMode});
 
+  Future<void> crateApiTorTorSetExitCountriesFrb(
+      {required List<String> countryCodes});
+
+  Future<void> crateApiTorTorSetMeteredFrb({required bool metered});
+
+  Future<void> crateApiTorTorSetProxiesFrb(
+      {required List<WeightedProxy> proxies});
+
+  Future<void> crateApiTorTorSetProxyChainFrb({required List<ProxyInfo> hops});
+
+  Future<void> crateApiTorTorSetProxyFailoverFrb(
+      {required List<ProxyInfo> proxies,
+      int? maxAttempts,
+      required bool sticky});
+
   Future<void> crateApiTorTorSetProxyFrb({ProxyInfo? proxy});
 
+  Future<void> crateApiTorTorSetTargetPolicyFrb(
+      {required List<String> allow, required List<String> deny});
+
+  Future<void> crateApiTorTorSetTargetRedactionFrb(
+      {required TargetRedaction redaction});
+
+  Future<void> crateApiTorTorSetUseProxyFrb({required bool useProxy});
+
+  Future<void> crateApiTorTorStartClientOnlyFrb(
+      {required String stateDir,
+      required String cacheDir,
+      required StartOptions options});
+
   Future<int> crateApiTorTorStartFrb(
       {required int socksPort,
       required String stateDir,
       required String cacheDir,
-      required bool useSystemProxy});
+      required StartOptions options});
+
+  Stream<BootstrapStatus> crateApiTorTorStartWithProgressFrb(
+      {required int socksPort,
+      required String stateDir,
+      required String cacheDir,
+      required StartOptions options});
+
+  Future<TorStatus> crateApiTypesTorStatusDefault();
+
+  Future<TorStatus> crateApiTorTorStatusFrb();
 
   Future<void> crateApiTorTorStopFrb();
+
+  Future<bool> crateApiTorTorStopGracefulFrb({required BigInt timeoutMs});
+
+  Stream<LogEntry> crateApiTorTorSubscribeLogsFrb();
+
+  Future<void> crateApiTorTorValidateOnionAddressFrb({required String address});
 }
 
 class RustLibApiImpl extends RustLibApiImplPlatform implements RustLibApi {
@@ -102,13 +200,215 @@ class RustLibApiImpl extends RustLibApiImplPlatform implements RustLibApi {
   });
 
   @override
-  Future<String> crateApiTorTorHelloFrb() {
+  Future<void> crateApiTorTorAddIsolationMappingFrb(
+      {required String contactId, required String token}) {
     return handler.executeNormal(NormalTask(
       callFfi: (port_) {
         final serializer = SseSerializer(generalizedFrbRustBinding);
+        sse_encode_String(contactId, serializer);
+        sse_encode_String(token, serializer);
         pdeCallFfi(generalizedFrbRustBinding, serializer,
             funcId: 1, port: port_);
       },
+      codec: SseCodec(
+        decodeSuccessData: sse_decode_unit,
+        decodeErrorData: sse_decode_AnyhowException,
+      ),
+      constMeta: kCrateApiTorTorAddIsolationMappingFrbConstMeta,
+      argValues: [contactId, token],
+      apiImpl: this,
+    ));
+  }
+
+  TaskConstMeta get kCrateApiTorTorAddIsolationMappingFrbConstMeta =>
+      const TaskConstMeta(
+        debugName: "tor_add_isolation_mapping_frb",
+        argNames: ["contactId", "token"],
+      );
+
+  @override
+  Future<CircuitHandle> crateApiTorTorBuildCircuitFrb(
+      {required String targetHint}) {
+    return handler.executeNormal(NormalTask(
+      callFfi: (port_) {
+        final serializer = SseSerializer(generalizedFrbRustBinding);
+        sse_encode_String(targetHint, serializer);
+        pdeCallFfi(generalizedFrbRustBinding, serializer,
+            funcId: 2, port: port_);
+      },
+      codec: SseCodec(
+        decodeSuccessData: sse_decode_circuit_handle,
+        decodeErrorData: sse_decode_AnyhowException,
+      ),
+      constMeta: kCrateApiTorTorBuildCircuitFrbConstMeta,
+      argValues: [targetHint],
+      apiImpl: this,
+    ));
+  }
+
+  TaskConstMeta get kCrateApiTorTorBuildCircuitFrbConstMeta =>
+      const TaskConstMeta(
+        debugName: "tor_build_circuit_frb",
+        argNames: ["targetHint"],
+      );
+
+  @override
+  Future<void> crateApiTorTorClearCacheFrb({required String cacheDir}) {
+    return handler.executeNormal(NormalTask(
+      callFfi: (port_) {
+        final serializer = SseSerializer(generalizedFrbRustBinding);
+        sse_encode_String(cacheDir, serializer);
+        pdeCallFfi(generalizedFrbRustBinding, serializer,
+            funcId: 3, port: port_);
+      },
+      codec: SseCodec(
+        decodeSuccessData: sse_decode_unit,
+        decodeErrorData: sse_decode_AnyhowException,
+      ),
+      constMeta: kCrateApiTorTorClearCacheFrbConstMeta,
+      argValues: [cacheDir],
+      apiImpl: this,
+    ));
+  }
+
+  TaskConstMeta get kCrateApiTorTorClearCacheFrbConstMeta =>
+      const TaskConstMeta(
+        debugName: "tor_clear_cache_frb",
+        argNames: ["cacheDir"],
+      );
+
+  @override
+  Future<bool> crateApiTorTorCloseIsolatedListenerFrb({required int port}) {
+    return handler.executeNormal(NormalTask(
+      callFfi: (port_) {
+        final serializer = SseSerializer(generalizedFrbRustBinding);
+        sse_encode_u_16(port, serializer);
+        pdeCallFfi(generalizedFrbRustBinding, serializer,
+            funcId: 4, port: port_);
+      },
+      codec: SseCodec(
+        decodeSuccessData: sse_decode_bool,
+        decodeErrorData: null,
+      ),
+      constMeta: kCrateApiTorTorCloseIsolatedListenerFrbConstMeta,
+      argValues: [port],
+      apiImpl: this,
+    ));
+  }
+
+  TaskConstMeta get kCrateApiTorTorCloseIsolatedListenerFrbConstMeta =>
+      const TaskConstMeta(
+        debugName: "tor_close_isolated_listener_frb",
+        argNames: ["port"],
+      );
+
+  @override
+  Future<LocalStream> crateApiTorTorConnectIsolatedFrb(
+      {required String host, required int port, required String isolationKey}) {
+    return handler.executeNormal(NormalTask(
+      callFfi: (port_) {
+        final serializer = SseSerializer(generalizedFrbRustBinding);
+        sse_encode_String(host, serializer);
+        sse_encode_u_16(port, serializer);
+        sse_encode_String(isolationKey, serializer);
+        pdeCallFfi(generalizedFrbRustBinding, serializer,
+            funcId: 5, port: port_);
+      },
+      codec: SseCodec(
+        decodeSuccessData: sse_decode_local_stream,
+        decodeErrorData: sse_decode_AnyhowException,
+      ),
+      constMeta: kCrateApiTorTorConnectIsolatedFrbConstMeta,
+      argValues: [host, port, isolationKey],
+      apiImpl: this,
+    ));
+  }
+
+  TaskConstMeta get kCrateApiTorTorConnectIsolatedFrbConstMeta =>
+      const TaskConstMeta(
+        debugName: "tor_connect_isolated_frb",
+        argNames: ["host", "port", "isolationKey"],
+      );
+
+  @override
+  Future<String> crateApiTorTorEffectiveConfigFrb() {
+    return handler.executeNormal(NormalTask(
+      callFfi: (port_) {
+        final serializer = SseSerializer(generalizedFrbRustBinding);
+        pdeCallFfi(generalizedFrbRustBinding, serializer,
+            funcId: 6, port: port_);
+      },
+      codec: SseCodec(
+        decodeSuccessData: sse_decode_String,
+        decodeErrorData: sse_decode_AnyhowException,
+      ),
+      constMeta: kCrateApiTorTorEffectiveConfigFrbConstMeta,
+      argValues: [],
+      apiImpl: this,
+    ));
+  }
+
+  TaskConstMeta get kCrateApiTorTorEffectiveConfigFrbConstMeta =>
+      const TaskConstMeta(
+        debugName: "tor_effective_config_frb",
+        argNames: [],
+      );
+
+  @override
+  Future<List<String>> crateApiTorTorEnabledFeaturesFrb() {
+    return handler.executeNormal(NormalTask(
+      callFfi: (port_) {
+        final serializer = SseSerializer(generalizedFrbRustBinding);
+        pdeCallFfi(generalizedFrbRustBinding, serializer,
+            funcId: 7, port: port_);
+      },
+      codec: SseCodec(
+        decodeSuccessData: sse_decode_list_String,
+        decodeErrorData: null,
+      ),
+      constMeta: kCrateApiTorTorEnabledFeaturesFrbConstMeta,
+      argValues: [],
+      apiImpl: this,
+    ));
+  }
+
+  TaskConstMeta get kCrateApiTorTorEnabledFeaturesFrbConstMeta =>
+      const TaskConstMeta(
+        debugName: "tor_enabled_features_frb",
+        argNames: [],
+      );
+
+  @override
+  Future<FdUsage> crateApiTorTorFdUsageFrb() {
+    return handler.executeNormal(NormalTask(
+      callFfi: (port_) {
+        final serializer = SseSerializer(generalizedFrbRustBinding);
+        pdeCallFfi(generalizedFrbRustBinding, serializer,
+            funcId: 8, port: port_);
+      },
+      codec: SseCodec(
+        decodeSuccessData: sse_decode_fd_usage,
+        decodeErrorData: sse_decode_AnyhowException,
+      ),
+      constMeta: kCrateApiTorTorFdUsageFrbConstMeta,
+      argValues: [],
+      apiImpl: this,
+    ));
+  }
+
+  TaskConstMeta get kCrateApiTorTorFdUsageFrbConstMeta => const TaskConstMeta(
+        debugName: "tor_fd_usage_frb",
+        argNames: [],
+      );
+
+  @override
+  Future<String> crateApiTorTorHelloFrb() {
+    return handler.executeNormal(NormalTask(
+      callFfi: (port_) {
+        final serializer = SseSerializer(generalizedFrbRustBinding);
+        pdeCallFfi(generalizedFrbRustBinding, serializer,
+            funcId: 9, port: port_);
+      },
       codec: SseCodec(
         decodeSuccessData: sse_decode_String,
         decodeErrorData: null,
@@ -125,327 +425,2156 @@ class RustLibApiImpl extends RustLibApiImplPlatform implements RustLibApi {
       );
 
   @override
-  Future<void> crateApiTorTorSetDormantFrb({required bool softMode}) {
+  Future<List<IsolationMapping>> crateApiTorTorListIsolationMappingsFrb() {
     return handler.executeNormal(NormalTask(
       callFfi: (port_) {
         final serializer = SseSerializer(generalizedFrbRustBinding);
-        sse_encode_bool(softMode, serializer);
         pdeCallFfi(generalizedFrbRustBinding, serializer,
-            funcId: 2, port: port_);
+            funcId: 10, port: port_);
       },
       codec: SseCodec(
-        decodeSuccessData: sse_decode_unit,
+        decodeSuccessData: sse_decode_list_isolation_mapping,
         decodeErrorData: null,
       ),
-      constMeta: kCrateApiTorTorSetDormantFrbConstMeta,
-      argValues: [softMode],
+      constMeta: kCrateApiTorTorListIsolationMappingsFrbConstMeta,
+      argValues: [],
       apiImpl: this,
     ));
   }
 
-  TaskConstMeta get kCrateApiTorTorSetDormantFrbConstMeta =>
+  TaskConstMeta get kCrateApiTorTorListIsolationMappingsFrbConstMeta =>
       const TaskConstMeta(
-        debugName: "tor_set_dormant_frb",
-        argNames: ["softMode"],
+        debugName: "tor_list_isolation_mappings_frb",
+        argNames: [],
       );
 
   @override
-  Future<void> crateApiTorTorSetProxyFrb({ProxyInfo? proxy}) {
+  Future<void> crateApiTorTorNewIdentityFrb() {
     return handler.executeNormal(NormalTask(
       callFfi: (port_) {
         final serializer = SseSerializer(generalizedFrbRustBinding);
-        sse_encode_opt_box_autoadd_proxy_info(proxy, serializer);
         pdeCallFfi(generalizedFrbRustBinding, serializer,
-            funcId: 3, port: port_);
+            funcId: 11, port: port_);
       },
       codec: SseCodec(
         decodeSuccessData: sse_decode_unit,
-        decodeErrorData: null,
+        decodeErrorData: sse_decode_AnyhowException,
       ),
-      constMeta: kCrateApiTorTorSetProxyFrbConstMeta,
-      argValues: [proxy],
+      constMeta: kCrateApiTorTorNewIdentityFrbConstMeta,
+      argValues: [],
       apiImpl: this,
     ));
   }
 
-  TaskConstMeta get kCrateApiTorTorSetProxyFrbConstMeta => const TaskConstMeta(
-        debugName: "tor_set_proxy_frb",
-        argNames: ["proxy"],
+  TaskConstMeta get kCrateApiTorTorNewIdentityFrbConstMeta =>
+      const TaskConstMeta(
+        debugName: "tor_new_identity_frb",
+        argNames: [],
       );
 
   @override
-  Future<int> crateApiTorTorStartFrb(
-      {required int socksPort,
-      required String stateDir,
-      required String cacheDir,
-      required bool useSystemProxy}) {
+  Future<void> crateApiTorTorNotifyNetworkChangedFrb({required bool online}) {
     return handler.executeNormal(NormalTask(
       callFfi: (port_) {
         final serializer = SseSerializer(generalizedFrbRustBinding);
-        sse_encode_u_16(socksPort, serializer);
-        sse_encode_String(stateDir, serializer);
-        sse_encode_String(cacheDir, serializer);
-        sse_encode_bool(useSystemProxy, serializer);
+        sse_encode_bool(online, serializer);
         pdeCallFfi(generalizedFrbRustBinding, serializer,
-            funcId: 4, port: port_);
+            funcId: 12, port: port_);
       },
       codec: SseCodec(
-        decodeSuccessData: sse_decode_u_16,
-        decodeErrorData: sse_decode_AnyhowException,
+        decodeSuccessData: sse_decode_unit,
+        decodeErrorData: null,
       ),
-      constMeta: kCrateApiTorTorStartFrbConstMeta,
-      argValues: [socksPort, stateDir, cacheDir, useSystemProxy],
+      constMeta: kCrateApiTorTorNotifyNetworkChangedFrbConstMeta,
+      argValues: [online],
       apiImpl: this,
     ));
   }
 
-  TaskConstMeta get kCrateApiTorTorStartFrbConstMeta => const TaskConstMeta(
-        debugName: "tor_start_frb",
-        argNames: ["socksPort", "stateDir", "cacheDir", "useSystemProxy"],
+  TaskConstMeta get kCrateApiTorTorNotifyNetworkChangedFrbConstMeta =>
+      const TaskConstMeta(
+        debugName: "tor_notify_network_changed_frb",
+        argNames: ["online"],
       );
 
   @override
-  Future<void> crateApiTorTorStopFrb() {
-    return handler.executeNormal(NormalTask(
+  Stream<BootstrapStatus> crateApiTorTorObserveBootstrapFrb() {
+    final sink = RustStreamSink<BootstrapStatus>();
+    unawaited(handler.executeNormal(NormalTask(
       callFfi: (port_) {
         final serializer = SseSerializer(generalizedFrbRustBinding);
+        sse_encode_StreamSink_bootstrap_status_Sse(sink, serializer);
         pdeCallFfi(generalizedFrbRustBinding, serializer,
-            funcId: 5, port: port_);
+            funcId: 13, port: port_);
       },
       codec: SseCodec(
         decodeSuccessData: sse_decode_unit,
         decodeErrorData: null,
       ),
-      constMeta: kCrateApiTorTorStopFrbConstMeta,
-      argValues: [],
+      constMeta: kCrateApiTorTorObserveBootstrapFrbConstMeta,
+      argValues: [sink],
       apiImpl: this,
-    ));
+    )));
+    return sink.stream;
+  }
+
+  TaskConstMeta get kCrateApiTorTorObserveBootstrapFrbConstMeta =>
+      const TaskConstMeta(
+        debugName: "tor_observe_bootstrap_frb",
+        argNames: ["sink"],
+      );
+
+  @override
+  Stream<ConnectionEvent> crateApiTorTorObserveConnectionsFrb() {
+    final sink = RustStreamSink<ConnectionEvent>();
+    unawaited(handler.executeNormal(NormalTask(
+      callFfi: (port_) {
+        final serializer = SseSerializer(generalizedFrbRustBinding);
+        sse_encode_StreamSink_connection_event_Sse(sink, serializer);
+        pdeCallFfi(generalizedFrbRustBinding, serializer,
+            funcId: 14, port: port_);
+      },
+      codec: SseCodec(
+        decodeSuccessData: sse_decode_unit,
+        decodeErrorData: null,
+      ),
+      constMeta: kCrateApiTorTorObserveConnectionsFrbConstMeta,
+      argValues: [sink],
+      apiImpl: this,
+    )));
+    return sink.stream;
+  }
+
+  TaskConstMeta get kCrateApiTorTorObserveConnectionsFrbConstMeta =>
+      const TaskConstMeta(
+        debugName: "tor_observe_connections_frb",
+        argNames: ["sink"],
+      );
+
+  @override
+  Stream<TorStatus> crateApiTorTorObserveStatusFrb() {
+    final sink = RustStreamSink<TorStatus>();
+    unawaited(handler.executeNormal(NormalTask(
+      callFfi: (port_) {
+        final serializer = SseSerializer(generalizedFrbRustBinding);
+        sse_encode_StreamSink_tor_status_Sse(sink, serializer);
+        pdeCallFfi(generalizedFrbRustBinding, serializer,
+            funcId: 15, port: port_);
+      },
+      codec: SseCodec(
+        decodeSuccessData: sse_decode_unit,
+        decodeErrorData: null,
+      ),
+      constMeta: kCrateApiTorTorObserveStatusFrbConstMeta,
+      argValues: [sink],
+      apiImpl: this,
+    )));
+    return sink.stream;
+  }
+
+  TaskConstMeta get kCrateApiTorTorObserveStatusFrbConstMeta =>
+      const TaskConstMeta(
+        debugName: "tor_observe_status_frb",
+        argNames: ["sink"],
+      );
+
+  @override
+  Future<int> crateApiTorTorOpenIsolatedListenerFrb() {
+    return handler.executeNormal(NormalTask(
+      callFfi: (port_) {
+        final serializer = SseSerializer(generalizedFrbRustBinding);
+        pdeCallFfi(generalizedFrbRustBinding, serializer,
+            funcId: 16, port: port_);
+      },
+      codec: SseCodec(
+        decodeSuccessData: sse_decode_u_16,
+        decodeErrorData: sse_decode_AnyhowException,
+      ),
+      constMeta: kCrateApiTorTorOpenIsolatedListenerFrbConstMeta,
+      argValues: [],
+      apiImpl: this,
+    ));
+  }
+
+  TaskConstMeta get kCrateApiTorTorOpenIsolatedListenerFrbConstMeta =>
+      const TaskConstMeta(
+        debugName: "tor_open_isolated_listener_frb",
+        argNames: [],
+      );
+
+  @override
+  Future<LocalStream> crateApiTorTorOpenStreamOnCircuitFrb(
+      {required CircuitHandle handle,
+      required String host,
+      required int port}) {
+    return handler.executeNormal(NormalTask(
+      callFfi: (port_) {
+        final serializer = SseSerializer(generalizedFrbRustBinding);
+        sse_encode_box_autoadd_circuit_handle(handle, serializer);
+        sse_encode_String(host, serializer);
+        sse_encode_u_16(port, serializer);
+        pdeCallFfi(generalizedFrbRustBinding, serializer,
+            funcId: 17, port: port_);
+      },
+      codec: SseCodec(
+        decodeSuccessData: sse_decode_local_stream,
+        decodeErrorData: sse_decode_AnyhowException,
+      ),
+      constMeta: kCrateApiTorTorOpenStreamOnCircuitFrbConstMeta,
+      argValues: [handle, host, port],
+      apiImpl: this,
+    ));
+  }
+
+  TaskConstMeta get kCrateApiTorTorOpenStreamOnCircuitFrbConstMeta =>
+      const TaskConstMeta(
+        debugName: "tor_open_stream_on_circuit_frb",
+        argNames: ["handle", "host", "port"],
+      );
+
+  @override
+  Future<int> crateApiTorTorRebindSocksFrb({required int newPort}) {
+    return handler.executeNormal(NormalTask(
+      callFfi: (port_) {
+        final serializer = SseSerializer(generalizedFrbRustBinding);
+        sse_encode_u_16(newPort, serializer);
+        pdeCallFfi(generalizedFrbRustBinding, serializer,
+            funcId: 18, port: port_);
+      },
+      codec: SseCodec(
+        decodeSuccessData: sse_decode_u_16,
+        decodeErrorData: sse_decode_AnyhowException,
+      ),
+      constMeta: kCrateApiTorTorRebindSocksFrbConstMeta,
+      argValues: [newPort],
+      apiImpl: this,
+    ));
+  }
+
+  TaskConstMeta get kCrateApiTorTorRebindSocksFrbConstMeta =>
+      const TaskConstMeta(
+        debugName: "tor_rebind_socks_frb",
+        argNames: ["newPort"],
+      );
+
+  @override
+  Future<void> crateApiTorTorReleaseCircuitFrb(
+      {required CircuitHandle handle}) {
+    return handler.executeNormal(NormalTask(
+      callFfi: (port_) {
+        final serializer = SseSerializer(generalizedFrbRustBinding);
+        sse_encode_box_autoadd_circuit_handle(handle, serializer);
+        pdeCallFfi(generalizedFrbRustBinding, serializer,
+            funcId: 19, port: port_);
+      },
+      codec: SseCodec(
+        decodeSuccessData: sse_decode_unit,
+        decodeErrorData: null,
+      ),
+      constMeta: kCrateApiTorTorReleaseCircuitFrbConstMeta,
+      argValues: [handle],
+      apiImpl: this,
+    ));
+  }
+
+  TaskConstMeta get kCrateApiTorTorReleaseCircuitFrbConstMeta =>
+      const TaskConstMeta(
+        debugName: "tor_release_circuit_frb",
+        argNames: ["handle"],
+      );
+
+  @override
+  Future<bool> crateApiTorTorRemoveIsolationMappingFrb(
+      {required String contactId}) {
+    return handler.executeNormal(NormalTask(
+      callFfi: (port_) {
+        final serializer = SseSerializer(generalizedFrbRustBinding);
+        sse_encode_String(contactId, serializer);
+        pdeCallFfi(generalizedFrbRustBinding, serializer,
+            funcId: 20, port: port_);
+      },
+      codec: SseCodec(
+        decodeSuccessData: sse_decode_bool,
+        decodeErrorData: null,
+      ),
+      constMeta: kCrateApiTorTorRemoveIsolationMappingFrbConstMeta,
+      argValues: [contactId],
+      apiImpl: this,
+    ));
+  }
+
+  TaskConstMeta get kCrateApiTorTorRemoveIsolationMappingFrbConstMeta =>
+      const TaskConstMeta(
+        debugName: "tor_remove_isolation_mapping_frb",
+        argNames: ["contactId"],
+      );
+
+  @override
+  Future<void> crateApiTorTorResetConsensusFrb({required String cacheDir}) {
+    return handler.executeNormal(NormalTask(
+      callFfi: (port_) {
+        final serializer = SseSerializer(generalizedFrbRustBinding);
+        sse_encode_String(cacheDir, serializer);
+        pdeCallFfi(generalizedFrbRustBinding, serializer,
+            funcId: 21, port: port_);
+      },
+      codec: SseCodec(
+        decodeSuccessData: sse_decode_unit,
+        decodeErrorData: sse_decode_AnyhowException,
+      ),
+      constMeta: kCrateApiTorTorResetConsensusFrbConstMeta,
+      argValues: [cacheDir],
+      apiImpl: this,
+    ));
+  }
+
+  TaskConstMeta get kCrateApiTorTorResetConsensusFrbConstMeta =>
+      const TaskConstMeta(
+        debugName: "tor_reset_consensus_frb",
+        argNames: ["cacheDir"],
+      );
+
+  @override
+  Future<void> crateApiTorTorResetGuardsFrb({required String stateDir}) {
+    return handler.executeNormal(NormalTask(
+      callFfi: (port_) {
+        final serializer = SseSerializer(generalizedFrbRustBinding);
+        sse_encode_String(stateDir, serializer);
+        pdeCallFfi(generalizedFrbRustBinding, serializer,
+            funcId: 22, port: port_);
+      },
+      codec: SseCodec(
+        decodeSuccessData: sse_decode_unit,
+        decodeErrorData: sse_decode_AnyhowException,
+      ),
+      constMeta: kCrateApiTorTorResetGuardsFrbConstMeta,
+      argValues: [stateDir],
+      apiImpl: this,
+    ));
+  }
+
+  TaskConstMeta get kCrateApiTorTorResetGuardsFrbConstMeta =>
+      const TaskConstMeta(
+        debugName: "tor_reset_guards_frb",
+        argNames: ["stateDir"],
+      );
+
+  @override
+  Future<List<String>> crateApiTorTorResolveFrb({required String name}) {
+    return handler.executeNormal(NormalTask(
+      callFfi: (port_) {
+        final serializer = SseSerializer(generalizedFrbRustBinding);
+        sse_encode_String(name, serializer);
+        pdeCallFfi(generalizedFrbRustBinding, serializer,
+            funcId: 23, port: port_);
+      },
+      codec: SseCodec(
+        decodeSuccessData: sse_decode_list_String,
+        decodeErrorData: sse_decode_AnyhowException,
+      ),
+      constMeta: kCrateApiTorTorResolveFrbConstMeta,
+      argValues: [name],
+      apiImpl: this,
+    ));
+  }
+
+  TaskConstMeta get kCrateApiTorTorResolveFrbConstMeta => const TaskConstMeta(
+        debugName: "tor_resolve_frb",
+        argNames: ["name"],
+      );
+
+  @override
+  Future<bool> crateApiTorTorSeedDirectoryCacheFrb(
+      {required String bundleDir, required String cacheDir}) {
+    return handler.executeNormal(NormalTask(
+      callFfi: (port_) {
+        final serializer = SseSerializer(generalizedFrbRustBinding);
+        sse_encode_String(bundleDir, serializer);
+        sse_encode_String(cacheDir, serializer);
+        pdeCallFfi(generalizedFrbRustBinding, serializer,
+            funcId: 24, port: port_);
+      },
+      codec: SseCodec(
+        decodeSuccessData: sse_decode_bool,
+        decodeErrorData: sse_decode_AnyhowException,
+      ),
+      constMeta: kCrateApiTorTorSeedDirectoryCacheFrbConstMeta,
+      argValues: [bundleDir, cacheDir],
+      apiImpl: this,
+    ));
+  }
+
+  TaskConstMeta get kCrateApiTorTorSeedDirectoryCacheFrbConstMeta =>
+      const TaskConstMeta(
+        debugName: "tor_seed_directory_cache_frb",
+        argNames: ["bundleDir", "cacheDir"],
+      );
+
+  @override
+  Future<void> crateApiTorTorSetAutoRestartFrb({required bool enabled}) {
+    return handler.executeNormal(NormalTask(
+      callFfi: (port_) {
+        final serializer = SseSerializer(generalizedFrbRustBinding);
+        sse_encode_bool(enabled, serializer);
+        pdeCallFfi(generalizedFrbRustBinding, serializer,
+            funcId: 25, port: port_);
+      },
+      codec: SseCodec(
+        decodeSuccessData: sse_decode_unit,
+        decodeErrorData: null,
+      ),
+      constMeta: kCrateApiTorTorSetAutoRestartFrbConstMeta,
+      argValues: [enabled],
+      apiImpl: this,
+    ));
+  }
+
+  TaskConstMeta get kCrateApiTorTorSetAutoRestartFrbConstMeta =>
+      const TaskConstMeta(
+        debugName: "tor_set_auto_restart_frb",
+        argNames: ["enabled"],
+      );
+
+  @override
+  Future<void> crateApiTorTorSetDormantFrb({required bool softMode}) {
+    return handler.executeNormal(NormalTask(
+      callFfi: (port_) {
+        final serializer = SseSerializer(generalizedFrbRustBinding);
+        sse_encode_bool(softMode, serializer);
+        pdeCallFfi(generalizedFrbRustBinding, serializer,
+            funcId: 26, port: port_);
+      },
+      codec: SseCodec(
+        decodeSuccessData: sse_decode_unit,
+        decodeErrorData: null,
+      ),
+      constMeta: kCrateApiTorTorSetDormantFrbConstMeta,
+      argValues: [softMode],
+      apiImpl: this,
+    ));
+  }
+
+  TaskConstMeta get kCrateApiTorTorSetDormantFrbConstMeta =>
+      const TaskConstMeta(
+        debugName: "tor_set_dormant_frb",
+        argNames: ["softMode"],
+      );
+
+  @override
+  Future<void> crateApiTorTorSetExitCountriesFrb(
+      {required List<String> countryCodes}) {
+    return handler.executeNormal(NormalTask(
+      callFfi: (port_) {
+        final serializer = SseSerializer(generalizedFrbRustBinding);
+        sse_encode_list_String(countryCodes, serializer);
+        pdeCallFfi(generalizedFrbRustBinding, serializer,
+            funcId: 27, port: port_);
+      },
+      codec: SseCodec(
+        decodeSuccessData: sse_decode_unit,
+        decodeErrorData: sse_decode_AnyhowException,
+      ),
+      constMeta: kCrateApiTorTorSetExitCountriesFrbConstMeta,
+      argValues: [countryCodes],
+      apiImpl: this,
+    ));
+  }
+
+  TaskConstMeta get kCrateApiTorTorSetExitCountriesFrbConstMeta =>
+      const TaskConstMeta(
+        debugName: "tor_set_exit_countries_frb",
+        argNames: ["countryCodes"],
+      );
+
+  @override
+  Future<void> crateApiTorTorSetMeteredFrb({required bool metered}) {
+    return handler.executeNormal(NormalTask(
+      callFfi: (port_) {
+        final serializer = SseSerializer(generalizedFrbRustBinding);
+        sse_encode_bool(metered, serializer);
+        pdeCallFfi(generalizedFrbRustBinding, serializer,
+            funcId: 28, port: port_);
+      },
+      codec: SseCodec(
+        decodeSuccessData: sse_decode_unit,
+        decodeErrorData: null,
+      ),
+      constMeta: kCrateApiTorTorSetMeteredFrbConstMeta,
+      argValues: [metered],
+      apiImpl: this,
+    ));
+  }
+
+  TaskConstMeta get kCrateApiTorTorSetMeteredFrbConstMeta =>
+      const TaskConstMeta(
+        debugName: "tor_set_metered_frb",
+        argNames: ["metered"],
+      );
+
+  @override
+  Future<void> crateApiTorTorSetProxiesFrb(
+      {required List<WeightedProxy> proxies}) {
+    return handler.executeNormal(NormalTask(
+      callFfi: (port_) {
+        final serializer = SseSerializer(generalizedFrbRustBinding);
+        sse_encode_list_weighted_proxy(proxies, serializer);
+        pdeCallFfi(generalizedFrbRustBinding, serializer,
+            funcId: 29, port: port_);
+      },
+      codec: SseCodec(
+        decodeSuccessData: sse_decode_unit,
+        decodeErrorData: sse_decode_AnyhowException,
+      ),
+      constMeta: kCrateApiTorTorSetProxiesFrbConstMeta,
+      argValues: [proxies],
+      apiImpl: this,
+    ));
+  }
+
+  TaskConstMeta get kCrateApiTorTorSetProxiesFrbConstMeta =>
+      const TaskConstMeta(
+        debugName: "tor_set_proxies_frb",
+        argNames: ["proxies"],
+      );
+
+  @override
+  Future<void> crateApiTorTorSetProxyChainFrb({required List<ProxyInfo> hops}) {
+    return handler.executeNormal(NormalTask(
+      callFfi: (port_) {
+        final serializer = SseSerializer(generalizedFrbRustBinding);
+        sse_encode_list_proxy_info(hops, serializer);
+        pdeCallFfi(generalizedFrbRustBinding, serializer,
+            funcId: 30, port: port_);
+      },
+      codec: SseCodec(
+        decodeSuccessData: sse_decode_unit,
+        decodeErrorData: sse_decode_AnyhowException,
+      ),
+      constMeta: kCrateApiTorTorSetProxyChainFrbConstMeta,
+      argValues: [hops],
+      apiImpl: this,
+    ));
+  }
+
+  TaskConstMeta get kCrateApiTorTorSetProxyChainFrbConstMeta =>
+      const TaskConstMeta(
+        debugName: "tor_set_proxy_chain_frb",
+        argNames: ["hops"],
+      );
+
+  @override
+  Future<void> crateApiTorTorSetProxyFailoverFrb(
+      {required List<ProxyInfo> proxies,
+      int? maxAttempts,
+      required bool sticky}) {
+    return handler.executeNormal(NormalTask(
+      callFfi: (port_) {
+        final serializer = SseSerializer(generalizedFrbRustBinding);
+        sse_encode_list_proxy_info(proxies, serializer);
+        sse_encode_opt_box_autoadd_u_32(maxAttempts, serializer);
+        sse_encode_bool(sticky, serializer);
+        pdeCallFfi(generalizedFrbRustBinding, serializer,
+            funcId: 31, port: port_);
+      },
+      codec: SseCodec(
+        decodeSuccessData: sse_decode_unit,
+        decodeErrorData: sse_decode_AnyhowException,
+      ),
+      constMeta: kCrateApiTorTorSetProxyFailoverFrbConstMeta,
+      argValues: [proxies, maxAttempts, sticky],
+      apiImpl: this,
+    ));
+  }
+
+  TaskConstMeta get kCrateApiTorTorSetProxyFailoverFrbConstMeta =>
+      const TaskConstMeta(
+        debugName: "tor_set_proxy_failover_frb",
+        argNames: ["proxies", "maxAttempts", "sticky"],
+      );
+
+  @override
+  Future<void> crateApiTorTorSetProxyFrb({ProxyInfo? proxy}) {
+    return handler.executeNormal(NormalTask(
+      callFfi: (port_) {
+        final serializer = SseSerializer(generalizedFrbRustBinding);
+        sse_encode_opt_box_autoadd_proxy_info(proxy, serializer);
+        pdeCallFfi(generalizedFrbRustBinding, serializer,
+            funcId: 32, port: port_);
+      },
+      codec: SseCodec(
+        decodeSuccessData: sse_decode_unit,
+        decodeErrorData: sse_decode_AnyhowException,
+      ),
+      constMeta: kCrateApiTorTorSetProxyFrbConstMeta,
+      argValues: [proxy],
+      apiImpl: this,
+    ));
+  }
+
+  TaskConstMeta get kCrateApiTorTorSetProxyFrbConstMeta => const TaskConstMeta(
+        debugName: "tor_set_proxy_frb",
+        argNames: ["proxy"],
+      );
+
+  @override
+  Future<void> crateApiTorTorSetTargetPolicyFrb(
+      {required List<String> allow, required List<String> deny}) {
+    return handler.executeNormal(NormalTask(
+      callFfi: (port_) {
+        final serializer = SseSerializer(generalizedFrbRustBinding);
+        sse_encode_list_String(allow, serializer);
+        sse_encode_list_String(deny, serializer);
+        pdeCallFfi(generalizedFrbRustBinding, serializer,
+            funcId: 33, port: port_);
+      },
+      codec: SseCodec(
+        decodeSuccessData: sse_decode_unit,
+        decodeErrorData: sse_decode_AnyhowException,
+      ),
+      constMeta: kCrateApiTorTorSetTargetPolicyFrbConstMeta,
+      argValues: [allow, deny],
+      apiImpl: this,
+    ));
+  }
+
+  TaskConstMeta get kCrateApiTorTorSetTargetPolicyFrbConstMeta =>
+      const TaskConstMeta(
+        debugName: "tor_set_target_policy_frb",
+        argNames: ["allow", "deny"],
+      );
+
+  @override
+  Future<void> crateApiTorTorSetTargetRedactionFrb(
+      {required TargetRedaction redaction}) {
+    return handler.executeNormal(NormalTask(
+      callFfi: (port_) {
+        final serializer = SseSerializer(generalizedFrbRustBinding);
+        sse_encode_target_redaction(redaction, serializer);
+        pdeCallFfi(generalizedFrbRustBinding, serializer,
+            funcId: 34, port: port_);
+      },
+      codec: SseCodec(
+        decodeSuccessData: sse_decode_unit,
+        decodeErrorData: null,
+      ),
+      constMeta: kCrateApiTorTorSetTargetRedactionFrbConstMeta,
+      argValues: [redaction],
+      apiImpl: this,
+    ));
+  }
+
+  TaskConstMeta get kCrateApiTorTorSetTargetRedactionFrbConstMeta =>
+      const TaskConstMeta(
+        debugName: "tor_set_target_redaction_frb",
+        argNames: ["redaction"],
+      );
+
+  @override
+  Future<void> crateApiTorTorSetUseProxyFrb({required bool useProxy}) {
+    return handler.executeNormal(NormalTask(
+      callFfi: (port_) {
+        final serializer = SseSerializer(generalizedFrbRustBinding);
+        sse_encode_bool(useProxy, serializer);
+        pdeCallFfi(generalizedFrbRustBinding, serializer,
+            funcId: 35, port: port_);
+      },
+      codec: SseCodec(
+        decodeSuccessData: sse_decode_unit,
+        decodeErrorData: null,
+      ),
+      constMeta: kCrateApiTorTorSetUseProxyFrbConstMeta,
+      argValues: [useProxy],
+      apiImpl: this,
+    ));
+  }
+
+  TaskConstMeta get kCrateApiTorTorSetUseProxyFrbConstMeta =>
+      const TaskConstMeta(
+        debugName: "tor_set_use_proxy_frb",
+        argNames: ["useProxy"],
+      );
+
+  @override
+  Future<void> crateApiTorTorStartClientOnlyFrb(
+      {required String stateDir,
+      required String cacheDir,
+      required StartOptions options}) {
+    return handler.executeNormal(NormalTask(
+      callFfi: (port_) {
+        final serializer = SseSerializer(generalizedFrbRustBinding);
+        sse_encode_String(stateDir, serializer);
+        sse_encode_String(cacheDir, serializer);
+        sse_encode_box_autoadd_start_options(options, serializer);
+        pdeCallFfi(generalizedFrbRustBinding, serializer,
+            funcId: 36, port: port_);
+      },
+      codec: SseCodec(
+        decodeSuccessData: sse_decode_unit,
+        decodeErrorData: sse_decode_AnyhowException,
+      ),
+      constMeta: kCrateApiTorTorStartClientOnlyFrbConstMeta,
+      argValues: [stateDir, cacheDir, options],
+      apiImpl: this,
+    ));
+  }
+
+  TaskConstMeta get kCrateApiTorTorStartClientOnlyFrbConstMeta =>
+      const TaskConstMeta(
+        debugName: "tor_start_client_only_frb",
+        argNames: ["stateDir", "cacheDir", "options"],
+      );
+
+  @override
+  Future<int> crateApiTorTorStartFrb(
+      {required int socksPort,
+      required String stateDir,
+      required String cacheDir,
+      required StartOptions options}) {
+    return handler.executeNormal(NormalTask(
+      callFfi: (port_) {
+        final serializer = SseSerializer(generalizedFrbRustBinding);
+        sse_encode_u_16(socksPort, serializer);
+        sse_encode_String(stateDir, serializer);
+        sse_encode_String(cacheDir, serializer);
+        sse_encode_box_autoadd_start_options(options, serializer);
+        pdeCallFfi(generalizedFrbRustBinding, serializer,
+            funcId: 37, port: port_);
+      },
+      codec: SseCodec(
+        decodeSuccessData: sse_decode_u_16,
+        decodeErrorData: sse_decode_AnyhowException,
+      ),
+      constMeta: kCrateApiTorTorStartFrbConstMeta,
+      argValues: [socksPort, stateDir, cacheDir, options],
+      apiImpl: this,
+    ));
+  }
+
+  TaskConstMeta get kCrateApiTorTorStartFrbConstMeta => const TaskConstMeta(
+        debugName: "tor_start_frb",
+        argNames: ["socksPort", "stateDir", "cacheDir", "options"],
+      );
+
+  @override
+  Stream<BootstrapStatus> crateApiTorTorStartWithProgressFrb(
+      {required int socksPort,
+      required String stateDir,
+      required String cacheDir,
+      required StartOptions options}) {
+    final sink = RustStreamSink<BootstrapStatus>();
+    unawaited(handler.executeNormal(NormalTask(
+      callFfi: (port_) {
+        final serializer = SseSerializer(generalizedFrbRustBinding);
+        sse_encode_StreamSink_bootstrap_status_Sse(sink, serializer);
+        sse_encode_u_16(socksPort, serializer);
+        sse_encode_String(stateDir, serializer);
+        sse_encode_String(cacheDir, serializer);
+        sse_encode_box_autoadd_start_options(options, serializer);
+        pdeCallFfi(generalizedFrbRustBinding, serializer,
+            funcId: 38, port: port_);
+      },
+      codec: SseCodec(
+        decodeSuccessData: sse_decode_unit,
+        decodeErrorData: sse_decode_AnyhowException,
+      ),
+      constMeta: kCrateApiTorTorStartWithProgressFrbConstMeta,
+      argValues: [sink, socksPort, stateDir, cacheDir, options],
+      apiImpl: this,
+    )));
+    return sink.stream;
+  }
+
+  TaskConstMeta get kCrateApiTorTorStartWithProgressFrbConstMeta =>
+      const TaskConstMeta(
+        debugName: "tor_start_with_progress_frb",
+        argNames: ["sink", "socksPort", "stateDir", "cacheDir", "options"],
+      );
+
+  @override
+  Future<TorStatus> crateApiTypesTorStatusDefault() {
+    return handler.executeNormal(NormalTask(
+      callFfi: (port_) {
+        final serializer = SseSerializer(generalizedFrbRustBinding);
+        pdeCallFfi(generalizedFrbRustBinding, serializer,
+            funcId: 39, port: port_);
+      },
+      codec: SseCodec(
+        decodeSuccessData: sse_decode_tor_status,
+        decodeErrorData: null,
+      ),
+      constMeta: kCrateApiTypesTorStatusDefaultConstMeta,
+      argValues: [],
+      apiImpl: this,
+    ));
+  }
+
+  TaskConstMeta get kCrateApiTypesTorStatusDefaultConstMeta =>
+      const TaskConstMeta(
+        debugName: "tor_status_default",
+        argNames: [],
+      );
+
+  @override
+  Future<TorStatus> crateApiTorTorStatusFrb() {
+    return handler.executeNormal(NormalTask(
+      callFfi: (port_) {
+        final serializer = SseSerializer(generalizedFrbRustBinding);
+        pdeCallFfi(generalizedFrbRustBinding, serializer,
+            funcId: 40, port: port_);
+      },
+      codec: SseCodec(
+        decodeSuccessData: sse_decode_tor_status,
+        decodeErrorData: null,
+      ),
+      constMeta: kCrateApiTorTorStatusFrbConstMeta,
+      argValues: [],
+      apiImpl: this,
+    ));
+  }
+
+  TaskConstMeta get kCrateApiTorTorStatusFrbConstMeta => const TaskConstMeta(
+        debugName: "tor_status_frb",
+        argNames: [],
+      );
+
+  @override
+  Future<void> crateApiTorTorStopFrb() {
+    return handler.executeNormal(NormalTask(
+      callFfi: (port_) {
+        final serializer = SseSerializer(generalizedFrbRustBinding);
+        pdeCallFfi(generalizedFrbRustBinding, serializer,
+            funcId: 41, port: port_);
+      },
+      codec: SseCodec(
+        decodeSuccessData: sse_decode_unit,
+        decodeErrorData: null,
+      ),
+      constMeta: kCrateApiTorTorStopFrbConstMeta,
+      argValues: [],
+      apiImpl: this,
+    ));
+  }
+
+  TaskConstMeta get kCrateApiTorTorStopFrbConstMeta => const TaskConstMeta(
+        debugName: "tor_stop_frb",
+        argNames: [],
+      );
+
+  @override
+  Future<bool> crateApiTorTorStopGracefulFrb({required BigInt timeoutMs}) {
+    return handler.executeNormal(NormalTask(
+      callFfi: (port_) {
+        final serializer = SseSerializer(generalizedFrbRustBinding);
+        sse_encode_u_64(timeoutMs, serializer);
+        pdeCallFfi(generalizedFrbRustBinding, serializer,
+            funcId: 42, port: port_);
+      },
+      codec: SseCodec(
+        decodeSuccessData: sse_decode_bool,
+        decodeErrorData: null,
+      ),
+      constMeta: kCrateApiTorTorStopGracefulFrbConstMeta,
+      argValues: [timeoutMs],
+      apiImpl: this,
+    ));
+  }
+
+  TaskConstMeta get kCrateApiTorTorStopGracefulFrbConstMeta =>
+      const TaskConstMeta(
+        debugName: "tor_stop_graceful_frb",
+        argNames: ["timeoutMs"],
+      );
+
+  @override
+  Stream<LogEntry> crateApiTorTorSubscribeLogsFrb() {
+    final sink = RustStreamSink<LogEntry>();
+    unawaited(handler.executeNormal(NormalTask(
+      callFfi: (port_) {
+        final serializer = SseSerializer(generalizedFrbRustBinding);
+        sse_encode_StreamSink_log_entry_Sse(sink, serializer);
+        pdeCallFfi(generalizedFrbRustBinding, serializer,
+            funcId: 43, port: port_);
+      },
+      codec: SseCodec(
+        decodeSuccessData: sse_decode_unit,
+        decodeErrorData: null,
+      ),
+      constMeta: kCrateApiTorTorSubscribeLogsFrbConstMeta,
+      argValues: [sink],
+      apiImpl: this,
+    )));
+    return sink.stream;
+  }
+
+  TaskConstMeta get kCrateApiTorTorSubscribeLogsFrbConstMeta =>
+      const TaskConstMeta(
+        debugName: "tor_subscribe_logs_frb",
+        argNames: ["sink"],
+      );
+
+  @override
+  Future<void> crateApiTorTorValidateOnionAddressFrb(
+      {required String address}) {
+    return handler.executeNormal(NormalTask(
+      callFfi: (port_) {
+        final serializer = SseSerializer(generalizedFrbRustBinding);
+        sse_encode_String(address, serializer);
+        pdeCallFfi(generalizedFrbRustBinding, serializer,
+            funcId: 44, port: port_);
+      },
+      codec: SseCodec(
+        decodeSuccessData: sse_decode_unit,
+        decodeErrorData: sse_decode_AnyhowException,
+      ),
+      constMeta: kCrateApiTorTorValidateOnionAddressFrbConstMeta,
+      argValues: [address],
+      apiImpl: this,
+    ));
+  }
+
+  TaskConstMeta get kCrateApiTorTorValidateOnionAddressFrbConstMeta =>
+      const TaskConstMeta(
+        debugName: "tor_validate_onion_address_frb",
+        argNames: ["address"],
+      );
+
+  @protected
+  AnyhowException dco_decode_AnyhowException(dynamic raw) {
+    // Codec=Dco (DartCObject based), see doc to use other codecs
+    return AnyhowException(raw as String);
+  }
+
+  @protected
+  RustStreamSink<BootstrapStatus> dco_decode_StreamSink_bootstrap_status_Sse(
+      dynamic raw) {
+    // Codec=Dco (DartCObject based), see doc to use other codecs
+    throw UnimplementedError();
+  }
+
+  @protected
+  RustStreamSink<ConnectionEvent> dco_decode_StreamSink_connection_event_Sse(
+      dynamic raw) {
+    // Codec=Dco (DartCObject based), see doc to use other codecs
+    throw UnimplementedError();
+  }
+
+  @protected
+  RustStreamSink<LogEntry> dco_decode_StreamSink_log_entry_Sse(dynamic raw) {
+    // Codec=Dco (DartCObject based), see doc to use other codecs
+    throw UnimplementedError();
+  }
+
+  @protected
+  RustStreamSink<TorStatus> dco_decode_StreamSink_tor_status_Sse(dynamic raw) {
+    // Codec=Dco (DartCObject based), see doc to use other codecs
+    throw UnimplementedError();
+  }
+
+  @protected
+  String dco_decode_String(dynamic raw) {
+    // Codec=Dco (DartCObject based), see doc to use other codecs
+    return raw as String;
+  }
+
+  @protected
+  bool dco_decode_bool(dynamic raw) {
+    // Codec=Dco (DartCObject based), see doc to use other codecs
+    return raw as bool;
+  }
+
+  @protected
+  BootstrapStalled dco_decode_bootstrap_stalled(dynamic raw) {
+    // Codec=Dco (DartCObject based), see doc to use other codecs
+    final arr = raw as List<dynamic>;
+    if (arr.length != 2)
+      throw Exception('unexpected arr length: expect 2 but see ${arr.length}');
+    return BootstrapStalled(
+      lastPhase: dco_decode_String(arr[0]),
+      suggestion: dco_decode_String(arr[1]),
+    );
+  }
+
+  @protected
+  BootstrapStatus dco_decode_bootstrap_status(dynamic raw) {
+    // Codec=Dco (DartCObject based), see doc to use other codecs
+    final arr = raw as List<dynamic>;
+    if (arr.length != 2)
+      throw Exception('unexpected arr length: expect 2 but see ${arr.length}');
+    return BootstrapStatus(
+      fraction: dco_decode_f_32(arr[0]),
+      summary: dco_decode_String(arr[1]),
+    );
+  }
+
+  @protected
+  BootstrapStalled dco_decode_box_autoadd_bootstrap_stalled(dynamic raw) {
+    // Codec=Dco (DartCObject based), see doc to use other codecs
+    return dco_decode_bootstrap_stalled(raw);
+  }
+
+  @protected
+  CircuitHandle dco_decode_box_autoadd_circuit_handle(dynamic raw) {
+    // Codec=Dco (DartCObject based), see doc to use other codecs
+    return dco_decode_circuit_handle(raw);
+  }
+
+  @protected
+  ProxyInfo dco_decode_box_autoadd_proxy_info(dynamic raw) {
+    // Codec=Dco (DartCObject based), see doc to use other codecs
+    return dco_decode_proxy_info(raw);
+  }
+
+  @protected
+  SnowflakeConfig dco_decode_box_autoadd_snowflake_config(dynamic raw) {
+    // Codec=Dco (DartCObject based), see doc to use other codecs
+    return dco_decode_snowflake_config(raw);
+  }
+
+  @protected
+  StartOptions dco_decode_box_autoadd_start_options(dynamic raw) {
+    // Codec=Dco (DartCObject based), see doc to use other codecs
+    return dco_decode_start_options(raw);
+  }
+
+  @protected
+  int dco_decode_box_autoadd_u_16(dynamic raw) {
+    // Codec=Dco (DartCObject based), see doc to use other codecs
+    return raw as int;
+  }
+
+  @protected
+  int dco_decode_box_autoadd_u_32(dynamic raw) {
+    // Codec=Dco (DartCObject based), see doc to use other codecs
+    return raw as int;
+  }
+
+  @protected
+  int dco_decode_box_autoadd_u_8(dynamic raw) {
+    // Codec=Dco (DartCObject based), see doc to use other codecs
+    return raw as int;
+  }
+
+  @protected
+  BridgeLine dco_decode_bridge_line(dynamic raw) {
+    // Codec=Dco (DartCObject based), see doc to use other codecs
+    final arr = raw as List<dynamic>;
+    if (arr.length != 1)
+      throw Exception('unexpected arr length: expect 1 but see ${arr.length}');
+    return BridgeLine(
+      line: dco_decode_String(arr[0]),
+    );
+  }
+
+  @protected
+  CircuitHandle dco_decode_circuit_handle(dynamic raw) {
+    // Codec=Dco (DartCObject based), see doc to use other codecs
+    final arr = raw as List<dynamic>;
+    if (arr.length != 1)
+      throw Exception('unexpected arr length: expect 1 but see ${arr.length}');
+    return CircuitHandle(
+      id: dco_decode_u_64(arr[0]),
+    );
+  }
+
+  @protected
+  ConnectionEvent dco_decode_connection_event(dynamic raw) {
+    // Codec=Dco (DartCObject based), see doc to use other codecs
+    final arr = raw as List<dynamic>;
+    if (arr.length != 5)
+      throw Exception('unexpected arr length: expect 5 but see ${arr.length}');
+    return ConnectionEvent(
+      stage: dco_decode_connection_stage(arr[0]),
+      target: dco_decode_String(arr[1]),
+      via: dco_decode_String(arr[2]),
+      elapsedMs: dco_decode_opt_box_autoadd_u_32(arr[3]),
+      error: dco_decode_opt_String(arr[4]),
+    );
+  }
+
+  @protected
+  ConnectionStage dco_decode_connection_stage(dynamic raw) {
+    // Codec=Dco (DartCObject based), see doc to use other codecs
+    return ConnectionStage.values[raw as int];
+  }
+
+  @protected
+  DirectoryMirror dco_decode_directory_mirror(dynamic raw) {
+    // Codec=Dco (DartCObject based), see doc to use other codecs
+    final arr = raw as List<dynamic>;
+    if (arr.length != 3)
+      throw Exception('unexpected arr length: expect 3 but see ${arr.length}');
+    return DirectoryMirror(
+      rsaIdentity: dco_decode_String(arr[0]),
+      edIdentity: dco_decode_String(arr[1]),
+      addresses: dco_decode_list_String(arr[2]),
+    );
+  }
+
+  @protected
+  double dco_decode_f_32(dynamic raw) {
+    // Codec=Dco (DartCObject based), see doc to use other codecs
+    return raw as double;
+  }
+
+  @protected
+  FdUsage dco_decode_fd_usage(dynamic raw) {
+    // Codec=Dco (DartCObject based), see doc to use other codecs
+    final arr = raw as List<dynamic>;
+    if (arr.length != 3)
+      throw Exception('unexpected arr length: expect 3 but see ${arr.length}');
+    return FdUsage(
+      open: dco_decode_u_64(arr[0]),
+      softLimit: dco_decode_u_64(arr[1]),
+      hardLimit: dco_decode_u_64(arr[2]),
+    );
+  }
+
+  @protected
+  int dco_decode_i_32(dynamic raw) {
+    // Codec=Dco (DartCObject based), see doc to use other codecs
+    return raw as int;
+  }
+
+  @protected
+  IsolationMapping dco_decode_isolation_mapping(dynamic raw) {
+    // Codec=Dco (DartCObject based), see doc to use other codecs
+    final arr = raw as List<dynamic>;
+    if (arr.length != 2)
+      throw Exception('unexpected arr length: expect 2 but see ${arr.length}');
+    return IsolationMapping(
+      contactId: dco_decode_String(arr[0]),
+      token: dco_decode_String(arr[1]),
+    );
+  }
+
+  @protected
+  List<String> dco_decode_list_String(dynamic raw) {
+    // Codec=Dco (DartCObject based), see doc to use other codecs
+    return (raw as List<dynamic>).map(dco_decode_String).toList();
+  }
+
+  @protected
+  List<BridgeLine> dco_decode_list_bridge_line(dynamic raw) {
+    // Codec=Dco (DartCObject based), see doc to use other codecs
+    return (raw as List<dynamic>).map(dco_decode_bridge_line).toList();
+  }
+
+  @protected
+  List<DirectoryMirror> dco_decode_list_directory_mirror(dynamic raw) {
+    // Codec=Dco (DartCObject based), see doc to use other codecs
+    return (raw as List<dynamic>).map(dco_decode_directory_mirror).toList();
+  }
+
+  @protected
+  List<IsolationMapping> dco_decode_list_isolation_mapping(dynamic raw) {
+    // Codec=Dco (DartCObject based), see doc to use other codecs
+    return (raw as List<dynamic>).map(dco_decode_isolation_mapping).toList();
+  }
+
+  @protected
+  Uint8List dco_decode_list_prim_u_8_strict(dynamic raw) {
+    // Codec=Dco (DartCObject based), see doc to use other codecs
+    return raw as Uint8List;
+  }
+
+  @protected
+  List<ProxyInfo> dco_decode_list_proxy_info(dynamic raw) {
+    // Codec=Dco (DartCObject based), see doc to use other codecs
+    return (raw as List<dynamic>).map(dco_decode_proxy_info).toList();
+  }
+
+  @protected
+  List<WeightedProxy> dco_decode_list_weighted_proxy(dynamic raw) {
+    // Codec=Dco (DartCObject based), see doc to use other codecs
+    return (raw as List<dynamic>).map(dco_decode_weighted_proxy).toList();
+  }
+
+  @protected
+  LocalStream dco_decode_local_stream(dynamic raw) {
+    // Codec=Dco (DartCObject based), see doc to use other codecs
+    final arr = raw as List<dynamic>;
+    if (arr.length != 2)
+      throw Exception('unexpected arr length: expect 2 but see ${arr.length}');
+    return LocalStream(
+      port: dco_decode_u_16(arr[0]),
+      token: dco_decode_String(arr[1]),
+    );
+  }
+
+  @protected
+  LogEntry dco_decode_log_entry(dynamic raw) {
+    // Codec=Dco (DartCObject based), see doc to use other codecs
+    final arr = raw as List<dynamic>;
+    if (arr.length != 3)
+      throw Exception('unexpected arr length: expect 3 but see ${arr.length}');
+    return LogEntry(
+      level: dco_decode_String(arr[0]),
+      target: dco_decode_String(arr[1]),
+      message: dco_decode_String(arr[2]),
+    );
+  }
+
+  @protected
+  String? dco_decode_opt_String(dynamic raw) {
+    // Codec=Dco (DartCObject based), see doc to use other codecs
+    return raw == null ? null : dco_decode_String(raw);
+  }
+
+  @protected
+  BootstrapStalled? dco_decode_opt_box_autoadd_bootstrap_stalled(dynamic raw) {
+    // Codec=Dco (DartCObject based), see doc to use other codecs
+    return raw == null ? null : dco_decode_box_autoadd_bootstrap_stalled(raw);
+  }
+
+  @protected
+  ProxyInfo? dco_decode_opt_box_autoadd_proxy_info(dynamic raw) {
+    // Codec=Dco (DartCObject based), see doc to use other codecs
+    return raw == null ? null : dco_decode_box_autoadd_proxy_info(raw);
+  }
+
+  @protected
+  SnowflakeConfig? dco_decode_opt_box_autoadd_snowflake_config(dynamic raw) {
+    // Codec=Dco (DartCObject based), see doc to use other codecs
+    return raw == null ? null : dco_decode_box_autoadd_snowflake_config(raw);
+  }
+
+  @protected
+  int? dco_decode_opt_box_autoadd_u_16(dynamic raw) {
+    // Codec=Dco (DartCObject based), see doc to use other codecs
+    return raw == null ? null : dco_decode_box_autoadd_u_16(raw);
+  }
+
+  @protected
+  int? dco_decode_opt_box_autoadd_u_32(dynamic raw) {
+    // Codec=Dco (DartCObject based), see doc to use other codecs
+    return raw == null ? null : dco_decode_box_autoadd_u_32(raw);
+  }
+
+  @protected
+  int? dco_decode_opt_box_autoadd_u_8(dynamic raw) {
+    // Codec=Dco (DartCObject based), see doc to use other codecs
+    return raw == null ? null : dco_decode_box_autoadd_u_8(raw);
+  }
+
+  @protected
+  Uint8List? dco_decode_opt_list_prim_u_8_strict(dynamic raw) {
+    // Codec=Dco (DartCObject based), see doc to use other codecs
+    return raw == null ? null : dco_decode_list_prim_u_8_strict(raw);
+  }
+
+  @protected
+  ProxyInfo dco_decode_proxy_info(dynamic raw) {
+    // Codec=Dco (DartCObject based), see doc to use other codecs
+    final arr = raw as List<dynamic>;
+    if (arr.length != 7)
+      throw Exception('unexpected arr length: expect 7 but see ${arr.length}');
+    return ProxyInfo(
+      address: dco_decode_String(arr[0]),
+      port: dco_decode_u_16(arr[1]),
+      proxyType: dco_decode_proxy_type(arr[2]),
+      username: dco_decode_opt_String(arr[3]),
+      password: dco_decode_opt_String(arr[4]),
+      tlsServerName: dco_decode_opt_String(arr[5]),
+      tlsCertSha256: dco_decode_opt_String(arr[6]),
+    );
+  }
+
+  @protected
+  ProxyType dco_decode_proxy_type(dynamic raw) {
+    // Codec=Dco (DartCObject based), see doc to use other codecs
+    return ProxyType.values[raw as int];
+  }
+
+  @protected
+  SnowflakeConfig dco_decode_snowflake_config(dynamic raw) {
+    // Codec=Dco (DartCObject based), see doc to use other codecs
+    final arr = raw as List<dynamic>;
+    if (arr.length != 6)
+      throw Exception('unexpected arr length: expect 6 but see ${arr.length}');
+    return SnowflakeConfig(
+      bridgeLine: dco_decode_String(arr[0]),
+      binaryPath: dco_decode_String(arr[1]),
+      brokerUrl: dco_decode_opt_String(arr[2]),
+      frontDomain: dco_decode_opt_String(arr[3]),
+      stunServers: dco_decode_list_String(arr[4]),
+      arguments: dco_decode_list_String(arr[5]),
+    );
+  }
+
+  @protected
+  StartOptions dco_decode_start_options(dynamic raw) {
+    // Codec=Dco (DartCObject based), see doc to use other codecs
+    final arr = raw as List<dynamic>;
+    if (arr.length != 14)
+      throw Exception('unexpected arr length: expect 14 but see ${arr.length}');
+    return StartOptions(
+      useSystemProxy: dco_decode_bool(arr[0]),
+      connectionLog: dco_decode_bool(arr[1]),
+      directoryMirrors: dco_decode_list_directory_mirror(arr[2]),
+      bridges: dco_decode_list_bridge_line(arr[3]),
+      obfs4BinaryPath: dco_decode_opt_String(arr[4]),
+      snowflake: dco_decode_opt_box_autoadd_snowflake_config(arr[5]),
+      preemptiveCircuits: dco_decode_opt_box_autoadd_u_8(arr[6]),
+      consensusTimeToleranceSecs: dco_decode_opt_box_autoadd_u_32(arr[7]),
+      socksConnectTimeoutSecs: dco_decode_opt_box_autoadd_u_32(arr[8]),
+      socksIdleTimeoutSecs: dco_decode_opt_box_autoadd_u_32(arr[9]),
+      bindAddr: dco_decode_opt_String(arr[10]),
+      bootstrapTimeoutSecs: dco_decode_opt_box_autoadd_u_32(arr[11]),
+      proxyHandshakeTimeoutSecs: dco_decode_opt_box_autoadd_u_32(arr[12]),
+      socks5MethodOrder: dco_decode_opt_list_prim_u_8_strict(arr[13]),
+    );
+  }
+
+  @protected
+  TargetRedaction dco_decode_target_redaction(dynamic raw) {
+    // Codec=Dco (DartCObject based), see doc to use other codecs
+    return TargetRedaction.values[raw as int];
+  }
+
+  @protected
+  TorStatus dco_decode_tor_status(dynamic raw) {
+    // Codec=Dco (DartCObject based), see doc to use other codecs
+    final arr = raw as List<dynamic>;
+    if (arr.length != 8)
+      throw Exception('unexpected arr length: expect 8 but see ${arr.length}');
+    return TorStatus(
+      running: dco_decode_bool(arr[0]),
+      socksPort: dco_decode_opt_box_autoadd_u_16(arr[1]),
+      bootstrapPercent: dco_decode_u_8(arr[2]),
+      readyForTraffic: dco_decode_bool(arr[3]),
+      blockage: dco_decode_opt_String(arr[4]),
+      bootstrapStalled: dco_decode_opt_box_autoadd_bootstrap_stalled(arr[5]),
+      healthError: dco_decode_opt_String(arr[6]),
+      dormant: dco_decode_bool(arr[7]),
+    );
+  }
+
+  @protected
+  int dco_decode_u_16(dynamic raw) {
+    // Codec=Dco (DartCObject based), see doc to use other codecs
+    return raw as int;
+  }
+
+  @protected
+  int dco_decode_u_32(dynamic raw) {
+    // Codec=Dco (DartCObject based), see doc to use other codecs
+    return raw as int;
+  }
+
+  @protected
+  BigInt dco_decode_u_64(dynamic raw) {
+    // Codec=Dco (DartCObject based), see doc to use other codecs
+    return dcoDecodeU64(raw);
+  }
+
+  @protected
+  int dco_decode_u_8(dynamic raw) {
+    // Codec=Dco (DartCObject based), see doc to use other codecs
+    return raw as int;
+  }
+
+  @protected
+  void dco_decode_unit(dynamic raw) {
+    // Codec=Dco (DartCObject based), see doc to use other codecs
+    return;
+  }
+
+  @protected
+  WeightedProxy dco_decode_weighted_proxy(dynamic raw) {
+    // Codec=Dco (DartCObject based), see doc to use other codecs
+    final arr = raw as List<dynamic>;
+    if (arr.length != 2)
+      throw Exception('unexpected arr length: expect 2 but see ${arr.length}');
+    return WeightedProxy(
+      proxy: dco_decode_proxy_info(arr[0]),
+      weight: dco_decode_u_32(arr[1]),
+    );
+  }
+
+  @protected
+  AnyhowException sse_decode_AnyhowException(SseDeserializer deserializer) {
+    // Codec=Sse (Serialization based), see doc to use other codecs
+    var inner = sse_decode_String(deserializer);
+    return AnyhowException(inner);
+  }
+
+  @protected
+  RustStreamSink<BootstrapStatus> sse_decode_StreamSink_bootstrap_status_Sse(
+      SseDeserializer deserializer) {
+    // Codec=Sse (Serialization based), see doc to use other codecs
+    throw UnimplementedError('Unreachable ()');
+  }
+
+  @protected
+  RustStreamSink<ConnectionEvent> sse_decode_StreamSink_connection_event_Sse(
+      SseDeserializer deserializer) {
+    // Codec=Sse (Serialization based), see doc to use other codecs
+    throw UnimplementedError('Unreachable ()');
+  }
+
+  @protected
+  RustStreamSink<LogEntry> sse_decode_StreamSink_log_entry_Sse(
+      SseDeserializer deserializer) {
+    // Codec=Sse (Serialization based), see doc to use other codecs
+    throw UnimplementedError('Unreachable ()');
+  }
+
+  @protected
+  RustStreamSink<TorStatus> sse_decode_StreamSink_tor_status_Sse(
+      SseDeserializer deserializer) {
+    // Codec=Sse (Serialization based), see doc to use other codecs
+    throw UnimplementedError('Unreachable ()');
+  }
+
+  @protected
+  String sse_decode_String(SseDeserializer deserializer) {
+    // Codec=Sse (Serialization based), see doc to use other codecs
+    var inner = sse_decode_list_prim_u_8_strict(deserializer);
+    return utf8.decoder.convert(inner);
+  }
+
+  @protected
+  bool sse_decode_bool(SseDeserializer deserializer) {
+    // Codec=Sse (Serialization based), see doc to use other codecs
+    return deserializer.buffer.getUint8() != 0;
+  }
+
+  @protected
+  BootstrapStalled sse_decode_bootstrap_stalled(SseDeserializer deserializer) {
+    // Codec=Sse (Serialization based), see doc to use other codecs
+    var var_lastPhase = sse_decode_String(deserializer);
+    var var_suggestion = sse_decode_String(deserializer);
+    return BootstrapStalled(
+        lastPhase: var_lastPhase, suggestion: var_suggestion);
+  }
+
+  @protected
+  BootstrapStatus sse_decode_bootstrap_status(SseDeserializer deserializer) {
+    // Codec=Sse (Serialization based), see doc to use other codecs
+    var var_fraction = sse_decode_f_32(deserializer);
+    var var_summary = sse_decode_String(deserializer);
+    return BootstrapStatus(fraction: var_fraction, summary: var_summary);
+  }
+
+  @protected
+  BootstrapStalled sse_decode_box_autoadd_bootstrap_stalled(
+      SseDeserializer deserializer) {
+    // Codec=Sse (Serialization based), see doc to use other codecs
+    return (sse_decode_bootstrap_stalled(deserializer));
+  }
+
+  @protected
+  CircuitHandle sse_decode_box_autoadd_circuit_handle(
+      SseDeserializer deserializer) {
+    // Codec=Sse (Serialization based), see doc to use other codecs
+    return (sse_decode_circuit_handle(deserializer));
+  }
+
+  @protected
+  ProxyInfo sse_decode_box_autoadd_proxy_info(SseDeserializer deserializer) {
+    // Codec=Sse (Serialization based), see doc to use other codecs
+    return (sse_decode_proxy_info(deserializer));
+  }
+
+  @protected
+  SnowflakeConfig sse_decode_box_autoadd_snowflake_config(
+      SseDeserializer deserializer) {
+    // Codec=Sse (Serialization based), see doc to use other codecs
+    return (sse_decode_snowflake_config(deserializer));
+  }
+
+  @protected
+  StartOptions sse_decode_box_autoadd_start_options(
+      SseDeserializer deserializer) {
+    // Codec=Sse (Serialization based), see doc to use other codecs
+    return (sse_decode_start_options(deserializer));
+  }
+
+  @protected
+  int sse_decode_box_autoadd_u_16(SseDeserializer deserializer) {
+    // Codec=Sse (Serialization based), see doc to use other codecs
+    return (sse_decode_u_16(deserializer));
+  }
+
+  @protected
+  int sse_decode_box_autoadd_u_32(SseDeserializer deserializer) {
+    // Codec=Sse (Serialization based), see doc to use other codecs
+    return (sse_decode_u_32(deserializer));
+  }
+
+  @protected
+  int sse_decode_box_autoadd_u_8(SseDeserializer deserializer) {
+    // Codec=Sse (Serialization based), see doc to use other codecs
+    return (sse_decode_u_8(deserializer));
+  }
+
+  @protected
+  BridgeLine sse_decode_bridge_line(SseDeserializer deserializer) {
+    // Codec=Sse (Serialization based), see doc to use other codecs
+    var var_line = sse_decode_String(deserializer);
+    return BridgeLine(line: var_line);
+  }
+
+  @protected
+  CircuitHandle sse_decode_circuit_handle(SseDeserializer deserializer) {
+    // Codec=Sse (Serialization based), see doc to use other codecs
+    var var_id = sse_decode_u_64(deserializer);
+    return CircuitHandle(id: var_id);
+  }
+
+  @protected
+  ConnectionEvent sse_decode_connection_event(SseDeserializer deserializer) {
+    // Codec=Sse (Serialization based), see doc to use other codecs
+    var var_stage = sse_decode_connection_stage(deserializer);
+    var var_target = sse_decode_String(deserializer);
+    var var_via = sse_decode_String(deserializer);
+    var var_elapsedMs = sse_decode_opt_box_autoadd_u_32(deserializer);
+    var var_error = sse_decode_opt_String(deserializer);
+    return ConnectionEvent(
+        stage: var_stage,
+        target: var_target,
+        via: var_via,
+        elapsedMs: var_elapsedMs,
+        error: var_error);
+  }
+
+  @protected
+  ConnectionStage sse_decode_connection_stage(SseDeserializer deserializer) {
+    // Codec=Sse (Serialization based), see doc to use other codecs
+    var inner = sse_decode_i_32(deserializer);
+    return ConnectionStage.values[inner];
+  }
+
+  @protected
+  DirectoryMirror sse_decode_directory_mirror(SseDeserializer deserializer) {
+    // Codec=Sse (Serialization based), see doc to use other codecs
+    var var_rsaIdentity = sse_decode_String(deserializer);
+    var var_edIdentity = sse_decode_String(deserializer);
+    var var_addresses = sse_decode_list_String(deserializer);
+    return DirectoryMirror(
+        rsaIdentity: var_rsaIdentity,
+        edIdentity: var_edIdentity,
+        addresses: var_addresses);
+  }
+
+  @protected
+  double sse_decode_f_32(SseDeserializer deserializer) {
+    // Codec=Sse (Serialization based), see doc to use other codecs
+    return deserializer.buffer.getFloat32();
+  }
+
+  @protected
+  FdUsage sse_decode_fd_usage(SseDeserializer deserializer) {
+    // Codec=Sse (Serialization based), see doc to use other codecs
+    var var_open = sse_decode_u_64(deserializer);
+    var var_softLimit = sse_decode_u_64(deserializer);
+    var var_hardLimit = sse_decode_u_64(deserializer);
+    return FdUsage(
+        open: var_open, softLimit: var_softLimit, hardLimit: var_hardLimit);
+  }
+
+  @protected
+  int sse_decode_i_32(SseDeserializer deserializer) {
+    // Codec=Sse (Serialization based), see doc to use other codecs
+    return deserializer.buffer.getInt32();
+  }
+
+  @protected
+  IsolationMapping sse_decode_isolation_mapping(SseDeserializer deserializer) {
+    // Codec=Sse (Serialization based), see doc to use other codecs
+    var var_contactId = sse_decode_String(deserializer);
+    var var_token = sse_decode_String(deserializer);
+    return IsolationMapping(contactId: var_contactId, token: var_token);
+  }
+
+  @protected
+  List<String> sse_decode_list_String(SseDeserializer deserializer) {
+    // Codec=Sse (Serialization based), see doc to use other codecs
+
+    var len_ = sse_decode_i_32(deserializer);
+    var ans_ = <String>[];
+    for (var idx_ = 0; idx_ < len_; ++idx_) {
+      ans_.add(sse_decode_String(deserializer));
+    }
+    return ans_;
+  }
+
+  @protected
+  List<BridgeLine> sse_decode_list_bridge_line(SseDeserializer deserializer) {
+    // Codec=Sse (Serialization based), see doc to use other codecs
+
+    var len_ = sse_decode_i_32(deserializer);
+    var ans_ = <BridgeLine>[];
+    for (var idx_ = 0; idx_ < len_; ++idx_) {
+      ans_.add(sse_decode_bridge_line(deserializer));
+    }
+    return ans_;
+  }
+
+  @protected
+  List<DirectoryMirror> sse_decode_list_directory_mirror(
+      SseDeserializer deserializer) {
+    // Codec=Sse (Serialization based), see doc to use other codecs
+
+    var len_ = sse_decode_i_32(deserializer);
+    var ans_ = <DirectoryMirror>[];
+    for (var idx_ = 0; idx_ < len_; ++idx_) {
+      ans_.add(sse_decode_directory_mirror(deserializer));
+    }
+    return ans_;
+  }
+
+  @protected
+  List<IsolationMapping> sse_decode_list_isolation_mapping(
+      SseDeserializer deserializer) {
+    // Codec=Sse (Serialization based), see doc to use other codecs
+
+    var len_ = sse_decode_i_32(deserializer);
+    var ans_ = <IsolationMapping>[];
+    for (var idx_ = 0; idx_ < len_; ++idx_) {
+      ans_.add(sse_decode_isolation_mapping(deserializer));
+    }
+    return ans_;
+  }
+
+  @protected
+  Uint8List sse_decode_list_prim_u_8_strict(SseDeserializer deserializer) {
+    // Codec=Sse (Serialization based), see doc to use other codecs
+    var len_ = sse_decode_i_32(deserializer);
+    return deserializer.buffer.getUint8List(len_);
+  }
+
+  @protected
+  List<ProxyInfo> sse_decode_list_proxy_info(SseDeserializer deserializer) {
+    // Codec=Sse (Serialization based), see doc to use other codecs
+
+    var len_ = sse_decode_i_32(deserializer);
+    var ans_ = <ProxyInfo>[];
+    for (var idx_ = 0; idx_ < len_; ++idx_) {
+      ans_.add(sse_decode_proxy_info(deserializer));
+    }
+    return ans_;
+  }
+
+  @protected
+  List<WeightedProxy> sse_decode_list_weighted_proxy(
+      SseDeserializer deserializer) {
+    // Codec=Sse (Serialization based), see doc to use other codecs
+
+    var len_ = sse_decode_i_32(deserializer);
+    var ans_ = <WeightedProxy>[];
+    for (var idx_ = 0; idx_ < len_; ++idx_) {
+      ans_.add(sse_decode_weighted_proxy(deserializer));
+    }
+    return ans_;
+  }
+
+  @protected
+  LocalStream sse_decode_local_stream(SseDeserializer deserializer) {
+    // Codec=Sse (Serialization based), see doc to use other codecs
+    var var_port = sse_decode_u_16(deserializer);
+    var var_token = sse_decode_String(deserializer);
+    return LocalStream(port: var_port, token: var_token);
+  }
+
+  @protected
+  LogEntry sse_decode_log_entry(SseDeserializer deserializer) {
+    // Codec=Sse (Serialization based), see doc to use other codecs
+    var var_level = sse_decode_String(deserializer);
+    var var_target = sse_decode_String(deserializer);
+    var var_message = sse_decode_String(deserializer);
+    return LogEntry(level: var_level, target: var_target, message: var_message);
+  }
+
+  @protected
+  String? sse_decode_opt_String(SseDeserializer deserializer) {
+    // Codec=Sse (Serialization based), see doc to use other codecs
+
+    if (sse_decode_bool(deserializer)) {
+      return (sse_decode_String(deserializer));
+    } else {
+      return null;
+    }
+  }
+
+  @protected
+  BootstrapStalled? sse_decode_opt_box_autoadd_bootstrap_stalled(
+      SseDeserializer deserializer) {
+    // Codec=Sse (Serialization based), see doc to use other codecs
+
+    if (sse_decode_bool(deserializer)) {
+      return (sse_decode_box_autoadd_bootstrap_stalled(deserializer));
+    } else {
+      return null;
+    }
+  }
+
+  @protected
+  ProxyInfo? sse_decode_opt_box_autoadd_proxy_info(
+      SseDeserializer deserializer) {
+    // Codec=Sse (Serialization based), see doc to use other codecs
+
+    if (sse_decode_bool(deserializer)) {
+      return (sse_decode_box_autoadd_proxy_info(deserializer));
+    } else {
+      return null;
+    }
+  }
+
+  @protected
+  SnowflakeConfig? sse_decode_opt_box_autoadd_snowflake_config(
+      SseDeserializer deserializer) {
+    // Codec=Sse (Serialization based), see doc to use other codecs
+
+    if (sse_decode_bool(deserializer)) {
+      return (sse_decode_box_autoadd_snowflake_config(deserializer));
+    } else {
+      return null;
+    }
+  }
+
+  @protected
+  int? sse_decode_opt_box_autoadd_u_16(SseDeserializer deserializer) {
+    // Codec=Sse (Serialization based), see doc to use other codecs
+
+    if (sse_decode_bool(deserializer)) {
+      return (sse_decode_box_autoadd_u_16(deserializer));
+    } else {
+      return null;
+    }
+  }
+
+  @protected
+  int? sse_decode_opt_box_autoadd_u_32(SseDeserializer deserializer) {
+    // Codec=Sse (Serialization based), see doc to use other codecs
+
+    if (sse_decode_bool(deserializer)) {
+      return (sse_decode_box_autoadd_u_32(deserializer));
+    } else {
+      return null;
+    }
+  }
+
+  @protected
+  int? sse_decode_opt_box_autoadd_u_8(SseDeserializer deserializer) {
+    // Codec=Sse (Serialization based), see doc to use other codecs
+
+    if (sse_decode_bool(deserializer)) {
+      return (sse_decode_box_autoadd_u_8(deserializer));
+    } else {
+      return null;
+    }
+  }
+
+  @protected
+  Uint8List? sse_decode_opt_list_prim_u_8_strict(
+      SseDeserializer deserializer) {
+    // Codec=Sse (Serialization based), see doc to use other codecs
+
+    if (sse_decode_bool(deserializer)) {
+      return (sse_decode_list_prim_u_8_strict(deserializer));
+    } else {
+      return null;
+    }
+  }
+
+  @protected
+  ProxyInfo sse_decode_proxy_info(SseDeserializer deserializer) {
+    // Codec=Sse (Serialization based), see doc to use other codecs
+    var var_address = sse_decode_String(deserializer);
+    var var_port = sse_decode_u_16(deserializer);
+    var var_proxyType = sse_decode_proxy_type(deserializer);
+    var var_username = sse_decode_opt_String(deserializer);
+    var var_password = sse_decode_opt_String(deserializer);
+    var var_tlsServerName = sse_decode_opt_String(deserializer);
+    var var_tlsCertSha256 = sse_decode_opt_String(deserializer);
+    return ProxyInfo(
+        address: var_address,
+        port: var_port,
+        proxyType: var_proxyType,
+        username: var_username,
+        password: var_password,
+        tlsServerName: var_tlsServerName,
+        tlsCertSha256: var_tlsCertSha256);
+  }
+
+  @protected
+  ProxyType sse_decode_proxy_type(SseDeserializer deserializer) {
+    // Codec=Sse (Serialization based), see doc to use other codecs
+    var inner = sse_decode_i_32(deserializer);
+    return ProxyType.values[inner];
+  }
+
+  @protected
+  SnowflakeConfig sse_decode_snowflake_config(SseDeserializer deserializer) {
+    // Codec=Sse (Serialization based), see doc to use other codecs
+    var var_bridgeLine = sse_decode_String(deserializer);
+    var var_binaryPath = sse_decode_String(deserializer);
+    var var_brokerUrl = sse_decode_opt_String(deserializer);
+    var var_frontDomain = sse_decode_opt_String(deserializer);
+    var var_stunServers = sse_decode_list_String(deserializer);
+    var var_arguments = sse_decode_list_String(deserializer);
+    return SnowflakeConfig(
+        bridgeLine: var_bridgeLine,
+        binaryPath: var_binaryPath,
+        brokerUrl: var_brokerUrl,
+        frontDomain: var_frontDomain,
+        stunServers: var_stunServers,
+        arguments: var_arguments);
+  }
+
+  @protected
+  StartOptions sse_decode_start_options(SseDeserializer deserializer) {
+    // Codec=Sse (Serialization based), see doc to use other codecs
+    var var_useSystemProxy = sse_decode_bool(deserializer);
+    var var_connectionLog = sse_decode_bool(deserializer);
+    var var_directoryMirrors = sse_decode_list_directory_mirror(deserializer);
+    var var_bridges = sse_decode_list_bridge_line(deserializer);
+    var var_obfs4BinaryPath = sse_decode_opt_String(deserializer);
+    var var_snowflake =
+        sse_decode_opt_box_autoadd_snowflake_config(deserializer);
+    var var_preemptiveCircuits = sse_decode_opt_box_autoadd_u_8(deserializer);
+    var var_consensusTimeToleranceSecs =
+        sse_decode_opt_box_autoadd_u_32(deserializer);
+    var var_socksConnectTimeoutSecs =
+        sse_decode_opt_box_autoadd_u_32(deserializer);
+    var var_socksIdleTimeoutSecs =
+        sse_decode_opt_box_autoadd_u_32(deserializer);
+    var var_bindAddr = sse_decode_opt_String(deserializer);
+    var var_bootstrapTimeoutSecs =
+        sse_decode_opt_box_autoadd_u_32(deserializer);
+    var var_proxyHandshakeTimeoutSecs =
+        sse_decode_opt_box_autoadd_u_32(deserializer);
+    var var_socks5MethodOrder =
+        sse_decode_opt_list_prim_u_8_strict(deserializer);
+    return StartOptions(
+        useSystemProxy: var_useSystemProxy,
+        connectionLog: var_connectionLog,
+        directoryMirrors: var_directoryMirrors,
+        bridges: var_bridges,
+        obfs4BinaryPath: var_obfs4BinaryPath,
+        snowflake: var_snowflake,
+        preemptiveCircuits: var_preemptiveCircuits,
+        consensusTimeToleranceSecs: var_consensusTimeToleranceSecs,
+        socksConnectTimeoutSecs: var_socksConnectTimeoutSecs,
+        socksIdleTimeoutSecs: var_socksIdleTimeoutSecs,
+        bindAddr: var_bindAddr,
+        bootstrapTimeoutSecs: var_bootstrapTimeoutSecs,
+        proxyHandshakeTimeoutSecs: var_proxyHandshakeTimeoutSecs,
+        socks5MethodOrder: var_socks5MethodOrder);
+  }
+
+  @protected
+  TargetRedaction sse_decode_target_redaction(SseDeserializer deserializer) {
+    // Codec=Sse (Serialization based), see doc to use other codecs
+    var inner = sse_decode_i_32(deserializer);
+    return TargetRedaction.values[inner];
+  }
+
+  @protected
+  TorStatus sse_decode_tor_status(SseDeserializer deserializer) {
+    // Codec=Sse (Serialization based), see doc to use other codecs
+    var var_running = sse_decode_bool(deserializer);
+    var var_socksPort = sse_decode_opt_box_autoadd_u_16(deserializer);
+    var var_bootstrapPercent = sse_decode_u_8(deserializer);
+    var var_readyForTraffic = sse_decode_bool(deserializer);
+    var var_blockage = sse_decode_opt_String(deserializer);
+    var var_bootstrapStalled =
+        sse_decode_opt_box_autoadd_bootstrap_stalled(deserializer);
+    var var_healthError = sse_decode_opt_String(deserializer);
+    var var_dormant = sse_decode_bool(deserializer);
+    return TorStatus(
+        running: var_running,
+        socksPort: var_socksPort,
+        bootstrapPercent: var_bootstrapPercent,
+        readyForTraffic: var_readyForTraffic,
+        blockage: var_blockage,
+        bootstrapStalled: var_bootstrapStalled,
+        healthError: var_healthError,
+        dormant: var_dormant);
+  }
+
+  @protected
+  int sse_decode_u_16(SseDeserializer deserializer) {
+    // Codec=Sse (Serialization based), see doc to use other codecs
+    return deserializer.buffer.getUint16();
+  }
+
+  @protected
+  int sse_decode_u_32(SseDeserializer deserializer) {
+    // Codec=Sse (Serialization based), see doc to use other codecs
+    return deserializer.buffer.getUint32();
   }
 
-  TaskConstMeta get kCrateApiTorTorStopFrbConstMeta => const TaskConstMeta(
-        debugName: "tor_stop_frb",
-        argNames: [],
-      );
+  @protected
+  BigInt sse_decode_u_64(SseDeserializer deserializer) {
+    // Codec=Sse (Serialization based), see doc to use other codecs
+    return deserializer.buffer.getBigUint64();
+  }
 
   @protected
-  AnyhowException dco_decode_AnyhowException(dynamic raw) {
-    // Codec=Dco (DartCObject based), see doc to use other codecs
-    return AnyhowException(raw as String);
+  int sse_decode_u_8(SseDeserializer deserializer) {
+    // Codec=Sse (Serialization based), see doc to use other codecs
+    return deserializer.buffer.getUint8();
   }
 
   @protected
-  String dco_decode_String(dynamic raw) {
-    // Codec=Dco (DartCObject based), see doc to use other codecs
-    return raw as String;
+  void sse_decode_unit(SseDeserializer deserializer) {
+    // Codec=Sse (Serialization based), see doc to use other codecs
   }
 
   @protected
-  bool dco_decode_bool(dynamic raw) {
-    // Codec=Dco (DartCObject based), see doc to use other codecs
-    return raw as bool;
+  WeightedProxy sse_decode_weighted_proxy(SseDeserializer deserializer) {
+    // Codec=Sse (Serialization based), see doc to use other codecs
+    var var_proxy = sse_decode_proxy_info(deserializer);
+    var var_weight = sse_decode_u_32(deserializer);
+    return WeightedProxy(proxy: var_proxy, weight: var_weight);
   }
 
   @protected
-  ProxyInfo dco_decode_box_autoadd_proxy_info(dynamic raw) {
-    // Codec=Dco (DartCObject based), see doc to use other codecs
-    return dco_decode_proxy_info(raw);
+  void sse_encode_AnyhowException(
+      AnyhowException self, SseSerializer serializer) {
+    // Codec=Sse (Serialization based), see doc to use other codecs
+    sse_encode_String(self.message, serializer);
   }
 
   @protected
-  int dco_decode_i_32(dynamic raw) {
-    // Codec=Dco (DartCObject based), see doc to use other codecs
-    return raw as int;
+  void sse_encode_StreamSink_bootstrap_status_Sse(
+      RustStreamSink<BootstrapStatus> self, SseSerializer serializer) {
+    // Codec=Sse (Serialization based), see doc to use other codecs
+    sse_encode_String(
+        self.setupAndSerialize(
+            codec: SseCodec(
+          decodeSuccessData: sse_decode_bootstrap_status,
+          decodeErrorData: sse_decode_AnyhowException,
+        )),
+        serializer);
   }
 
   @protected
-  Uint8List dco_decode_list_prim_u_8_strict(dynamic raw) {
-    // Codec=Dco (DartCObject based), see doc to use other codecs
-    return raw as Uint8List;
+  void sse_encode_StreamSink_connection_event_Sse(
+      RustStreamSink<ConnectionEvent> self, SseSerializer serializer) {
+    // Codec=Sse (Serialization based), see doc to use other codecs
+    sse_encode_String(
+        self.setupAndSerialize(
+            codec: SseCodec(
+          decodeSuccessData: sse_decode_connection_event,
+          decodeErrorData: sse_decode_AnyhowException,
+        )),
+        serializer);
   }
 
   @protected
-  String? dco_decode_opt_String(dynamic raw) {
-    // Codec=Dco (DartCObject based), see doc to use other codecs
-    return raw == null ? null : dco_decode_String(raw);
+  void sse_encode_StreamSink_log_entry_Sse(
+      RustStreamSink<LogEntry> self, SseSerializer serializer) {
+    // Codec=Sse (Serialization based), see doc to use other codecs
+    sse_encode_String(
+        self.setupAndSerialize(
+            codec: SseCodec(
+          decodeSuccessData: sse_decode_log_entry,
+          decodeErrorData: sse_decode_AnyhowException,
+        )),
+        serializer);
   }
 
   @protected
-  ProxyInfo? dco_decode_opt_box_autoadd_proxy_info(dynamic raw) {
-    // Codec=Dco (DartCObject based), see doc to use other codecs
-    return raw == null ? null : dco_decode_box_autoadd_proxy_info(raw);
+  void sse_encode_StreamSink_tor_status_Sse(
+      RustStreamSink<TorStatus> self, SseSerializer serializer) {
+    // Codec=Sse (Serialization based), see doc to use other codecs
+    sse_encode_String(
+        self.setupAndSerialize(
+            codec: SseCodec(
+          decodeSuccessData: sse_decode_tor_status,
+          decodeErrorData: sse_decode_AnyhowException,
+        )),
+        serializer);
   }
 
   @protected
-  ProxyInfo dco_decode_proxy_info(dynamic raw) {
-    // Codec=Dco (DartCObject based), see doc to use other codecs
-    final arr = raw as List<dynamic>;
-    if (arr.length != 5)
-      throw Exception('unexpected arr length: expect 5 but see ${arr.length}');
-    return ProxyInfo(
-      address: dco_decode_String(arr[0]),
-      port: dco_decode_u_16(arr[1]),
-      proxyType: dco_decode_proxy_type(arr[2]),
-      username: dco_decode_opt_String(arr[3]),
-      password: dco_decode_opt_String(arr[4]),
-    );
+  void sse_encode_String(String self, SseSerializer serializer) {
+    // Codec=Sse (Serialization based), see doc to use other codecs
+    sse_encode_list_prim_u_8_strict(utf8.encoder.convert(self), serializer);
   }
 
   @protected
-  ProxyType dco_decode_proxy_type(dynamic raw) {
-    // Codec=Dco (DartCObject based), see doc to use other codecs
-    return ProxyType.values[raw as int];
+  void sse_encode_bool(bool self, SseSerializer serializer) {
+    // Codec=Sse (Serialization based), see doc to use other codecs
+    serializer.buffer.putUint8(self ? 1 : 0);
   }
 
   @protected
-  int dco_decode_u_16(dynamic raw) {
-    // Codec=Dco (DartCObject based), see doc to use other codecs
-    return raw as int;
+  void sse_encode_bootstrap_stalled(
+      BootstrapStalled self, SseSerializer serializer) {
+    // Codec=Sse (Serialization based), see doc to use other codecs
+    sse_encode_String(self.lastPhase, serializer);
+    sse_encode_String(self.suggestion, serializer);
   }
 
   @protected
-  int dco_decode_u_8(dynamic raw) {
-    // Codec=Dco (DartCObject based), see doc to use other codecs
-    return raw as int;
+  void sse_encode_bootstrap_status(
+      BootstrapStatus self, SseSerializer serializer) {
+    // Codec=Sse (Serialization based), see doc to use other codecs
+    sse_encode_f_32(self.fraction, serializer);
+    sse_encode_String(self.summary, serializer);
   }
 
   @protected
-  void dco_decode_unit(dynamic raw) {
-    // Codec=Dco (DartCObject based), see doc to use other codecs
-    return;
+  void sse_encode_box_autoadd_bootstrap_stalled(
+      BootstrapStalled self, SseSerializer serializer) {
+    // Codec=Sse (Serialization based), see doc to use other codecs
+    sse_encode_bootstrap_stalled(self, serializer);
   }
 
   @protected
-  AnyhowException sse_decode_AnyhowException(SseDeserializer deserializer) {
+  void sse_encode_box_autoadd_circuit_handle(
+      CircuitHandle self, SseSerializer serializer) {
     // Codec=Sse (Serialization based), see doc to use other codecs
-    var inner = sse_decode_String(deserializer);
-    return AnyhowException(inner);
+    sse_encode_circuit_handle(self, serializer);
   }
 
   @protected
-  String sse_decode_String(SseDeserializer deserializer) {
+  void sse_encode_box_autoadd_proxy_info(
+      ProxyInfo self, SseSerializer serializer) {
     // Codec=Sse (Serialization based), see doc to use other codecs
-    var inner = sse_decode_list_prim_u_8_strict(deserializer);
-    return utf8.decoder.convert(inner);
+    sse_encode_proxy_info(self, serializer);
   }
 
   @protected
-  bool sse_decode_bool(SseDeserializer deserializer) {
+  void sse_encode_box_autoadd_snowflake_config(
+      SnowflakeConfig self, SseSerializer serializer) {
     // Codec=Sse (Serialization based), see doc to use other codecs
-    return deserializer.buffer.getUint8() != 0;
+    sse_encode_snowflake_config(self, serializer);
   }
 
   @protected
-  ProxyInfo sse_decode_box_autoadd_proxy_info(SseDeserializer deserializer) {
+  void sse_encode_box_autoadd_start_options(
+      StartOptions self, SseSerializer serializer) {
     // Codec=Sse (Serialization based), see doc to use other codecs
-    return (sse_decode_proxy_info(deserializer));
+    sse_encode_start_options(self, serializer);
   }
 
   @protected
-  int sse_decode_i_32(SseDeserializer deserializer) {
+  void sse_encode_box_autoadd_u_16(int self, SseSerializer serializer) {
     // Codec=Sse (Serialization based), see doc to use other codecs
-    return deserializer.buffer.getInt32();
+    sse_encode_u_16(self, serializer);
   }
 
   @protected
-  Uint8List sse_decode_list_prim_u_8_strict(SseDeserializer deserializer) {
+  void sse_encode_box_autoadd_u_32(int self, SseSerializer serializer) {
     // Codec=Sse (Serialization based), see doc to use other codecs
-    var len_ = sse_decode_i_32(deserializer);
-    return deserializer.buffer.getUint8List(len_);
+    sse_encode_u_32(self, serializer);
   }
 
   @protected
-  String? sse_decode_opt_String(SseDeserializer deserializer) {
+  void sse_encode_box_autoadd_u_8(int self, SseSerializer serializer) {
     // Codec=Sse (Serialization based), see doc to use other codecs
+    sse_encode_u_8(self, serializer);
+  }
 
-    if (sse_decode_bool(deserializer)) {
-      return (sse_decode_String(deserializer));
-    } else {
-      return null;
-    }
+  @protected
+  void sse_encode_bridge_line(BridgeLine self, SseSerializer serializer) {
+    // Codec=Sse (Serialization based), see doc to use other codecs
+    sse_encode_String(self.line, serializer);
   }
 
   @protected
-  ProxyInfo? sse_decode_opt_box_autoadd_proxy_info(
-      SseDeserializer deserializer) {
+  void sse_encode_circuit_handle(CircuitHandle self, SseSerializer serializer) {
     // Codec=Sse (Serialization based), see doc to use other codecs
+    sse_encode_u_64(self.id, serializer);
+  }
 
-    if (sse_decode_bool(deserializer)) {
-      return (sse_decode_box_autoadd_proxy_info(deserializer));
-    } else {
-      return null;
-    }
+  @protected
+  void sse_encode_connection_event(
+      ConnectionEvent self, SseSerializer serializer) {
+    // Codec=Sse (Serialization based), see doc to use other codecs
+    sse_encode_connection_stage(self.stage, serializer);
+    sse_encode_String(self.target, serializer);
+    sse_encode_String(self.via, serializer);
+    sse_encode_opt_box_autoadd_u_32(self.elapsedMs, serializer);
+    sse_encode_opt_String(self.error, serializer);
   }
 
   @protected
-  ProxyInfo sse_decode_proxy_info(SseDeserializer deserializer) {
+  void sse_encode_connection_stage(
+      ConnectionStage self, SseSerializer serializer) {
     // Codec=Sse (Serialization based), see doc to use other codecs
-    var var_address = sse_decode_String(deserializer);
-    var var_port = sse_decode_u_16(deserializer);
-    var var_proxyType = sse_decode_proxy_type(deserializer);
-    var var_username = sse_decode_opt_String(deserializer);
-    var var_password = sse_decode_opt_String(deserializer);
-    return ProxyInfo(
-        address: var_address,
-        port: var_port,
-        proxyType: var_proxyType,
-        username: var_username,
-        password: var_password);
+    sse_encode_i_32(self.index, serializer);
   }
 
   @protected
-  ProxyType sse_decode_proxy_type(SseDeserializer deserializer) {
+  void sse_encode_directory_mirror(
+      DirectoryMirror self, SseSerializer serializer) {
     // Codec=Sse (Serialization based), see doc to use other codecs
-    var inner = sse_decode_i_32(deserializer);
-    return ProxyType.values[inner];
+    sse_encode_String(self.rsaIdentity, serializer);
+    sse_encode_String(self.edIdentity, serializer);
+    sse_encode_list_String(self.addresses, serializer);
   }
 
   @protected
-  int sse_decode_u_16(SseDeserializer deserializer) {
+  void sse_encode_f_32(double self, SseSerializer serializer) {
     // Codec=Sse (Serialization based), see doc to use other codecs
-    return deserializer.buffer.getUint16();
+    serializer.buffer.putFloat32(self);
   }
 
   @protected
-  int sse_decode_u_8(SseDeserializer deserializer) {
+  void sse_encode_fd_usage(FdUsage self, SseSerializer serializer) {
     // Codec=Sse (Serialization based), see doc to use other codecs
-    return deserializer.buffer.getUint8();
+    sse_encode_u_64(self.open, serializer);
+    sse_encode_u_64(self.softLimit, serializer);
+    sse_encode_u_64(self.hardLimit, serializer);
   }
 
   @protected
-  void sse_decode_unit(SseDeserializer deserializer) {
+  void sse_encode_i_32(int self, SseSerializer serializer) {
     // Codec=Sse (Serialization based), see doc to use other codecs
+    serializer.buffer.putInt32(self);
   }
 
   @protected
-  void sse_encode_AnyhowException(
-      AnyhowException self, SseSerializer serializer) {
+  void sse_encode_isolation_mapping(
+      IsolationMapping self, SseSerializer serializer) {
     // Codec=Sse (Serialization based), see doc to use other codecs
-    sse_encode_String(self.message, serializer);
+    sse_encode_String(self.contactId, serializer);
+    sse_encode_String(self.token, serializer);
   }
 
   @protected
-  void sse_encode_String(String self, SseSerializer serializer) {
+  void sse_encode_list_String(List<String> self, SseSerializer serializer) {
     // Codec=Sse (Serialization based), see doc to use other codecs
-    sse_encode_list_prim_u_8_strict(utf8.encoder.convert(self), serializer);
+    sse_encode_i_32(self.length, serializer);
+    for (final item in self) {
+      sse_encode_String(item, serializer);
+    }
   }
 
   @protected
-  void sse_encode_bool(bool self, SseSerializer serializer) {
+  void sse_encode_list_bridge_line(
+      List<BridgeLine> self, SseSerializer serializer) {
     // Codec=Sse (Serialization based), see doc to use other codecs
-    serializer.buffer.putUint8(self ? 1 : 0);
+    sse_encode_i_32(self.length, serializer);
+    for (final item in self) {
+      sse_encode_bridge_line(item, serializer);
+    }
   }
 
   @protected
-  void sse_encode_box_autoadd_proxy_info(
-      ProxyInfo self, SseSerializer serializer) {
+  void sse_encode_list_directory_mirror(
+      List<DirectoryMirror> self, SseSerializer serializer) {
     // Codec=Sse (Serialization based), see doc to use other codecs
-    sse_encode_proxy_info(self, serializer);
+    sse_encode_i_32(self.length, serializer);
+    for (final item in self) {
+      sse_encode_directory_mirror(item, serializer);
+    }
   }
 
   @protected
-  void sse_encode_i_32(int self, SseSerializer serializer) {
+  void sse_encode_list_isolation_mapping(
+      List<IsolationMapping> self, SseSerializer serializer) {
     // Codec=Sse (Serialization based), see doc to use other codecs
-    serializer.buffer.putInt32(self);
+    sse_encode_i_32(self.length, serializer);
+    for (final item in self) {
+      sse_encode_isolation_mapping(item, serializer);
+    }
   }
 
   @protected
@@ -456,6 +2585,41 @@ class RustLibApiImpl extends RustLibApiImplPlatform implements RustLibApi {
     serializer.buffer.putUint8List(self);
   }
 
+  @protected
+  void sse_encode_list_proxy_info(
+      List<ProxyInfo> self, SseSerializer serializer) {
+    // Codec=Sse (Serialization based), see doc to use other codecs
+    sse_encode_i_32(self.length, serializer);
+    for (final item in self) {
+      sse_encode_proxy_info(item, serializer);
+    }
+  }
+
+  @protected
+  void sse_encode_list_weighted_proxy(
+      List<WeightedProxy> self, SseSerializer serializer) {
+    // Codec=Sse (Serialization based), see doc to use other codecs
+    sse_encode_i_32(self.length, serializer);
+    for (final item in self) {
+      sse_encode_weighted_proxy(item, serializer);
+    }
+  }
+
+  @protected
+  void sse_encode_local_stream(LocalStream self, SseSerializer serializer) {
+    // Codec=Sse (Serialization based), see doc to use other codecs
+    sse_encode_u_16(self.port, serializer);
+    sse_encode_String(self.token, serializer);
+  }
+
+  @protected
+  void sse_encode_log_entry(LogEntry self, SseSerializer serializer) {
+    // Codec=Sse (Serialization based), see doc to use other codecs
+    sse_encode_String(self.level, serializer);
+    sse_encode_String(self.target, serializer);
+    sse_encode_String(self.message, serializer);
+  }
+
   @protected
   void sse_encode_opt_String(String? self, SseSerializer serializer) {
     // Codec=Sse (Serialization based), see doc to use other codecs
@@ -466,6 +2630,17 @@ class RustLibApiImpl extends RustLibApiImplPlatform implements RustLibApi {
     }
   }
 
+  @protected
+  void sse_encode_opt_box_autoadd_bootstrap_stalled(
+      BootstrapStalled? self, SseSerializer serializer) {
+    // Codec=Sse (Serialization based), see doc to use other codecs
+
+    sse_encode_bool(self != null, serializer);
+    if (self != null) {
+      sse_encode_box_autoadd_bootstrap_stalled(self, serializer);
+    }
+  }
+
   @protected
   void sse_encode_opt_box_autoadd_proxy_info(
       ProxyInfo? self, SseSerializer serializer) {
@@ -477,6 +2652,58 @@ class RustLibApiImpl extends RustLibApiImplPlatform implements RustLibApi {
     }
   }
 
+  @protected
+  void sse_encode_opt_box_autoadd_snowflake_config(
+      SnowflakeConfig? self, SseSerializer serializer) {
+    // Codec=Sse (Serialization based), see doc to use other codecs
+
+    sse_encode_bool(self != null, serializer);
+    if (self != null) {
+      sse_encode_box_autoadd_snowflake_config(self, serializer);
+    }
+  }
+
+  @protected
+  void sse_encode_opt_box_autoadd_u_16(int? self, SseSerializer serializer) {
+    // Codec=Sse (Serialization based), see doc to use other codecs
+
+    sse_encode_bool(self != null, serializer);
+    if (self != null) {
+      sse_encode_box_autoadd_u_16(self, serializer);
+    }
+  }
+
+  @protected
+  void sse_encode_opt_box_autoadd_u_32(int? self, SseSerializer serializer) {
+    // Codec=Sse (Serialization based), see doc to use other codecs
+
+    sse_encode_bool(self != null, serializer);
+    if (self != null) {
+      sse_encode_box_autoadd_u_32(self, serializer);
+    }
+  }
+
+  @protected
+  void sse_encode_opt_box_autoadd_u_8(int? self, SseSerializer serializer) {
+    // Codec=Sse (Serialization based), see doc to use other codecs
+
+    sse_encode_bool(self != null, serializer);
+    if (self != null) {
+      sse_encode_box_autoadd_u_8(self, serializer);
+    }
+  }
+
+  @protected
+  void sse_encode_opt_list_prim_u_8_strict(
+      Uint8List? self, SseSerializer serializer) {
+    // Codec=Sse (Serialization based), see doc to use other codecs
+
+    sse_encode_bool(self != null, serializer);
+    if (self != null) {
+      sse_encode_list_prim_u_8_strict(self, serializer);
+    }
+  }
+
   @protected
   void sse_encode_proxy_info(ProxyInfo self, SseSerializer serializer) {
     // Codec=Sse (Serialization based), see doc to use other codecs
@@ -485,6 +2712,8 @@ class RustLibApiImpl extends RustLibApiImplPlatform implements RustLibApi {
     sse_encode_proxy_type(self.proxyType, serializer);
     sse_encode_opt_String(self.username, serializer);
     sse_encode_opt_String(self.password, serializer);
+    sse_encode_opt_String(self.tlsServerName, serializer);
+    sse_encode_opt_String(self.tlsCertSha256, serializer);
   }
 
   @protected
@@ -493,12 +2722,77 @@ class RustLibApiImpl extends RustLibApiImplPlatform implements RustLibApi {
     sse_encode_i_32(self.index, serializer);
   }
 
+  @protected
+  void sse_encode_snowflake_config(
+      SnowflakeConfig self, SseSerializer serializer) {
+    // Codec=Sse (Serialization based), see doc to use other codecs
+    sse_encode_String(self.bridgeLine, serializer);
+    sse_encode_String(self.binaryPath, serializer);
+    sse_encode_opt_String(self.brokerUrl, serializer);
+    sse_encode_opt_String(self.frontDomain, serializer);
+    sse_encode_list_String(self.stunServers, serializer);
+    sse_encode_list_String(self.arguments, serializer);
+  }
+
+  @protected
+  void sse_encode_start_options(StartOptions self, SseSerializer serializer) {
+    // Codec=Sse (Serialization based), see doc to use other codecs
+    sse_encode_bool(self.useSystemProxy, serializer);
+    sse_encode_bool(self.connectionLog, serializer);
+    sse_encode_list_directory_mirror(self.directoryMirrors, serializer);
+    sse_encode_list_bridge_line(self.bridges, serializer);
+    sse_encode_opt_String(self.obfs4BinaryPath, serializer);
+    sse_encode_opt_box_autoadd_snowflake_config(self.snowflake, serializer);
+    sse_encode_opt_box_autoadd_u_8(self.preemptiveCircuits, serializer);
+    sse_encode_opt_box_autoadd_u_32(
+        self.consensusTimeToleranceSecs, serializer);
+    sse_encode_opt_box_autoadd_u_32(self.socksConnectTimeoutSecs, serializer);
+    sse_encode_opt_box_autoadd_u_32(self.socksIdleTimeoutSecs, serializer);
+    sse_encode_opt_String(self.bindAddr, serializer);
+    sse_encode_opt_box_autoadd_u_32(self.bootstrapTimeoutSecs, serializer);
+    sse_encode_opt_box_autoadd_u_32(self.proxyHandshakeTimeoutSecs, serializer);
+    sse_encode_opt_list_prim_u_8_strict(self.socks5MethodOrder, serializer);
+  }
+
+  @protected
+  void sse_encode_target_redaction(
+      TargetRedaction self, SseSerializer serializer) {
+    // Codec=Sse (Serialization based), see doc to use other codecs
+    sse_encode_i_32(self.index, serializer);
+  }
+
+  @protected
+  void sse_encode_tor_status(TorStatus self, SseSerializer serializer) {
+    // Codec=Sse (Serialization based), see doc to use other codecs
+    sse_encode_bool(self.running, serializer);
+    sse_encode_opt_box_autoadd_u_16(self.socksPort, serializer);
+    sse_encode_u_8(self.bootstrapPercent, serializer);
+    sse_encode_bool(self.readyForTraffic, serializer);
+    sse_encode_opt_String(self.blockage, serializer);
+    sse_encode_opt_box_autoadd_bootstrap_stalled(
+        self.bootstrapStalled, serializer);
+    sse_encode_opt_String(self.healthError, serializer);
+    sse_encode_bool(self.dormant, serializer);
+  }
+
   @protected
   void sse_encode_u_16(int self, SseSerializer serializer) {
     // Codec=Sse (Serialization based), see doc to use other codecs
     serializer.buffer.putUint16(self);
   }
 
+  @protected
+  void sse_encode_u_32(int self, SseSerializer serializer) {
+    // Codec=Sse (Serialization based), see doc to use other codecs
+    serializer.buffer.putUint32(self);
+  }
+
+  @protected
+  void sse_encode_u_64(BigInt self, SseSerializer serializer) {
+    // Codec=Sse (Serialization based), see doc to use other codecs
+    serializer.buffer.putBigUint64(self);
+  }
+
   @protected
   void sse_encode_u_8(int self, SseSerializer serializer) {
     // Codec=Sse (Serialization based), see doc to use other codecs
@@ -509,4 +2803,11 @@ class RustLibApiImpl extends RustLibApiImplPlatform implements RustLibApi {
   void sse_encode_unit(void self, SseSerializer serializer) {
     // Codec=Sse (Serialization based), see doc to use other codecs
   }
+
+  @protected
+  void sse_encode_weighted_proxy(WeightedProxy self, SseSerializer serializer) {
+    // Codec=Sse (Serialization based), see doc to use other codecs
+    sse_encode_proxy_info(self.proxy, serializer);
+    sse_encode_u_32(self.weight, serializer);
+  }
 }
@@ -22,124 +22,542 @@ abstract class RustLibApiImplPlatform extends BaseApiImpl<RustLibWire> {
   @protected
   AnyhowException dco_decode_AnyhowException(dynamic raw);
 
+  @protected
+  RustStreamSink<BootstrapStatus> dco_decode_StreamSink_bootstrap_status_Sse(
+      dynamic raw);
+
+  @protected
+  RustStreamSink<ConnectionEvent> dco_decode_StreamSink_connection_event_Sse(
+      dynamic raw);
+
+  @protected
+  RustStreamSink<LogEntry> dco_decode_StreamSink_log_entry_Sse(dynamic raw);
+
+  @protected
+  RustStreamSink<TorStatus> dco_decode_StreamSink_tor_status_Sse(dynamic raw);
+
   @protected
   String dco_decode_String(dynamic raw);
 
   @protected
   bool dco_decode_bool(dynamic raw);
 
+  @protected
+  BootstrapStalled dco_decode_bootstrap_stalled(dynamic raw);
+
+  @protected
+  BootstrapStatus dco_decode_bootstrap_status(dynamic raw);
+
+  @protected
+  BootstrapStalled dco_decode_box_autoadd_bootstrap_stalled(dynamic raw);
+
+  @protected
+  CircuitHandle dco_decode_box_autoadd_circuit_handle(dynamic raw);
+
   @protected
   ProxyInfo dco_decode_box_autoadd_proxy_info(dynamic raw);
 
+  @protected
+  SnowflakeConfig dco_decode_box_autoadd_snowflake_config(dynamic raw);
+
+  @protected
+  StartOptions dco_decode_box_autoadd_start_options(dynamic raw);
+
+  @protected
+  int dco_decode_box_autoadd_u_16(dynamic raw);
+
+  @protected
+  int dco_decode_box_autoadd_u_32(dynamic raw);
+
+  @protected
+  int dco_decode_box_autoadd_u_8(dynamic raw);
+
+  @protected
+  BridgeLine dco_decode_bridge_line(dynamic raw);
+
+  @protected
+  CircuitHandle dco_decode_circuit_handle(dynamic raw);
+
+  @protected
+  ConnectionEvent dco_decode_connection_event(dynamic raw);
+
+  @protected
+  ConnectionStage dco_decode_connection_stage(dynamic raw);
+
+  @protected
+  DirectoryMirror dco_decode_directory_mirror(dynamic raw);
+
+  @protected
+  double dco_decode_f_32(dynamic raw);
+
+  @protected
+  FdUsage dco_decode_fd_usage(dynamic raw);
+
   @protected
   int dco_decode_i_32(dynamic raw);
 
+  @protected
+  IsolationMapping dco_decode_isolation_mapping(dynamic raw);
+
+  @protected
+  List<String> dco_decode_list_String(dynamic raw);
+
+  @protected
+  List<BridgeLine> dco_decode_list_bridge_line(dynamic raw);
+
+  @protected
+  List<DirectoryMirror> dco_decode_list_directory_mirror(dynamic raw);
+
+  @protected
+  List<IsolationMapping> dco_decode_list_isolation_mapping(dynamic raw);
+
   @protected
   Uint8List dco_decode_list_prim_u_8_strict(dynamic raw);
 
+  @protected
+  List<ProxyInfo> dco_decode_list_proxy_info(dynamic raw);
+
+  @protected
+  List<WeightedProxy> dco_decode_list_weighted_proxy(dynamic raw);
+
+  @protected
+  LocalStream dco_decode_local_stream(dynamic raw);
+
+  @protected
+  LogEntry dco_decode_log_entry(dynamic raw);
+
   @protected
   String? dco_decode_opt_String(dynamic raw);
 
+  @protected
+  BootstrapStalled? dco_decode_opt_box_autoadd_bootstrap_stalled(dynamic raw);
+
   @protected
   ProxyInfo? dco_decode_opt_box_autoadd_proxy_info(dynamic raw);
 
+  @protected
+  SnowflakeConfig? dco_decode_opt_box_autoadd_snowflake_config(dynamic raw);
+
+  @protected
+  int? dco_decode_opt_box_autoadd_u_16(dynamic raw);
+
+  @protected
+  int? dco_decode_opt_box_autoadd_u_32(dynamic raw);
+
+  @protected
+  int? dco_decode_opt_box_autoadd_u_8(dynamic raw);
+
+  @protected
+  Uint8List? dco_decode_opt_list_prim_u_8_strict(dynamic raw);
+
   @protected
   ProxyInfo dco_decode_proxy_info(dynamic raw);
 
   @protected
   ProxyType dco_decode_proxy_type(dynamic raw);
 
+  @protected
+  SnowflakeConfig dco_decode_snowflake_config(dynamic raw);
+
+  @protected
+  StartOptions dco_decode_start_options(dynamic raw);
+
+  @protected
+  TargetRedaction dco_decode_target_redaction(dynamic raw);
+
+  @protected
+  TorStatus dco_decode_tor_status(dynamic raw);
+
   @protected
   int dco_decode_u_16(dynamic raw);
 
+  @protected
+  int dco_decode_u_32(dynamic raw);
+
+  @protected
+  BigInt dco_decode_u_64(dynamic raw);
+
   @protected
   int dco_decode_u_8(dynamic raw);
 
   @protected
   void dco_decode_unit(dynamic raw);
 
+  @protected
+  WeightedProxy dco_decode_weighted_proxy(dynamic raw);
+
   @protected
   AnyhowException sse_decode_AnyhowException(SseDeserializer deserializer);
 
+  @protected
+  RustStreamSink<BootstrapStatus> sse_decode_StreamSink_bootstrap_status_Sse(
+      SseDeserializer deserializer);
+
+  @protected
+  RustStreamSink<ConnectionEvent> sse_decode_StreamSink_connection_event_Sse(
+      SseDeserializer deserializer);
+
+  @protected
+  RustStreamSink<LogEntry> sse_decode_StreamSink_log_entry_Sse(
+      SseDeserializer deserializer);
+
+  @protected
+  RustStreamSink<TorStatus> sse_decode_StreamSink_tor_status_Sse(
+      SseDeserializer deserializer);
+
   @protected
   String sse_decode_String(SseDeserializer deserializer);
 
   @protected
   bool sse_decode_bool(SseDeserializer deserializer);
 
+  @protected
+  BootstrapStalled sse_decode_bootstrap_stalled(SseDeserializer deserializer);
+
+  @protected
+  BootstrapStatus sse_decode_bootstrap_status(SseDeserializer deserializer);
+
+  @protected
+  BootstrapStalled sse_decode_box_autoadd_bootstrap_stalled(
+      SseDeserializer deserializer);
+
+  @protected
+  CircuitHandle sse_decode_box_autoadd_circuit_handle(
+      SseDeserializer deserializer);
+
   @protected
   ProxyInfo sse_decode_box_autoadd_proxy_info(SseDeserializer deserializer);
 
+  @protected
+  SnowflakeConfig sse_decode_box_autoadd_snowflake_config(
+      SseDeserializer deserializer);
+
+  @protected
+  StartOptions sse_decode_box_autoadd_start_options(
+      SseDeserializer deserializer);
+
+  @protected
+  int sse_decode_box_autoadd_u_16(SseDeserializer deserializer);
+
+  @protected
+  int sse_decode_box_autoadd_u_32(SseDeserializer deserializer);
+
+  @protected
+  int sse_decode_box_autoadd_u_8(SseDeserializer deserializer);
+
+  @protected
+  BridgeLine sse_decode_bridge_line(SseDeserializer deserializer);
+
+  @protected
+  CircuitHandle sse_decode_circuit_handle(SseDeserializer deserializer);
+
+  @protected
+  ConnectionEvent sse_decode_connection_event(SseDeserializer deserializer);
+
+  @protected
+  ConnectionStage sse_decode_connection_stage(SseDeserializer deserializer);
+
+  @protected
+  DirectoryMirror sse_decode_directory_mirror(SseDeserializer deserializer);
+
+  @protected
+  double sse_decode_f_32(SseDeserializer deserializer);
+
+  @protected
+  FdUsage sse_decode_fd_usage(SseDeserializer deserializer);
+
   @protected
   int sse_decode_i_32(SseDeserializer deserializer);
 
+  @protected
+  IsolationMapping sse_decode_isolation_mapping(SseDeserializer deserializer);
+
+  @protected
+  List<String> sse_decode_list_String(SseDeserializer deserializer);
+
+  @protected
+  List<BridgeLine> sse_decode_list_bridge_line(SseDeserializer deserializer);
+
+  @protected
+  List<DirectoryMirror> sse_decode_list_directory_mirror(
+      SseDeserializer deserializer);
+
+  @protected
+  List<IsolationMapping> sse_decode_list_isolation_mapping(
+      SseDeserializer deserializer);
+
   @protected
   Uint8List sse_decode_list_prim_u_8_strict(SseDeserializer deserializer);
 
+  @protected
+  List<ProxyInfo> sse_decode_list_proxy_info(SseDeserializer deserializer);
+
+  @protected
+  List<WeightedProxy> sse_decode_list_weighted_proxy(
+      SseDeserializer deserializer);
+
+  @protected
+  LocalStream sse_decode_local_stream(SseDeserializer deserializer);
+
+  @protected
+  LogEntry sse_decode_log_entry(SseDeserializer deserializer);
+
   @protected
   String? sse_decode_opt_String(SseDeserializer deserializer);
 
+  @protected
+  BootstrapStalled? sse_decode_opt_box_autoadd_bootstrap_stalled(
+      SseDeserializer deserializer);
+
   @protected
   ProxyInfo? sse_decode_opt_box_autoadd_proxy_info(
       SseDeserializer deserializer);
 
+  @protected
+  SnowflakeConfig? sse_decode_opt_box_autoadd_snowflake_config(
+      SseDeserializer deserializer);
+
+  @protected
+  int? sse_decode_opt_box_autoadd_u_16(SseDeserializer deserializer);
+
+  @protected
+  int? sse_decode_opt_box_autoadd_u_32(SseDeserializer deserializer);
+
+  @protected
+  int? sse_decode_opt_box_autoadd_u_8(SseDeserializer deserializer);
+
+  @protected
+  Uint8List? sse_decode_opt_list_prim_u_8_strict(
+      SseDeserializer deserializer);
+
   @protected
   ProxyInfo sse_decode_proxy_info(SseDeserializer deserializer);
 
   @protected
   ProxyType sse_decode_proxy_type(SseDeserializer deserializer);
 
+  @protected
+  SnowflakeConfig sse_decode_snowflake_config(SseDeserializer deserializer);
+
+  @protected
+  StartOptions sse_decode_start_options(SseDeserializer deserializer);
+
+  @protected
+  TargetRedaction sse_decode_target_redaction(SseDeserializer deserializer);
+
+  @protected
+  TorStatus sse_decode_tor_status(SseDeserializer deserializer);
+
   @protected
   int sse_decode_u_16(SseDeserializer deserializer);
 
+  @protected
+  int sse_decode_u_32(SseDeserializer deserializer);
+
+  @protected
+  BigInt sse_decode_u_64(SseDeserializer deserializer);
+
   @protected
   int sse_decode_u_8(SseDeserializer deserializer);
 
   @protected
   void sse_decode_unit(SseDeserializer deserializer);
 
+  @protected
+  WeightedProxy sse_decode_weighted_proxy(SseDeserializer deserializer);
+
   @protected
   void sse_encode_AnyhowException(
       AnyhowException self, SseSerializer serializer);
 
+  @protected
+  void sse_encode_StreamSink_bootstrap_status_Sse(
+      RustStreamSink<BootstrapStatus> self, SseSerializer serializer);
+
+  @protected
+  void sse_encode_StreamSink_connection_event_Sse(
+      RustStreamSink<ConnectionEvent> self, SseSerializer serializer);
+
+  @protected
+  void sse_encode_StreamSink_log_entry_Sse(
+      RustStreamSink<LogEntry> self, SseSerializer serializer);
+
+  @protected
+  void sse_encode_StreamSink_tor_status_Sse(
+      RustStreamSink<TorStatus> self, SseSerializer serializer);
+
   @protected
   void sse_encode_String(String self, SseSerializer serializer);
 
   @protected
   void sse_encode_bool(bool self, SseSerializer serializer);
 
+  @protected
+  void sse_encode_bootstrap_stalled(
+      BootstrapStalled self, SseSerializer serializer);
+
+  @protected
+  void sse_encode_bootstrap_status(
+      BootstrapStatus self, SseSerializer serializer);
+
+  @protected
+  void sse_encode_box_autoadd_bootstrap_stalled(
+      BootstrapStalled self, SseSerializer serializer);
+
+  @protected
+  void sse_encode_box_autoadd_circuit_handle(
+      CircuitHandle self, SseSerializer serializer);
+
   @protected
   void sse_encode_box_autoadd_proxy_info(
       ProxyInfo self, SseSerializer serializer);
 
+  @protected
+  void sse_encode_box_autoadd_snowflake_config(
+      SnowflakeConfig self, SseSerializer serializer);
+
+  @protected
+  void sse_encode_box_autoadd_start_options(
+      StartOptions self, SseSerializer serializer);
+
+  @protected
+  void sse_encode_box_autoadd_u_16(int self, SseSerializer serializer);
+
+  @protected
+  void sse_encode_box_autoadd_u_32(int self, SseSerializer serializer);
+
+  @protected
+  void sse_encode_box_autoadd_u_8(int self, SseSerializer serializer);
+
+  @protected
+  void sse_encode_bridge_line(BridgeLine self, SseSerializer serializer);
+
+  @protected
+  void sse_encode_circuit_handle(CircuitHandle self, SseSerializer serializer);
+
+  @protected
+  void sse_encode_connection_event(
+      ConnectionEvent self, SseSerializer serializer);
+
+  @protected
+  void sse_encode_connection_stage(
+      ConnectionStage self, SseSerializer serializer);
+
+  @protected
+  void sse_encode_directory_mirror(
+      DirectoryMirror self, SseSerializer serializer);
+
+  @protected
+  void sse_encode_f_32(double self, SseSerializer serializer);
+
+  @protected
+  void sse_encode_fd_usage(FdUsage self, SseSerializer serializer);
+
   @protected
   void sse_encode_i_32(int self, SseSerializer serializer);
 
+  @protected
+  void sse_encode_isolation_mapping(
+      IsolationMapping self, SseSerializer serializer);
+
+  @protected
+  void sse_encode_list_String(List<String> self, SseSerializer serializer);
+
+  @protected
+  void sse_encode_list_bridge_line(
+      List<BridgeLine> self, SseSerializer serializer);
+
+  @protected
+  void sse_encode_list_directory_mirror(
+      List<DirectoryMirror> self, SseSerializer serializer);
+
+  @protected
+  void sse_encode_list_isolation_mapping(
+      List<IsolationMapping> self, SseSerializer serializer);
+
   @protected
   void sse_encode_list_prim_u_8_strict(
       Uint8List self, SseSerializer serializer);
 
+  @protected
+  void sse_encode_list_proxy_info(
+      List<ProxyInfo> self, SseSerializer serializer);
+
+  @protected
+  void sse_encode_list_weighted_proxy(
+      List<WeightedProxy> self, SseSerializer serializer);
+
+  @protected
+  void sse_encode_local_stream(LocalStream self, SseSerializer serializer);
+
+  @protected
+  void sse_encode_log_entry(LogEntry self, SseSerializer serializer);
+
   @protected
   void sse_encode_opt_String(String? self, SseSerializer serializer);
 
+  @protected
+  void sse_encode_opt_box_autoadd_bootstrap_stalled(
+      BootstrapStalled? self, SseSerializer serializer);
+
   @protected
   void sse_encode_opt_box_autoadd_proxy_info(
       ProxyInfo? self, SseSerializer serializer);
 
+  @protected
+  void sse_encode_opt_box_autoadd_snowflake_config(
+      SnowflakeConfig? self, SseSerializer serializer);
+
+  @protected
+  void sse_encode_opt_box_autoadd_u_16(int? self, SseSerializer serializer);
+
+  @protected
+  void sse_encode_opt_box_autoadd_u_32(int? self, SseSerializer serializer);
+
+  @protected
+  void sse_encode_opt_box_autoadd_u_8(int? self, SseSerializer serializer);
+
+  @protected
+  void sse_encode_opt_list_prim_u_8_strict(
+      Uint8List? self, SseSerializer serializer);
+
   @protected
   void sse_encode_proxy_info(ProxyInfo self, SseSerializer serializer);
 
   @protected
   void sse_encode_proxy_type(ProxyType self, SseSerializer serializer);
 
+  @protected
+  void sse_encode_snowflake_config(
+      SnowflakeConfig self, SseSerializer serializer);
+
+  @protected
+  void sse_encode_start_options(StartOptions self, SseSerializer serializer);
+
+  @protected
+  void sse_encode_target_redaction(
+      TargetRedaction self, SseSerializer serializer);
+
+  @protected
+  void sse_encode_tor_status(TorStatus self, SseSerializer serializer);
+
   @protected
   void sse_encode_u_16(int self, SseSerializer serializer);
 
+  @protected
+  void sse_encode_u_32(int self, SseSerializer serializer);
+
+  @protected
+  void sse_encode_u_64(BigInt self, SseSerializer serializer);
+
   @protected
   void sse_encode_u_8(int self, SseSerializer serializer);
 
   @protected
   void sse_encode_unit(void self, SseSerializer serializer);
+
+  @protected
+  void sse_encode_weighted_proxy(WeightedProxy self, SseSerializer serializer);
 }
 
 // Section: wire_class
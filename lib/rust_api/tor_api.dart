@@ -15,29 +15,83 @@ class TorApi {
   /// Start Tor service
   /// 
   /// Parameters:
-  /// - [socksPort]: SOCKS proxy port
+  /// - [socksPort]: SOCKS proxy port, 0 for a free port chosen by the OS
   /// - [stateDir]: State directory path
   /// - [cacheDir]: Cache directory path
-  /// - [useSystemProxy]: Whether to use system proxy (default: true)
+  /// - [options]: Everything else, see [StartOptions] (default: all defaults)
   /// 
-  /// When useSystemProxy is true, Tor will read proxy from global state.
-  /// Use TorApi.setProxy() to update the proxy configuration.
+  /// With [StartOptions.useSystemProxy], Tor reads the proxy from global
+  /// state; use TorApi.setProxy() to update the proxy configuration.
+  /// 
+  /// [StartOptions.connectionLog] leaves a record on disk of when and how
+  /// Tor was used, so leave it off unless an audit trail is required.
+  /// [StartOptions.bindAddr] other than localhost makes the SOCKS port,
+  /// which has no authentication, reachable from other devices.
+  /// 
+  /// If the client was already started with [startClientOnly], the SOCKS
+  /// listener is added to it and the options that shape the client (such
+  /// as bridges, timeouts and circuits) are those it was started with.
   /// 
   /// Returns the actual port number on success.
   static Future<int> start({
     required int socksPort,
     required String stateDir,
     required String cacheDir,
-    bool useSystemProxy = true,
+    StartOptions options = const StartOptions(),
   }) async {
     return await torStartFrb(
       socksPort: socksPort,
       stateDir: stateDir,
       cacheDir: cacheDir,
-      useSystemProxy: useSystemProxy,
+      options: options,
     );
   }
 
+  /// [start] with bootstrap progress, e.g. for a progress bar
+  ///
+  /// The stream reports how far bootstrap has come while Tor starts and
+  /// closes once the start has finished; a successful start ends with a
+  /// fraction of 1.0. The SOCKS port is then in [TorStatus.socksPort].
+  /// Parameters are as for [start].
+  static Stream<BootstrapStatus> startWithProgress({
+    required int socksPort,
+    required String stateDir,
+    required String cacheDir,
+    StartOptions options = const StartOptions(),
+  }) =>
+      torStartWithProgressFrb(
+        socksPort: socksPort,
+        stateDir: stateDir,
+        cacheDir: cacheDir,
+        options: options,
+      );
+
+  /// Start Tor without a local SOCKS listener
+  ///
+  /// Use this in builds without the `socks` feature, or when nothing outside
+  /// the app needs the SOCKS port. A later [start] call adds the listener to
+  /// the running client. [options] are as for [start], except that the
+  /// SOCKS idle timeout and bind address are taken from that later call.
+  static Future<void> startClientOnly({
+    required String stateDir,
+    required String cacheDir,
+    StartOptions options = const StartOptions(),
+  }) =>
+      torStartClientOnlyFrb(
+        stateDir: stateDir,
+        cacheDir: cacheDir,
+        options: options,
+      );
+
+  /// Move the SOCKS listener to [newPort] without restarting Tor
+  ///
+  /// Use this when the port turns out to be taken after [start]; the
+  /// bootstrapped client is kept. If [newPort] cannot be listened on this
+  /// throws and the old port stays in use. Returns the new port; pass 0 for
+  /// a free one.
+  static Future<int> rebindSocks(int newPort) =>
+      torRebindSocksFrb(newPort: newPort);
+
   /// Update current proxy configuration
   /// 
   /// Pass null to clear proxy (use direct connection).
@@ -45,6 +99,12 @@ class TorApi {
   /// 
   /// This can be called while Tor is running to update proxy dynamically.
   /// 
+  /// Throws if the proxy can never be connected to: an empty or non-IP
+  /// address, a reserved one (0.0.0.0, 255.255.255.255, multicast), port 0,
+  /// or an HTTPS proxy with neither [ProxyInfo.tlsServerName] nor a valid
+  /// [ProxyInfo.tlsCertSha256]. The previous proxy is kept in that case. IPv6
+  /// addresses may be given with or without brackets.
+  /// 
   /// Example:
   /// ```dart
   /// // Set SOCKS5 proxy
@@ -59,15 +119,339 @@ class TorApi {
   /// // Clear proxy (use direct connection)
   /// TorApi.setProxy(null);
   /// ```
-  static void setProxy(ProxyInfo? proxy) {
-    torSetProxyFrb(proxy: proxy);
+  static Future<void> setProxy(ProxyInfo? proxy) {
+    return torSetProxyFrb(proxy: proxy);
+  }
+
+  /// Spread connections across several proxies
+  ///
+  /// Each new connection picks a proxy at random in proportion to its
+  /// weight. Proxies that failed within the last minute are skipped while
+  /// others are available. Pass an empty list to clear the proxies.
+  ///
+  /// Throws as [setProxy] does if any proxy is invalid, keeping the
+  /// previous proxies.
+  ///
+  /// Example:
+  /// ```dart
+  /// TorApi.setProxies([
+  ///   WeightedProxy(proxy: proxyA, weight: 3),
+  ///   WeightedProxy(proxy: proxyB, weight: 1),
+  /// ]);
+  /// ```
+  static Future<void> setProxies(List<WeightedProxy> proxies) {
+    return torSetProxiesFrb(proxies: proxies);
   }
 
+  /// Route connections through several proxies in series
+  ///
+  /// The first hop is dialled, each hop connects to the next and the last
+  /// one to the target, so only the last hop learns the target. Hops must
+  /// be SOCKS5, SOCKS4 or HTTP CONNECT proxies. The chain replaces the
+  /// proxies from [setProxy] and [setProxies] until either is called again.
+  ///
+  /// Throws as [setProxy] does if any hop is invalid, and also if [hops] is
+  /// empty or has an HTTPS proxy, keeping the previous proxies.
+  static Future<void> setProxyChain(List<ProxyInfo> hops) =>
+      torSetProxyChainFrb(hops: hops);
+
+  /// Connect through the first of several proxies that works
+  ///
+  /// [proxies] are tried highest priority first, at most [maxAttempts] of
+  /// them per connection (all if null), moving on when one cannot be
+  /// connected to or fails its handshake. With [sticky], the proxy that
+  /// worked last is tried first from then on, so a dead primary does not
+  /// slow down every connection. Replaces the proxies from [setProxy],
+  /// [setProxies] and [setProxyChain] until one of them is called again.
+  ///
+  /// Throws as [setProxy] does if any proxy is invalid, and also if
+  /// [proxies] is empty, keeping the previous proxies.
+  static Future<void> setProxyFailover(
+    List<ProxyInfo> proxies, {
+    int? maxAttempts,
+    bool sticky = false,
+  }) =>
+      torSetProxyFailoverFrb(
+        proxies: proxies,
+        maxAttempts: maxAttempts,
+        sticky: sticky,
+      );
+
+  /// Switch proxy use on or off without restarting Tor
+  ///
+  /// Overrides the `useSystemProxy` given to [start]. When off, new
+  /// connections go direct even if a proxy is set; existing connections
+  /// keep their route.
+  static Future<void> setUseProxy(bool useProxy) =>
+      torSetUseProxyFrb(useProxy: useProxy);
+
+  /// Check that [address] is a well-formed v3 .onion address
+  ///
+  /// Throws with a message saying what is wrong (missing `.onion`, wrong
+  /// length, unsupported v2 address, bad characters or checksum), suitable
+  /// for showing to the user.
+  static Future<void> validateOnionAddress(String address) =>
+      torValidateOnionAddressFrb(address: address);
+
+  /// Resolve [name] to IP addresses through Tor
+  ///
+  /// The exit relay does the lookup, so the local resolver never sees it.
+  /// Throws with "Host not found: [name]" if the name does not exist, which
+  /// retrying will not fix, and with "Could not resolve [name]: ..." for
+  /// other failures such as Tor not being started or bootstrapped yet.
+  static Future<List<String>> resolve(String name) =>
+      torResolveFrb(name: name);
+
+  /// Build a circuit that several streams can share
+  ///
+  /// [targetHint] is a "host:port" the circuit must reach, usually the peer
+  /// the streams are for. The exit relay sees all streams on the circuit as
+  /// one user, so only group streams that belong to the same session. Call
+  /// [releaseCircuit] when done; [stop] releases all circuits.
+  static Future<CircuitHandle> buildCircuit(String targetHint) =>
+      torBuildCircuitFrb(targetHint: targetHint);
+
+  /// Open a stream to [host]:[port] on a circuit from [buildCircuit]
+  ///
+  /// Returns a port on 127.0.0.1 to connect a socket to within a minute,
+  /// and a token to send on it before anything else. Other local apps can
+  /// connect to the port too, so it only relays a connection that sends the
+  /// token; keep the token private.
+  ///
+  /// Example:
+  /// ```dart
+  /// final local = await TorApi.openStreamOnCircuit(handle,
+  ///     host: 'example.com', port: 443);
+  /// final socket =
+  ///     await Socket.connect(InternetAddress.loopbackIPv4, local.port);
+  /// socket.add(ascii.encode(local.token));
+  /// ```
+  static Future<LocalStream> openStreamOnCircuit(
+    CircuitHandle handle, {
+    required String host,
+    required int port,
+  }) =>
+      torOpenStreamOnCircuitFrb(handle: handle, host: host, port: port);
+
+  /// Release a circuit from [buildCircuit] and close its streams
+  static Future<void> releaseCircuit(CircuitHandle handle) =>
+      torReleaseCircuitFrb(handle: handle);
+
+  /// Open a stream to [host]:[port] that only shares circuits with streams
+  /// opened with the same [isolationKey]
+  ///
+  /// Use one key per activity that must not be linked, e.g. per account.
+  /// Returns a port on 127.0.0.1 and the token to send on it first, as
+  /// [openStreamOnCircuit] does.
+  static Future<LocalStream> connectIsolated({
+    required String host,
+    required int port,
+    required String isolationKey,
+  }) =>
+      torConnectIsolatedFrb(host: host, port: port, isolationKey: isolationKey);
+
+  /// Open another SOCKS port whose connections never share circuits with
+  /// those through the main port
+  ///
+  /// Reuses the running client, so no new bootstrap is needed. The port
+  /// stays open until [closeIsolatedListener] or [stop].
+  static Future<int> openIsolatedListener() =>
+      torOpenIsolatedListenerFrb();
+
+  /// Close a port from [openIsolatedListener]
+  static Future<bool> closeIsolatedListener(int port) =>
+      torCloseIsolatedListenerFrb(port: port);
+
+  /// Use new circuits for connections made from now on, like Tor Browser's
+  /// "New Identity"
+  ///
+  /// Open connections keep their circuits. The SOCKS port stays the same.
+  static Future<void> newIdentity() => torNewIdentityFrb();
+
+  /// Make circuits from [buildCircuit] exit in one of [countryCodes]
+  ///
+  /// Codes are two letters, e.g. `['us', 'de']`; each new circuit exits in
+  /// one of the countries, picked at random. An empty list allows any
+  /// country. Only [buildCircuit] honours this, not the SOCKS port. Needs a
+  /// build with the `exit-country` feature.
+  static Future<void> setExitCountries(List<String> countryCodes) =>
+      torSetExitCountriesFrb(countryCodes: countryCodes);
+
+  /// Give SOCKS connections that use [contactId] as username the circuits
+  /// of [token]
+  ///
+  /// Contacts mapped to the same token share circuits; mapping a contact
+  /// again moves its new connections to new circuits.
+  static Future<void> addIsolationMapping(String contactId, String token) =>
+      torAddIsolationMappingFrb(contactId: contactId, token: token);
+
+  /// Remove the isolation mapping of [contactId]; returns whether there was one
+  static Future<bool> removeIsolationMapping(String contactId) =>
+      torRemoveIsolationMappingFrb(contactId: contactId);
+
+  /// List the isolation mappings, sorted by contact id
+  static Future<List<IsolationMapping>> listIsolationMappings() =>
+      torListIsolationMappingsFrb();
+
+  /// Report whether the device currently has network connectivity
+  ///
+  /// Call with `false` when the device goes offline (e.g. airplane mode):
+  /// a following [start] then returns without bootstrapping instead of
+  /// retrying in vain. Calling with `true` starts the deferred bootstrap.
+  static Future<void> notifyNetworkChanged({required bool online}) =>
+      torNotifyNetworkChangedFrb(online: online);
+
+  /// Restrict which IP addresses Tor may connect to
+  ///
+  /// Entries use CIDR notation (`10.0.0.0/8`, `fe80::/10`) or bare addresses.
+  /// Addresses in [deny] are always refused; if [allow] is non-empty, only
+  /// addresses in it are permitted. Throws if an entry cannot be parsed.
+  ///
+  /// Call with two empty lists to remove the policy.
+  static Future<void> setTargetPolicy({
+    List<String> allow = const [],
+    List<String> deny = const [],
+  }) =>
+      torSetTargetPolicyFrb(allow: allow, deny: deny);
+
+  /// Choose how connection targets appear in logs
+  ///
+  /// Applies to the debug log and the connection log. The default,
+  /// [TargetRedaction.hashed], keeps connections apart without revealing
+  /// their targets, so logs can be shared for support. Use
+  /// [TargetRedaction.full] only when the full addresses are needed.
+  static Future<void> setTargetRedaction(TargetRedaction redaction) =>
+      torSetTargetRedactionFrb(redaction: redaction);
+
+  /// Restart Tor automatically when it stops working
+  ///
+  /// A running client is checked every ten minutes. After three failed
+  /// checks in a row [TorStatus.healthError] is set and, if [enabled], Tor
+  /// is stopped and started again with the same parameters. Off by default.
+  static Future<void> setAutoRestart(bool enabled) =>
+      torSetAutoRestartFrb(enabled: enabled);
+
+  /// Save data while the device is on a metered network
+  ///
+  /// While [metered], Tor stops building spare circuits ahead of time and
+  /// skips its health checks. Unlike [setDormant] Tor stays fully usable;
+  /// the first connection may just take longer. Call this whenever the
+  /// network changes, e.g. from a connectivity listener. Off by default.
+  static Future<void> setMetered(bool metered) =>
+      torSetMeteredFrb(metered: metered);
+
   /// Stop Tor service
+  ///
+  /// A pending [start] or [startClientOnly] is cancelled and its future
+  /// completes with an error. Dropping that future does not cancel it.
   static Future<void> stop() => torStopFrb();
 
+  /// Stop Tor service, giving open SOCKS connections time to finish
+  ///
+  /// New SOCKS connections are refused at once, while those already open get
+  /// up to [timeout] to close before Tor shuts down and cuts them off.
+  /// Returns whether they all finished in time. Cancels a pending start like
+  /// [stop].
+  static Future<bool> stopGracefully(Duration timeout) =>
+      torStopGracefulFrb(timeoutMs: BigInt.from(timeout.inMilliseconds));
+
+  /// Forget the selected guards so that new ones are picked on next [start]
+  ///
+  /// Only the guard state in [stateDir] is removed. Throws unless Tor is
+  /// stopped.
+  static Future<void> resetGuards({required String stateDir}) =>
+      torResetGuardsFrb(stateDir: stateDir);
+
+  /// Drop the cached consensus so that it is downloaded on next [start]
+  ///
+  /// The microdescriptors and certificates cached with it in [cacheDir] go
+  /// too; guards are kept. Throws unless Tor is stopped.
+  static Future<void> resetConsensus({required String cacheDir}) =>
+      torResetConsensusFrb(cacheDir: cacheDir);
+
+  /// Empty [cacheDir] so that the next [start] begins from a clean slate
+  ///
+  /// For when Tor will not bootstrap, e.g. after an OS update; guards in
+  /// the state directory are kept. Throws unless Tor is stopped.
+  static Future<void> clearCache({required String cacheDir}) =>
+      torClearCacheFrb(cacheDir: cacheDir);
+
+  /// Seed [cacheDir] with a directory cache shipped with the app
+  ///
+  /// Extract the bundled cache (`dir.sqlite3` and `dir_blobs` copied from
+  /// another client's cache directory) to [bundleDir] first. It is only
+  /// used if its consensus has not expired and [cacheDir] holds no
+  /// unexpired one; then the first [start] skips the directory download.
+  /// Returns whether the bundle was used. A stale bundle is not an error,
+  /// Tor just bootstraps as usual. The consensus signatures are checked
+  /// when Tor loads the cache. Throws unless Tor is stopped.
+  static Future<bool> seedDirectoryCache({
+    required String bundleDir,
+    required String cacheDir,
+  }) =>
+      torSeedDirectoryCacheFrb(bundleDir: bundleDir, cacheDir: cacheDir);
+
   /// Set dormant mode
+  ///
+  /// Call with [softMode] when the app goes to the background: Tor suspends
+  /// its background work but keeps its circuits, so calling this with false
+  /// on resume carries on without bootstrapping again. Tor stays in soft
+  /// mode while [setMetered] is on. To also close circuits, [stop] Tor
+  /// instead; arti has no dormant mode that does.
   static Future<void> setDormant({required bool softMode}) =>
       torSetDormantFrb(softMode: softMode);
+
+  /// Query current file descriptor usage
+  ///
+  /// Returns open descriptor count and the soft/hard NOFILE limits.
+  /// Throws on Windows, where this is not supported.
+  static Future<FdUsage> fdUsage() => torFdUsageFrb();
+
+  /// List the optional features compiled into the native library
+  static Future<List<String>> enabledFeatures() => torEnabledFeaturesFrb();
+
+  /// Get the config of the running client as TOML
+  ///
+  /// For display or support logs. Settings left at arti's defaults are
+  /// omitted and bridge lines are redacted. Throws if Tor is not running.
+  static Future<String> effectiveConfig() => torEffectiveConfigFrb();
+}
+
+/// Read-only view of a Tor instance owned elsewhere
+///
+/// Use this from code (or a background isolate) that should follow Tor's
+/// status without being able to stop or reconfigure it. It attaches to
+/// whatever instance is running in the process; nothing is started.
+class TorObserver {
+  const TorObserver();
+
+  /// Current status
+  Future<TorStatus> status() => torStatusFrb();
+
+  /// Current status, followed by every change
+  ///
+  /// Keeps emitting across stop and restart of the service. Cancel the
+  /// subscription to detach.
+  Stream<TorStatus> statusChanges() => torObserveStatusFrb();
+
+  /// Current bootstrap progress with arti's phase, followed by every change
+  ///
+  /// For a progress bar: listen before calling [TorApi.start] or while it
+  /// runs. Goes back to 0 when Tor stops. Cancel the subscription to detach.
+  Stream<BootstrapStatus> bootstrapProgress() => torObserveBootstrapFrb();
+
+  /// Outgoing connection attempts from now on: each one's start and then
+  /// its success or failure, with the proxy it went through
+  ///
+  /// Targets are redacted as set by [TorApi.setTargetRedaction]. A listener
+  /// that falls far behind misses the oldest events. Cancel the
+  /// subscription to detach.
+  Stream<ConnectionEvent> connections() => torObserveConnectionsFrb();
+
+  /// Tor's log lines from now on, error down to debug level
+  ///
+  /// For a debug console. If the listener falls behind, lines are dropped
+  /// and a warning says how many. Cancel the subscription to detach.
+  Stream<LogEntry> logs() => torSubscribeLogsFrb();
 }
 
@@ -181,7 +181,7 @@ class Tor {
         socksPort: newPort,
         stateDir: stateDir.path,
         cacheDir: cacheDir.path,
-        useSystemProxy: useSystemProxy,
+        options: frb.StartOptions(useSystemProxy: useSystemProxy),
       );
 
       // Set the started flag.
@@ -256,7 +256,7 @@ class Tor {
   }
 
   /// Callback invoked when system proxy changes
-  void _onProxyChanged(ProxyInfo? proxy) {
+  Future<void> _onProxyChanged(ProxyInfo? proxy) async {
     if (proxy != null) {
       // Convert from ProxyInfo to FRB ProxyInfo
       final frbProxyInfo = frb.ProxyInfo(
@@ -271,14 +271,20 @@ class Tor {
 
       debugPrint('[Tor] 🔄 Proxy changed, updating Rust: ${proxy.address}:${proxy.port} (${proxy.type.name})');
 
-      TorApi.setProxy(frbProxyInfo);
+      try {
+        await TorApi.setProxy(frbProxyInfo);
+      } catch (e) {
+        // Rust keeps the previous proxy when this one is rejected
+        debugPrint('[Tor] ❌ Proxy rejected: $e');
+        return;
+      }
 
       debugPrint('[Tor] ✅ Rust proxy updated');
     } else {
       debugPrint('[Tor] 🔄 Proxy removed, clearing Rust proxy');
 
       // Clear proxy (use direct connection)
-      TorApi.setProxy(null);
+      await TorApi.setProxy(null);
 
       debugPrint('[Tor] ✅ Rust proxy cleared (direct connection)');
     }
@@ -7,19 +7,57 @@
 //! This module contains all the public APIs exposed to Dart via flutter_rust_bridge.
 //! 
 //! ## Organization
-//! - `types`: Data types (ProxyInfo, ProxyType)
+//! - `types`: Data types (BootstrapStalled, BootstrapStatus, BridgeLine, CircuitHandle, ConnectionEvent, ConnectionStage, ProxyInfo, ProxyType, WeightedProxy, DirectoryMirror, FdUsage, IsolationMapping, LocalStream, LogEntry, SnowflakeConfig, StartOptions, TorStatus, TargetRedaction)
 //! - `tor`: Tor service APIs (start, stop, setProxy, etc.)
 
 pub mod types;
 pub mod tor;
 
 // Re-export public types and functions
-pub use types::{ProxyInfo, ProxyType};
+pub use types::{BootstrapStalled, BootstrapStatus, BridgeLine, CircuitHandle, ConnectionEvent, ConnectionStage, DirectoryMirror, FdUsage, IsolationMapping, LocalStream, LogEntry, ProxyInfo, ProxyType, SnowflakeConfig, StartOptions, TargetRedaction, TorStatus, WeightedProxy};
 pub use tor::{
     tor_hello_frb,
     tor_start_frb,
+    tor_start_with_progress_frb,
+    tor_start_client_only_frb,
+    tor_rebind_socks_frb,
     tor_set_proxy_frb,
+    tor_set_proxies_frb,
+    tor_set_proxy_chain_frb,
+    tor_set_proxy_failover_frb,
+    tor_set_use_proxy_frb,
+    tor_set_target_policy_frb,
+    tor_set_target_redaction_frb,
+    tor_notify_network_changed_frb,
+    tor_resolve_frb,
+    tor_build_circuit_frb,
+    tor_open_stream_on_circuit_frb,
+    tor_release_circuit_frb,
+    tor_connect_isolated_frb,
+    tor_open_isolated_listener_frb,
+    tor_close_isolated_listener_frb,
+    tor_new_identity_frb,
+    tor_set_exit_countries_frb,
+    tor_add_isolation_mapping_frb,
+    tor_remove_isolation_mapping_frb,
+    tor_list_isolation_mappings_frb,
+    tor_validate_onion_address_frb,
+    tor_status_frb,
+    tor_observe_status_frb,
+    tor_observe_bootstrap_frb,
+    tor_observe_connections_frb,
+    tor_subscribe_logs_frb,
+    tor_set_auto_restart_frb,
+    tor_set_metered_frb,
     tor_stop_frb,
+    tor_stop_graceful_frb,
+    tor_reset_guards_frb,
+    tor_reset_consensus_frb,
+    tor_clear_cache_frb,
+    tor_seed_directory_cache_frb,
     tor_set_dormant_frb,
+    tor_fd_usage_frb,
+    tor_enabled_features_frb,
+    tor_effective_config_frb,
 };
 
@@ -10,6 +10,12 @@ use flutter_rust_bridge::frb;
 pub enum ProxyType {
     Socks5,
     HttpConnect,
+    /// SOCKS4, or SOCKS4a for domain names; the username is sent as user id
+    Socks4,
+    /// HTTP CONNECT inside TLS, so that neither the credentials nor the
+    /// target cross the network in the clear; needs the `https-proxy`
+    /// feature and tls_server_name or tls_cert_sha256
+    HttpsConnect,
 }
 
 /// Proxy information passed from Dart
@@ -21,5 +27,296 @@ pub struct ProxyInfo {
     pub proxy_type: ProxyType,
     pub username: Option<String>,
     pub password: Option<String>,
+    /// HTTPS proxies: the host name the proxy's certificate must be issued
+    /// for, also sent as SNI
+    pub tls_server_name: Option<String>,
+    /// HTTPS proxies: SHA-256 of the proxy's certificate (DER) as 64 hex
+    /// digits, optionally separated by colons as openssl prints it. If set, the
+    /// certificate only has to match it and is not checked against CAs or
+    /// tls_server_name, for proxies with self-signed certificates.
+    pub tls_cert_sha256: Option<String>,
 }
 
+/// How connection targets appear in logs
+///
+/// Applies to the debug log and the connection log. `Hashed` keeps targets
+/// apart without revealing them; the hash key is random per process, so
+/// hashes only match within one run.
+#[frb]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TargetRedaction {
+    /// The full address, e.g. "203.0.113.5:9001"
+    Full,
+    /// The IP without the port
+    HostOnly,
+    /// A keyed hash of the address
+    Hashed,
+    /// Nothing; targets are logged as "redacted"
+    None,
+}
+
+/// Proxy with a relative weight for load balancing
+///
+/// New connections are spread across proxies in proportion to their weights.
+/// A weight of 0 disables the proxy.
+#[frb]
+#[derive(Debug, Clone)]
+pub struct WeightedProxy {
+    pub proxy: ProxyInfo,
+    pub weight: u32,
+}
+
+/// Directory mirror to bootstrap from
+///
+/// A mirror is a relay that caches directory documents; it is what arti
+/// calls a fallback directory. It only serves copies, which are still
+/// checked against the directory authorities' signatures, so a bad mirror
+/// can stall bootstrap but cannot forge the network view.
+///
+/// `rsa_identity` is the relay's hex fingerprint, `ed_identity` its base64
+/// Ed25519 identity, and `addresses` its ORPorts as "ip:port".
+#[frb]
+#[derive(Debug, Clone)]
+pub struct DirectoryMirror {
+    pub rsa_identity: String,
+    pub ed_identity: String,
+    pub addresses: Vec<String>,
+}
+
+/// Bridge to reach the Tor network through, for where Tor is blocked
+///
+/// `line` is a bridge line as handed out by BridgeDB, e.g.
+/// "obfs4 192.0.2.3:443 <fingerprint> cert=... iat-mode=0". Only obfs4
+/// bridges are supported; they need the obfs4proxy or lyrebird binary. For
+/// Snowflake see SnowflakeConfig.
+#[frb]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BridgeLine {
+    pub line: String,
+}
+
+/// Snowflake bridge to reach the Tor network through, for where obfs4
+/// bridges are blocked too
+///
+/// `bridge_line` is a snowflake bridge line as shipped with Tor Browser,
+/// e.g. "snowflake 192.0.2.3:80 <fingerprint> fingerprint=<fingerprint>
+/// url=... fronts=... ice=...". `broker_url`, `front_domain` and
+/// `stun_servers` replace its url=, fronts= and ice= settings if given;
+/// STUN servers are written as "stun:host:port". `binary_path` is the
+/// snowflake client (snowflake-client or lyrebird), run with `arguments`.
+#[frb]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SnowflakeConfig {
+    pub bridge_line: String,
+    pub binary_path: String,
+    pub broker_url: Option<String>,
+    pub front_domain: Option<String>,
+    pub stun_servers: Vec<String>,
+    pub arguments: Vec<String>,
+}
+
+/// Settings for tor_start_frb() and tor_start_client_only_frb()
+///
+/// Everything about starting Tor except the SOCKS port and the directories,
+/// so that both calls take the same settings. Settings that shape the client
+/// only apply if the call creates it, not when tor_start_frb() adds a SOCKS
+/// listener to a client from tor_start_client_only_frb(). Timeouts are in
+/// whole seconds; 0 is rejected.
+#[frb]
+#[derive(Debug, Clone)]
+pub struct StartOptions {
+    /// Connect through the proxy from tor_set_proxy_frb(); direct if false
+    /// or no proxy is set
+    #[frb(default = true)]
+    pub use_system_proxy: bool,
+    /// Append every outgoing connection (time, target, proxy, bytes,
+    /// duration) to `connections.log` in the state directory, rotated at
+    /// 1 MiB. This is an audit trail of when and how Tor was used that
+    /// persists on disk, so only enable it where that is wanted.
+    #[frb(default = false)]
+    pub connection_log: bool,
+    /// Replace arti's built-in fallback directories for the first download
+    /// of the directory. Mirrors are relays that cache directory documents,
+    /// not directory authorities. If bootstrap through them fails or takes
+    /// over a minute, the built-in fallback directories are used instead.
+    #[frb(default = "[]")]
+    pub directory_mirrors: Vec<DirectoryMirror>,
+    /// Connect only through these obfs4 bridges, for networks where Tor
+    /// relays are blocked; each is checked before Tor starts. They need the
+    /// `bridges` feature and obfs4_binary_path.
+    #[frb(default = "[]")]
+    pub bridges: Vec<BridgeLine>,
+    /// The obfs4proxy or lyrebird executable that arti runs to speak obfs4.
+    /// It connects to the bridges itself, so the proxy from
+    /// tor_set_proxy_frb() is not used for them.
+    pub obfs4_binary_path: Option<String>,
+    /// Snowflake bridge, for networks that also block obfs4 bridges; alone
+    /// or next to `bridges`, with the same requirements
+    pub snowflake: Option<SnowflakeConfig>,
+    /// Exit circuits built ahead of time for each port Tor expects to be
+    /// used. None keeps arti's default of 2; 0 turns this off, which saves
+    /// battery and bandwidth at the cost of slower first connections. At
+    /// most 8.
+    pub preemptive_circuits: Option<u8>,
+    /// For devices whose clock is off and cannot be corrected: a consensus
+    /// is accepted this long before it becomes valid and after it expires.
+    /// None keeps arti's defaults (1 day before, 3 days after); at most 7
+    /// days. Every bit of extra tolerance also lets an attacker feed the
+    /// device an old consensus for that much longer, so only raise it as far
+    /// as the clock needs.
+    pub consensus_time_tolerance_secs: Option<u32>,
+    /// How long a stream may take to open through Tor; on timeout a SOCKS
+    /// client gets an error reply. None keeps arti's 10 seconds.
+    pub socks_connect_timeout_secs: Option<u32>,
+    /// Close SOCKS connections that carry no traffic for this long; None
+    /// never does. Only used by tor_start_frb().
+    pub socks_idle_timeout_secs: Option<u32>,
+    /// IP address the SOCKS listener listens on, e.g. 0.0.0.0 for all
+    /// interfaces; None keeps it on localhost. Anything but localhost
+    /// exposes the listener, which has no authentication, to every device
+    /// that can reach that address. Only used by tor_start_frb(), and
+    /// ignored if the SOCKS listener is already running.
+    pub bind_addr: Option<String>,
+    /// Give up on bootstrap after this long, failing with "Bootstrap timed
+    /// out after ...". The half-started client is dropped, so starting again
+    /// starts over. None waits as long as bootstrap takes.
+    pub bootstrap_timeout_secs: Option<u32>,
+    /// Give up on a proxy that has not finished connecting and its
+    /// handshake this long after it was dialled, and try the next one. None
+    /// keeps 30 seconds.
+    pub proxy_handshake_timeout_secs: Option<u32>,
+    /// SOCKS5 authentication methods to offer proxies, most preferred
+    /// first, e.g. [0] to never offer username/password to proxies that reject
+    /// clients offering a method they do not use. Username/password (2) is
+    /// only offered with credentials; unknown methods are skipped. None offers
+    /// every method the credentials allow.
+    pub socks5_method_order: Option<Vec<u8>>,
+}
+
+/// Circuit built with tor_build_circuit_frb(), for opening several
+/// streams on it
+#[frb]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CircuitHandle {
+    pub id: u64,
+}
+
+/// Local port relaying a stream, from tor_open_stream_on_circuit_frb() or
+/// tor_connect_isolated_frb()
+///
+/// Connect a socket to `port` on 127.0.0.1 and send `token`, as ASCII,
+/// before anything else. Other local apps can connect to the port too; the
+/// token is how the relay tells the app apart from them, so keep it
+/// private. Connections that do not send it within 5 seconds are closed.
+/// The port relays a single connection and is closed if none sends the
+/// token within a minute.
+#[frb]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LocalStream {
+    pub port: u16,
+    /// 32 hex digits
+    pub token: String,
+}
+
+/// SOCKS username mapped to an isolation token, see
+/// tor_add_isolation_mapping_frb()
+#[frb]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IsolationMapping {
+    pub contact_id: String,
+    pub token: String,
+}
+
+/// File descriptor usage of the current process
+#[frb]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FdUsage {
+    pub open: u64,
+    pub soft_limit: u64,
+    pub hard_limit: u64,
+}
+
+/// Bootstrap progress, as reported by tor_start_with_progress_frb()
+#[frb]
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct BootstrapStatus {
+    /// How far bootstrap has come, 0.0 to 1.0
+    pub fraction: f32,
+    /// Arti's description of the current phase, e.g. "30%: connecting to the internet"
+    pub summary: String,
+}
+
+/// Bootstrap that has made no progress for a while
+#[frb]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BootstrapStalled {
+    /// What bootstrap was doing when it stopped making progress
+    pub last_phase: String,
+    /// What the user can try to get it unstuck
+    pub suggestion: String,
+}
+
+/// Snapshot of the Tor service, as seen by observers
+#[frb]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TorStatus {
+    /// A client exists (it may still be bootstrapping)
+    pub running: bool,
+    /// Port of the local SOCKS listener, if one is running
+    pub socks_port: Option<u16>,
+    /// Bootstrap progress, 0 to 100
+    pub bootstrap_percent: u8,
+    /// Bootstrapped far enough to carry traffic
+    pub ready_for_traffic: bool,
+    /// Why bootstrap appears to be stuck, if it is
+    pub blockage: Option<String>,
+    /// Set while bootstrap has not progressed for a while, with a hint
+    pub bootstrap_stalled: Option<BootstrapStalled>,
+    /// Why the client failed its periodic health checks, if it did
+    pub health_error: Option<String>,
+    /// The running client is in soft dormant mode, see tor_set_dormant_frb()
+    /// and tor_set_metered_frb()
+    pub dormant: bool,
+}
+
+/// Log line from Tor, as sent by tor_subscribe_logs_frb()
+#[frb]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LogEntry {
+    /// "ERROR", "WARN", "INFO" or "DEBUG"
+    pub level: String,
+    /// Where the line comes from, usually an arti module such as
+    /// "tor_guardmgr::guard"
+    pub target: String,
+    /// The message, followed by any other fields as " name=value"
+    pub message: String,
+}
+
+/// Stage of an outgoing connection attempt, see ConnectionEvent
+#[frb]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConnectionStage {
+    /// The attempt started
+    Started,
+    /// The stream is ready for use
+    Connected,
+    /// The attempt failed
+    Failed,
+}
+
+/// Outgoing connection attempt, as sent by tor_observe_connections_frb()
+#[frb]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConnectionEvent {
+    pub stage: ConnectionStage,
+    /// Where the connection goes, redacted as in the logs
+    pub target: String,
+    /// How it gets there, as in the connection log, e.g.
+    /// "socks5:127.0.0.1:1080" or "direct"
+    pub via: String,
+    /// Connected: how long the attempt took, in milliseconds
+    pub elapsed_ms: Option<u32>,
+    /// Failed: why; "Proxy failed: ..." if the proxy failed rather than the
+    /// target
+    pub error: Option<String>,
+}
@@ -0,0 +1,516 @@
+// SPDX-FileCopyrightText: 2025 0xChat
+//
+// SPDX-License-Identifier: MIT
+
+//! Opt-in audit log of outgoing connections
+//!
+//! Each line records when a connection was opened, its target, the proxy it
+//! went through, the bytes sent and received and how long it stayed open.
+//! A stream that failed also records why, e.g. `error=proxy:ConnectionReset`
+//! when the connection to its proxy broke (see `ProxyStreamError`).
+//! Targets are redacted as set with `TargetRedaction` (hashed by default).
+//! Direct connections record why no proxy was used (see `DirectReason`), e.g.
+//! `via=direct:no-proxy-set`, so unexpected direct traffic can be traced.
+//!
+//! Privacy: the targets are Tor relays and bridges (or whatever the proxy was
+//! asked to reach), so the log shows when Tor was used and through which
+//! entry points. Anyone who can read the state directory can see this after
+//! the fact. It is off unless `start` is called with `connection_log`.
+//!
+//! The same stream wrapper also closes accepted (SOCKS) connections that
+//! carry no traffic for the listener's idle timeout, if one is set, maps
+//! their SOCKS usernames to isolation tokens (see `socks_isolation`) and
+//! counts them while open, so a graceful stop can wait for them.
+
+use std::fs::{self, File, OpenOptions};
+use std::future::Future;
+use std::io::{Error as IoError, ErrorKind, IoSlice, Result as IoResult, Write};
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant, SystemTime};
+
+use futures::{AsyncRead, AsyncWrite, Stream, StreamExt};
+use log::warn;
+use time::format_description::well_known::Rfc3339;
+use time::OffsetDateTime;
+use tokio::sync::watch;
+use tokio::time::Sleep;
+use tor_rtcompat::{NetStreamListener, StreamOps};
+
+use crate::proxy_provider::{ProxyLink, ProxyStream, ProxyStreamError};
+use crate::socks_isolation::{IsolationMap, SocksUsernameRewrite};
+
+/// Log file name inside the state directory
+pub const CONNECTION_LOG_FILE: &str = "connections.log";
+
+/// Size at which the log is rotated; one rotated file (`.1`) is kept
+const MAX_LOG_SIZE: u64 = 1024 * 1024;
+
+/// Append-only connection log with size-capped rotation
+pub struct ConnectionLog {
+    path: PathBuf,
+    file: Mutex<File>,
+}
+
+impl ConnectionLog {
+    /// Open (or create) the log in `state_dir`
+    pub fn open(state_dir: &Path) -> IoResult<Self> {
+        fs::create_dir_all(state_dir)?;
+        let path = state_dir.join(CONNECTION_LOG_FILE);
+        let file = OpenOptions::new().create(true).append(true).open(&path)?;
+        Ok(Self {
+            path,
+            file: Mutex::new(file),
+        })
+    }
+
+    /// Append one line, rotating first if the file has reached the size cap
+    fn append(&self, line: &str) -> IoResult<()> {
+        let mut file = self.file.lock().unwrap();
+        if file.metadata()?.len() >= MAX_LOG_SIZE {
+            let mut rotated = self.path.clone().into_os_string();
+            rotated.push(".1");
+            fs::rename(&self.path, rotated)?;
+            *file = OpenOptions::new().create(true).append(true).open(&self.path)?;
+        }
+        writeln!(file, "{}", line)
+    }
+}
+
+/// Connection being tracked for the log
+struct Entry {
+    log: Arc<ConnectionLog>,
+    opened_at: SystemTime,
+    opened: Instant,
+    target: String,
+    via: String,
+    sent: u64,
+    received: u64,
+    /// First error of the stream, see error_field
+    error: Option<String>,
+}
+
+impl Entry {
+    fn finish(self) {
+        let opened_at = OffsetDateTime::from(self.opened_at)
+            .format(&Rfc3339)
+            .unwrap_or_default();
+        let mut line = format!(
+            "{} target={} via={} sent={} received={} duration_ms={}",
+            opened_at,
+            self.target,
+            self.via,
+            self.sent,
+            self.received,
+            self.opened.elapsed().as_millis(),
+        );
+        if let Some(error) = &self.error {
+            line.push_str(&format!(" error={}", error));
+        }
+        // Failing to log must not affect the connection itself
+        if let Err(e) = self.log.append(&line) {
+            warn!("Failed to write connection log: {}", e);
+        }
+    }
+}
+
+/// Deadline that closes a stream after a period without traffic
+struct IdleTimer {
+    timeout: Duration,
+    sleep: Pin<Box<Sleep>>,
+}
+
+impl IdleTimer {
+    fn new(timeout: Duration) -> Self {
+        Self {
+            timeout,
+            sleep: Box::pin(tokio::time::sleep(timeout)),
+        }
+    }
+
+    /// Push the deadline back after traffic
+    fn reset(&mut self) {
+        self.sleep.as_mut().reset(tokio::time::Instant::now() + self.timeout);
+    }
+
+    /// The error to fail with once the deadline has passed
+    fn poll_expired(&mut self, cx: &mut Context<'_>) -> Poll<IoError> {
+        self.sleep.as_mut().poll(cx).map(|()| {
+            IoError::new(
+                ErrorKind::TimedOut,
+                format!("No traffic for {} seconds", self.timeout.as_secs()),
+            )
+        })
+    }
+}
+
+/// Number of accepted streams still open
+///
+/// Clones share the count. Streams are counted from when they are accepted
+/// until they are dropped, i.e. until the SOCKS server is done with them.
+#[derive(Clone)]
+pub struct ActiveStreams(Arc<watch::Sender<usize>>);
+
+impl Default for ActiveStreams {
+    fn default() -> Self {
+        Self(Arc::new(watch::channel(0).0))
+    }
+}
+
+impl ActiveStreams {
+    /// Count one more stream until the returned guard is dropped
+    fn track(&self) -> ActiveStream {
+        self.0.send_modify(|count| *count += 1);
+        ActiveStream(Arc::clone(&self.0))
+    }
+
+    /// Wait until no counted stream is open
+    pub async fn drained(&self) {
+        let mut count = self.0.subscribe();
+        // Cannot fail: we hold the sender
+        let _ = count.wait_for(|&count| count == 0).await;
+    }
+}
+
+/// One open stream in ActiveStreams
+struct ActiveStream(Arc<watch::Sender<usize>>);
+
+impl Drop for ActiveStream {
+    fn drop(&mut self) {
+        self.0.send_modify(|count| *count -= 1);
+    }
+}
+
+/// Stream wrapper that counts traffic and writes a log line when dropped
+///
+/// For streams opened through a proxy, read and write errors are also
+/// wrapped in a ProxyStreamError naming the proxy.
+///
+/// This only observes: bytes pass through unchanged. Keep it that way, and
+/// make any future transform on these streams opt-in per stream, like the
+/// SOCKS username mapping of accepted streams. Everything
+/// here is TLS to a relay or bridge (possibly inside a proxy tunnel), so
+/// compressing it costs CPU and saves nothing.
+pub struct LoggedStream<S> {
+    inner: S,
+    entry: Option<Entry>,
+    proxy: Option<ProxyLink>,
+    idle: Option<IdleTimer>,
+    socks_rewrite: Option<SocksUsernameRewrite>,
+    _active: Option<ActiveStream>,
+}
+
+impl<S> LoggedStream<S> {
+    /// Wrap a stream that is not logged
+    pub fn unlogged(inner: S) -> Self {
+        Self {
+            inner,
+            entry: None,
+            proxy: None,
+            idle: None,
+            socks_rewrite: None,
+            _active: None,
+        }
+    }
+
+    /// Wrap a stream to `target` (already redacted), opened at `opened` via
+    /// `via`, logging to `log` if set
+    pub fn new(
+        inner: S,
+        log: Option<Arc<ConnectionLog>>,
+        target: String,
+        via: String,
+        opened_at: SystemTime,
+        opened: Instant,
+    ) -> Self {
+        let entry = log.map(|log| Entry {
+            log,
+            opened_at,
+            opened,
+            target,
+            via,
+            sent: 0,
+            received: 0,
+            error: None,
+        });
+        Self {
+            inner,
+            entry,
+            proxy: None,
+            idle: None,
+            socks_rewrite: None,
+            _active: None,
+        }
+    }
+
+    /// Mark the stream as going through a proxy, to tag its errors
+    pub fn with_proxy(mut self, proxy: ProxyLink) -> Self {
+        self.proxy = Some(proxy);
+        self
+    }
+
+    /// Fail reads and writes once the stream has carried no traffic in
+    /// either direction for `timeout`
+    pub fn with_idle_timeout(mut self, timeout: Duration) -> Self {
+        self.idle = Some(IdleTimer::new(timeout));
+        self
+    }
+
+    /// Map the SOCKS5 username the client sends through `map`
+    pub fn with_socks_username_map(mut self, map: IsolationMap) -> Self {
+        self.socks_rewrite = Some(SocksUsernameRewrite::new(map));
+        self
+    }
+
+    /// Count the stream in `streams` until it is dropped
+    pub fn tracked_by(mut self, streams: &ActiveStreams) -> Self {
+        self._active = Some(streams.track());
+        self
+    }
+
+    /// Restart the idle timer on traffic, or time out while waiting for it
+    fn track_idle(&mut self, cx: &mut Context<'_>, result: Poll<IoResult<usize>>) -> Poll<IoResult<usize>> {
+        let Some(idle) = &mut self.idle else {
+            return result;
+        };
+        match result {
+            Poll::Ready(Ok(n)) if n > 0 => {
+                idle.reset();
+                result
+            }
+            Poll::Pending => idle.poll_expired(cx).map(Err),
+            result => result,
+        }
+    }
+
+    /// Wrap an error from a proxied stream, see ProxyLink::tag
+    fn tag_error<T>(&mut self, result: Poll<IoResult<T>>) -> Poll<IoResult<T>> {
+        match (result, &mut self.proxy) {
+            (Poll::Ready(Err(e)), Some(proxy)) => Poll::Ready(Err(proxy.tag(e))),
+            (result, _) => result,
+        }
+    }
+
+    /// Keep the first error of the stream for its log line
+    fn record_error<T>(&mut self, result: Poll<IoResult<T>>) -> Poll<IoResult<T>> {
+        if let (Poll::Ready(Err(e)), Some(entry)) = (&result, &mut self.entry) {
+            entry.error.get_or_insert_with(|| error_field(e));
+        }
+        result
+    }
+}
+
+/// How a stream error is logged: its kind, prefixed with `proxy:` if the
+/// connection to the proxy broke rather than the stream itself
+fn error_field(error: &IoError) -> String {
+    match ProxyStreamError::from_io(error) {
+        Some(e) => format!("proxy:{:?}", e.source.kind()),
+        None => format!("{:?}", error.kind()),
+    }
+}
+
+impl<S> Drop for LoggedStream<S> {
+    fn drop(&mut self) {
+        if let Some(entry) = self.entry.take() {
+            entry.finish();
+        }
+    }
+}
+
+impl<S: AsyncRead + Unpin> AsyncRead for LoggedStream<S> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<IoResult<usize>> {
+        let this = self.get_mut();
+        let result = match &mut this.socks_rewrite {
+            Some(rewrite) => rewrite.poll_read(&mut this.inner, cx, buf),
+            None => Pin::new(&mut this.inner).poll_read(cx, buf),
+        };
+        let result = this.track_idle(cx, result);
+        if let (Poll::Ready(Ok(n)), Some(entry)) = (&result, &mut this.entry) {
+            entry.received += *n as u64;
+        }
+        let result = this.tag_error(result);
+        this.record_error(result)
+    }
+}
+
+impl<S: AsyncWrite + Unpin> AsyncWrite for LoggedStream<S> {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<IoResult<usize>> {
+        let this = self.get_mut();
+        let result = Pin::new(&mut this.inner).poll_write(cx, buf);
+        let result = this.track_idle(cx, result);
+        if let (Poll::Ready(Ok(n)), Some(entry)) = (&result, &mut this.entry) {
+            entry.sent += *n as u64;
+        }
+        let result = this.tag_error(result);
+        this.record_error(result)
+    }
+
+    fn poll_write_vectored(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        bufs: &[IoSlice<'_>],
+    ) -> Poll<IoResult<usize>> {
+        let this = self.get_mut();
+        let result = Pin::new(&mut this.inner).poll_write_vectored(cx, bufs);
+        let result = this.track_idle(cx, result);
+        if let (Poll::Ready(Ok(n)), Some(entry)) = (&result, &mut this.entry) {
+            entry.sent += *n as u64;
+        }
+        let result = this.tag_error(result);
+        this.record_error(result)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<IoResult<()>> {
+        Pin::new(&mut self.get_mut().inner).poll_flush(cx)
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<IoResult<()>> {
+        Pin::new(&mut self.get_mut().inner).poll_close(cx)
+    }
+}
+
+impl<S: StreamOps> StreamOps for LoggedStream<S> {
+    fn set_tcp_notsent_lowat(&self, notsent_lowat: u32) -> IoResult<()> {
+        self.inner.set_tcp_notsent_lowat(notsent_lowat)
+    }
+
+    fn new_handle(&self) -> Box<dyn StreamOps + Send + Unpin> {
+        self.inner.new_handle()
+    }
+}
+
+type IncomingItem<S> = IoResult<(S, SocketAddr)>;
+
+/// Listener wrapper yielding (unlogged) plain `LoggedStream`s, so that
+/// listeners and outgoing connections share a stream type
+pub struct LoggedListener<L> {
+    inner: L,
+    idle_timeout: Option<Duration>,
+    isolation_map: IsolationMap,
+    active_streams: ActiveStreams,
+}
+
+impl<L> LoggedListener<L> {
+    /// Wrap `inner`, closing accepted streams idle for `idle_timeout` if set,
+    /// mapping their SOCKS usernames through `isolation_map` and counting
+    /// them in `active_streams` while open
+    pub fn new(
+        inner: L,
+        idle_timeout: Option<Duration>,
+        isolation_map: IsolationMap,
+        active_streams: ActiveStreams,
+    ) -> Self {
+        Self {
+            inner,
+            idle_timeout,
+            isolation_map,
+            active_streams,
+        }
+    }
+}
+
+/// Incoming streams of a LoggedListener
+pub struct LoggedIncoming<I> {
+    inner: I,
+    idle_timeout: Option<Duration>,
+    isolation_map: IsolationMap,
+    active_streams: ActiveStreams,
+}
+
+impl<I, S> Stream for LoggedIncoming<I>
+where
+    I: Stream<Item = IncomingItem<S>> + Unpin,
+{
+    type Item = IncomingItem<LoggedStream<ProxyStream<S>>>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        let idle_timeout = this.idle_timeout;
+        let isolation_map = &this.isolation_map;
+        let active_streams = &this.active_streams;
+        this.inner.poll_next_unpin(cx).map(|item| {
+            item.map(|result| {
+                result.map(|(stream, addr)| {
+                    let stream = LoggedStream::unlogged(ProxyStream::Plain(stream))
+                        .with_socks_username_map(Arc::clone(isolation_map))
+                        .tracked_by(active_streams);
+                    match idle_timeout {
+                        Some(timeout) => (stream.with_idle_timeout(timeout), addr),
+                        None => (stream, addr),
+                    }
+                })
+            })
+        })
+    }
+}
+
+impl<L> NetStreamListener for LoggedListener<L>
+where
+    L: NetStreamListener,
+    L::Stream: Unpin,
+    L::Incoming: Stream<Item = IncomingItem<L::Stream>>,
+{
+    type Stream = LoggedStream<ProxyStream<L::Stream>>;
+    type Incoming = LoggedIncoming<L::Incoming>;
+
+    fn incoming(self) -> Self::Incoming {
+        LoggedIncoming {
+            inner: self.inner.incoming(),
+            idle_timeout: self.idle_timeout,
+            isolation_map: self.isolation_map,
+            active_streams: self.active_streams,
+        }
+    }
+
+    fn local_addr(&self) -> IoResult<SocketAddr> {
+        self.inner.local_addr()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::io::Cursor;
+    use futures::{AsyncReadExt, AsyncWriteExt, FutureExt};
+
+    #[test]
+    fn test_error_field() {
+        let mut link = ProxyLink::new("127.0.0.1:1080".parse().unwrap(), None);
+        let error = link.tag(IoError::new(ErrorKind::ConnectionReset, "reset"));
+        assert_eq!(error_field(&error), "proxy:ConnectionReset");
+        assert_eq!(error_field(&IoError::new(ErrorKind::TimedOut, "idle")), "TimedOut");
+    }
+
+    #[test]
+    fn test_drain_waits_for_open_streams() {
+        let streams = ActiveStreams::default();
+        futures::executor::block_on(streams.drained());
+
+        let mut stream = LoggedStream::unlogged(Cursor::new(b"response".to_vec())).tracked_by(&streams);
+        let mut drained = Box::pin(streams.drained());
+        assert!((&mut drained).now_or_never().is_none());
+
+        // The stream keeps working while a stop waits for it
+        futures::executor::block_on(async {
+            let mut response = Vec::new();
+            stream.read_to_end(&mut response).await.unwrap();
+            assert_eq!(response, b"response");
+            stream.write_all(b"request").await.unwrap();
+        });
+        assert!((&mut drained).now_or_never().is_none());
+
+        drop(stream);
+        assert!(drained.now_or_never().is_some());
+    }
+}
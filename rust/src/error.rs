@@ -7,6 +7,10 @@ use log::{error, warn};
 use std::cell::RefCell;
 use std::error::Error;
 use std::ffi::{c_char, CString};
+use std::fmt;
+use std::time::Duration;
+
+use crate::proxy_provider::{ProxyError, ProxyErrorKind};
 //pub(crate) use crate::unwrap_or_return;
 
 thread_local! {
@@ -59,3 +63,70 @@ pub fn update_last_error<E: Error + 'static>(err: E) {
 pub fn take_last_error() -> Option<Box<dyn Error>> {
     LAST_ERROR.with(|prev| prev.borrow_mut().take())
 }
+
+/// Errors returned through the FRB API that Dart may want to tell apart
+#[derive(Debug)]
+pub enum TorError {
+    /// The requested functionality was compiled out of this build
+    FeatureDisabled(&'static str),
+    /// A .onion address is malformed; `reason` says how
+    InvalidOnionAddress { reason: String },
+    /// A proxy address can never be connected to; `reason` says why
+    InvalidProxy { reason: String },
+    /// `action` needs Tor to be stopped first
+    StillRunning { action: &'static str },
+    /// The exit relay reported that `name` does not exist (NXDOMAIN)
+    HostNotFound { name: String },
+    /// `name` could not be resolved for another reason, e.g. Tor is not
+    /// running or not bootstrapped, or the circuit failed; worth retrying
+    ResolveFailed { name: String, reason: String },
+    /// Bootstrap did not finish within the timeout given to start; the
+    /// client was dropped, so starting again begins afresh
+    BootstrapTimedOut { after: Duration },
+    /// Connecting through a proxy failed before the stream reached its
+    /// target; `kind` says how
+    ProxyFailed { kind: ProxyErrorKind, message: String },
+}
+
+impl TorError {
+    /// The proxy failure carried by an error from a connection attempt, if
+    /// the proxy rather than the target failed (see `ProxyError::from_io`)
+    pub fn from_proxy_io(error: &std::io::Error) -> Option<Self> {
+        ProxyError::from_io(error).map(|proxy_error| Self::ProxyFailed {
+            kind: proxy_error.kind,
+            message: proxy_error.to_string(),
+        })
+    }
+}
+
+impl fmt::Display for TorError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::FeatureDisabled(feature) => {
+                write!(f, "Feature '{}' is not enabled in this build", feature)
+            }
+            Self::InvalidOnionAddress { reason } => write!(f, "Invalid onion address: {}", reason),
+            Self::InvalidProxy { reason } => write!(f, "Invalid proxy: {}", reason),
+            Self::StillRunning { action } => write!(f, "Tor must be stopped before {}", action),
+            Self::HostNotFound { name } => write!(f, "Host not found: {}", name),
+            Self::ResolveFailed { name, reason } => {
+                write!(f, "Could not resolve {}: {}", name, reason)
+            }
+            Self::BootstrapTimedOut { after } => {
+                write!(f, "Bootstrap timed out after {}s", after.as_secs())
+            }
+            Self::ProxyFailed { message, .. } => write!(f, "Proxy failed: {}", message),
+        }
+    }
+}
+
+impl Error for TorError {}
+
+/// Fail with `TorError::FeatureDisabled` unless `feature` was compiled in
+pub fn ensure_feature(feature: &'static str) -> Result<(), TorError> {
+    if crate::util::enabled_features().contains(&feature) {
+        Ok(())
+    } else {
+        Err(TorError::FeatureDisabled(feature))
+    }
+}
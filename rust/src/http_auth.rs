@@ -0,0 +1,327 @@
+// SPDX-FileCopyrightText: 2025 0xChat
+//
+// SPDX-License-Identifier: MIT
+
+//! Answers to HTTP proxy authentication challenges
+//!
+//! A proxy that wants credentials sends one or more challenges in
+//! `Proxy-Authenticate`. Basic (RFC 7617) sends the password merely encoded;
+//! Digest (RFC 7616) sends a hash of it bound to the proxy's nonce, so it is
+//! preferred when both are offered, and SHA-256 over MD5.
+
+use std::collections::BTreeMap;
+use std::io::Result as IoResult;
+
+use base64::{engine::general_purpose::STANDARD, Engine as _};
+use md5::Md5;
+use sha2::{Digest, Sha256};
+
+use crate::proxy_provider::{ProxyAuth, ProxyError, ProxyErrorKind};
+
+/// One challenge from a `Proxy-Authenticate` header
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AuthChallenge {
+    /// Scheme in lowercase, e.g. "basic" or "digest"
+    pub scheme: String,
+    /// Parameters by lowercase name, with quoted values unquoted
+    pub params: BTreeMap<String, String>,
+}
+
+/// Split a `Proxy-Authenticate` value into its challenges
+///
+/// Challenges and their parameters are both separated by commas, but only a
+/// challenge starts with a scheme that is not followed by "=". Parameters
+/// before the first scheme and token68 values (e.g. of Negotiate) are
+/// skipped, as no scheme we answer uses them.
+pub fn parse_challenges(value: &str) -> Vec<AuthChallenge> {
+    let mut challenges: Vec<AuthChallenge> = Vec::new();
+    let mut rest = value;
+    loop {
+        rest = rest.trim_start_matches([' ', '\t', ',']);
+        if rest.is_empty() {
+            return challenges;
+        }
+        let end = rest.find([' ', '\t', ',', '=']).unwrap_or(rest.len());
+        let (token, after) = rest.split_at(end);
+        match after.trim_start_matches([' ', '\t']).strip_prefix('=') {
+            Some(value) => {
+                let (value, remaining) = param_value(value.trim_start_matches([' ', '\t']));
+                if let Some(challenge) = challenges.last_mut() {
+                    challenge.params.insert(token.to_ascii_lowercase(), value);
+                }
+                rest = remaining;
+            }
+            None => {
+                challenges.push(AuthChallenge {
+                    scheme: token.to_ascii_lowercase(),
+                    params: BTreeMap::new(),
+                });
+                rest = after;
+            }
+        }
+    }
+}
+
+/// A parameter value at the start of `input`, token or quoted string, and
+/// what follows it
+fn param_value(input: &str) -> (String, &str) {
+    let Some(quoted) = input.strip_prefix('"') else {
+        let end = input.find([' ', '\t', ',']).unwrap_or(input.len());
+        return (input[..end].to_string(), &input[end..]);
+    };
+    let mut value = String::new();
+    let mut chars = quoted.char_indices();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '"' => return (value, &quoted[i + 1..]),
+            '\\' => value.extend(chars.next().map(|(_, c)| c)),
+            c => value.push(c),
+        }
+    }
+    // Unterminated: take the rest
+    (value, "")
+}
+
+/// `Proxy-Authorization` value for Basic authentication
+pub fn basic_authorization(auth: &ProxyAuth) -> String {
+    let credentials = format!("{}:{}", auth.username, auth.password);
+    format!("Basic {}", STANDARD.encode(credentials.as_bytes()))
+}
+
+/// `Proxy-Authorization` value answering the strongest of `challenges`
+/// that can be answered, for a request of `method` to `uri`
+///
+/// Fails with `UnsupportedMethod` if there is none, e.g. when Digest asks
+/// for an algorithm other than MD5 and SHA-256 or their -sess variants.
+pub fn authorization(
+    challenges: &[AuthChallenge],
+    auth: &ProxyAuth,
+    method: &str,
+    uri: &str,
+) -> IoResult<String> {
+    let digest = challenges
+        .iter()
+        .filter(|challenge| challenge.scheme == "digest")
+        .filter_map(|challenge| DigestAlgorithm::of(challenge).map(|algorithm| (algorithm, challenge)))
+        .max_by_key(|(algorithm, _)| algorithm.hash == DigestHash::Sha256);
+    if let Some((algorithm, challenge)) = digest {
+        let cnonce = format!("{:032x}", rand::random::<u128>());
+        return digest_authorization(challenge, algorithm, auth, method, uri, &cnonce);
+    }
+    if challenges.iter().any(|challenge| challenge.scheme == "basic") {
+        return Ok(basic_authorization(auth));
+    }
+
+    let message = match challenges.iter().find(|challenge| challenge.scheme == "digest") {
+        Some(challenge) => format!(
+            "HTTP proxy asks for Digest with unsupported algorithm {}",
+            challenge.params.get("algorithm").map_or("", String::as_str)
+        ),
+        None => "HTTP proxy offers neither Basic nor Digest authentication".to_string(),
+    };
+    Err(ProxyError::new(ProxyErrorKind::UnsupportedMethod, message).into())
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum DigestHash {
+    Md5,
+    Sha256,
+}
+
+/// Algorithm of a Digest challenge
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct DigestAlgorithm {
+    hash: DigestHash,
+    /// The -sess variant, which mixes both nonces into the password hash
+    session: bool,
+}
+
+impl DigestAlgorithm {
+    /// The algorithm `challenge` asks for, MD5 if it names none, or None if
+    /// it is not supported
+    fn of(challenge: &AuthChallenge) -> Option<Self> {
+        let name = challenge.params.get("algorithm").map_or("MD5", String::as_str);
+        let (hash, session) = match name.to_ascii_uppercase().as_str() {
+            "MD5" => (DigestHash::Md5, false),
+            "MD5-SESS" => (DigestHash::Md5, true),
+            "SHA-256" => (DigestHash::Sha256, false),
+            "SHA-256-SESS" => (DigestHash::Sha256, true),
+            _ => return None,
+        };
+        Some(Self { hash, session })
+    }
+
+    fn name(self) -> &'static str {
+        match (self.hash, self.session) {
+            (DigestHash::Md5, false) => "MD5",
+            (DigestHash::Md5, true) => "MD5-sess",
+            (DigestHash::Sha256, false) => "SHA-256",
+            (DigestHash::Sha256, true) => "SHA-256-sess",
+        }
+    }
+
+    /// Lowercase hex hash of `data`
+    fn hex(self, data: &str) -> String {
+        let hash = match self.hash {
+            DigestHash::Md5 => Md5::digest(data.as_bytes()).to_vec(),
+            DigestHash::Sha256 => Sha256::digest(data.as_bytes()).to_vec(),
+        };
+        hash.iter().map(|byte| format!("{:02x}", byte)).collect()
+    }
+}
+
+/// `Proxy-Authorization` value answering a Digest `challenge` (RFC 7616,
+/// section 3.4) with client nonce `cnonce`
+///
+/// Uses qop "auth" if offered, else "auth-int" (the body of a CONNECT is
+/// empty), else the qop-less form of RFC 2069.
+fn digest_authorization(
+    challenge: &AuthChallenge,
+    algorithm: DigestAlgorithm,
+    auth: &ProxyAuth,
+    method: &str,
+    uri: &str,
+    cnonce: &str,
+) -> IoResult<String> {
+    let param = |name: &str| challenge.params.get(name).map_or("", String::as_str);
+    let (realm, nonce) = (param("realm"), param("nonce"));
+    if nonce.is_empty() {
+        return Err(ProxyError::new(ProxyErrorKind::ProtocolViolation, "HTTP proxy sent a Digest challenge without nonce").into());
+    }
+    let offered: Vec<&str> = param("qop").split(',').map(str::trim).collect();
+    let qop = ["auth", "auth-int"].into_iter().find(|qop| offered.contains(qop));
+    const NC: &str = "00000001";
+
+    let mut ha1 = algorithm.hex(&format!("{}:{}:{}", auth.username, realm, auth.password));
+    if algorithm.session {
+        ha1 = algorithm.hex(&format!("{}:{}:{}", ha1, nonce, cnonce));
+    }
+    let ha2 = match qop {
+        Some("auth-int") => algorithm.hex(&format!("{}:{}:{}", method, uri, algorithm.hex(""))),
+        _ => algorithm.hex(&format!("{}:{}", method, uri)),
+    };
+    let response = match qop {
+        Some(qop) => algorithm.hex(&format!("{}:{}:{}:{}:{}:{}", ha1, nonce, NC, cnonce, qop, ha2)),
+        None => algorithm.hex(&format!("{}:{}:{}", ha1, nonce, ha2)),
+    };
+
+    let userhash = param("userhash").eq_ignore_ascii_case("true");
+    let username = if userhash {
+        algorithm.hex(&format!("{}:{}", auth.username, realm))
+    } else {
+        auth.username.clone()
+    };
+    let mut value = format!(
+        "Digest username={}, realm={}, nonce={}, uri={}, algorithm={}, response={}",
+        quote(&username),
+        quote(realm),
+        quote(nonce),
+        quote(uri),
+        algorithm.name(),
+        quote(&response),
+    );
+    if let Some(qop) = qop {
+        value.push_str(&format!(", qop={}, nc={}, cnonce={}", qop, NC, quote(cnonce)));
+    }
+    if let Some(opaque) = challenge.params.get("opaque") {
+        value.push_str(&format!(", opaque={}", quote(opaque)));
+    }
+    if userhash {
+        value.push_str(", userhash=true");
+    }
+    Ok(value)
+}
+
+/// `value` as a quoted string
+fn quote(value: &str) -> String {
+    let mut quoted = String::with_capacity(value.len() + 2);
+    quoted.push('"');
+    for c in value.chars() {
+        if c == '"' || c == '\\' {
+            quoted.push('\\');
+        }
+        quoted.push(c);
+    }
+    quoted.push('"');
+    quoted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn mufasa() -> ProxyAuth {
+        ProxyAuth {
+            username: "Mufasa".to_string(),
+            password: "Circle of Life".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_parse_challenges() {
+        let challenges = parse_challenges(
+            "Negotiate, Digest realm=\"a, \\\"b\\\"\", qop=\"auth,auth-int\", algorithm=SHA-256,Basic realm=proxy",
+        );
+        let schemes: Vec<_> = challenges.iter().map(|challenge| challenge.scheme.as_str()).collect();
+        assert_eq!(schemes, ["negotiate", "digest", "basic"]);
+        assert!(challenges[0].params.is_empty());
+        assert_eq!(challenges[1].params["realm"], "a, \"b\"");
+        assert_eq!(challenges[1].params["qop"], "auth,auth-int");
+        assert_eq!(challenges[1].params["algorithm"], "SHA-256");
+        assert_eq!(challenges[2].params["realm"], "proxy");
+
+        assert!(parse_challenges("realm=orphan").is_empty());
+    }
+
+    #[test]
+    fn test_digest_rfc7616_example() {
+        // RFC 7616, section 3.9.1
+        let challenge = |algorithm: &str| AuthChallenge {
+            scheme: "digest".to_string(),
+            params: BTreeMap::from([
+                ("realm".to_string(), "http-auth@example.org".to_string()),
+                ("qop".to_string(), "auth, auth-int".to_string()),
+                ("algorithm".to_string(), algorithm.to_string()),
+                ("nonce".to_string(), "7ypf/xlj9XXwfDPEoM4URrv/xwf94BcCAzFZH4GiTo0v".to_string()),
+                ("opaque".to_string(), "FQhe/qaU925kfnzjCev0ciny7QMkPqMAFRtzCUYo5tdS".to_string()),
+            ]),
+        };
+        let answer = |algorithm: &str| {
+            let challenge = challenge(algorithm);
+            let algorithm = DigestAlgorithm::of(&challenge).unwrap();
+            let cnonce = "f2/wE4q74E6zIJEtWaHKaf5wv/H5QzzpXusqGemxURZJ";
+            digest_authorization(&challenge, algorithm, &mufasa(), "GET", "/dir/index.html", cnonce).unwrap()
+        };
+
+        assert_eq!(
+            answer("MD5"),
+            "Digest username=\"Mufasa\", realm=\"http-auth@example.org\", \
+             nonce=\"7ypf/xlj9XXwfDPEoM4URrv/xwf94BcCAzFZH4GiTo0v\", uri=\"/dir/index.html\", \
+             algorithm=MD5, response=\"8ca523f5e9506fed4657c9700eebdbec\", qop=auth, nc=00000001, \
+             cnonce=\"f2/wE4q74E6zIJEtWaHKaf5wv/H5QzzpXusqGemxURZJ\", \
+             opaque=\"FQhe/qaU925kfnzjCev0ciny7QMkPqMAFRtzCUYo5tdS\""
+        );
+        assert!(answer("SHA-256")
+            .contains("response=\"753927fa0e85d155564e2e272a28d1802ca10daf4496794697cf8db5856cb6c1\""));
+    }
+
+    #[test]
+    fn test_authorization_picks_strongest() {
+        let auth = mufasa();
+        let answer = |header: &str| authorization(&parse_challenges(header), &auth, "CONNECT", "example.com:443");
+
+        assert_eq!(answer("Basic realm=\"proxy\"").unwrap(), "Basic TXVmYXNhOkNpcmNsZSBvZiBMaWZl");
+        let digest = answer("Basic realm=\"proxy\", Digest realm=\"proxy\", nonce=\"n\"").unwrap();
+        assert!(digest.starts_with("Digest username=\"Mufasa\""), "{}", digest);
+        assert!(digest.contains("algorithm=MD5,"));
+        let digest =
+            answer("Digest realm=\"p\", nonce=\"n\", Digest realm=\"p\", nonce=\"n\", algorithm=SHA-256").unwrap();
+        assert!(digest.contains("algorithm=SHA-256,"));
+
+        let err = answer("Digest realm=\"p\", nonce=\"n\", algorithm=SHA-512-256").unwrap_err();
+        assert_eq!(ProxyError::from_io(&err).unwrap().kind, ProxyErrorKind::UnsupportedMethod);
+        assert_eq!(err.to_string(), "HTTP proxy asks for Digest with unsupported algorithm SHA-512-256");
+        let err = answer("Negotiate").unwrap_err();
+        assert_eq!(ProxyError::from_io(&err).unwrap().kind, ProxyErrorKind::UnsupportedMethod);
+    }
+}
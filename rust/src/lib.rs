@@ -17,11 +17,15 @@ pub mod manager;
 // Internal modules
 #[macro_use]
 mod error;
-mod proxy_provider;
+mod connection_log;
+mod http_auth;
+mod log_forward;
+pub mod proxy_provider;
+mod socks_isolation;
 mod util;
 
 // Re-export API types for frb_generated.rs
-pub use api::{ProxyInfo, ProxyType};
+pub use api::{BootstrapStalled, CircuitHandle, DirectoryMirror, FdUsage, IsolationMapping, LogEntry, ProxyInfo, ProxyType, TargetRedaction, TorStatus, WeightedProxy};
 
 // Re-export util functions for platform-specific features
 #[cfg(not(target_os = "windows"))]
@@ -0,0 +1,232 @@
+// SPDX-FileCopyrightText: 2025 0xChat
+//
+// SPDX-License-Identifier: MIT
+
+//! Tor log lines for Dart
+//!
+//! Arti logs through `tracing`. The first subscription installs a layer as
+//! the global tracing subscriber; it turns events into `LogEntry` values and
+//! queues them for every subscribed sink. Queues are bounded: while a sink
+//! does not keep up, new lines are dropped and counted, and the count is
+//! reported in the stream once there is room again.
+//!
+//! The crate's own debug log (targets "tor::manager" and "tor::proxy") goes
+//! through `tracing` as well, so it is forwarded with arti's.
+
+use std::fmt::{self, Write as _};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+use once_cell::sync::Lazy;
+use tokio::sync::mpsc;
+use tracing::field::{Field, Visit};
+use tracing::level_filters::LevelFilter;
+use tracing::subscriber::Interest;
+use tracing::{Event, Level, Metadata, Subscriber};
+use tracing_subscriber::layer::{Context, Layer, SubscriberExt};
+
+use crate::api::LogEntry;
+use crate::frb_generated::StreamSink;
+
+/// Lines queued per subscriber before new ones are dropped
+const QUEUE_LEN: usize = 1024;
+
+/// Most verbose level forwarded; arti's trace output is far too much
+const MAX_LEVEL: Level = Level::DEBUG;
+
+/// Target of the lines this module adds itself
+const TARGET: &str = "tor::log";
+
+/// One subscribed sink, as seen by the layer
+struct Subscription {
+    queue: mpsc::Sender<LogEntry>,
+    dropped: Arc<AtomicU64>,
+}
+
+impl Subscription {
+    /// Queue `entry`, or count it as dropped if the queue is full
+    ///
+    /// Returns false once the sink's side is gone.
+    fn offer(&self, entry: LogEntry) -> bool {
+        match self.queue.try_send(entry) {
+            Ok(()) => true,
+            Err(mpsc::error::TrySendError::Full(_)) => {
+                self.dropped.fetch_add(1, Ordering::Relaxed);
+                true
+            }
+            Err(mpsc::error::TrySendError::Closed(_)) => false,
+        }
+    }
+}
+
+type Subscriptions = Arc<Mutex<Vec<Subscription>>>;
+
+// Sinks passed to subscribe() that are still listening
+static SUBSCRIPTIONS: Lazy<Subscriptions> = Lazy::new(Subscriptions::default);
+
+// Whether the forwarding layer became the global tracing subscriber; only
+// tried once, as there can be just one
+static INSTALLED: Lazy<bool> = Lazy::new(|| {
+    let subscriber = tracing_subscriber::registry().with(ForwardLayer {
+        subscriptions: SUBSCRIPTIONS.clone(),
+    });
+    match tracing::subscriber::set_global_default(subscriber) {
+        Ok(()) => true,
+        Err(e) => {
+            log::warn!(target: TARGET, "Cannot forward Tor logs: {}", e);
+            false
+        }
+    }
+});
+
+/// Send Tor's log lines to `sink` until Dart stops listening
+///
+/// Lines from before the call are not sent. If another tracing subscriber
+/// was installed first, the stream only explains that and ends.
+pub async fn subscribe(sink: StreamSink<LogEntry>) {
+    if !*INSTALLED {
+        let _ = sink.add(notice(
+            "WARN",
+            "another tracing subscriber is installed, Tor logs are not available",
+        ));
+        return;
+    }
+    let (queue, mut entries) = mpsc::channel(QUEUE_LEN);
+    let dropped = Arc::new(AtomicU64::new(0));
+    SUBSCRIPTIONS.lock().unwrap().push(Subscription {
+        queue,
+        dropped: dropped.clone(),
+    });
+    // Dropping `entries` on the way out tells the layer to forget this sink
+    while let Some(entry) = entries.recv().await {
+        let lost = dropped.swap(0, Ordering::Relaxed);
+        if lost > 0 {
+            let message = format!("{} log lines dropped, too many to keep up", lost);
+            if sink.add(notice("WARN", &message)).is_err() {
+                break;
+            }
+        }
+        if sink.add(entry).is_err() {
+            break;
+        }
+    }
+}
+
+/// Line from this module rather than from Tor
+fn notice(level: &str, message: &str) -> LogEntry {
+    LogEntry {
+        level: level.to_string(),
+        target: TARGET.to_string(),
+        message: message.to_string(),
+    }
+}
+
+/// Tracing layer handing events to the subscriptions
+struct ForwardLayer {
+    subscriptions: Subscriptions,
+}
+
+impl<S: Subscriber> Layer<S> for ForwardLayer {
+    fn register_callsite(&self, metadata: &'static Metadata<'static>) -> Interest {
+        if *metadata.level() <= MAX_LEVEL {
+            // Depends on whether anyone is subscribed, so ask every time
+            Interest::sometimes()
+        } else {
+            Interest::never()
+        }
+    }
+
+    fn enabled(&self, metadata: &Metadata<'_>, _ctx: Context<'_, S>) -> bool {
+        *metadata.level() <= MAX_LEVEL && !self.subscriptions.lock().unwrap().is_empty()
+    }
+
+    fn max_level_hint(&self) -> Option<LevelFilter> {
+        Some(LevelFilter::from_level(MAX_LEVEL))
+    }
+
+    fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+        let entry = entry_of(event);
+        self.subscriptions.lock().unwrap().retain(|subscription| subscription.offer(entry.clone()));
+    }
+}
+
+/// Format `event` as a log line
+fn entry_of(event: &Event<'_>) -> LogEntry {
+    let metadata = event.metadata();
+    let mut fields = Fields::default();
+    event.record(&mut fields);
+    LogEntry {
+        level: metadata.level().to_string(),
+        target: metadata.target().to_string(),
+        message: fields.message + &fields.rest,
+    }
+}
+
+/// The message of an event and its other fields as " name=value"
+#[derive(Default)]
+struct Fields {
+    message: String,
+    rest: String,
+}
+
+impl Visit for Fields {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        if field.name() == "message" {
+            self.message.push_str(value);
+        } else {
+            let _ = write!(self.rest, " {}={}", field.name(), value);
+        }
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        if field.name() == "message" {
+            let _ = write!(self.message, "{:?}", value);
+        } else {
+            let _ = write!(self.rest, " {}={:?}", field.name(), value);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_forwards_formatted_events() {
+        let subscriptions = Subscriptions::default();
+        let (queue, mut entries) = mpsc::channel(QUEUE_LEN);
+        subscriptions.lock().unwrap().push(Subscription {
+            queue,
+            dropped: Arc::default(),
+        });
+        let subscriber = tracing_subscriber::registry().with(ForwardLayer {
+            subscriptions: subscriptions.clone(),
+        });
+        tracing::subscriber::with_default(subscriber, || {
+            tracing::info!(target: "tor_guardmgr", guard = "relay1", "guard usable");
+            tracing::trace!("not forwarded");
+        });
+
+        let entry = entries.try_recv().unwrap();
+        assert_eq!(entry.level, "INFO");
+        assert_eq!(entry.target, "tor_guardmgr");
+        assert_eq!(entry.message, "guard usable guard=relay1");
+        assert!(entries.try_recv().is_err());
+    }
+
+    #[test]
+    fn test_bounded_queue_and_unsubscribe() {
+        let (queue, entries) = mpsc::channel(2);
+        let subscription = Subscription {
+            queue,
+            dropped: Arc::default(),
+        };
+        for _ in 0..5 {
+            assert!(subscription.offer(notice("INFO", "line")));
+        }
+        assert_eq!(subscription.dropped.load(Ordering::Relaxed), 3);
+
+        drop(entries);
+        assert!(!subscription.offer(notice("INFO", "line")));
+    }
+}
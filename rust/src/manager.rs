@@ -1,104 +1,986 @@
-use std::sync::{Arc, Mutex};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, Instant};
+use futures::future::{AbortHandle, AbortRegistration, Abortable, BoxFuture};
+use futures::{FutureExt, StreamExt};
 use once_cell::sync::Lazy;
-use arti_client::config::CfgPath;
-use arti_client::TorClientConfig;
+use arti_client::config::dir::FallbackDirBuilder;
+use arti_client::config::{CfgPath, Reconfigure, TorClientConfigBuilder};
+#[cfg(feature = "bridges")]
+use arti_client::config::{pt::TransportConfigBuilder, BridgeConfigBuilder};
+use arti_client::{
+    BootstrapBehavior, DataStream, DormantMode, ErrorKind, HasKind, IsolationToken, StreamPrefs,
+    TorClient, TorClientConfig,
+};
 use tor_rtcompat::tokio::TokioNativeTlsRuntime;
-use tor_rtcompat::RuntimeSubstExt;
+use tor_rtcompat::{CompoundRuntime, RuntimeSubstExt};
+#[cfg(feature = "socks")]
 use tor_config::Listen;
+#[cfg(feature = "socks")]
 use arti::socks;
+use tokio::sync::{broadcast, watch};
+use tokio::io::AsyncReadExt;
+use tokio::time::timeout;
 use tokio::task::JoinHandle;
-use std::net::SocketAddr;
+use std::net::{IpAddr, SocketAddr};
+use std::path::Path;
+use base64::{engine::general_purpose::STANDARD_NO_PAD, Engine as _};
+use tor_llcrypto::pk::ed25519::Ed25519Identity;
+use tor_llcrypto::pk::rsa::RsaIdentity;
+#[cfg(feature = "exit-country")]
+use tor_geoip::CountryCode;
+use time::{Date, Month, OffsetDateTime, PrimitiveDateTime, Time};
 
-use crate::api::{ProxyInfo, ProxyType};
-use crate::proxy_provider::{ProxyAuth, ProxyConfig, ProxyTcpProvider};
+use crate::api::{
+    BootstrapStalled, BootstrapStatus, BridgeLine, CircuitHandle, ConnectionEvent, ConnectionStage,
+    DirectoryMirror, IsolationMapping, LocalStream, ProxyInfo, ProxyType, SnowflakeConfig,
+    StartOptions, TargetRedaction, TorStatus, WeightedProxy,
+};
+use crate::connection_log::{ActiveStreams, ConnectionLog};
+use crate::socks_isolation::{IsolationMap, TOKEN_PREFIX};
+use crate::frb_generated::StreamSink;
+use crate::error::{ensure_feature, TorError};
+use crate::util;
+use crate::proxy_provider::{
+    redact_target, AuthRefreshCallback, DirectReason, IpCidr, ProxyAuth, ProxyCallback,
+    ProxyConfig, ProxyFailover, ProxyObserver, ProxyTarget, ProxyTcpProvider, RetryPolicy,
+    TargetPolicy, DEFAULT_TARGET_REDACTION,
+};
+use rand::seq::IndexedRandom;
+use rand::Rng;
 
-// Global proxy state that Dart can update at any time
-static CURRENT_PROXY: Lazy<Mutex<Option<ProxyInfo>>> = Lazy::new(|| Mutex::new(None));
+// Debug log of the service, through `tracing` at debug level with target
+// "tor::manager", so that tor_subscribe_logs_frb() forwards it along with
+// arti's log and the host app's subscriber can filter or redirect it
+#[cfg(not(feature = "stderr-log"))]
+macro_rules! manager_log {
+    ($($arg:tt)*) => {
+        tracing::debug!(target: "tor::manager", $($arg)*)
+    };
+}
+
+// With the stderr-log feature, also printed to stderr if util::DEBUG_OUTPUT
+#[cfg(feature = "stderr-log")]
+macro_rules! manager_log {
+    ($($arg:tt)*) => {{
+        if *crate::util::DEBUG_OUTPUT {
+            eprintln!("[RUST] {}", format!($($arg)*));
+        }
+        tracing::debug!(target: "tor::manager", $($arg)*)
+    }};
+}
+
+// Proxy state of the client from start, which Dart can update at any time
+static PROXY_STATE: Lazy<Arc<ProxyState>> = Lazy::new(Arc::default);
+
+// How long a failed proxy is skipped
+const PROXY_FAILURE_COOLDOWN: Duration = Duration::from_secs(60);
+
+// Last connectivity reported by Dart; while false, new clients are created
+// without bootstrapping (see notify_network_changed)
+static NETWORK_ONLINE: Lazy<Mutex<bool>> = Lazy::new(|| Mutex::new(true));
+
+// Files and directories in cache_dir holding arti's consensus, with the
+// microdescriptors and authority certificates fetched for it
+const DIR_CACHE_FILES: [&str; 3] = ["dir.sqlite3", "dir.sqlite3-journal", "dir_blobs"];
+
+// How long custom directory mirrors get to bootstrap before falling back to
+// arti's built-in fallback directories
+const MIRROR_BOOTSTRAP_TIMEOUT: Duration = Duration::from_secs(60);
+
+// How long bootstrap may take while cache_dir holds a consensus before the
+// cache is assumed to be bad, cleared, and bootstrap tried once more; longer
+// than MIRROR_BOOTSTRAP_TIMEOUT so mirrors get their chance first
+const CACHED_BOOTSTRAP_TIMEOUT: Duration = Duration::from_secs(120);
+
+// Destination allow/deny lists, shared with the proxy provider
+static TARGET_POLICY: Lazy<Arc<RwLock<TargetPolicy>>> = Lazy::new(Arc::default);
+
+// How connection targets are logged, shared with the proxy provider
+static TARGET_REDACTION: Lazy<Arc<RwLock<TargetRedaction>>> =
+    Lazy::new(|| Arc::new(RwLock::new(DEFAULT_TARGET_REDACTION)));
+
+// Idle timeout for connections accepted by the SOCKS listener, shared with
+// the proxy provider, which reads it when the listener is opened
+static SOCKS_IDLE_TIMEOUT: Lazy<Arc<RwLock<Option<Duration>>>> = Lazy::new(Arc::default);
+
+// SOCKS usernames (contact ids) mapped to isolation tokens, shared with the
+// proxy provider, which applies it to connections accepted by the listener
+static SOCKS_ISOLATION_MAP: Lazy<IsolationMap> = Lazy::new(IsolationMap::default);
+
+// Connections accepted by the SOCKS listener that are still open, counted by
+// the proxy provider so that stop_gracefully can wait for them
+static SOCKS_STREAMS: Lazy<ActiveStreams> = Lazy::new(ActiveStreams::default);
+
+// Config keys whose values effective_config() leaves out: bridge lines name
+// unlisted relays, and pluggable transport ones carry their secrets
+const REDACTED_CONFIG_KEYS: [&str; 1] = ["bridges"];
+
+// How long a new SOCKS listener gets to fail before it is taken to be
+// listening (see rebind_socks)
+#[cfg(feature = "socks")]
+const SOCKS_LISTEN_CHECK: Duration = Duration::from_millis(200);
+
+// Upper bound for the preemptive circuits per predicted port (arti's
+// default is 2); more only costs battery and bandwidth
+const MAX_PREEMPTIVE_CIRCUITS: u8 = 8;
+
+// Upper bound for the consensus validity tolerance. Every second of it is a
+// second longer that an old consensus can be replayed to us, so it only
+// stretches far enough to cover a clock that is days off.
+const MAX_CONSENSUS_TOLERANCE: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+
+// Health checks (see spawn_health_task): a resolve through Tor every
+// HEALTH_CHECK_INTERVAL, with the client considered unhealthy after
+// HEALTH_CHECK_MAX_FAILURES failures in a row
+const HEALTH_CHECK_INTERVAL: Duration = Duration::from_secs(10 * 60);
+const HEALTH_CHECK_TIMEOUT: Duration = Duration::from_secs(60);
+const HEALTH_CHECK_MAX_FAILURES: u32 = 3;
+const HEALTH_CHECK_HOST: &str = "www.torproject.org";
+
+// How long bootstrap may go without its progress increasing before STATUS
+// reports it as stalled
+const BOOTSTRAP_STALL_TIMEOUT: Duration = Duration::from_secs(45);
+
+// Whether an unhealthy client is restarted, rather than only reported
+static AUTO_RESTART: Lazy<Mutex<bool>> = Lazy::new(|| Mutex::new(false));
+
+// Whether the network is metered; background work that circuits do not
+// need is paused while set (see set_metered)
+static METERED: Lazy<Mutex<bool>> = Lazy::new(|| Mutex::new(false));
+
+// Whether the app asked for dormant mode, e.g. while in the background
+// (see set_dormant)
+static DORMANT: Lazy<Mutex<bool>> = Lazy::new(|| Mutex::new(false));
+
+/// Runtime used by the client: the FRB Tokio runtime with our proxy-aware TCP provider
+type TorRuntime = CompoundRuntime<
+    TokioNativeTlsRuntime,
+    TokioNativeTlsRuntime,
+    TokioNativeTlsRuntime,
+    ProxyTcpProvider<TokioNativeTlsRuntime>,
+    TokioNativeTlsRuntime,
+    TokioNativeTlsRuntime,
+    TokioNativeTlsRuntime,
+>;
+
+/// Local SOCKS listener task
+#[cfg_attr(not(feature = "socks"), allow(dead_code))]
+struct SocksListener {
+    /// Address given to `start`; None for localhost
+    bind: Option<IpAddr>,
+    port: u16,
+    handle: JoinHandle<anyhow::Result<()>>,
+}
+
+/// Background task of a running client; aborted when dropped
+struct ClientTask(JoinHandle<()>);
+
+impl Drop for ClientTask {
+    fn drop(&mut self) {
+        self.0.abort();
+    }
+}
+
+/// Running Tor service
+#[cfg_attr(not(feature = "socks"), allow(dead_code))]
+struct TorState {
+    client: TorClient<TorRuntime>,
+    /// None when started with `start_client_only`
+    socks: Option<SocksListener>,
+    /// Config the client runs with, for effective_config(); replaced by
+    /// default_dirs if bootstrap falls back to it
+    config: Arc<Mutex<TorClientConfigBuilder>>,
+    /// Config with arti's built-in fallback directories, kept when custom
+    /// directory mirrors replace them, to retry with if the mirrors fail
+    default_dirs: Option<TorClientConfigBuilder>,
+    _status_task: ClientTask,
+    _health_task: ClientTask,
+}
+
+/// What a client was started with, to start it again after a failed health check
+#[derive(Clone)]
+struct ClientParams {
+    state_dir: String,
+    cache_dir: String,
+    connection_log: bool,
+    directory_mirrors: Vec<DirectoryMirror>,
+    bridges: Vec<BridgeLine>,
+    obfs4_binary_path: Option<String>,
+    snowflake: Option<SnowflakeConfig>,
+    connect_timeout: Option<Duration>,
+    preemptive_circuits: Option<u8>,
+    consensus_tolerance: Option<Duration>,
+    bootstrap_timeout: Option<Duration>,
+    handshake_deadline: Option<Duration>,
+    socks5_method_order: Option<Vec<u8>>,
+}
+
+impl ClientParams {
+    /// The client part of the options to `start` and `start_client_only`
+    ///
+    /// Non-empty directory_mirrors replace arti's fallback directories for
+    /// bootstrap (see bootstrap_with_fallback). Non-empty bridges make the
+    /// client connect only through these obfs4 bridges, run by the binary
+    /// at obfs4_binary_path (see configure_bridges); snowflake adds a
+    /// Snowflake bridge, alone or next to them.
+    ///
+    /// The connect timeout bounds how long a stream may take to open through
+    /// Tor (arti's default is 10s). preemptive_circuits sets how many exit
+    /// circuits are kept ready per predicted port (at most
+    /// MAX_PREEMPTIVE_CIRCUITS), and the consensus tolerance how long before
+    /// its valid-after and after its valid-until time a consensus is still
+    /// accepted (at most MAX_CONSENSUS_TOLERANCE); both are checked in
+    /// create_client. The bootstrap timeout fails a slow bootstrap with
+    /// `TorError::BootstrapTimedOut` (see bootstrap_client). The handshake
+    /// deadline bounds dialling a proxy plus its whole handshake
+    /// (proxy_provider's DEFAULT_HANDSHAKE_DEADLINE if not set), and the
+    /// SOCKS5 method order which authentication methods SOCKS5 proxies are
+    /// offered (see Socks5AuthMethods::set_order).
+    fn new(state_dir: String, cache_dir: String, options: StartOptions) -> anyhow::Result<Self> {
+        Ok(ClientParams {
+            state_dir,
+            cache_dir,
+            connection_log: options.connection_log,
+            directory_mirrors: options.directory_mirrors,
+            bridges: options.bridges,
+            obfs4_binary_path: options.obfs4_binary_path,
+            snowflake: options.snowflake,
+            connect_timeout: timeout_secs(
+                options.socks_connect_timeout_secs,
+                "SOCKS connect timeout",
+            )?,
+            preemptive_circuits: options.preemptive_circuits,
+            consensus_tolerance: options
+                .consensus_time_tolerance_secs
+                .map(|secs| Duration::from_secs(secs.into())),
+            bootstrap_timeout: timeout_secs(options.bootstrap_timeout_secs, "Bootstrap timeout")?,
+            handshake_deadline: timeout_secs(
+                options.proxy_handshake_timeout_secs,
+                "Proxy handshake timeout",
+            )?,
+            socks5_method_order: options.socks5_method_order,
+        })
+    }
+}
+
+/// A timeout in whole seconds from StartOptions; 0 is rejected, as it
+/// would fail every attempt
+fn timeout_secs(secs: Option<u32>, name: &str) -> anyhow::Result<Option<Duration>> {
+    match secs {
+        Some(0) => anyhow::bail!("{} must be at least 1 second", name),
+        secs => Ok(secs.map(|secs| Duration::from_secs(secs.into()))),
+    }
+}
+
+// Circuits pinned with build_circuit, by handle id; cleared by stop()
+static PINNED_CIRCUITS: Lazy<Mutex<HashMap<u64, PinnedCircuit>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+static NEXT_CIRCUIT_ID: AtomicU64 = AtomicU64::new(1);
+
+// Streams from connect_isolated by isolation key; cleared by stop()
+static ISOLATION_KEYS: Lazy<Mutex<HashMap<String, PinnedCircuit>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+// SOCKS listeners from open_isolated_listener by port; closed by stop()
+static ISOLATED_LISTENERS: Lazy<Mutex<HashMap<u16, SocksListener>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+// How long the local ports returned by open_stream_on_circuit and
+// connect_isolated wait for the app to connect
+const PINNED_STREAM_ACCEPT_TIMEOUT: Duration = Duration::from_secs(60);
+
+// How long a connection to such a port has to send the port's token
+const STREAM_TOKEN_TIMEOUT: Duration = Duration::from_secs(5);
+
+// Countries circuits from build_circuit may exit in, as upper-case ISO
+// 3166-1 alpha-2 codes; empty for any country
+static EXIT_COUNTRIES: Lazy<RwLock<Vec<String>>> = Lazy::new(RwLock::default);
+
+/// Streams that share a circuit: arti only puts streams with the same
+/// isolation token on one circuit
+struct PinnedCircuit {
+    isolation: IsolationToken,
+    /// Country the circuit exits in, if restricted by set_exit_countries
+    exit_country: Option<String>,
+    /// Tasks relaying the streams opened on this circuit to local ports
+    relays: Vec<ClientTask>,
+}
 
 // Tor service state
-static STATE: Lazy<Mutex<Option<(u16, JoinHandle<anyhow::Result<()>>)>>> = 
-    Lazy::new(|| Mutex::new(None));
+static STATE: Lazy<Mutex<Option<TorState>>> = Lazy::new(|| Mutex::new(None));
+
+// Latest status for observers. Only the manager writes it; any isolate can
+// read or subscribe without touching STATE.
+static STATUS: Lazy<watch::Sender<TorStatus>> =
+    Lazy::new(|| watch::channel(TorStatus::default()).0);
+
+// Bootstrap progress of the current client with arti's description of the
+// phase, for start_with_progress(). STATUS only keeps the percentage.
+static BOOTSTRAP_PROGRESS: Lazy<watch::Sender<BootstrapStatus>> =
+    Lazy::new(|| watch::channel(BootstrapStatus::default()).0);
+
+// Connection attempts for observe_connections(). Each listener can fall
+// CONNECTION_EVENT_QUEUE events behind before it misses some.
+static CONNECTION_EVENTS: Lazy<broadcast::Sender<ConnectionEvent>> =
+    Lazy::new(|| broadcast::channel(CONNECTION_EVENT_QUEUE).0);
+const CONNECTION_EVENT_QUEUE: usize = 256;
+
+// Bootstraps still in progress, so that stop() can cancel them. Dart cannot
+// cancel an FRB future, so without this a start() racing a stop() would keep
+// connecting and then install a client nobody asked for.
+static PENDING_BOOTSTRAPS: Lazy<Mutex<HashMap<u64, AbortHandle>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+static NEXT_BOOTSTRAP_ID: AtomicU64 = AtomicU64::new(0);
+
+/// Entry in PENDING_BOOTSTRAPS, removed again when dropped
+struct PendingBootstrap(u64);
+
+impl PendingBootstrap {
+    fn register() -> (Self, AbortRegistration) {
+        let (handle, registration) = AbortHandle::new_pair();
+        let id = NEXT_BOOTSTRAP_ID.fetch_add(1, Ordering::Relaxed);
+        PENDING_BOOTSTRAPS.lock().unwrap().insert(id, handle);
+        (PendingBootstrap(id), registration)
+    }
+}
+
+impl Drop for PendingBootstrap {
+    fn drop(&mut self) {
+        PENDING_BOOTSTRAPS.lock().unwrap().remove(&self.0);
+    }
+}
 
-/// Proxy callback implementation that reads from global state
-struct StaticProxyProvider;
+/// Proxies of one client, read on every connection so that Dart can change
+/// them while it runs
+struct ProxyState {
+    /// Empty means direct
+    proxies: Mutex<Vec<WeightedProxy>>,
+    /// Chain or failover from set_proxy_chain or set_proxy_failover, used
+    /// instead of `proxies` if set
+    route: Mutex<Option<ProxyConfig>>,
+    /// Whether connections use `proxies` at all; set by start and toggled by
+    /// set_use_proxy without restarting
+    use_proxy: Mutex<bool>,
+    /// When each proxy last failed, to skip it for PROXY_FAILURE_COOLDOWN
+    failures: Mutex<HashMap<SocketAddr, Instant>>,
+}
+
+impl Default for ProxyState {
+    fn default() -> Self {
+        Self {
+            proxies: Mutex::new(Vec::new()),
+            route: Mutex::new(None),
+            use_proxy: Mutex::new(true),
+            failures: Mutex::new(HashMap::new()),
+        }
+    }
+}
+
+/// Proxy callback implementation that reads a client's ProxyState
+struct StaticProxyProvider(Arc<ProxyState>);
 
 impl StaticProxyProvider {
-    fn get_current_proxy() -> Option<ProxyConfig> {
-        let proxy_guard = CURRENT_PROXY.lock().unwrap();
+    fn get_current_proxy(&self) -> Result<ProxyConfig, DirectReason> {
+        if !*self.0.use_proxy.lock().unwrap() {
+            manager_log!("Proxy use is switched off, returning Direct");
+            return Err(DirectReason::ProxyDisabled);
+        }
+        if let Some(route) = self.0.route.lock().unwrap().as_ref() {
+            manager_log!("✅ Returning proxy route {:?}", route);
+            return Ok(route.clone());
+        }
+        let proxies = self.0.proxies.lock().unwrap();
         
-        eprintln!("[RUST] get_current_proxy called, CURRENT_PROXY contains: {:?}",
-                  proxy_guard.as_ref().map(|p| format!("{}:{} ({:?})", p.address, p.port, p.proxy_type)));
+        manager_log!("get_current_proxy called, proxies: {:?}",
+                     proxies.iter().map(|p| format!("{}:{} ({:?}, weight {})",
+                         p.proxy.address, p.proxy.port, p.proxy.proxy_type, p.weight)).collect::<Vec<_>>());
         
-        if let Some(proxy_info) = proxy_guard.as_ref() {
-            let proxy_addr: SocketAddr = format!("{}:{}", proxy_info.address, proxy_info.port)
-                .parse()
-                .ok()?;
-            
-            let auth = if proxy_info.username.is_some() || proxy_info.password.is_some() {
-                Some(ProxyAuth {
-                    username: proxy_info.username.clone().unwrap_or_default(),
-                    password: proxy_info.password.clone().unwrap_or_default(),
-                })
-            } else {
-                None
-            };
+        let enabled: Vec<&WeightedProxy> = proxies.iter().filter(|p| p.weight > 0).collect();
+        if enabled.is_empty() {
+            manager_log!("⚠️ No proxy configured, returning Direct");
+            return Err(DirectReason::NoProxySet);
+        }
+        let candidates: Vec<(&ProxyInfo, ProxyConfig, u32)> = enabled
+            .iter()
+            .filter_map(|p| Some((&p.proxy, proxy_config_for(&p.proxy)?, p.weight)))
+            .collect();
+        if candidates.is_empty() {
+            manager_log!("⚠️ No proxy has a valid address, returning Direct");
+            return Err(DirectReason::InvalidProxyAddress);
+        }
 
-            let config = match proxy_info.proxy_type {
-                ProxyType::Socks5 => Some(ProxyConfig::Socks5 { proxy_addr, auth }),
-                ProxyType::HttpConnect => Some(ProxyConfig::HttpConnect { proxy_addr, auth }),
-            };
-            
-            eprintln!("[RUST] ✅ Returning proxy config: {:?}", config);
-            config
+        // Skip proxies that failed recently, unless all of them did: falling
+        // back to a direct connection would bypass the user's proxy setup
+        let available: Vec<_> = {
+            let mut failures = self.0.failures.lock().unwrap();
+            failures.retain(|_, failed_at| failed_at.elapsed() < PROXY_FAILURE_COOLDOWN);
+            candidates
+                .iter()
+                .filter(|(_, config, _)| {
+                    proxy_addr_of(config).is_none_or(|addr| !failures.contains_key(&addr))
+                })
+                .collect()
+        };
+        let available = if available.is_empty() {
+            manager_log!("⚠️ All proxies are cooling down after failures, using them anyway");
+            candidates.iter().collect()
         } else {
-            eprintln!("[RUST] ⚠️ CURRENT_PROXY is None, returning Direct");
-            None
+            available
+        };
+
+        // Weighted random choice
+        let total: u64 = available.iter().map(|(_, _, weight)| u64::from(*weight)).sum();
+        let mut pick = rand::rng().random_range(0..total);
+        for (info, config, weight) in available {
+            if pick < u64::from(*weight) {
+                manager_log!("✅ Returning proxy config for {}:{}: {:?}", info.address, info.port, config);
+                return Ok(config.clone());
+            }
+            pick -= u64::from(*weight);
+        }
+        unreachable!("pick is below the total weight")
+    }
+}
+
+impl ProxyCallback for StaticProxyProvider {
+    fn get_proxy(&self, target: &SocketAddr) -> Option<ProxyConfig> {
+        self.resolve(target).ok()
+    }
+
+    fn resolve(&self, target: &SocketAddr) -> Result<ProxyConfig, DirectReason> {
+        let proxy = self.get_current_proxy();
+        if let Ok(ref p) = proxy {
+            let redaction = *TARGET_REDACTION.read().unwrap();
+            let target = redact_target(redaction, &ProxyTarget::Addr(*target));
+            manager_log!("Dynamic proxy for {} -> {:?}", target, p);
+        }
+        proxy
+    }
+
+    fn report_failure(&self, proxy_addr: &SocketAddr) {
+        manager_log!("Proxy {} failed, cooling down for {:?}", proxy_addr, PROXY_FAILURE_COOLDOWN);
+        self.0.failures.lock().unwrap().insert(*proxy_addr, Instant::now());
+    }
+}
+
+/// Retries a rejected handshake with the proxy's current credentials
+///
+/// A connection picks its proxy, credentials included, when it starts. If
+/// tor_set_proxy_frb() changes the credentials while the handshake is under
+/// way, the proxy may reject the old ones; the retry then uses the new ones
+/// instead of failing the connection. Credentials that did not change are
+/// not tried again.
+impl AuthRefreshCallback for StaticProxyProvider {
+    fn refresh_auth(
+        &self,
+        proxy_addr: SocketAddr,
+        rejected: ProxyAuth,
+    ) -> BoxFuture<'_, Option<ProxyAuth>> {
+        let current = self
+            .0
+            .proxies
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|p| util::proxy_socket_addr(&p.proxy) == Some(proxy_addr))
+            .find_map(|p| proxy_auth_for(&p.proxy))
+            .filter(|auth| {
+                auth.username != rejected.username || auth.password != rejected.password
+            });
+        if current.is_some() {
+            manager_log!("Proxy {} rejected old credentials, retrying with the current ones", proxy_addr);
         }
+        futures::future::ready(current).boxed()
+    }
+}
+
+/// Convert proxy info from Dart into a proxy config, None if the address or
+/// the certificate pin is invalid
+fn proxy_config_for(proxy_info: &ProxyInfo) -> Option<ProxyConfig> {
+    let proxy_addr = util::proxy_socket_addr(proxy_info)?;
+    
+    let auth = proxy_auth_for(proxy_info);
+
+    match proxy_info.proxy_type {
+        ProxyType::Socks5 => Some(ProxyConfig::Socks5 {
+            proxy_addr,
+            auth,
+            handshake_timeout: None,
+        }),
+        ProxyType::HttpConnect => Some(ProxyConfig::HttpConnect {
+            proxy_addr,
+            auth,
+            handshake_timeout: None,
+            max_response_len: None,
+        }),
+        ProxyType::Socks4 => Some(ProxyConfig::Socks4 {
+            proxy_addr,
+            user_id: proxy_info.username.clone(),
+            handshake_timeout: None,
+        }),
+        ProxyType::HttpsConnect => Some(ProxyConfig::HttpsConnect {
+            proxy_addr,
+            server_name: proxy_info
+                .tls_server_name
+                .as_deref()
+                .unwrap_or_default()
+                .trim()
+                .to_string(),
+            cert_sha256: match proxy_info.tls_cert_sha256.as_deref() {
+                Some(pin) => Some(util::parse_cert_sha256(pin)?),
+                None => None,
+            },
+            auth,
+            handshake_timeout: None,
+            max_response_len: None,
+        }),
+    }
+}
+
+/// Credentials from proxy info, None if neither username nor password is set
+fn proxy_auth_for(proxy_info: &ProxyInfo) -> Option<ProxyAuth> {
+    if proxy_info.username.is_none() && proxy_info.password.is_none() {
+        return None;
+    }
+    Some(ProxyAuth {
+        username: proxy_info.username.clone().unwrap_or_default(),
+        password: proxy_info.password.clone().unwrap_or_default(),
+    })
+}
+
+/// Address of the proxy server a config connects through
+fn proxy_addr_of(config: &ProxyConfig) -> Option<SocketAddr> {
+    match config {
+        ProxyConfig::Socks5 { proxy_addr, .. }
+        | ProxyConfig::Socks4 { proxy_addr, .. }
+        | ProxyConfig::HttpConnect { proxy_addr, .. }
+        | ProxyConfig::HttpsConnect { proxy_addr, .. } => Some(*proxy_addr),
+        ProxyConfig::Chain(hops) => hops.first().and_then(proxy_addr_of),
+        ProxyConfig::Failover(failover) => failover.proxies().first().and_then(proxy_addr_of),
+        ProxyConfig::Direct | ProxyConfig::Dynamic(_) | ProxyConfig::DynamicAsync(_) => None,
     }
 }
 
 /// Start Tor service
 /// 
-/// If use_system_proxy is true, Tor will read proxy from global state (set via set_proxy).
+/// If options.use_system_proxy is true, Tor will read proxy from global state (set via set_proxy).
 /// If false or no proxy is set, direct connections will be used.
+///
+/// A socks_port of 0 listens on a free port chosen by the OS; the port
+/// actually used is returned (see resolve_socks_port).
+///
+/// options.bind_addr is the IP address to listen on; None listens on
+/// localhost only. Any other address lets other devices on that network use
+/// the listener, which has no authentication. options.socks_idle_timeout_secs
+/// closes SOCKS connections without traffic for that long. The other options
+/// describe the client, see ClientParams::new.
+///
+/// If a client is already running without a SOCKS listener (see
+/// `start_client_only`), the listener is added to that client. That client
+/// keeps the options it was started with, as arti only takes them when a
+/// client is created.
+#[cfg(feature = "socks")]
 pub async fn start(
     socks_port: u16,
     state_dir: String,
     cache_dir: String,
+    options: StartOptions,
+) -> anyhow::Result<u16> {
+    let use_system_proxy = options.use_system_proxy;
+    manager_log!("start called: port={}, use_proxy={}", socks_port, use_system_proxy);
+    let bind = options.bind_addr.as_deref().map(parse_bind_addr).transpose()?;
+    let idle_timeout = timeout_secs(options.socks_idle_timeout_secs, "SOCKS idle timeout")?;
+    let params = ClientParams::new(state_dir, cache_dir, options)?;
+    start_socks(socks_port, bind, idle_timeout, params, use_system_proxy).await
+}
+
+/// `start` with its options already checked, also used to restart
+#[cfg(feature = "socks")]
+async fn start_socks(
+    socks_port: u16,
+    bind: Option<IpAddr>,
+    idle_timeout: Option<Duration>,
+    params: ClientParams,
     use_system_proxy: bool,
 ) -> anyhow::Result<u16> {
-    eprintln!("[RUST] start called: port={}, use_proxy={}", socks_port, use_system_proxy);
-    
-    // If already started, return existing port
-    if let Some((port, _)) = STATE.lock().unwrap().as_ref() {
-        eprintln!("[RUST] Already started, returning port {}", port);
-        return Ok(*port);
+    // If already started, return existing port, unless the SOCKS proxy task
+    // has exited in the meantime, in which case start afresh
+    let existing_state = {
+        let mut state = STATE.lock().unwrap();
+        match state.as_ref().map(|existing| &existing.socks) {
+            Some(Some(socks)) if !socks.handle.is_finished() => {
+                manager_log!("Already started, returning port {}", socks.port);
+                return Ok(socks.port);
+            }
+            Some(Some(socks)) => {
+                manager_log!("SOCKS proxy task on port {} has exited, restarting", socks.port);
+                *state = None;
+                None
+            }
+            Some(None) => state.take(),
+            None => None,
+        }
+    };
+
+    let mut tor_state = match existing_state {
+        Some(tor_state) => {
+            manager_log!("Reusing running client for SOCKS listener");
+            tor_state
+        }
+        None => bootstrap_client(params, use_system_proxy).await?,
+    };
+    *SOCKS_IDLE_TIMEOUT.write().unwrap() = idle_timeout;
+    let socks_port = match resolve_socks_port(bind, socks_port) {
+        Ok(socks_port) => socks_port,
+        Err(e) => {
+            // Keep the bootstrapped client for the next attempt
+            set_state(tor_state);
+            return Err(e);
+        }
+    };
+
+    // The listener task is owned by STATE and aborted by stop()
+    if let Some(ip) = bind.filter(|ip| !ip.is_loopback()) {
+        manager_log!("SOCKS listener bound to {}, reachable from outside this device", ip);
     }
+    tor_state.socks = Some(SocksListener {
+        bind,
+        port: socks_port,
+        handle: spawn_socks_listener(&tor_state.client, bind, socks_port),
+    });
+    set_state(tor_state);
+    manager_log!("start completed successfully, returning port {}", socks_port);
+    Ok(socks_port)
+}
 
-    eprintln!("[RUST] Getting current Tokio runtime from FRB...");
-    let base_runtime = TokioNativeTlsRuntime::current()?;
-    eprintln!("[RUST] Runtime obtained successfully");
-    
-    // Always use proxy provider, but with Direct config when proxy is disabled
-    let proxy_config = if use_system_proxy {
-        eprintln!("[RUST] Setting up proxy provider (reads from global state)");
-        
-        // Create a ProxyConfig that dynamically reads from CURRENT_PROXY
-        ProxyConfig::Dynamic(Arc::new(move |target: &SocketAddr| {
-            let proxy = StaticProxyProvider::get_current_proxy();
-            if let Some(ref p) = proxy {
-                if std::env::var("TOR_PROXY_DEBUG").is_ok() {
-                    eprintln!("[RUST] Dynamic proxy for {} -> {:?}", target, p);
-                }
+/// Start Tor service (unavailable: built without the `socks` feature)
+#[cfg(not(feature = "socks"))]
+pub async fn start(
+    _socks_port: u16,
+    _state_dir: String,
+    _cache_dir: String,
+    _options: StartOptions,
+) -> anyhow::Result<u16> {
+    Err(TorError::FeatureDisabled("socks").into())
+}
+
+/// The port to listen on when `socks_port` is requested
+///
+/// arti takes port 0 to mean no listener at all, so for 0 the OS is asked
+/// for a free port instead. The probe socket is closed before arti binds the
+/// port, leaving a short window in which another process could take it;
+/// arti then fails to listen as for any port in use. The port is probed on
+/// `bind`, or on localhost if that is None.
+#[cfg(feature = "socks")]
+fn resolve_socks_port(bind: Option<IpAddr>, socks_port: u16) -> anyhow::Result<u16> {
+    if socks_port != 0 {
+        return Ok(socks_port);
+    }
+    let ip = bind.unwrap_or(IpAddr::V4(std::net::Ipv4Addr::LOCALHOST));
+    let probe = std::net::TcpListener::bind((ip, 0))
+        .map_err(|e| anyhow::anyhow!("Cannot find a free SOCKS port: {}", e))?;
+    let port = probe.local_addr()?.port();
+    manager_log!("SOCKS port 0 requested, using free port {}", port);
+    Ok(port)
+}
+
+/// The IP address `start` should bind the SOCKS listener to
+#[cfg(feature = "socks")]
+fn parse_bind_addr(addr: &str) -> anyhow::Result<IpAddr> {
+    addr.trim().parse().map_err(|_| {
+        anyhow::anyhow!(
+            "Invalid SOCKS bind address {:?}: expected an IP address such as 0.0.0.0 or 192.168.1.5",
+            addr
+        )
+    })
+}
+
+/// Run a SOCKS listener for `client` on `bind`:`socks_port`, or on
+/// localhost if `bind` is None
+#[cfg(feature = "socks")]
+fn spawn_socks_listener(
+    client: &TorClient<TorRuntime>,
+    bind: Option<IpAddr>,
+    socks_port: u16,
+) -> JoinHandle<anyhow::Result<()>> {
+    let runtime = client.runtime().clone();
+    let client = client.clone();
+    tokio::spawn(async move {
+        let listen = match bind {
+            // Listen has no constructor for one address, but parses one
+            // as in arti's config file
+            Some(ip) => {
+                let addr = SocketAddr::new(ip, socks_port).to_string();
+                toml::Value::String(addr).try_into::<Listen>()?
             }
-            proxy
-        }))
+            None => Listen::new_localhost(socks_port),
+        };
+        socks::run_socks_proxy(runtime, client, listen, None).await
+    })
+}
+
+/// Move the SOCKS listener to another port, keeping the bootstrapped client
+///
+/// The new listener is opened before the old one is closed, so if it cannot
+/// listen (e.g. the port is taken) this fails and the old port stays in use.
+/// A socks_port of 0 moves it to a free port, as for `start`. The listener
+/// stays on the address it was bound to.
+#[cfg(feature = "socks")]
+pub async fn rebind_socks(socks_port: u16) -> anyhow::Result<u16> {
+    manager_log!("rebind_socks called: port={}", socks_port);
+    let (client, bind) = match STATE.lock().unwrap().as_ref() {
+        Some(TorState { socks: Some(socks), .. })
+            if socks.port == socks_port && !socks.handle.is_finished() =>
+        {
+            return Ok(socks_port);
+        }
+        Some(TorState { socks: Some(socks), client, .. }) => (client.clone(), socks.bind),
+        Some(TorState { socks: None, .. }) => {
+            anyhow::bail!("No SOCKS listener is running; use start to add one")
+        }
+        None => anyhow::bail!("Tor is not running"),
+    };
+
+    let socks_port = resolve_socks_port(bind, socks_port)?;
+
+    // run_socks_proxy returns straight away if it cannot listen at all
+    let mut handle = spawn_socks_listener(&client, bind, socks_port);
+    if let Ok(result) = timeout(SOCKS_LISTEN_CHECK, &mut handle).await {
+        let error = match result {
+            Ok(Err(e)) => e,
+            Ok(Ok(())) => anyhow::anyhow!("SOCKS listener exited"),
+            Err(e) => e.into(),
+        };
+        return Err(error.context(format!("Cannot listen on SOCKS port {}", socks_port)));
+    }
+
+    let mut state = STATE.lock().unwrap();
+    let Some(tor_state) = state.as_mut() else {
+        handle.abort();
+        anyhow::bail!("Tor was stopped while moving the SOCKS listener");
+    };
+    if let Some(old) = tor_state.socks.replace(SocksListener { bind, port: socks_port, handle }) {
+        manager_log!("Closing SOCKS listener on port {}", old.port);
+        old.handle.abort();
+    }
+    STATUS.send_modify(|status| status.socks_port = Some(socks_port));
+    manager_log!("SOCKS listener moved to port {}", socks_port);
+    Ok(socks_port)
+}
+
+/// Move the SOCKS listener (unavailable: built without the `socks` feature)
+#[cfg(not(feature = "socks"))]
+pub async fn rebind_socks(_socks_port: u16) -> anyhow::Result<u16> {
+    Err(TorError::FeatureDisabled("socks").into())
+}
+
+/// Start Tor without a local SOCKS listener
+///
+/// Bootstraps and keeps a client for in-process use only. Returns
+/// immediately if a client is already running. The options are as for
+/// `start`, less the SOCKS idle timeout and bind address, which are left
+/// to the `start` call that adds the listener.
+pub async fn start_client_only(
+    state_dir: String,
+    cache_dir: String,
+    options: StartOptions,
+) -> anyhow::Result<()> {
+    let use_system_proxy = options.use_system_proxy;
+    manager_log!("start_client_only called: use_proxy={}", use_system_proxy);
+    let params = ClientParams::new(state_dir, cache_dir, options)?;
+    start_client(params, use_system_proxy).await
+}
+
+/// `start_client_only` with its options already checked, also used to
+/// restart
+async fn start_client(params: ClientParams, use_system_proxy: bool) -> anyhow::Result<()> {
+    if STATE.lock().unwrap().is_some() {
+        manager_log!("Already started");
+        return Ok(());
+    }
+
+    let tor_state = bootstrap_client(params, use_system_proxy).await?;
+    set_state(tor_state);
+    manager_log!("start_client_only completed successfully");
+    Ok(())
+}
+
+/// Create and bootstrap a TorClient on the proxy-aware runtime
+///
+/// Returns it as a TorState without SOCKS listener, with bootstrap progress
+/// already being forwarded to observers. It connects through PROXY_STATE.
+///
+/// If cache_dir holds a consensus but bootstrap fails because the cache is
+/// corrupted or does not finish within CACHED_BOOTSTRAP_TIMEOUT, the
+/// directory cache is cleared and bootstrap starts over once with a new
+/// client; a bad cache otherwise keeps failing on every start.
+///
+/// With `params.bootstrap_timeout`, all of this must finish within that
+/// time. On expiry the bootstrap future is dropped, and with it the client
+/// and its tasks; nothing has gone into STATE yet, so a later start begins
+/// afresh. Clients created while offline are returned without waiting.
+async fn bootstrap_client(params: ClientParams, use_system_proxy: bool) -> anyhow::Result<TorState> {
+    // Always resolve proxies dynamically from global state, so that
+    // set_use_proxy can switch between proxied and direct at runtime
+    manager_log!("Setting up proxy provider (reads from global state, use_proxy={})", use_system_proxy);
+    *PROXY_STATE.use_proxy.lock().unwrap() = use_system_proxy;
+
+    // While offline, create the client without bootstrapping. Manual mode
+    // keeps connection attempts from starting a bootstrap that cannot succeed;
+    // notify_network_changed() starts it once connectivity returns.
+    let online = *NETWORK_ONLINE.lock().unwrap();
+    let behavior = if online {
+        BootstrapBehavior::OnDemand
     } else {
-        eprintln!("[RUST] Using direct connections (no proxy)");
-        ProxyConfig::Direct
+        BootstrapBehavior::Manual
+    };
+    let cached = cached_consensus_valid_until(Path::new(&params.cache_dir)).is_some();
+    let tor_state = new_tor_state(&params, behavior)?;
+    if !online {
+        manager_log!("Network is down, TorClient created with bootstrap deferred");
+        return Ok(tor_state);
+    }
+
+    // Bootstrap runs inside this future rather than a spawned task, so it is
+    // dropped together with the caller, and stop() can abort it
+    let (_pending, registration) = PendingBootstrap::register();
+    let bootstrap = async {
+        let first =
+            bootstrap_with_fallback(&tor_state.client, &tor_state.config, tor_state.default_dirs.as_ref());
+        if !cached {
+            return first.await.map(|()| tor_state);
+        }
+        match timeout(CACHED_BOOTSTRAP_TIMEOUT, first).await {
+            Ok(Ok(())) => return Ok(tor_state),
+            Ok(Err(e)) if !is_cache_error(&e) => return Err(e),
+            Ok(Err(e)) => manager_log!("Bootstrap from the directory cache failed: {}", e),
+            Err(_) => manager_log!("Bootstrap with a cached directory timed out"),
+        }
+
+        // The client has the cache open, so it goes before the cache does
+        drop(tor_state);
+        manager_log!("Clearing the directory cache and bootstrapping again");
+        remove_dir_cache(Path::new(&params.cache_dir))?;
+        // Only reached online, where clients bootstrap on demand
+        let tor_state = new_tor_state(&params, BootstrapBehavior::OnDemand)?;
+        bootstrap_with_fallback(&tor_state.client, &tor_state.config, tor_state.default_dirs.as_ref())
+            .await?;
+        Ok(tor_state)
+    };
+    let bootstrap = Abortable::new(bootstrap, registration);
+    let result = match params.bootstrap_timeout {
+        Some(limit) => match timeout(limit, bootstrap).await {
+            Ok(result) => result,
+            Err(_) => {
+                manager_log!("Bootstrap did not finish within {:?}, dropping the client", limit);
+                // The dropped status task may have reported progress already
+                clear_status();
+                return Err(TorError::BootstrapTimedOut { after: limit }.into());
+            }
+        },
+        None => bootstrap.await,
     };
+    let tor_state = result.map_err(|_| anyhow::anyhow!("Tor start was cancelled by stop()"))??;
+    manager_log!("TorClient created and bootstrapped");
+    Ok(tor_state)
+}
+
+/// Create a client connecting through PROXY_STATE, with its status and
+/// health tasks, as a TorState without SOCKS listener
+fn new_tor_state(params: &ClientParams, behavior: BootstrapBehavior) -> anyhow::Result<TorState> {
+    let NewClient { client, config, default_dirs } =
+        create_client(params, Arc::clone(&PROXY_STATE), SOCKS_STREAMS.clone(), behavior)?;
+    if matches!(dormant_mode(), DormantMode::Soft) {
+        client.set_dormant(DormantMode::Soft);
+    }
+    Ok(TorState {
+        _status_task: spawn_status_task(&client),
+        _health_task: spawn_health_task(&client, params.clone()),
+        client,
+        socks: None,
+        config: Arc::new(Mutex::new(config)),
+        default_dirs,
+    })
+}
+
+/// Whether bootstrap failed with `error` because arti could not use its
+/// directory cache
+fn is_cache_error(error: &anyhow::Error) -> bool {
+    error
+        .downcast_ref::<arti_client::Error>()
+        .is_some_and(|e| e.kind() == ErrorKind::CacheCorrupted)
+}
+
+/// Client from create_client, not bootstrapped yet
+struct NewClient {
+    client: TorClient<TorRuntime>,
+    /// Config the client runs with
+    config: TorClientConfigBuilder,
+    /// As for `TorState::default_dirs`
+    default_dirs: Option<TorClientConfigBuilder>,
+}
+
+/// Create a TorClient on the proxy-aware runtime that connects through
+/// `proxies` and counts the connections its SOCKS listener accepts in
+/// `streams`
+///
+/// The connect timeout, preemptive circuit count and consensus tolerance in
+/// `params` replace arti's defaults if set.
+fn create_client(
+    params: &ClientParams,
+    proxies: Arc<ProxyState>,
+    streams: ActiveStreams,
+    behavior: BootstrapBehavior,
+) -> anyhow::Result<NewClient> {
+    let ClientParams {
+        state_dir,
+        cache_dir,
+        connection_log,
+        directory_mirrors,
+        bridges,
+        obfs4_binary_path,
+        snowflake,
+        connect_timeout,
+        preemptive_circuits,
+        consensus_tolerance,
+        bootstrap_timeout: _,
+        handshake_deadline,
+        socks5_method_order,
+    } = params.clone();
+
+    manager_log!("Getting current Tokio runtime from FRB...");
+    let base_runtime = TokioNativeTlsRuntime::current()?;
+    manager_log!("Runtime obtained successfully");
+    let proxy_source = Arc::new(StaticProxyProvider(proxies));
+    let proxy_config = ProxyConfig::Dynamic(Arc::clone(&proxy_source) as Arc<dyn ProxyCallback>);
     
-    let proxy_provider = ProxyTcpProvider::new(base_runtime.clone(), proxy_config);
+    // A proxy that drops established streams is cooled down like one that
+    // refuses connections (see StaticProxyProvider). Refused, reset and
+    // timed out connections are retried with backoff, so a proxy that
+    // hiccups once does not fail a circuit.
+    let mut proxy_provider = ProxyTcpProvider::new(base_runtime.clone(), proxy_config)
+        .with_target_policy(Arc::clone(&TARGET_POLICY))
+        .with_target_redaction(Arc::clone(&TARGET_REDACTION))
+        .with_listener_idle_timeout(Arc::clone(&SOCKS_IDLE_TIMEOUT))
+        .with_listener_isolation_map(Arc::clone(&SOCKS_ISOLATION_MAP))
+        .with_listener_streams(streams)
+        .with_failover_on_disconnect()
+        .with_retry_policy(RetryPolicy::default())
+        .with_auth_refresh(proxy_source)
+        .with_observer(Arc::new(ConnectionEventObserver));
+    if let Some(deadline) = handshake_deadline {
+        proxy_provider = proxy_provider.with_handshake_deadline(deadline);
+    }
+    if let Some(order) = socks5_method_order {
+        proxy_provider = proxy_provider.with_socks5_method_order(&order);
+    }
+    if connection_log {
+        let log = ConnectionLog::open(Path::new(&state_dir))?;
+        manager_log!("Connection log enabled in {}", state_dir);
+        proxy_provider = proxy_provider.with_connection_log(Arc::new(log));
+    }
     let runtime = base_runtime.with_tcp_provider(proxy_provider);
 
     let mut cfg_builder = TorClientConfig::builder();
@@ -107,62 +989,1656 @@ pub async fn start(
         .state_dir(CfgPath::new(state_dir))
         .cache_dir(CfgPath::new(cache_dir));
     cfg_builder.address_filter().allow_onion_addrs(true);
+    if let Some(connect_timeout) = connect_timeout {
+        cfg_builder.stream_timeouts().connect_timeout(connect_timeout);
+    }
+    if let Some(count) = preemptive_circuits {
+        anyhow::ensure!(
+            count <= MAX_PREEMPTIVE_CIRCUITS,
+            "preemptive_circuits must be at most {}",
+            MAX_PREEMPTIVE_CIRCUITS
+        );
+        let preemptive = cfg_builder.preemptive_circuits();
+        preemptive.min_exit_circs_for_port(count.into());
+        if count == 0 {
+            // arti builds nothing ahead while it has this many circuits
+            preemptive.disable_at_threshold(0);
+        }
+    }
+    if let Some(tolerance) = consensus_tolerance {
+        anyhow::ensure!(
+            tolerance <= MAX_CONSENSUS_TOLERANCE,
+            "consensus_time_tolerance_secs must be at most {}",
+            MAX_CONSENSUS_TOLERANCE.as_secs()
+        );
+        cfg_builder
+            .directory_tolerance()
+            .pre_valid_tolerance(tolerance)
+            .post_valid_tolerance(tolerance);
+    }
+
+    if !bridges.is_empty() || snowflake.is_some() {
+        manager_log!("Connecting through {} bridges, Snowflake: {}", bridges.len(), snowflake.is_some());
+        configure_bridges(&mut cfg_builder, &bridges, obfs4_binary_path.as_deref(), snowflake.as_ref())?;
+    }
 
+    let default_dirs = if directory_mirrors.is_empty() {
+        None
+    } else {
+        manager_log!("Bootstrapping from {} custom directory mirrors", directory_mirrors.len());
+        let default_dirs = cfg_builder.clone();
+        // Fail now rather than when bootstrap falls back to it
+        default_dirs.build()?;
+        cfg_builder
+            .tor_network()
+            .set_fallback_caches(fallback_dirs_for(&directory_mirrors)?);
+        Some(default_dirs)
+    };
     let cfg = cfg_builder.build()?;
-    eprintln!("[RUST] Config built, creating TorClient...");
+    manager_log!("Config built, creating TorClient...");
 
-    let client = arti_client::TorClient::with_runtime(runtime.clone())
+    let client = TorClient::with_runtime(runtime)
         .config(cfg)
-        .create_bootstrapped()
-        .await?;
-    eprintln!("[RUST] TorClient created and bootstrapped");
-
-    let runtime_clone = runtime.clone();
-    let client_clone = client.clone();
-    let proxy_handle = tokio::spawn(async move {
-        socks::run_socks_proxy(
-            runtime_clone,
-            client_clone,
-            Listen::new_localhost(socks_port),
-            None,
-        ).await
+        .bootstrap_behavior(behavior)
+        .create_unbootstrapped()?;
+    Ok(NewClient {
+        client,
+        config: cfg_builder,
+        default_dirs,
+    })
+}
+
+/// Convert directory mirrors from Dart into arti fallback directories
+fn fallback_dirs_for(mirrors: &[DirectoryMirror]) -> anyhow::Result<Vec<FallbackDirBuilder>> {
+    mirrors
+        .iter()
+        .map(|mirror| {
+            let rsa_identity = RsaIdentity::from_hex(&mirror.rsa_identity).ok_or_else(|| {
+                anyhow::anyhow!("Invalid directory mirror RSA identity: {}", mirror.rsa_identity)
+            })?;
+            let ed_identity = STANDARD_NO_PAD
+                .decode(mirror.ed_identity.trim_end_matches('='))
+                .ok()
+                .and_then(|bytes| Ed25519Identity::from_bytes(&bytes))
+                .ok_or_else(|| {
+                    anyhow::anyhow!("Invalid directory mirror Ed25519 identity: {}", mirror.ed_identity)
+                })?;
+
+            let mut fallback = FallbackDirBuilder::new();
+            fallback.rsa_identity(rsa_identity).ed_identity(ed_identity);
+            for address in &mirror.addresses {
+                let orport: SocketAddr = address
+                    .parse()
+                    .map_err(|_| anyhow::anyhow!("Invalid directory mirror address: {}", address))?;
+                fallback.orports().push(orport);
+            }
+            // Checks that at least one address was given
+            fallback.build()?;
+            Ok(fallback)
+        })
+        .collect()
+}
+
+/// Make `cfg_builder` reach the Tor network only through `bridges` and the
+/// `snowflake` bridge
+///
+/// Every bridge line is checked first. arti runs the binary at
+/// `obfs4_binary_path` to speak obfs4 to the bridges, and the snowflake
+/// client for the Snowflake bridge. Errors name bridges by their position
+/// rather than quoting the line, as bridge lines are secret.
+#[cfg(feature = "bridges")]
+fn configure_bridges(
+    cfg_builder: &mut TorClientConfigBuilder,
+    bridges: &[BridgeLine],
+    obfs4_binary_path: Option<&str>,
+    snowflake: Option<&SnowflakeConfig>,
+) -> anyhow::Result<()> {
+    let mut bridge_configs = bridges
+        .iter()
+        .enumerate()
+        .map(|(i, bridge)| {
+            let line = bridge.line.trim();
+            anyhow::ensure!(
+                line.split_whitespace().next() == Some("obfs4"),
+                "Bridge {} is not an obfs4 bridge",
+                i + 1
+            );
+            let config: BridgeConfigBuilder = line
+                .parse()
+                .map_err(|e| anyhow::anyhow!("Invalid bridge line {}: {}", i + 1, e))?;
+            config
+                .build()
+                .map_err(|e| anyhow::anyhow!("Invalid bridge line {}: {}", i + 1, e))?;
+            Ok(config)
+        })
+        .collect::<anyhow::Result<Vec<_>>>()?;
+
+    let mut transports = Vec::new();
+    if !bridge_configs.is_empty() {
+        let path = obfs4_binary_path
+            .ok_or_else(|| anyhow::anyhow!("obfs4_binary_path is required for obfs4 bridges"))?;
+        anyhow::ensure!(Path::new(path).is_file(), "obfs4 binary not found: {}", path);
+        let mut transport = TransportConfigBuilder::default();
+        transport
+            .protocols(vec!["obfs4".parse()?])
+            .path(CfgPath::new(path.to_string()));
+        transports.push(transport);
+    }
+
+    if let Some(snowflake) = snowflake {
+        let config: BridgeConfigBuilder = snowflake_bridge_line(snowflake)?
+            .parse()
+            .map_err(|e| anyhow::anyhow!("Invalid snowflake bridge line: {}", e))?;
+        config
+            .build()
+            .map_err(|e| anyhow::anyhow!("Invalid snowflake bridge line: {}", e))?;
+        bridge_configs.push(config);
+
+        let path = &snowflake.binary_path;
+        anyhow::ensure!(Path::new(path).is_file(), "snowflake binary not found: {}", path);
+        let mut transport = TransportConfigBuilder::default();
+        transport
+            .protocols(vec!["snowflake".parse()?])
+            .path(CfgPath::new(path.clone()))
+            .arguments(snowflake.arguments.clone());
+        transports.push(transport);
+    }
+
+    let bridges_builder = cfg_builder.bridges();
+    bridges_builder.bridges().extend(bridge_configs);
+    bridges_builder.transports().extend(transports);
+    Ok(())
+}
+
+/// The bridge line of `config`, with its broker, front domain and STUN
+/// servers in place of the line's own url=, fronts= and ice=
+///
+/// The snowflake client gets these as the bridge's settings when arti
+/// connects to it.
+#[cfg(feature = "bridges")]
+fn snowflake_bridge_line(config: &SnowflakeConfig) -> anyhow::Result<String> {
+    let mut words: Vec<&str> = config.bridge_line.split_whitespace().collect();
+    anyhow::ensure!(
+        words.first() == Some(&"snowflake"),
+        "Snowflake bridge line does not start with \"snowflake\""
+    );
+
+    let mut settings = Vec::new();
+    if let Some(url) = &config.broker_url {
+        settings.push(("url", url.clone()));
+    }
+    if let Some(front) = &config.front_domain {
+        settings.push(("fronts", front.clone()));
+    }
+    for server in &config.stun_servers {
+        anyhow::ensure!(!server.contains(','), "Invalid STUN server: {}", server);
+    }
+    if !config.stun_servers.is_empty() {
+        settings.push(("ice", config.stun_servers.join(",")));
+    }
+    for (key, value) in &settings {
+        anyhow::ensure!(
+            !value.is_empty() && !value.contains(char::is_whitespace),
+            "Invalid snowflake {} setting: {:?}",
+            key,
+            value
+        );
+    }
+
+    // front= is the older, single-domain form of fronts=
+    words.retain(|word| {
+        let Some((key, _)) = word.split_once('=') else {
+            return true;
+        };
+        let key = if key == "front" { "fronts" } else { key };
+        !settings.iter().any(|(setting, _)| *setting == key)
     });
+    let mut line = words.join(" ");
+    for (key, value) in settings {
+        line.push_str(&format!(" {}={}", key, value));
+    }
+    Ok(line)
+}
 
-    *STATE.lock().unwrap() = Some((socks_port, proxy_handle));
-    eprintln!("[RUST] start completed successfully, returning port {}", socks_port);
-    Ok(socks_port)
+#[cfg(not(feature = "bridges"))]
+fn configure_bridges(
+    _cfg_builder: &mut TorClientConfigBuilder,
+    _bridges: &[BridgeLine],
+    _obfs4_binary_path: Option<&str>,
+    _snowflake: Option<&SnowflakeConfig>,
+) -> anyhow::Result<()> {
+    Err(TorError::FeatureDisabled("bridges").into())
+}
+
+/// Bootstrap `client`, falling back to arti's built-in directories if it
+/// uses custom mirrors and cannot get through them
+///
+/// Mirrors are not probed up front: they are given MIRROR_BOOTSTRAP_TIMEOUT
+/// to complete bootstrap, and replaced by `default_dirs` if they fail or
+/// take longer.
+async fn bootstrap_with_fallback(
+    client: &TorClient<TorRuntime>,
+    config: &Mutex<TorClientConfigBuilder>,
+    default_dirs: Option<&TorClientConfigBuilder>,
+) -> anyhow::Result<()> {
+    let Some(default_dirs) = default_dirs else {
+        return Ok(client.bootstrap().await?);
+    };
+
+    match timeout(MIRROR_BOOTSTRAP_TIMEOUT, client.bootstrap()).await {
+        Ok(Ok(())) => return Ok(()),
+        Ok(Err(e)) => manager_log!("Bootstrap via directory mirrors failed: {}", e),
+        Err(_) => manager_log!("Bootstrap via directory mirrors timed out"),
+    }
+    manager_log!("Retrying bootstrap with default fallback directories");
+    client.reconfigure(&default_dirs.build()?, Reconfigure::WarnOnFailures)?;
+    *config.lock().unwrap() = default_dirs.clone();
+    Ok(client.bootstrap().await?)
+}
+
+/// Forward the client's bootstrap progress into STATUS
+///
+/// Arti keeps retrying a bootstrap that gets nowhere without saying so. If
+/// the progress has not increased for BOOTSTRAP_STALL_TIMEOUT while the
+/// client is not ready for traffic, bootstrap_stalled is set with a hint for
+/// the phase it is stuck in; it is cleared as soon as progress resumes. No
+/// stall is reported while the device is offline.
+fn spawn_status_task(client: &TorClient<TorRuntime>) -> ClientTask {
+    let mut events = client.bootstrap_events();
+    BOOTSTRAP_PROGRESS.send_replace(BootstrapStatus::default());
+    ClientTask(tokio::spawn(async move {
+        let mut percent = 0;
+        let mut ready = false;
+        let mut last_progress = Instant::now();
+        loop {
+            let next = if ready {
+                events.next().await
+            } else {
+                let wait = BOOTSTRAP_STALL_TIMEOUT.saturating_sub(last_progress.elapsed());
+                match timeout(wait, events.next()).await {
+                    Ok(next) => next,
+                    Err(_) => {
+                        // Check again after another BOOTSTRAP_STALL_TIMEOUT
+                        last_progress = Instant::now();
+                        if *NETWORK_ONLINE.lock().unwrap() {
+                            let stalled = bootstrap_stall(percent);
+                            manager_log!("Bootstrap stalled at {}% while {}", percent, stalled.last_phase);
+                            STATUS.send_modify(|status| status.bootstrap_stalled = Some(stalled));
+                        }
+                        continue;
+                    }
+                }
+            };
+            let Some(bootstrap) = next else { break };
+
+            let new_percent = (bootstrap.as_frac() * 100.0).round() as u8;
+            let was_ready = ready;
+            ready = bootstrap.ready_for_traffic();
+            // Losing readiness starts a new window rather than counting as stuck
+            let progressed = new_percent > percent || (was_ready && !ready);
+            if progressed {
+                last_progress = Instant::now();
+            }
+            percent = new_percent;
+            BOOTSTRAP_PROGRESS.send_replace(BootstrapStatus {
+                fraction: bootstrap.as_frac(),
+                summary: bootstrap.to_string(),
+            });
+            STATUS.send_modify(|status| {
+                status.bootstrap_percent = percent;
+                status.ready_for_traffic = ready;
+                status.blockage = bootstrap.blocked().map(|blockage| blockage.to_string());
+                if progressed || ready {
+                    status.bootstrap_stalled = None;
+                }
+            });
+        }
+    }))
+}
+
+/// Describe where a bootstrap stuck at `percent` is, and what may help
+///
+/// Arti counts reaching a guard as the first 15% of bootstrap (half of it
+/// for having any network at all) and the directory download as the rest,
+/// of which the consensus comes first and relay descriptors after.
+fn bootstrap_stall(percent: u8) -> BootstrapStalled {
+    let (last_phase, suggestion) = match percent {
+        0..=7 => (
+            "connecting to the Tor network",
+            "No Tor relay could be reached; your network may block Tor. Try connecting through a proxy.",
+        ),
+        8..=14 => (
+            "connecting to guards",
+            "Stuck connecting to guards; your network may block Tor. Try connecting through a proxy.",
+        ),
+        15..=39 => (
+            "downloading the consensus",
+            "The directory download is not completing. Check that the device clock is correct, or try directory mirrors.",
+        ),
+        _ => (
+            "downloading relay descriptors",
+            "The connection is too slow or keeps dropping. Try another network or a proxy.",
+        ),
+    };
+    BootstrapStalled {
+        last_phase: last_phase.to_string(),
+        suggestion: suggestion.to_string(),
+    }
+}
+
+/// Periodically check that a client which reports itself ready still works
+///
+/// A panic in one of arti's internal tasks can leave the client unable to
+/// carry traffic while it still looks ready. Each check resolves
+/// HEALTH_CHECK_HOST through Tor; after HEALTH_CHECK_MAX_FAILURES failures
+/// in a row the error is put in STATUS and, if AUTO_RESTART is set, the
+/// client is stopped and started again with the same parameters. Checks are
+/// skipped while offline, metered, dormant or not yet bootstrapped.
+fn spawn_health_task(client: &TorClient<TorRuntime>, params: ClientParams) -> ClientTask {
+    let client = client.clone();
+    ClientTask(tokio::spawn(async move {
+        let mut failures = 0;
+        loop {
+            tokio::time::sleep(HEALTH_CHECK_INTERVAL).await;
+            if !*NETWORK_ONLINE.lock().unwrap()
+                || *METERED.lock().unwrap()
+                || *DORMANT.lock().unwrap()
+                || !client.bootstrap_status().ready_for_traffic()
+            {
+                continue;
+            }
+
+            let error = match timeout(HEALTH_CHECK_TIMEOUT, client.resolve(HEALTH_CHECK_HOST)).await {
+                Ok(Ok(_)) => {
+                    failures = 0;
+                    STATUS.send_if_modified(|status| status.health_error.take().is_some());
+                    continue;
+                }
+                Ok(Err(e)) => e.to_string(),
+                Err(_) => "health check timed out".to_string(),
+            };
+            failures += 1;
+            manager_log!("Health check failed ({}/{}): {}", failures, HEALTH_CHECK_MAX_FAILURES, error);
+            if failures < HEALTH_CHECK_MAX_FAILURES {
+                continue;
+            }
+            STATUS.send_modify(|status| status.health_error = Some(error));
+
+            if *AUTO_RESTART.lock().unwrap() {
+                let socks = STATE
+                    .lock()
+                    .unwrap()
+                    .as_ref()
+                    .and_then(|state| state.socks.as_ref().map(|socks| (socks.bind, socks.port)));
+                manager_log!("Restarting unhealthy client");
+                // stop() drops the state owning this task, which is then
+                // aborted at its next await, so the restart gets its own task
+                stop();
+                tokio::spawn(restart(socks, params));
+                return;
+            }
+        }
+    }))
+}
+
+/// Start a client again after stop(), as start (with a SOCKS listener on
+/// the address and port in `socks`) or start_client_only did
+async fn restart(socks: Option<(Option<IpAddr>, u16)>, params: ClientParams) {
+    let use_proxy = *PROXY_STATE.use_proxy.lock().unwrap();
+    let result = match socks {
+        #[cfg(feature = "socks")]
+        Some((bind, socks_port)) => {
+            let idle_timeout = *SOCKS_IDLE_TIMEOUT.read().unwrap();
+            start_socks(socks_port, bind, idle_timeout, params, use_proxy).await.map(|_| ())
+        }
+        _ => start_client(params, use_proxy).await,
+    };
+    if let Err(e) = result {
+        manager_log!("Restart failed: {}", e);
+        STATUS.send_modify(|status| status.health_error = Some(format!("restart failed: {}", e)));
+    }
+}
+
+/// Install a new running state and tell observers about it
+fn set_state(tor_state: TorState) {
+    let socks_port = tor_state.socks.as_ref().map(|socks| socks.port);
+    *STATE.lock().unwrap() = Some(tor_state);
+    let dormant = matches!(dormant_mode(), DormantMode::Soft);
+    STATUS.send_modify(|status| {
+        status.running = true;
+        status.socks_port = socks_port;
+        status.dormant = dormant;
+    });
+}
+
+/// Config of the running client as TOML, for support and debugging
+///
+/// This is arti's config as set by start(), including directory mirrors or
+/// their replacement by the defaults. Settings left at arti's defaults are
+/// omitted, and sections in REDACTED_CONFIG_KEYS are replaced by "redacted".
+pub fn effective_config() -> anyhow::Result<String> {
+    let config = match STATE.lock().unwrap().as_ref() {
+        Some(state) => state.config.lock().unwrap().clone(),
+        None => anyhow::bail!("Tor is not running"),
+    };
+    let mut config = toml::Value::try_from(&config)?;
+    redact_config(&mut config);
+    Ok(toml::to_string_pretty(&config)?)
+}
+
+/// Replace the values of REDACTED_CONFIG_KEYS anywhere in `config`
+fn redact_config(config: &mut toml::Value) {
+    match config {
+        toml::Value::Table(table) => {
+            for (key, value) in table.iter_mut() {
+                if REDACTED_CONFIG_KEYS.contains(&key.as_str()) {
+                    *value = toml::Value::String("redacted".to_string());
+                } else {
+                    redact_config(value);
+                }
+            }
+        }
+        toml::Value::Array(values) => values.iter_mut().for_each(redact_config),
+        _ => {}
+    }
+}
+
+/// Current status of the Tor service
+pub fn status() -> TorStatus {
+    STATUS.borrow().clone()
+}
+
+/// Send the current status to `sink`, then every change to it
+///
+/// Observers cannot affect the service; the stream only ends when Dart
+/// stops listening.
+pub async fn observe_status(sink: StreamSink<TorStatus>) {
+    let mut changes = STATUS.subscribe();
+    loop {
+        let status = changes.borrow_and_update().clone();
+        if sink.add(status).is_err() {
+            break;
+        }
+        if changes.changed().await.is_err() {
+            break;
+        }
+    }
+}
+
+/// Send the current bootstrap progress to `sink`, then every change to it
+///
+/// Unlike `with_bootstrap_progress` this does not start anything and can be
+/// called before or during a start. Progress goes back to 0 when Tor stops
+/// and when a new client is created; the stream only ends when Dart stops
+/// listening.
+pub async fn observe_bootstrap(sink: StreamSink<BootstrapStatus>) {
+    let mut changes = BOOTSTRAP_PROGRESS.subscribe();
+    loop {
+        let progress = changes.borrow_and_update().clone();
+        if sink.add(progress).is_err() {
+            break;
+        }
+        if changes.changed().await.is_err() {
+            break;
+        }
+    }
+}
+
+/// Send every outgoing connection attempt to `sink` until Dart stops
+/// listening
+///
+/// Attempts from before the call are not sent. A listener that falls more
+/// than CONNECTION_EVENT_QUEUE events behind misses the oldest ones.
+pub async fn observe_connections(sink: StreamSink<ConnectionEvent>) {
+    let mut events = CONNECTION_EVENTS.subscribe();
+    loop {
+        match events.recv().await {
+            Ok(event) => {
+                if sink.add(event).is_err() {
+                    break;
+                }
+            }
+            Err(broadcast::error::RecvError::Lagged(missed)) => {
+                manager_log!("Connection observer fell behind, {} events dropped", missed);
+            }
+            Err(broadcast::error::RecvError::Closed) => break,
+        }
+    }
+}
+
+/// Proxy observer that hands connection attempts to observe_connections()
+struct ConnectionEventObserver;
+
+impl ConnectionEventObserver {
+    fn send(
+        &self,
+        stage: ConnectionStage,
+        target: &str,
+        via: &str,
+        elapsed: Option<Duration>,
+        error: Option<String>,
+    ) {
+        // Spare building the event while nobody listens
+        if CONNECTION_EVENTS.receiver_count() == 0 {
+            return;
+        }
+        let _ = CONNECTION_EVENTS.send(ConnectionEvent {
+            stage,
+            target: target.to_string(),
+            via: via.to_string(),
+            elapsed_ms: elapsed.map(|e| u32::try_from(e.as_millis()).unwrap_or(u32::MAX)),
+            error,
+        });
+    }
+}
+
+impl ProxyObserver for ConnectionEventObserver {
+    fn on_connect_start(&self, target: &str, via: &str) {
+        self.send(ConnectionStage::Started, target, via, None, None);
+    }
+
+    fn on_connect_success(&self, target: &str, via: &str, elapsed: Duration) {
+        self.send(ConnectionStage::Connected, target, via, Some(elapsed), None);
+    }
+
+    fn on_connect_error(&self, target: &str, via: &str, error: &std::io::Error) {
+        let error = match TorError::from_proxy_io(error) {
+            Some(proxy_error) => proxy_error.to_string(),
+            None => error.to_string(),
+        };
+        self.send(ConnectionStage::Failed, target, via, None, Some(error));
+    }
+}
+
+/// Run `start`, sending the client's bootstrap progress to `sink` meanwhile
+///
+/// Only changes after the call are sent, and once `start` has finished the
+/// progress at that point, so a successful bootstrap ends the stream at 1.0.
+/// A client that is already running sends just that last event.
+pub async fn with_bootstrap_progress<T>(
+    sink: StreamSink<BootstrapStatus>,
+    start: impl std::future::Future<Output = T>,
+) -> T {
+    let mut changes = BOOTSTRAP_PROGRESS.subscribe();
+    changes.borrow_and_update();
+    let forward = async {
+        while changes.changed().await.is_ok() {
+            let progress = changes.borrow_and_update().clone();
+            if sink.add(progress).is_err() {
+                break;
+            }
+        }
+        // Nobody listens any more, but start still has to finish
+        std::future::pending().await
+    };
+    let result = tokio::select! {
+        result = start => result,
+        never = forward => never,
+    };
+    let _ = sink.add(BOOTSTRAP_PROGRESS.borrow().clone());
+    result
+}
+
+/// Resolve a host name through Tor
+///
+/// The exit relay does the lookup, so nothing leaks to the local resolver.
+/// An unknown name fails with `TorError::HostNotFound`; anything else,
+/// including Tor not running yet, with `TorError::ResolveFailed`.
+pub async fn resolve(name: String) -> Result<Vec<String>, TorError> {
+    let client = STATE.lock().unwrap().as_ref().map(|state| state.client.clone());
+    let Some(client) = client else {
+        return Err(TorError::ResolveFailed {
+            name,
+            reason: "Tor is not running".to_string(),
+        });
+    };
+    match client.resolve(&name).await {
+        Ok(addrs) => Ok(addrs.iter().map(|addr| addr.to_string()).collect()),
+        Err(e) if e.kind() == ErrorKind::RemoteHostNotFound => {
+            manager_log!("resolve: no such host");
+            Err(TorError::HostNotFound { name })
+        }
+        Err(e) => {
+            manager_log!("resolve failed: {}", e);
+            Err(TorError::ResolveFailed {
+                name,
+                reason: e.to_string(),
+            })
+        }
+    }
+}
+
+/// Restrict circuits from build_circuit to exits in `country_codes`
+///
+/// Codes are ISO 3166-1 alpha-2, in either case ("us", "DE"); an empty list
+/// lifts the restriction. arti takes a single exit country per stream, so
+/// each new circuit exits in one of the countries chosen at random. Circuits
+/// already built keep their exit. Fails without changing the restriction if
+/// a code is not two letters, or in builds without the `exit-country`
+/// feature unless the list is empty.
+pub fn set_exit_countries(country_codes: Vec<String>) -> anyhow::Result<()> {
+    let countries = country_codes
+        .iter()
+        .map(|code| {
+            anyhow::ensure!(
+                code.len() == 2 && code.bytes().all(|b| b.is_ascii_alphabetic()),
+                "Invalid country code {:?}, expected two letters such as \"us\"",
+                code
+            );
+            Ok(code.to_ascii_uppercase())
+        })
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    if !countries.is_empty() {
+        ensure_feature("exit-country")?;
+    }
+    manager_log!("✅ set_exit_countries called: {:?}", countries);
+    *EXIT_COUNTRIES.write().unwrap() = countries;
+    Ok(())
+}
+
+/// Make streams opened with `prefs` exit in `country`, if given
+#[cfg(feature = "exit-country")]
+fn set_exit_country(prefs: &mut StreamPrefs, country: Option<&str>) -> anyhow::Result<()> {
+    if let Some(country) = country {
+        let code: CountryCode = country
+            .parse()
+            .map_err(|e| anyhow::anyhow!("Invalid country code {:?}: {}", country, e))?;
+        prefs.exit_country(code);
+    }
+    Ok(())
+}
+
+// Without the feature set_exit_countries() only accepts an empty list
+#[cfg(not(feature = "exit-country"))]
+fn set_exit_country(_prefs: &mut StreamPrefs, _country: Option<&str>) -> anyhow::Result<()> {
+    Ok(())
+}
+
+/// Build a circuit that can reach `target_hint` ("host:port") and register
+/// it for streams opened with open_stream_on_circuit
+///
+/// The circuit is built by opening a stream to `target_hint` that is closed
+/// again right away, so the first real stream does not wait for it.
+pub async fn build_circuit(target_hint: String) -> anyhow::Result<CircuitHandle> {
+    let Some(client) = STATE.lock().unwrap().as_ref().map(|state| state.client.clone()) else {
+        anyhow::bail!("Tor is not running");
+    };
+    let host = target_hint.rsplit_once(':').map_or(target_hint.as_str(), |(host, _)| host);
+    check_onion_host(host)?;
+    let isolation = IsolationToken::new();
+    let exit_country = EXIT_COUNTRIES.read().unwrap().choose(&mut rand::rng()).cloned();
+    let mut prefs = StreamPrefs::new();
+    prefs.set_isolation(isolation);
+    set_exit_country(&mut prefs, exit_country.as_deref())?;
+    client
+        .connect_with_prefs(target_hint.as_str(), &prefs)
+        .await
+        .map_err(|e| anyhow::anyhow!("Could not build a circuit to {}: {}", target_hint, e))?;
+
+    let id = NEXT_CIRCUIT_ID.fetch_add(1, Ordering::Relaxed);
+    PINNED_CIRCUITS.lock().unwrap().insert(
+        id,
+        PinnedCircuit {
+            isolation,
+            exit_country: exit_country.clone(),
+            relays: Vec::new(),
+        },
+    );
+    match exit_country {
+        Some(country) => manager_log!("Pinned circuit {} built, exiting in {}", id, country),
+        None => manager_log!("Pinned circuit {} built", id),
+    }
+    Ok(CircuitHandle { id })
+}
+
+/// Open a stream to `host`:`port` on a circuit from build_circuit
+///
+/// Returns a port on 127.0.0.1 that relays to the stream, with the token a
+/// connection to it must send first; see relay_stream.
+pub async fn open_stream_on_circuit(
+    handle: CircuitHandle,
+    host: String,
+    port: u16,
+) -> anyhow::Result<LocalStream> {
+    let (isolation, exit_country) = match PINNED_CIRCUITS.lock().unwrap().get(&handle.id) {
+        Some(circuit) => (circuit.isolation, circuit.exit_country.clone()),
+        None => anyhow::bail!("Unknown circuit handle {}; it may have been released or Tor stopped", handle.id),
+    };
+    check_onion_host(&host)?;
+    let Some(client) = STATE.lock().unwrap().as_ref().map(|state| state.client.clone()) else {
+        anyhow::bail!("Tor is not running");
+    };
+    let mut prefs = StreamPrefs::new();
+    prefs.set_isolation(isolation);
+    // Without the country arti could open the stream on a new circuit with
+    // the same isolation but any exit
+    set_exit_country(&mut prefs, exit_country.as_deref())?;
+    let stream = client
+        .connect_with_prefs((host.as_str(), port), &prefs)
+        .await
+        .map_err(|e| anyhow::anyhow!("Could not open a stream on circuit {}: {}", handle.id, e))?;
+    let (local, relay) = relay_stream(stream).await?;
+
+    let mut circuits = PINNED_CIRCUITS.lock().unwrap();
+    // Released or stopped while the stream was opening: dropping the relay
+    // closes the stream again
+    let circuit = circuits
+        .get_mut(&handle.id)
+        .ok_or_else(|| anyhow::anyhow!("Circuit handle {} was released while opening a stream", handle.id))?;
+    circuit.relays.retain(|relay| !relay.0.is_finished());
+    circuit.relays.push(relay);
+    Ok(local)
+}
+
+/// Open a stream to `host`:`port` that shares circuits only with other
+/// streams opened with the same `isolation_key`
+///
+/// The key is mapped to an isolation token on first use, so it can be any
+/// string, e.g. an account id. Returns a local port and token as
+/// open_stream_on_circuit does.
+pub async fn connect_isolated(
+    host: String,
+    port: u16,
+    isolation_key: String,
+) -> anyhow::Result<LocalStream> {
+    check_onion_host(&host)?;
+    let Some(client) = STATE.lock().unwrap().as_ref().map(|state| state.client.clone()) else {
+        anyhow::bail!("Tor is not running");
+    };
+    let isolation = ISOLATION_KEYS
+        .lock()
+        .unwrap()
+        .entry(isolation_key.clone())
+        .or_insert_with(|| PinnedCircuit {
+            isolation: IsolationToken::new(),
+            exit_country: None,
+            relays: Vec::new(),
+        })
+        .isolation;
+    let mut prefs = StreamPrefs::new();
+    prefs.set_isolation(isolation);
+    let stream = client
+        .connect_with_prefs((host.as_str(), port), &prefs)
+        .await
+        .map_err(|e| anyhow::anyhow!("Could not connect to {}:{}: {}", host, port, e))?;
+    let (local, relay) = relay_stream(stream).await?;
+
+    let mut keys = ISOLATION_KEYS.lock().unwrap();
+    // Stopped while the stream was opening: dropping the relay closes it
+    let streams = keys
+        .get_mut(&isolation_key)
+        .ok_or_else(|| anyhow::anyhow!("Tor was stopped while connecting to {}:{}", host, port))?;
+    streams.relays.retain(|relay| !relay.0.is_finished());
+    streams.relays.push(relay);
+    Ok(local)
+}
+
+/// Fail with `TorError::InvalidOnionAddress` if `host` is a malformed .onion
+/// address, rather than let arti spend a circuit on finding that out
+fn check_onion_host(host: &str) -> Result<(), TorError> {
+    if host.trim().to_ascii_lowercase().ends_with(".onion") {
+        util::validate_onion_address(host)?;
+    }
+    Ok(())
+}
+
+/// Relay `stream` to a port on 127.0.0.1, returned with the relay task
+///
+/// Any local process can connect to the port, so the first connection is
+/// not simply trusted: it has to send the returned token within
+/// STREAM_TOKEN_TIMEOUT, and connections that do not are closed. The port
+/// relays the first connection that does, and is closed after that or
+/// after PINNED_STREAM_ACCEPT_TIMEOUT.
+async fn relay_stream(mut stream: DataStream) -> anyhow::Result<(LocalStream, ClientTask)> {
+    let listener = tokio::net::TcpListener::bind((std::net::Ipv4Addr::LOCALHOST, 0)).await?;
+    let local_port = listener.local_addr()?.port();
+    let token = stream_token();
+    let expected = token.clone().into_bytes();
+    let relay = ClientTask(tokio::spawn(async move {
+        let deadline = tokio::time::Instant::now() + PINNED_STREAM_ACCEPT_TIMEOUT;
+        let mut local = loop {
+            let Ok(Ok((mut local, _))) = tokio::time::timeout_at(deadline, listener.accept()).await else {
+                manager_log!("Nobody connected to stream port {}, closing it", local_port);
+                return;
+            };
+            let mut sent = vec![0; expected.len()];
+            match timeout(STREAM_TOKEN_TIMEOUT, local.read_exact(&mut sent)).await {
+                Ok(Ok(_)) if tokens_match(&sent, &expected) => break local,
+                _ => manager_log!("Connection to stream port {} sent no valid token, closing it", local_port),
+            }
+        };
+        drop(listener);
+        if let Err(e) = tokio::io::copy_bidirectional(&mut local, &mut stream).await {
+            manager_log!("Stream on port {} ended: {}", local_port, e);
+        }
+    }));
+    Ok((LocalStream { port: local_port, token }, relay))
+}
+
+/// Random token for a port from relay_stream, as 32 hex digits
+fn stream_token() -> String {
+    rand::random::<[u8; 16]>().iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Compare tokens in constant time, so that timing does not reveal how much
+/// of a guess was right
+fn tokens_match(sent: &[u8], expected: &[u8]) -> bool {
+    sent.len() == expected.len()
+        && sent.iter().zip(expected).fold(0, |diff, (a, b)| diff | (a ^ b)) == 0
+}
+
+/// Open another SOCKS listener, on a free port, for an isolated view of the
+/// running client
+///
+/// Streams through it never share circuits with those through the main
+/// listener or other isolated listeners, while the client, its guards and
+/// its directory stay shared, so no new bootstrap is needed. Returns the
+/// port; it stays open until close_isolated_listener or stop.
+#[cfg(feature = "socks")]
+pub fn open_isolated_listener() -> anyhow::Result<u16> {
+    let Some(client) = STATE.lock().unwrap().as_ref().map(|state| state.client.clone()) else {
+        anyhow::bail!("Tor is not running");
+    };
+    let isolated = client.isolated_client();
+    let port = resolve_socks_port(None, 0)?;
+    let handle = spawn_socks_listener(&isolated, None, port);
+    ISOLATED_LISTENERS.lock().unwrap().insert(port, SocksListener { bind: None, port, handle });
+    manager_log!("Isolated SOCKS listener opened on port {}", port);
+    Ok(port)
+}
+
+/// Open an isolated SOCKS listener (unavailable: built without the `socks`
+/// feature)
+#[cfg(not(feature = "socks"))]
+pub fn open_isolated_listener() -> anyhow::Result<u16> {
+    Err(TorError::FeatureDisabled("socks").into())
+}
+
+/// Close a listener from open_isolated_listener
+///
+/// Connections it already accepted stay open. Returns false if there is no
+/// isolated listener on `port`.
+pub fn close_isolated_listener(port: u16) -> bool {
+    let Some(listener) = ISOLATED_LISTENERS.lock().unwrap().remove(&port) else {
+        return false;
+    };
+    listener.handle.abort();
+    manager_log!("Isolated SOCKS listener on port {} closed", port);
+    true
+}
+
+/// Close all listeners from open_isolated_listener
+fn close_isolated_listeners() {
+    for (_, listener) in ISOLATED_LISTENERS.lock().unwrap().drain() {
+        listener.handle.abort();
+    }
+}
+
+/// Make new connections use new circuits, like Tor Browser's "New Identity"
+///
+/// Arti has no public way to retire circuits, so the running client is
+/// replaced by an isolated view of it: guards and directory stay, but no
+/// later stream shares a circuit with an earlier one. The SOCKS listener is
+/// reopened on the same port to serve the new view. Streams already open
+/// keep their circuits, as do circuits from build_circuit and isolated
+/// listeners.
+pub async fn new_identity() -> anyhow::Result<()> {
+    let socks = {
+        let mut state = STATE.lock().unwrap();
+        let Some(tor_state) = state.as_mut() else {
+            anyhow::bail!("Tor is not running");
+        };
+        tor_state.client = tor_state.client.isolated_client();
+        tor_state.socks.take().map(|socks| (socks, tor_state.client.clone()))
+    };
+    manager_log!("New identity: later streams use new circuits");
+    if let Some((old, client)) = socks {
+        reopen_socks_listener(old, client).await?;
+    }
+    Ok(())
+}
+
+/// Serve `client` on the port of `old`, which must already be out of STATE
+#[cfg(feature = "socks")]
+async fn reopen_socks_listener(
+    old: SocksListener,
+    client: TorClient<TorRuntime>,
+) -> anyhow::Result<()> {
+    // Wait for the old listener to close its socket before binding the port
+    // again; connections it accepted run in their own tasks
+    old.handle.abort();
+    let _ = old.handle.await;
+
+    let mut handle = spawn_socks_listener(&client, old.bind, old.port);
+    if let Ok(result) = timeout(SOCKS_LISTEN_CHECK, &mut handle).await {
+        STATUS.send_modify(|status| status.socks_port = None);
+        let error = match result {
+            Ok(Err(e)) => e,
+            Ok(Ok(())) => anyhow::anyhow!("SOCKS listener exited"),
+            Err(e) => e.into(),
+        };
+        return Err(error.context(format!("Cannot listen on SOCKS port {} again", old.port)));
+    }
+
+    let mut state = STATE.lock().unwrap();
+    match state.as_mut() {
+        Some(tor_state) if tor_state.socks.is_none() => {
+            tor_state.socks = Some(SocksListener { bind: old.bind, port: old.port, handle });
+        }
+        // Stopped or rebound meanwhile
+        _ => handle.abort(),
+    }
+    Ok(())
+}
+
+/// Serve `client` on the port of `old` (unreachable: without the `socks`
+/// feature there is no listener)
+#[cfg(not(feature = "socks"))]
+async fn reopen_socks_listener(
+    old: SocksListener,
+    _client: TorClient<TorRuntime>,
+) -> anyhow::Result<()> {
+    old.handle.abort();
+    Ok(())
+}
+
+/// Forget a circuit from build_circuit, closing the streams opened on it
+pub fn release_circuit(handle: CircuitHandle) {
+    if PINNED_CIRCUITS.lock().unwrap().remove(&handle.id).is_some() {
+        manager_log!("Pinned circuit {} released", handle.id);
+    }
 }
 
 /// Update current proxy configuration
 /// 
 /// This can be called at any time (before or during Tor operation).
-/// Changes take effect for new connections.
-pub fn set_proxy(proxy: Option<ProxyInfo>) {
-    let mut current = CURRENT_PROXY.lock().unwrap();
+/// Changes take effect for new connections. Fails without changing the
+/// proxy if its address can never be connected to.
+pub fn set_proxy(proxy: Option<ProxyInfo>) -> Result<(), TorError> {
     match &proxy {
         Some(p) => {
-            eprintln!("[RUST] ✅ set_proxy called: Setting proxy to {}:{} ({:?})", p.address, p.port, p.proxy_type);
+            manager_log!("✅ set_proxy called: Setting proxy to {}:{} ({:?})", p.address, p.port, p.proxy_type);
         }
         None => {
-            eprintln!("[RUST] ✅ set_proxy called: Clearing proxy (direct connections)");
+            manager_log!("✅ set_proxy called: Clearing proxy (direct connections)");
         }
     }
-    *current = proxy;
+    set_proxies(
+        proxy
+            .into_iter()
+            .map(|proxy| WeightedProxy { proxy, weight: 1 })
+            .collect(),
+    )
+}
+
+/// Update current proxy configuration with several weighted proxies
+///
+/// An empty list clears the proxy. Fails without changing the proxies if
+/// any of them has an address that can never be connected to.
+pub fn set_proxies(proxies: Vec<WeightedProxy>) -> Result<(), TorError> {
+    for p in &proxies {
+        util::validate_proxy(&p.proxy)?;
+    }
+
+    let mut current = PROXY_STATE.proxies.lock().unwrap();
+    *current = proxies;
+    *PROXY_STATE.route.lock().unwrap() = None;
     
     // Verify it was set
-    eprintln!("[RUST] ✅ CURRENT_PROXY updated, now contains: {:?}", 
-              current.as_ref().map(|p| format!("{}:{}", p.address, p.port)));
+    manager_log!("✅ Proxies updated, now: {:?}", 
+                 current.iter().map(|p| format!("{}:{} (weight {})", p.proxy.address, p.proxy.port, p.weight)).collect::<Vec<_>>());
+    Ok(())
+}
+
+/// Route connections through several proxies in series
+///
+/// The first hop is dialled, each hop connects to the next and the last one
+/// to the target, so only the last hop learns the target and only the first
+/// one learns this device's address. Hops must be SOCKS5, SOCKS4 or HTTP
+/// CONNECT proxies. Replaces the proxies from set_proxy and set_proxies
+/// until they are called again. Fails without changing the route if the
+/// chain is empty or any hop is invalid.
+pub fn set_proxy_chain(hops: Vec<ProxyInfo>) -> Result<(), TorError> {
+    if hops.is_empty() {
+        return Err(TorError::InvalidProxy {
+            reason: "a proxy chain needs at least one hop".into(),
+        });
+    }
+    let mut chain = Vec::with_capacity(hops.len());
+    for hop in &hops {
+        if hop.proxy_type == ProxyType::HttpsConnect {
+            return Err(TorError::InvalidProxy {
+                reason: format!("{}:{} is an HTTPS proxy, which cannot be a chain hop",
+                                hop.address, hop.port),
+            });
+        }
+        chain.push(checked_proxy_config(hop)?);
+    }
+
+    set_route(ProxyConfig::Chain(chain));
+    manager_log!("✅ Proxy chain updated, now: {:?}",
+                 hops.iter().map(|p| format!("{}:{} ({:?})", p.address, p.port, p.proxy_type)).collect::<Vec<_>>());
+    Ok(())
+}
+
+/// Connect through the first of several proxies that works
+///
+/// Proxies are tried highest priority first, at most `max_attempts` of them
+/// per connection (all if None), moving on when one cannot be connected to
+/// or fails its handshake. With `sticky`, the proxy that worked last is
+/// tried first from then on. Replaces the proxies from set_proxy,
+/// set_proxies and set_proxy_chain until one of them is called again. Fails
+/// without changing the route if the list is empty or any proxy is invalid.
+pub fn set_proxy_failover(
+    proxies: Vec<ProxyInfo>,
+    max_attempts: Option<u32>,
+    sticky: bool,
+) -> Result<(), TorError> {
+    let configs = proxies.iter().map(checked_proxy_config).collect::<Result<Vec<_>, _>>()?;
+    let mut failover = ProxyFailover::new(configs)?.sticky(sticky);
+    if let Some(max_attempts) = max_attempts {
+        failover = failover.with_max_attempts(max_attempts as usize);
+    }
+
+    set_route(ProxyConfig::Failover(Arc::new(failover)));
+    manager_log!("✅ Proxy failover updated, now: {:?} (max attempts {:?}, sticky {})",
+                 proxies.iter().map(|p| format!("{}:{} ({:?})", p.address, p.port, p.proxy_type)).collect::<Vec<_>>(),
+                 max_attempts, sticky);
+    Ok(())
+}
+
+/// Validate proxy info from Dart and convert it into a proxy config
+fn checked_proxy_config(proxy: &ProxyInfo) -> Result<ProxyConfig, TorError> {
+    util::validate_proxy(proxy)?;
+    proxy_config_for(proxy).ok_or_else(|| TorError::InvalidProxy {
+        reason: format!("{}:{} is not a valid proxy address", proxy.address, proxy.port),
+    })
+}
+
+/// Use `route` for new connections instead of the weighted proxies
+fn set_route(route: ProxyConfig) {
+    let mut proxies = PROXY_STATE.proxies.lock().unwrap();
+    proxies.clear();
+    *PROXY_STATE.route.lock().unwrap() = Some(route);
+}
+
+/// Switch between using the configured proxies and connecting directly
+///
+/// Overrides the use_system_proxy value given to start. Changes take effect
+/// for new connections; established ones keep their route.
+pub fn set_use_proxy(use_proxy: bool) {
+    manager_log!("✅ set_use_proxy called: {}", use_proxy);
+    *PROXY_STATE.use_proxy.lock().unwrap() = use_proxy;
+}
+
+/// Set the destination allow/deny lists (CIDR notation)
+///
+/// Fails without changing the policy if any entry does not parse.
+pub fn set_target_policy(allow: Vec<String>, deny: Vec<String>) -> anyhow::Result<()> {
+    let parse = |list: Vec<String>| -> anyhow::Result<Vec<IpCidr>> {
+        list.iter()
+            .map(|s| IpCidr::parse(s).ok_or_else(|| anyhow::anyhow!("Invalid CIDR: {}", s)))
+            .collect()
+    };
+    let policy = TargetPolicy {
+        allow: parse(allow)?,
+        deny: parse(deny)?,
+    };
+    manager_log!("✅ set_target_policy called: {:?}", policy);
+    *TARGET_POLICY.write().unwrap() = policy;
+    Ok(())
+}
+
+/// Choose how connection targets appear in logs
+///
+/// Applies to connections opened from now on, including their connection
+/// log lines.
+pub fn set_target_redaction(redaction: TargetRedaction) {
+    manager_log!("✅ set_target_redaction called: {:?}", redaction);
+    *TARGET_REDACTION.write().unwrap() = redaction;
+}
+
+/// Isolate SOCKS connections with username `contact_id` by `token`
+///
+/// Connections accepted from now on with `contact_id` as SOCKS5 username
+/// share circuits with those of other contacts mapped to the same token,
+/// and no others (see socks_isolation). Replaces an earlier mapping of the
+/// contact; streams already open keep their circuits.
+pub fn add_isolation_mapping(contact_id: String, token: String) -> anyhow::Result<()> {
+    // SOCKS5 usernames are at most 255 bytes, and so is what arti gets
+    anyhow::ensure!(
+        !contact_id.is_empty() && contact_id.len() <= 255,
+        "Contact id must be 1 to 255 bytes long, got {}",
+        contact_id.len()
+    );
+    let max_token = 255 - TOKEN_PREFIX.len();
+    anyhow::ensure!(
+        !token.is_empty() && token.len() <= max_token,
+        "Isolation token must be 1 to {} bytes long, got {}",
+        max_token,
+        token.len()
+    );
+    manager_log!("Isolation mapping added for a contact");
+    SOCKS_ISOLATION_MAP.write().unwrap().insert(contact_id, token);
+    Ok(())
+}
+
+/// Remove the isolation mapping of `contact_id`; returns whether there was one
+///
+/// The contact's new connections are isolated by its raw username again.
+pub fn remove_isolation_mapping(contact_id: &str) -> bool {
+    SOCKS_ISOLATION_MAP.write().unwrap().remove(contact_id).is_some()
+}
+
+/// Current isolation mappings, sorted by contact id
+pub fn isolation_mappings() -> Vec<IsolationMapping> {
+    let mut mappings: Vec<_> = SOCKS_ISOLATION_MAP
+        .read()
+        .unwrap()
+        .iter()
+        .map(|(contact_id, token)| IsolationMapping {
+            contact_id: contact_id.clone(),
+            token: token.clone(),
+        })
+        .collect();
+    mappings.sort_by(|a, b| a.contact_id.cmp(&b.contact_id));
+    mappings
+}
+
+/// Choose whether a client that fails its health checks is restarted
+///
+/// Either way the failure is reported in the status (see spawn_health_task).
+pub fn set_auto_restart(enabled: bool) {
+    manager_log!("✅ set_auto_restart called: {}", enabled);
+    *AUTO_RESTART.lock().unwrap() = enabled;
+}
+
+/// Switch metered mode on or off
+///
+/// While metered, the client is kept in arti's soft dormant mode, which
+/// suspends its periodic background tasks such as building preemptive
+/// circuits and circuits that only measure build times, and health checks
+/// are skipped. The client stays usable: circuits are still built on demand,
+/// and the directory documents they need are still kept up to date.
+/// Applies to the running client and to clients started later.
+pub fn set_metered(metered: bool) {
+    manager_log!("✅ set_metered called: {}", metered);
+    *METERED.lock().unwrap() = metered;
+    apply_dormant_mode();
+}
+
+/// Put the client into soft dormant mode, or wake it up again
+///
+/// For apps going to the background: like metered mode this suspends the
+/// client's background tasks and health checks, without closing circuits or
+/// the SOCKS listener, so on resume the client carries on where it left off
+/// instead of bootstrapping again. Waking up keeps the client in soft
+/// dormant mode while metered. Applies to the running client and to clients
+/// started later. Arti has no harder dormant mode; soft_mode false is
+/// DormantMode::Normal.
+pub fn set_dormant(soft_mode: bool) {
+    manager_log!("✅ set_dormant called: {}", soft_mode);
+    *DORMANT.lock().unwrap() = soft_mode;
+    apply_dormant_mode();
+}
+
+/// Mode the client should be in, given the metered and dormant settings
+fn dormant_mode() -> DormantMode {
+    if *METERED.lock().unwrap() || *DORMANT.lock().unwrap() {
+        DormantMode::Soft
+    } else {
+        DormantMode::Normal
+    }
+}
+
+/// Put the running client, if any, into dormant_mode() and tell observers
+fn apply_dormant_mode() {
+    let mode = dormant_mode();
+    let running = match STATE.lock().unwrap().as_ref() {
+        Some(tor_state) => {
+            tor_state.client.set_dormant(mode);
+            true
+        }
+        None => false,
+    };
+    let dormant = running && matches!(mode, DormantMode::Soft);
+    STATUS.send_if_modified(|status| std::mem::replace(&mut status.dormant, dormant) != dormant);
+}
+
+/// Tell observers that no client is running
+fn clear_status() {
+    STATUS.send_replace(TorStatus::default());
+    BOOTSTRAP_PROGRESS.send_replace(BootstrapStatus::default());
 }
 
 /// Stop Tor service
+///
+/// Also cancels any start still bootstrapping; that call then fails.
 pub fn stop() {
-    if let Some((_port, handle)) = STATE.lock().unwrap().take() {
-        eprintln!("[RUST] Stopping Tor proxy");
-        handle.abort();
+    for (_, pending) in PENDING_BOOTSTRAPS.lock().unwrap().drain() {
+        manager_log!("Cancelling pending bootstrap");
+        pending.abort();
+    }
+    PINNED_CIRCUITS.lock().unwrap().clear();
+    ISOLATION_KEYS.lock().unwrap().clear();
+    close_isolated_listeners();
+    if let Some(state) = STATE.lock().unwrap().take() {
+        manager_log!("Stopping Tor proxy");
+        if let Some(socks) = state.socks {
+            socks.handle.abort();
+        }
     }
+    clear_status();
 }
 
-/// Set dormant mode (placeholder)
-pub fn set_dormant(_soft_mode: bool) {
-    eprintln!("[RUST] set_dormant not implemented (client not stored)");
+/// Stop Tor service, letting open SOCKS connections finish first
+///
+/// Stops in this order: the SOCKS listener stops accepting, connections it
+/// already accepted get up to `drain_timeout` to finish, and only then is
+/// the client dropped. Dropping the client first would cut those
+/// connections off mid-transfer. Returns whether they all finished in time.
+/// Like `stop`, this cancels any start still bootstrapping.
+pub async fn stop_gracefully(drain_timeout: Duration) -> bool {
+    for (_, pending) in PENDING_BOOTSTRAPS.lock().unwrap().drain() {
+        manager_log!("Cancelling pending bootstrap");
+        pending.abort();
+    }
+    let Some(mut state) = STATE.lock().unwrap().take() else {
+        clear_status();
+        return true;
+    };
+    manager_log!("Stopping Tor proxy gracefully");
+    if let Some(socks) = state.socks.take() {
+        // Accepted connections run in their own tasks, so this only stops
+        // new ones from being accepted
+        socks.handle.abort();
+        STATUS.send_modify(|status| status.socks_port = None);
+    }
+    close_isolated_listeners();
+
+    let drained = timeout(drain_timeout, SOCKS_STREAMS.drained()).await.is_ok();
+    if !drained {
+        manager_log!("SOCKS connections still open after {:?}, stopping anyway", drain_timeout);
+    }
+    PINNED_CIRCUITS.lock().unwrap().clear();
+    ISOLATION_KEYS.lock().unwrap().clear();
+    drop(state);
+    clear_status();
+    drained
+}
+
+/// Fail with `TorError::StillRunning` unless Tor is fully stopped
+fn ensure_stopped(action: &'static str) -> Result<(), TorError> {
+    if STATE.lock().unwrap().is_some() || !PENDING_BOOTSTRAPS.lock().unwrap().is_empty() {
+        return Err(TorError::StillRunning { action });
+    }
+    Ok(())
+}
+
+/// Remove a file or directory, treating one that does not exist as removed
+fn remove_path(path: &Path) -> std::io::Result<()> {
+    let result = if path.is_dir() {
+        std::fs::remove_dir_all(path)
+    } else {
+        std::fs::remove_file(path)
+    };
+    match result {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e),
+        _ => Ok(()),
+    }
+}
+
+/// Forget the selected guards, so that new ones are picked on the next start
+///
+/// Only arti's guard state (`state/guards.json` in state_dir) is removed.
+pub fn reset_guards(state_dir: String) -> anyhow::Result<()> {
+    ensure_stopped("resetting guards")?;
+    let guards = Path::new(&state_dir).join("state").join("guards.json");
+    manager_log!("Removing guard state {}", guards.display());
+    remove_path(&guards)?;
+    Ok(())
+}
+
+/// Drop the cached consensus, so that it is downloaded afresh on the next start
+///
+/// arti keeps the consensus in one store with the microdescriptors and
+/// authority certificates fetched for it, so those are dropped as well.
+/// Guards and other state in state_dir are kept.
+pub fn reset_consensus(cache_dir: String) -> anyhow::Result<()> {
+    ensure_stopped("resetting the consensus")?;
+    remove_dir_cache(Path::new(&cache_dir))?;
+    Ok(())
+}
+
+/// Remove arti's directory store (DIR_CACHE_FILES) from `cache_dir`
+fn remove_dir_cache(cache_dir: &Path) -> std::io::Result<()> {
+    for name in DIR_CACHE_FILES {
+        manager_log!("Removing directory cache {}", cache_dir.join(name).display());
+        remove_path(&cache_dir.join(name))?;
+    }
+    Ok(())
+}
+
+/// Empty cache_dir, for a clean slate on the next start
+///
+/// Removes everything in it, not only the directory store that
+/// reset_consensus drops; the directory itself is kept. Nothing in cache_dir
+/// is needed to start, unlike the guards and keys in state_dir.
+pub fn clear_cache(cache_dir: String) -> anyhow::Result<()> {
+    ensure_stopped("clearing the cache")?;
+    let entries = match std::fs::read_dir(&cache_dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+        Err(e) => return Err(e.into()),
+    };
+    manager_log!("Clearing cache directory {}", cache_dir);
+    for entry in entries {
+        remove_path(&entry?.path())?;
+    }
+    Ok(())
+}
+
+/// Copy a directory cache shipped with the app into cache_dir, so that the
+/// first start can skip downloading the consensus
+///
+/// `bundle_dir` holds a copy of another client's cache (`dir.sqlite3` and
+/// `dir_blobs`). It is only used if its consensus is still valid and
+/// cache_dir has no valid one of its own; returns whether it was copied. A
+/// stale or missing bundle is not an error: bootstrap then downloads the
+/// directory as usual. Signatures are not checked here; arti checks them
+/// against the authorities when it loads the cache, and downloads afresh if
+/// they do not hold.
+pub fn seed_directory_cache(bundle_dir: String, cache_dir: String) -> anyhow::Result<bool> {
+    ensure_stopped("seeding the directory cache")?;
+    let (bundle_dir, cache_dir) = (Path::new(&bundle_dir), Path::new(&cache_dir));
+    let now = OffsetDateTime::now_utc();
+
+    if cached_consensus_valid_until(cache_dir).is_some_and(|valid_until| valid_until > now) {
+        manager_log!("Directory cache already holds a valid consensus, not seeding");
+        return Ok(false);
+    }
+    match cached_consensus_valid_until(bundle_dir) {
+        Some(valid_until) if valid_until > now => {}
+        Some(valid_until) => {
+            manager_log!("Bundled consensus expired at {}, not seeding", valid_until);
+            return Ok(false);
+        }
+        None => {
+            manager_log!("No consensus found in bundle {}, not seeding", bundle_dir.display());
+            return Ok(false);
+        }
+    }
+
+    std::fs::create_dir_all(cache_dir)?;
+    for name in DIR_CACHE_FILES {
+        remove_path(&cache_dir.join(name))?;
+        let source = bundle_dir.join(name);
+        if source.exists() {
+            manager_log!("Seeding directory cache {}", cache_dir.join(name).display());
+            copy_path(&source, &cache_dir.join(name))?;
+        }
+    }
+    Ok(true)
+}
+
+/// Copy a file, or a directory with everything in it
+fn copy_path(from: &Path, to: &Path) -> std::io::Result<()> {
+    if !from.is_dir() {
+        return std::fs::copy(from, to).map(|_| ());
+    }
+    std::fs::create_dir_all(to)?;
+    for entry in std::fs::read_dir(from)? {
+        let entry = entry?;
+        copy_path(&entry.path(), &to.join(entry.file_name()))?;
+    }
+    Ok(())
+}
+
+/// Latest valid-until time of the consensuses in a directory cache
+///
+/// arti keeps each consensus as a text file under `dir_blobs`.
+fn cached_consensus_valid_until(cache_dir: &Path) -> Option<OffsetDateTime> {
+    std::fs::read_dir(cache_dir.join("dir_blobs"))
+        .ok()?
+        .filter_map(|entry| {
+            let text = std::fs::read_to_string(entry.ok()?.path()).ok()?;
+            if !text.starts_with("network-status-version 3") {
+                return None;
+            }
+            consensus_valid_until(&text)
+        })
+        .max()
+}
+
+/// The `valid-until` time of a consensus document ("YYYY-MM-DD HH:MM:SS", UTC)
+fn consensus_valid_until(consensus: &str) -> Option<OffsetDateTime> {
+    let value = consensus
+        .lines()
+        .find_map(|line| line.strip_prefix("valid-until "))?;
+    let (date, time) = value.trim().split_once(' ')?;
+    let date: Vec<u16> = date.split('-').map(|part| part.parse().ok()).collect::<Option<_>>()?;
+    let time: Vec<u8> = time.split(':').map(|part| part.parse().ok()).collect::<Option<_>>()?;
+    let (&[year, month, day], &[hour, minute, second]) = (date.as_slice(), time.as_slice()) else {
+        return None;
+    };
+    let month = Month::try_from(u8::try_from(month).ok()?).ok()?;
+    let date = Date::from_calendar_date(year.into(), month, u8::try_from(day).ok()?).ok()?;
+    let time = Time::from_hms(hour, minute, second).ok()?;
+    Some(PrimitiveDateTime::new(date, time).assume_utc())
+}
+
+/// Record a connectivity change reported by Dart
+///
+/// While offline, start() creates the client without bootstrapping it. When
+/// connectivity returns, a client that has not finished bootstrapping starts
+/// bootstrapping in the background; stop() cancels it.
+pub fn notify_network_changed(online: bool) {
+    manager_log!("notify_network_changed: online={}", online);
+    *NETWORK_ONLINE.lock().unwrap() = online;
+    if !online {
+        return;
+    }
+
+    let (client, config, default_dirs) = match STATE.lock().unwrap().as_ref() {
+        Some(state) if !state.client.bootstrap_status().ready_for_traffic() => {
+            (state.client.clone(), Arc::clone(&state.config), state.default_dirs.clone())
+        }
+        _ => return,
+    };
+    manager_log!("Network is back, starting deferred bootstrap");
+    tokio::spawn(async move {
+        let (_pending, registration) = PendingBootstrap::register();
+        let bootstrap = bootstrap_with_fallback(&client, &config, default_dirs.as_ref());
+        match Abortable::new(bootstrap, registration).await {
+            Ok(Ok(())) => manager_log!("Deferred bootstrap completed"),
+            Ok(Err(e)) => manager_log!("Deferred bootstrap failed: {}", e),
+            Err(_) => manager_log!("Deferred bootstrap cancelled by stop()"),
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fallback_dirs_for() {
+        let mirrors = [
+            DirectoryMirror {
+                rsa_identity: "0123456789ABCDEF0123456789ABCDEF01234567".to_string(),
+                ed_identity: "AQIDBAUGBwgJCgsMDQ4PEBESExQVFhcYGRobHB0eHyA".to_string(),
+                addresses: vec!["192.0.2.1:9001".to_string(), "[2001:db8::1]:9001".to_string()],
+            },
+            DirectoryMirror {
+                rsa_identity: "0123456789abcdef0123456789abcdef01234567".to_string(),
+                // Padded base64 is accepted too
+                ed_identity: "AQIDBAUGBwgJCgsMDQ4PEBESExQVFhcYGRobHB0eHyA=".to_string(),
+                addresses: vec!["192.0.2.2:443".to_string()],
+            },
+        ];
+        let mut dirs = fallback_dirs_for(&mirrors).unwrap();
+        assert_eq!(dirs.len(), 2);
+        assert_eq!(dirs[0].orports().len(), 2);
+        assert_eq!(dirs[1].orports().len(), 1);
+        assert!(fallback_dirs_for(&[]).unwrap().is_empty());
+
+        let mut mirror = mirrors[0].clone();
+        mirror.rsa_identity = "0123456789ABCDEF".to_string();
+        let err = fallback_dirs_for(&[mirror]).unwrap_err();
+        assert!(err.to_string().contains("RSA identity"), "{}", err);
+
+        let mut mirror = mirrors[0].clone();
+        mirror.ed_identity = "not base64!".to_string();
+        let err = fallback_dirs_for(&[mirror]).unwrap_err();
+        assert!(err.to_string().contains("Ed25519 identity"), "{}", err);
+        let mut mirror = mirrors[0].clone();
+        mirror.ed_identity = "AQIDBA".to_string();
+        let err = fallback_dirs_for(&[mirror]).unwrap_err();
+        assert!(err.to_string().contains("Ed25519 identity"), "{}", err);
+
+        let mut mirror = mirrors[0].clone();
+        mirror.addresses = vec!["192.0.2.1".to_string()];
+        let err = fallback_dirs_for(&[mirror]).unwrap_err();
+        assert!(err.to_string().contains("address: 192.0.2.1"), "{}", err);
+        let mut mirror = mirrors[0].clone();
+        mirror.addresses = vec!["relay.example:9001".to_string()];
+        assert!(fallback_dirs_for(&[mirror]).is_err());
+        let mut mirror = mirrors[0].clone();
+        mirror.addresses = Vec::new();
+        assert!(fallback_dirs_for(&[mirror]).is_err());
+    }
+
+    #[test]
+    fn test_consensus_valid_until() {
+        let consensus = "network-status-version 3 microdesc\n\
+                         vote-status consensus\n\
+                         valid-after 2025-03-01 12:00:00\n\
+                         fresh-until 2025-03-01 13:00:00\n\
+                         valid-until 2025-03-01 15:00:00\n\
+                         voting-delay 300 300\n";
+        let expected = PrimitiveDateTime::new(
+            Date::from_calendar_date(2025, Month::March, 1).unwrap(),
+            Time::from_hms(15, 0, 0).unwrap(),
+        )
+        .assume_utc();
+        assert_eq!(consensus_valid_until(consensus), Some(expected));
+
+        let without = consensus.replace("valid-until", "valid-till");
+        assert_eq!(consensus_valid_until(&without), None);
+        for malformed in [
+            "valid-until 2025-03-01",
+            "valid-until 2025-03-01T15:00:00",
+            "valid-until 2025-13-01 15:00:00",
+            "valid-until 2025-02-30 15:00:00",
+            "valid-until 2025-03-01 25:00:00",
+            "valid-until 2025-03 15:00:00",
+            "valid-until 2025-03-01 15:00:xx",
+        ] {
+            assert_eq!(consensus_valid_until(malformed), None, "{}", malformed);
+        }
+    }
+
+    #[test]
+    fn test_bootstrap_stall() {
+        assert_eq!(bootstrap_stall(0).last_phase, "connecting to the Tor network");
+        assert_eq!(bootstrap_stall(7).last_phase, "connecting to the Tor network");
+        assert_eq!(bootstrap_stall(8).last_phase, "connecting to guards");
+        assert_eq!(bootstrap_stall(14).last_phase, "connecting to guards");
+        assert_eq!(bootstrap_stall(15).last_phase, "downloading the consensus");
+        assert_eq!(bootstrap_stall(39).last_phase, "downloading the consensus");
+        assert_eq!(bootstrap_stall(40).last_phase, "downloading relay descriptors");
+        assert_eq!(bootstrap_stall(100).last_phase, "downloading relay descriptors");
+
+        assert!(bootstrap_stall(3).suggestion.contains("proxy"));
+        assert!(bootstrap_stall(20).suggestion.contains("clock"));
+    }
+
+    #[test]
+    fn test_redact_config() {
+        let mut config: toml::Value = toml::from_str(
+            r#"
+            [bridges]
+            enabled = true
+            bridges = ["obfs4 192.0.2.3:443 secret"]
+
+            [proxy]
+            socks_listen = 9150
+
+            [[nested]]
+            name = "first"
+            bridges = { line = "secret" }
+
+            [nested.inner]
+            bridges = "secret"
+            "#,
+        )
+        .unwrap();
+        redact_config(&mut config);
+        let redacted: toml::Value = toml::from_str(
+            r#"
+            bridges = "redacted"
+
+            [proxy]
+            socks_listen = 9150
+
+            [[nested]]
+            name = "first"
+            bridges = "redacted"
+
+            [nested.inner]
+            bridges = "redacted"
+            "#,
+        )
+        .unwrap();
+        assert_eq!(config, redacted);
+    }
+
+    #[cfg(feature = "socks")]
+    #[test]
+    fn test_parse_bind_addr() {
+        assert_eq!(parse_bind_addr("0.0.0.0").unwrap(), IpAddr::from([0, 0, 0, 0]));
+        assert_eq!(parse_bind_addr(" 192.168.1.5 ").unwrap(), IpAddr::from([192, 168, 1, 5]));
+        assert_eq!(parse_bind_addr("::1").unwrap(), IpAddr::from(std::net::Ipv6Addr::LOCALHOST));
+        for invalid in ["", "localhost", "192.168.1.256", "192.168.1.5:9050", "[::1]"] {
+            let err = parse_bind_addr(invalid).unwrap_err().to_string();
+            assert!(err.contains("Invalid SOCKS bind address"), "{}", err);
+        }
+    }
+
+    #[cfg(feature = "bridges")]
+    #[test]
+    fn test_snowflake_bridge_line() {
+        let line = "snowflake 192.0.2.3:80 2B280B23E1107BB62ABFC40DDCC8824814F80A72 \
+                    url=https://broker.example/ front=cdn.example ice=stun:a.example:3478";
+        let mut config = SnowflakeConfig {
+            bridge_line: line.to_string(),
+            binary_path: "snowflake-client".to_string(),
+            broker_url: None,
+            front_domain: None,
+            stun_servers: Vec::new(),
+            arguments: Vec::new(),
+        };
+        // Nothing to replace keeps the line as it is
+        assert_eq!(snowflake_bridge_line(&config).unwrap(), line);
+
+        config.broker_url = Some("https://other.example/".to_string());
+        config.front_domain = Some("front.example".to_string());
+        config.stun_servers =
+            vec!["stun:b.example:3478".to_string(), "stun:c.example:19302".to_string()];
+        assert_eq!(
+            snowflake_bridge_line(&config).unwrap(),
+            "snowflake 192.0.2.3:80 2B280B23E1107BB62ABFC40DDCC8824814F80A72 \
+             url=https://other.example/ fronts=front.example \
+             ice=stun:b.example:3478,stun:c.example:19302"
+        );
+
+        config.stun_servers = vec!["stun:a,b".to_string()];
+        assert!(snowflake_bridge_line(&config).is_err());
+        config.stun_servers = Vec::new();
+        config.front_domain = Some("two words".to_string());
+        assert!(snowflake_bridge_line(&config).is_err());
+        config.front_domain = None;
+        config.bridge_line = "obfs4 192.0.2.3:443 cert=x".to_string();
+        assert!(snowflake_bridge_line(&config).is_err());
+    }
 }
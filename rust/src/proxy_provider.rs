@@ -1,28 +1,54 @@
 //! Proxy support for Tor network connections
 //!
 //! This module provides TCP connection support through various proxy protocols
-//! including SOCKS5, HTTP CONNECT, and dynamic callback-based proxies.
+//! including SOCKS5, SOCKS4/4a, HTTP CONNECT (also over TLS), chains of
+//! these, prioritized failover lists, and dynamic callback-based proxies.
+//! With the `socks-udp` feature, SOCKS5 proxies can also relay UDP (see
+//! `associate_socks5_udp`).
 
+use std::collections::hash_map::RandomState;
+use std::collections::BTreeMap;
 use std::future::Future;
-use std::io::{Error as IoError, ErrorKind, Result as IoResult};
-use std::net::{IpAddr, SocketAddr};
+use std::hash::BuildHasher;
+use std::io::{Error as IoError, ErrorKind, IoSlice, Result as IoResult};
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
 use std::pin::Pin;
-use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+use std::task::{ready, Context, Poll};
+use std::time::{Duration, Instant, SystemTime};
 
+use futures::future::BoxFuture;
 use futures::{AsyncRead, AsyncWrite, FutureExt};
-use tor_rtcompat::{NetStreamProvider, StreamOps};
+use once_cell::sync::Lazy;
+#[cfg(feature = "https-proxy")]
+use sha2::{Digest, Sha256};
+use tor_rtcompat::{NetStreamProvider, StreamOps, TlsProvider};
 
-// Enable logging for debugging proxy connections
-#[cfg(debug_assertions)]
+use crate::api::TargetRedaction;
+use crate::connection_log::{ActiveStreams, ConnectionLog, LoggedListener, LoggedStream};
+use crate::error::TorError;
+use crate::http_auth::{self, AuthChallenge};
+use crate::socks_isolation::IsolationMap;
+
+// Debug log of proxy connections, through `tracing` at debug level with
+// target "tor::proxy", like manager_log!
+#[cfg(not(feature = "stderr-log"))]
 macro_rules! proxy_log {
     ($($arg:tt)*) => {
-        eprintln!("[TOR_PROXY] {}", format!($($arg)*))
+        tracing::debug!(target: "tor::proxy", $($arg)*)
     };
 }
 
-#[cfg(not(debug_assertions))]
+// With the stderr-log feature, also printed to stderr if util::DEBUG_OUTPUT
+#[cfg(feature = "stderr-log")]
 macro_rules! proxy_log {
-    ($($arg:tt)*) => {()};
+    ($($arg:tt)*) => {{
+        if *crate::util::DEBUG_OUTPUT {
+            eprintln!("[TOR_PROXY] {}", format!($($arg)*));
+        }
+        tracing::debug!(target: "tor::proxy", $($arg)*)
+    }};
 }
 
 /// Proxy configuration types
@@ -34,35 +60,230 @@ pub enum ProxyConfig {
     Socks5 {
         proxy_addr: SocketAddr,
         auth: Option<ProxyAuth>,
+        /// How long each handshake step may wait for the proxy, or
+        /// DEFAULT_HANDSHAKE_TIMEOUT
+        handshake_timeout: Option<Duration>,
+    },
+    /// SOCKS4 proxy, using SOCKS4a for domain-name targets
+    Socks4 {
+        proxy_addr: SocketAddr,
+        user_id: Option<String>,
+        handshake_timeout: Option<Duration>,
     },
     /// HTTP CONNECT proxy
     HttpConnect {
         proxy_addr: SocketAddr,
         auth: Option<ProxyAuth>,
+        handshake_timeout: Option<Duration>,
+        /// Longest response to CONNECT accepted, status line and headers,
+        /// or DEFAULT_MAX_HTTP_RESPONSE_LEN
+        max_response_len: Option<usize>,
     },
+    /// HTTP CONNECT proxy reached over TLS, so that neither the credentials
+    /// nor the target cross the network in the clear
+    ///
+    /// Needs the `https-proxy` feature. The proxy's certificate is checked
+    /// against the system's CAs and `server_name`, unless `cert_sha256` is
+    /// set: then it only has to match that, for proxies with self-signed
+    /// certificates. With neither, connecting fails.
+    HttpsConnect {
+        proxy_addr: SocketAddr,
+        /// Host name the certificate must be issued for, also sent as SNI;
+        /// may be empty if `cert_sha256` is set
+        server_name: String,
+        /// SHA-256 of the certificate (DER) the proxy must present
+        cert_sha256: Option<[u8; 32]>,
+        auth: Option<ProxyAuth>,
+        handshake_timeout: Option<Duration>,
+        /// As for `HttpConnect`
+        max_response_len: Option<usize>,
+    },
+    /// Proxies in series: the first is dialled, each one connects to the
+    /// next and the last to the target. Hops must be SOCKS5, SOCKS4 or HTTP
+    /// CONNECT proxies; only the last one learns the target.
+    Chain(Vec<ProxyConfig>),
+    /// Proxies tried in order until one connects, see ProxyFailover
+    Failover(Arc<ProxyFailover>),
     /// Dynamic callback-based proxy
     Dynamic(Arc<dyn ProxyCallback>),
+    /// Dynamic callback-based proxy whose decision needs async work
+    DynamicAsync(Arc<dyn AsyncProxyCallback>),
 }
 
 impl std::fmt::Debug for ProxyConfig {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             Self::Direct => write!(f, "ProxyConfig::Direct"),
-            Self::Socks5 { proxy_addr, auth } => f
+            Self::Socks5 { proxy_addr, auth, handshake_timeout } => f
                 .debug_struct("ProxyConfig::Socks5")
                 .field("proxy_addr", proxy_addr)
                 .field("auth", auth)
+                .field("handshake_timeout", handshake_timeout)
+                .finish(),
+            Self::Socks4 { proxy_addr, user_id, handshake_timeout } => f
+                .debug_struct("ProxyConfig::Socks4")
+                .field("proxy_addr", proxy_addr)
+                .field("user_id", user_id)
+                .field("handshake_timeout", handshake_timeout)
                 .finish(),
-            Self::HttpConnect { proxy_addr, auth } => f
+            Self::HttpConnect { proxy_addr, auth, handshake_timeout, max_response_len } => f
                 .debug_struct("ProxyConfig::HttpConnect")
                 .field("proxy_addr", proxy_addr)
                 .field("auth", auth)
+                .field("handshake_timeout", handshake_timeout)
+                .field("max_response_len", max_response_len)
+                .finish(),
+            Self::HttpsConnect { proxy_addr, server_name, cert_sha256, auth, handshake_timeout, max_response_len } => f
+                .debug_struct("ProxyConfig::HttpsConnect")
+                .field("proxy_addr", proxy_addr)
+                .field("server_name", server_name)
+                .field("cert_pinned", &cert_sha256.is_some())
+                .field("auth", auth)
+                .field("handshake_timeout", handshake_timeout)
+                .field("max_response_len", max_response_len)
                 .finish(),
+            Self::Chain(hops) => f.debug_tuple("ProxyConfig::Chain").field(hops).finish(),
+            Self::Failover(failover) => f.debug_tuple("ProxyConfig::Failover").field(failover).finish(),
             Self::Dynamic(_) => write!(f, "ProxyConfig::Dynamic(<callback>)"),
+            Self::DynamicAsync(_) => write!(f, "ProxyConfig::DynamicAsync(<callback>)"),
+        }
+    }
+}
+
+/// Prioritized proxies for `ProxyConfig::Failover`
+///
+/// A connection goes through the first proxy that works, trying at most
+/// `max_attempts` of them and moving on after a connection or handshake
+/// failure. When sticky, the proxy that worked last is tried first from then
+/// on, so a dead primary does not cost every connection an attempt;
+/// otherwise each connection starts with the first proxy again.
+#[derive(Debug)]
+pub struct ProxyFailover {
+    proxies: Vec<ProxyConfig>,
+    max_attempts: usize,
+    sticky: bool,
+    /// Index of the proxy tried first
+    first: AtomicUsize,
+}
+
+impl ProxyFailover {
+    /// Fail over between `proxies`, highest priority first, trying all of them
+    ///
+    /// Fails with `TorError::InvalidProxy` if `proxies` is empty or contains
+    /// anything but SOCKS5, SOCKS4, HTTP and HTTPS CONNECT proxies and chains
+    /// of them.
+    pub fn new(proxies: Vec<ProxyConfig>) -> Result<Self, TorError> {
+        if proxies.is_empty() {
+            return Err(TorError::InvalidProxy {
+                reason: "a failover list needs at least one proxy".into(),
+            });
+        }
+        if let Some(proxy) = proxies.iter().find(|proxy| {
+            !matches!(
+                proxy,
+                ProxyConfig::Socks5 { .. }
+                    | ProxyConfig::Socks4 { .. }
+                    | ProxyConfig::HttpConnect { .. }
+                    | ProxyConfig::HttpsConnect { .. }
+                    | ProxyConfig::Chain(_)
+            )
+        }) {
+            return Err(TorError::InvalidProxy {
+                reason: format!("failover entries must be proxies, not {:?}", proxy),
+            });
+        }
+        Ok(Self {
+            max_attempts: proxies.len(),
+            proxies,
+            sticky: false,
+            first: AtomicUsize::new(0),
+        })
+    }
+
+    /// Try at most `max_attempts` proxies per connection; at least one is
+    /// always tried
+    pub fn with_max_attempts(mut self, max_attempts: usize) -> Self {
+        self.max_attempts = max_attempts.clamp(1, self.proxies.len());
+        self
+    }
+
+    /// Start with the proxy that worked last rather than the first one
+    pub fn sticky(mut self, sticky: bool) -> Self {
+        self.sticky = sticky;
+        self
+    }
+
+    /// The proxies, highest priority first
+    pub fn proxies(&self) -> &[ProxyConfig] {
+        &self.proxies
+    }
+
+    /// Indices of the proxies to try for one connection, in order
+    fn order(&self) -> Vec<usize> {
+        let first = self.first.load(Ordering::Relaxed);
+        (0..self.proxies.len())
+            .map(|i| (first + i) % self.proxies.len())
+            .take(self.max_attempts)
+            .collect()
+    }
+
+    /// Note that the proxy at `index` connected
+    fn succeeded(&self, index: usize) {
+        if self.sticky {
+            self.first.store(index, Ordering::Relaxed);
+        }
+    }
+}
+
+/// When to try a connection again after a transient failure
+///
+/// Only failures that may go away on their own are retried: refused, reset
+/// and timed out connections, whether to the proxy or reported by it.
+/// Rejected credentials, blocked targets and protocol errors fail at once.
+/// Retry number n waits `base_delay * multiplier^(n-1)`, plus up to `jitter`
+/// of that at random so that connections that failed together do not all
+/// come back at the same moment.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RetryPolicy {
+    /// Attempts in total, including the first
+    pub max_attempts: u32,
+    /// Wait before the first retry
+    pub base_delay: Duration,
+    /// How much each wait grows over the previous one, at least 1.0
+    pub multiplier: f64,
+    /// Random extra wait as a fraction of the wait, 0.0 to 1.0
+    pub jitter: f64,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            base_delay: Duration::from_millis(500),
+            multiplier: 2.0,
+            jitter: 0.2,
         }
     }
 }
 
+impl RetryPolicy {
+    /// How long to wait before retry number `retry`, counting from 1
+    fn delay(&self, retry: u32) -> Duration {
+        let backoff = self.multiplier.max(1.0).powi(retry.saturating_sub(1) as i32);
+        let jitter = 1.0 + rand::random::<f64>() * self.jitter.clamp(0.0, 1.0);
+        let secs = self.base_delay.as_secs_f64() * backoff * jitter;
+        Duration::try_from_secs_f64(secs).unwrap_or(Duration::MAX)
+    }
+
+    /// Whether a connection that failed with `err` is worth trying again
+    fn is_retriable(err: &IoError) -> bool {
+        matches!(
+            err.kind(),
+            ErrorKind::ConnectionRefused | ErrorKind::ConnectionReset | ErrorKind::TimedOut
+        )
+    }
+}
+
 /// Proxy authentication credentials
 #[derive(Debug, Clone)]
 pub struct ProxyAuth {
@@ -70,11 +291,82 @@ pub struct ProxyAuth {
     pub password: String,
 }
 
+/// Where a proxy should connect to
+///
+/// A `Domain` target is sent to the proxy by name, so the proxy resolves it
+/// and the local resolver never sees the lookup. Only callers of
+/// `ProxyTcpProvider::connect_to` make them: arti connects to relays and
+/// bridges by address, so the Tor client itself never does.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum ProxyTarget {
+    Addr(SocketAddr),
+    Domain { host: String, port: u16 },
+}
+
+impl From<SocketAddr> for ProxyTarget {
+    fn from(addr: SocketAddr) -> Self {
+        Self::Addr(addr)
+    }
+}
+
+impl std::fmt::Display for ProxyTarget {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Addr(addr) => write!(f, "{}", addr),
+            Self::Domain { host, port } => write!(f, "{}:{}", host, port),
+        }
+    }
+}
+
+/// Why a connection went direct instead of through a proxy
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DirectReason {
+    /// The provider was created with `ProxyConfig::Direct`, or the callback
+    /// has proxies switched off
+    ProxyDisabled,
+    /// No proxy is set, or every proxy has weight 0
+    NoProxySet,
+    /// Proxies are set, but none has a valid address
+    InvalidProxyAddress,
+    /// The callback returned None without saying why
+    CallbackReturnedNone,
+    /// The callback returned `ProxyConfig::Direct`
+    CallbackChoseDirect,
+    /// The target is on this device, e.g. a pluggable transport's local
+    /// port, which a proxy cannot reach
+    Loopback,
+}
+
+impl std::fmt::Display for DirectReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let reason = match self {
+            Self::ProxyDisabled => "proxy-disabled",
+            Self::NoProxySet => "no-proxy-set",
+            Self::InvalidProxyAddress => "invalid-proxy-address",
+            Self::CallbackReturnedNone => "callback-returned-none",
+            Self::CallbackChoseDirect => "callback-chose-direct",
+            Self::Loopback => "loopback",
+        };
+        f.write_str(reason)
+    }
+}
+
 /// Trait for dynamic proxy callback
 pub trait ProxyCallback: Send + Sync {
     /// Get proxy address for the target connection
     /// Returns None to use direct connection
     fn get_proxy(&self, target: &SocketAddr) -> Option<ProxyConfig>;
+
+    /// Like get_proxy, but says why the connection should go direct
+    ///
+    /// The default only knows that get_proxy returned None; override it to
+    /// report a more precise reason.
+    fn resolve(&self, target: &SocketAddr) -> Result<ProxyConfig, DirectReason> {
+        self.get_proxy(target).ok_or(DirectReason::CallbackReturnedNone)
+    }
+
+    /// Called when connecting through a proxy returned by get_proxy failed
+    fn report_failure(&self, _proxy_addr: &SocketAddr) {}
 }
 
 /// Implement ProxyCallback for closures
@@ -87,333 +379,3465 @@ where
     }
 }
 
-/// Hybrid TCP provider that supports proxy connections
-#[derive(Clone)]
-pub struct ProxyTcpProvider<T> {
-    inner: T,
-    proxy_config: Arc<ProxyConfig>,
+/// IP network in CIDR notation, e.g. `10.0.0.0/8` or `fe80::/10`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IpCidr {
+    addr: IpAddr,
+    prefix_len: u8,
 }
 
-impl<T> ProxyTcpProvider<T> {
-    /// Create a new proxy TCP provider
-    pub fn new(inner: T, proxy_config: ProxyConfig) -> Self {
-        Self {
-            inner,
-            proxy_config: Arc::new(proxy_config),
+impl IpCidr {
+    /// Parse `addr/prefix_len`; a bare address is a single-host network
+    pub fn parse(s: &str) -> Option<Self> {
+        let s = s.trim();
+        let (addr, prefix_len) = match s.split_once('/') {
+            Some((addr, prefix_len)) => (addr, Some(prefix_len.parse::<u8>().ok()?)),
+            None => (s, None),
+        };
+        let addr: IpAddr = addr.parse().ok()?;
+        let max_len = if addr.is_ipv4() { 32 } else { 128 };
+        let prefix_len = prefix_len.unwrap_or(max_len);
+        if prefix_len > max_len {
+            return None;
         }
+        Some(Self { addr, prefix_len })
     }
 
-    /// Create a direct connection provider (no proxy)
-    #[allow(dead_code)]
-    pub fn direct(inner: T) -> Self {
-        Self::new(inner, ProxyConfig::Direct)
+    /// Whether `ip` lies in this network (IPv4-mapped IPv6 addresses match IPv4 networks)
+    pub fn contains(&self, ip: IpAddr) -> bool {
+        let (net, ip, bits) = match (self.addr, ip.to_canonical()) {
+            (IpAddr::V4(net), IpAddr::V4(ip)) => (u32::from(net).into(), u32::from(ip).into(), 32),
+            (IpAddr::V6(net), IpAddr::V6(ip)) => (u128::from(net), u128::from(ip), 128),
+            _ => return false,
+        };
+        if self.prefix_len == 0 {
+            return true;
+        }
+        let shift = bits - u32::from(self.prefix_len);
+        net >> shift == ip >> shift
     }
 }
 
-impl<T> NetStreamProvider for ProxyTcpProvider<T>
-where
-    T: NetStreamProvider + Clone + Send + Sync + 'static,
-    T::Stream: Send + Unpin + AsyncRead + AsyncWrite + StreamOps + 'static,
-{
-    type Stream = T::Stream;
-    type Listener = T::Listener;
-
-    fn connect<'a, 'b, 'c>(
-        &'a self,
-        addr: &'b SocketAddr,
-    ) -> Pin<Box<dyn Future<Output = IoResult<Self::Stream>> + Send + 'c>>
-    where
-        'a: 'c,
-        'b: 'c,
-        Self: 'c,
-    {
-        let addr = *addr;
-        let proxy_config = Arc::clone(&self.proxy_config);
-        let inner = self.inner.clone();
+/// Destination policy checked before every outgoing connection
+///
+/// Targets in `deny` are always refused. If `allow` is non-empty, only
+/// targets in it are permitted.
+#[derive(Debug, Clone, Default)]
+pub struct TargetPolicy {
+    pub allow: Vec<IpCidr>,
+    pub deny: Vec<IpCidr>,
+}
 
-        async move {
-            // Resolve proxy configuration (handle dynamic case)
-            let effective_config = match proxy_config.as_ref() {
-                ProxyConfig::Dynamic(callback) => {
-                    proxy_log!("Resolving dynamic proxy for target: {}", addr);
-                    let resolved = callback.get_proxy(&addr).unwrap_or(ProxyConfig::Direct);
-                    match &resolved {
-                        ProxyConfig::Direct => proxy_log!("Dynamic proxy resolved to: Direct connection"),
-                        ProxyConfig::Socks5 { proxy_addr, .. } => proxy_log!("Dynamic proxy resolved to: SOCKS5 via {}", proxy_addr),
-                        ProxyConfig::HttpConnect { proxy_addr, .. } => proxy_log!("Dynamic proxy resolved to: HTTP CONNECT via {}", proxy_addr),
-                        _ => {}
-                    }
-                    resolved
-                }
-                config => config.clone(),
-            };
+impl TargetPolicy {
+    /// Whether connecting to `ip` is permitted
+    pub fn permits(&self, ip: IpAddr) -> bool {
+        !self.deny.iter().any(|net| net.contains(ip))
+            && (self.allow.is_empty() || self.allow.iter().any(|net| net.contains(ip)))
+    }
 
-            // Connect through proxy or directly
-            match effective_config {
-                ProxyConfig::Direct => {
-                    // Direct connection
-                    proxy_log!("Connecting directly to {}", addr);
-                    inner.connect(&addr).await
-                }
-                ProxyConfig::Socks5 { proxy_addr, auth } => {
-                    // Connect via SOCKS5
-                    proxy_log!("Connecting to {} via SOCKS5 proxy at {} (auth: {})", 
-                              addr, proxy_addr, auth.is_some());
-                    let result = connect_socks5(inner, proxy_addr, addr, auth.as_ref()).await;
-                    if result.is_ok() {
-                        proxy_log!("✓ Successfully connected to {} via SOCKS5 proxy {}", addr, proxy_addr);
-                    } else {
-                        proxy_log!("✗ Failed to connect to {} via SOCKS5 proxy {}: {:?}", 
-                                  addr, proxy_addr, result.as_ref().err());
-                    }
-                    result
-                }
-                ProxyConfig::HttpConnect { proxy_addr, auth } => {
-                    // Connect via HTTP CONNECT
-                    proxy_log!("Connecting to {} via HTTP CONNECT proxy at {} (auth: {})", 
-                              addr, proxy_addr, auth.is_some());
-                    let result = connect_http(inner, proxy_addr, addr, auth.as_ref()).await;
-                    if result.is_ok() {
-                        proxy_log!("✓ Successfully connected to {} via HTTP CONNECT proxy {}", addr, proxy_addr);
-                    } else {
-                        proxy_log!("✗ Failed to connect to {} via HTTP CONNECT proxy {}: {:?}", 
-                                  addr, proxy_addr, result.as_ref().err());
-                    }
-                    result
-                }
-                ProxyConfig::Dynamic(_) => {
-                    unreachable!("Dynamic config should have been resolved")
-                }
-            }
+    /// Whether connecting to `target` is permitted
+    ///
+    /// A domain name is not resolved here, so it is only permitted when
+    /// there is no allow list to check it against.
+    pub fn permits_target(&self, target: &ProxyTarget) -> bool {
+        match target {
+            ProxyTarget::Addr(addr) => self.permits(addr.ip()),
+            ProxyTarget::Domain { .. } => self.allow.is_empty(),
         }
-        .boxed()
     }
+}
 
-    fn listen<'a, 'b, 'c>(
-        &'a self,
-        addr: &'b SocketAddr,
-    ) -> Pin<Box<dyn Future<Output = IoResult<Self::Listener>> + Send + 'c>>
-    where
-        'a: 'c,
-        'b: 'c,
-        Self: 'c,
-    {
-        self.inner.listen(addr)
+/// Redaction used until one is set, chosen so logs can be shared as they are
+pub const DEFAULT_TARGET_REDACTION: TargetRedaction = TargetRedaction::Hashed;
+
+/// How long a proxy handshake step may wait for the proxy when the config
+/// does not say
+pub const DEFAULT_HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Longest response to an HTTP CONNECT request accepted when the config does
+/// not say
+pub const DEFAULT_MAX_HTTP_RESPONSE_LEN: usize = 8192;
+
+/// How long connecting to a proxy and the whole handshake with it may take,
+/// unless set with `ProxyTcpProvider::with_handshake_deadline`
+pub const DEFAULT_HANDSHAKE_DEADLINE: Duration = Duration::from_secs(30);
+
+// Key for hashed targets; random so the hashes cannot be reversed by
+// hashing every address
+static TARGET_HASH_KEY: Lazy<RandomState> = Lazy::new(RandomState::new);
+
+/// How `target` is written to logs under `redaction`
+pub fn redact_target(redaction: TargetRedaction, target: &ProxyTarget) -> String {
+    match redaction {
+        TargetRedaction::Full => target.to_string(),
+        TargetRedaction::HostOnly => match target {
+            ProxyTarget::Addr(addr) => addr.ip().to_string(),
+            ProxyTarget::Domain { host, .. } => host.clone(),
+        },
+        TargetRedaction::Hashed => format!("hash:{:016x}", TARGET_HASH_KEY.hash_one(target)),
+        TargetRedaction::None => "redacted".to_string(),
     }
 }
 
-/// Connect to target via SOCKS5 proxy
-async fn connect_socks5<T>(
-    provider: T,
-    proxy_addr: SocketAddr,
-    target_addr: SocketAddr,
-    auth: Option<&ProxyAuth>,
-) -> IoResult<T::Stream>
-where
-    T: NetStreamProvider,
-{
-    // Connect to proxy server
-    let mut stream = provider.connect(&proxy_addr).await?;
+/// Error on a proxied stream after the proxy handshake succeeded
+///
+/// Returned as the inner error of an `io::Error` with the original kind, so
+/// callers can tell that the connection to the proxy broke, rather than the
+/// connection attempt; see `ProxyStreamError::from_io`.
+#[derive(Debug)]
+pub struct ProxyStreamError {
+    pub proxy_addr: SocketAddr,
+    pub source: IoError,
+}
 
-    // SOCKS5 handshake
-    // Method selection
-    if let Some(auth) = auth {
-        // With authentication
-        let methods = [0x05, 0x02, 0x00, 0x02]; // Version 5, 2 methods: no auth, username/password
-        write_all(&mut stream, &methods).await?;
+impl ProxyStreamError {
+    /// The proxy error carried by `error`, if it came from a proxied stream
+    pub fn from_io(error: &IoError) -> Option<&Self> {
+        error.get_ref()?.downcast_ref()
+    }
+}
 
-        let mut response = [0u8; 2];
-        read_exact(&mut stream, &mut response).await?;
+impl std::fmt::Display for ProxyStreamError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Connection through proxy {} lost: {}", self.proxy_addr, self.source)
+    }
+}
 
-        if response[0] != 0x05 {
-            return Err(IoError::new(ErrorKind::Other, "Invalid SOCKS5 version"));
-        }
+impl std::error::Error for ProxyStreamError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.source)
+    }
+}
 
-        if response[1] == 0x02 {
-            // Username/password authentication
-            let username = auth.username.as_bytes();
-            let password = auth.password.as_bytes();
+/// Category of a failure to connect through a proxy
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProxyErrorKind {
+    /// The proxy rejected our credentials
+    AuthFailed,
+    /// The proxy refused the handshake or the connection to the target
+    HandshakeFailed,
+    /// The proxy itself could not be connected to
+    Unreachable,
+    /// The proxy sent something its protocol does not allow
+    ProtocolViolation,
+    /// The HTTP proxy's response to CONNECT was longer than allowed
+    ResponseTooLarge,
+    /// The proxy stopped answering during the handshake
+    Timeout,
+    /// The proxy accepts none of the authentication methods we offered
+    UnsupportedMethod,
+    /// The SOCKS5 proxy could not or would not connect to the target
+    ConnectRejected(Socks5Reply),
+    /// The HTTP proxy answered CONNECT with this status, other than 200 or
+    /// 407 (which is `AuthFailed`)
+    HttpRejected(u16),
+}
 
-            let mut auth_req = vec![0x01]; // Auth version
-            auth_req.push(username.len() as u8);
-            auth_req.extend_from_slice(username);
-            auth_req.push(password.len() as u8);
-            auth_req.extend_from_slice(password);
+/// Kind of the `io::Error` a CONNECT refused with HTTP `status` fails with
+fn http_status_io_kind(status: u16) -> ErrorKind {
+    match status {
+        403 => ErrorKind::PermissionDenied,
+        502 => ErrorKind::HostUnreachable,
+        504 => ErrorKind::TimedOut,
+        _ => ErrorKind::Other,
+    }
+}
 
-            write_all(&mut stream, &auth_req).await?;
+/// What a CONNECT refused with HTTP `status` means, for the error message
+fn http_status_meaning(status: u16) -> &'static str {
+    match status {
+        403 => "HTTP proxy forbids connecting to this target",
+        502 => "HTTP proxy could not reach the target",
+        504 => "HTTP proxy timed out reaching the target",
+        _ => "HTTP CONNECT failed",
+    }
+}
 
-            let mut auth_resp = [0u8; 2];
-            read_exact(&mut stream, &mut auth_resp).await?;
+/// Reply code of a SOCKS5 proxy that failed a CONNECT (RFC 1928, section 6)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Socks5Reply {
+    /// 0x01
+    GeneralFailure,
+    /// 0x02, the proxy's rules do not allow the connection
+    NotAllowed,
+    /// 0x03
+    NetworkUnreachable,
+    /// 0x04
+    HostUnreachable,
+    /// 0x05, the target refused the connection
+    ConnectionRefused,
+    /// 0x06
+    TtlExpired,
+    /// 0x07
+    CommandNotSupported,
+    /// 0x08
+    AddressTypeNotSupported,
+    /// A code RFC 1928 does not define
+    Other(u8),
+}
 
-            if auth_resp[1] != 0x00 {
-                return Err(IoError::new(ErrorKind::PermissionDenied, "SOCKS5 auth failed"));
-            }
-        } else if response[1] != 0x00 {
-            return Err(IoError::new(ErrorKind::Other, "No acceptable SOCKS5 methods"));
+impl Socks5Reply {
+    /// The reply for a non-zero REP field
+    fn from_code(code: u8) -> Self {
+        match code {
+            0x01 => Self::GeneralFailure,
+            0x02 => Self::NotAllowed,
+            0x03 => Self::NetworkUnreachable,
+            0x04 => Self::HostUnreachable,
+            0x05 => Self::ConnectionRefused,
+            0x06 => Self::TtlExpired,
+            0x07 => Self::CommandNotSupported,
+            0x08 => Self::AddressTypeNotSupported,
+            code => Self::Other(code),
         }
-    } else {
-        // No authentication
-        let methods = [0x05, 0x01, 0x00]; // Version 5, 1 method: no auth
-        write_all(&mut stream, &methods).await?;
+    }
 
-        let mut response = [0u8; 2];
-        read_exact(&mut stream, &mut response).await?;
+    /// Kind of the `io::Error` a connection rejected with this reply fails with
+    fn io_kind(self) -> ErrorKind {
+        match self {
+            Self::NotAllowed => ErrorKind::PermissionDenied,
+            Self::NetworkUnreachable => ErrorKind::NetworkUnreachable,
+            Self::HostUnreachable => ErrorKind::HostUnreachable,
+            Self::ConnectionRefused => ErrorKind::ConnectionRefused,
+            Self::TtlExpired => ErrorKind::TimedOut,
+            Self::CommandNotSupported | Self::AddressTypeNotSupported => ErrorKind::Unsupported,
+            Self::GeneralFailure | Self::Other(_) => ErrorKind::Other,
+        }
+    }
+}
 
-        if response[0] != 0x05 || response[1] != 0x00 {
-            return Err(IoError::new(ErrorKind::Other, "SOCKS5 handshake failed"));
+impl std::fmt::Display for Socks5Reply {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::GeneralFailure => write!(f, "general SOCKS server failure"),
+            Self::NotAllowed => write!(f, "connection not allowed by ruleset"),
+            Self::NetworkUnreachable => write!(f, "network unreachable"),
+            Self::HostUnreachable => write!(f, "host unreachable"),
+            Self::ConnectionRefused => write!(f, "connection refused"),
+            Self::TtlExpired => write!(f, "TTL expired"),
+            Self::CommandNotSupported => write!(f, "command not supported"),
+            Self::AddressTypeNotSupported => write!(f, "address type not supported"),
+            Self::Other(code) => write!(f, "unknown reply {:#04x}", code),
         }
     }
+}
 
-    // Connection request
-    let mut request = vec![0x05, 0x01, 0x00]; // Version, CONNECT, reserved
+/// Error connecting through a proxy, before the stream reached the target
+///
+/// Returned as the inner error of an `io::Error` whose kind follows the
+/// category (`PermissionDenied` for `AuthFailed`, `InvalidData` for
+/// `ProtocolViolation` and `ResponseTooLarge`, `TimedOut` for `Timeout`,
+/// `Unsupported` for `UnsupportedMethod`, one following the reply for
+/// `ConnectRejected` or the status for `HttpRejected`, otherwise the kind of
+/// the underlying error), so callers
+/// can tell it from a failure of the target itself; see `ProxyError::from_io`.
+#[derive(Debug)]
+pub struct ProxyError {
+    pub kind: ProxyErrorKind,
+    pub message: String,
+    pub source: Option<IoError>,
+}
 
-    match target_addr.ip() {
-        IpAddr::V4(ip) => {
-            request.push(0x01); // IPv4
-            request.extend_from_slice(&ip.octets());
+impl ProxyError {
+    pub fn new(kind: ProxyErrorKind, message: impl Into<String>) -> Self {
+        Self {
+            kind,
+            message: message.into(),
+            source: None,
         }
-        IpAddr::V6(ip) => {
-            request.push(0x04); // IPv6
-            request.extend_from_slice(&ip.octets());
+    }
+
+    /// Connecting to the proxy at `proxy_addr` failed with `error`
+    fn unreachable(proxy_addr: SocketAddr, error: IoError) -> Self {
+        Self {
+            kind: ProxyErrorKind::Unreachable,
+            message: format!("Could not connect to proxy {}", proxy_addr),
+            source: Some(error),
         }
     }
-    request.extend_from_slice(&target_addr.port().to_be_bytes());
 
-    write_all(&mut stream, &request).await?;
+    /// Classify an error from a `protocol` handshake
+    ///
+    /// Errors that already carry a ProxyError keep it; other timeouts and
+    /// permission errors (e.g. from a custom SOCKS5 auth method) become
+    /// `Timeout` and `AuthFailed`, anything else `HandshakeFailed`.
+    fn handshake(protocol: &str, error: IoError) -> Self {
+        if Self::from_io(&error).is_some() {
+            let inner = error.into_inner().expect("checked above");
+            return *inner.downcast::<Self>().expect("checked above");
+        }
+        let (kind, message) = match error.kind() {
+            ErrorKind::TimedOut => (ProxyErrorKind::Timeout, format!("{} proxy did not answer in time", protocol)),
+            ErrorKind::PermissionDenied => (ProxyErrorKind::AuthFailed, format!("{} auth failed", protocol)),
+            _ => (ProxyErrorKind::HandshakeFailed, format!("{} handshake failed", protocol)),
+        };
+        Self {
+            kind,
+            message,
+            source: Some(error),
+        }
+    }
 
-    // Read response
-    let mut response = [0u8; 10];
-    read_exact(&mut stream, &mut response[0..4]).await?;
+    /// Say that this happened at hop `index` (from 0) of a chain of `hops`
+    fn at_hop(mut self, index: usize, hops: usize) -> Self {
+        self.message = format!("Proxy chain hop {} of {}: {}", index + 1, hops, self.message);
+        self
+    }
 
-    if response[0] != 0x05 {
-        return Err(IoError::new(ErrorKind::Other, "Invalid SOCKS5 response"));
+    /// The proxy error carried by `error`, if it came from a proxy handshake
+    pub fn from_io(error: &IoError) -> Option<&Self> {
+        error.get_ref()?.downcast_ref()
     }
 
-    if response[1] != 0x00 {
-        return Err(IoError::new(
-            ErrorKind::Other,
-            format!("SOCKS5 connection failed: {}", response[1]),
-        ));
+    /// Kind of the `io::Error` this is returned as
+    fn io_kind(&self) -> ErrorKind {
+        match self.kind {
+            ProxyErrorKind::AuthFailed => ErrorKind::PermissionDenied,
+            ProxyErrorKind::ProtocolViolation | ProxyErrorKind::ResponseTooLarge => ErrorKind::InvalidData,
+            ProxyErrorKind::Timeout => ErrorKind::TimedOut,
+            ProxyErrorKind::UnsupportedMethod => ErrorKind::Unsupported,
+            ProxyErrorKind::ConnectRejected(reply) => reply.io_kind(),
+            ProxyErrorKind::HttpRejected(status) => http_status_io_kind(status),
+            ProxyErrorKind::HandshakeFailed | ProxyErrorKind::Unreachable => self
+                .source
+                .as_ref()
+                .map_or(ErrorKind::Other, IoError::kind),
+        }
     }
+}
 
-    // Skip the rest of the response based on address type
-    match response[3] {
-        0x01 => read_exact(&mut stream, &mut response[0..6]).await?, // IPv4 + port
-        0x04 => read_exact(&mut stream, &mut response[0..18]).await?, // IPv6 + port
-        0x03 => {
-            // Domain name
-            let mut len = [0u8; 1];
-            read_exact(&mut stream, &mut len).await?;
-            let mut domain = vec![0u8; len[0] as usize + 2]; // domain + port
-            read_exact(&mut stream, &mut domain).await?;
+impl std::fmt::Display for ProxyError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.source {
+            Some(source) => write!(f, "{}: {}", self.message, source),
+            None => write!(f, "{}", self.message),
         }
-        _ => return Err(IoError::new(ErrorKind::Other, "Unknown SOCKS5 address type")),
     }
+}
 
-    Ok(stream)
+impl std::error::Error for ProxyError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        self.source.as_ref().map(|source| source as _)
+    }
 }
 
-/// Connect to target via HTTP CONNECT proxy
-async fn connect_http<T>(
-    provider: T,
-    proxy_addr: SocketAddr,
-    target_addr: SocketAddr,
-    auth: Option<&ProxyAuth>,
-) -> IoResult<T::Stream>
+impl From<ProxyError> for IoError {
+    fn from(error: ProxyError) -> Self {
+        IoError::new(error.io_kind(), error)
+    }
+}
+
+/// Proxy an established stream goes through
+pub struct ProxyLink {
+    addr: SocketAddr,
+    /// Run on the first error, e.g. to fail over to another proxy
+    on_failure: Option<Box<dyn FnOnce() + Send + Sync>>,
+}
+
+impl ProxyLink {
+    pub fn new(addr: SocketAddr, on_failure: Option<Box<dyn FnOnce() + Send + Sync>>) -> Self {
+        Self { addr, on_failure }
+    }
+
+    /// Wrap an error from the stream in a ProxyStreamError
+    pub fn tag(&mut self, error: IoError) -> IoError {
+        proxy_log!("✗ Stream through proxy {} failed: {}", self.addr, error);
+        if let Some(on_failure) = self.on_failure.take() {
+            on_failure();
+        }
+        IoError::new(
+            error.kind(),
+            ProxyStreamError {
+                proxy_addr: self.addr,
+                source: error,
+            },
+        )
+    }
+}
+
+/// Async variant of ProxyCallback, for decisions that need network access
+/// (e.g. asking a policy service or refreshing credentials)
+pub trait AsyncProxyCallback: Send + Sync {
+    /// Get proxy address for the target connection
+    /// Resolves to None to use direct connection
+    fn get_proxy(&self, target: SocketAddr) -> BoxFuture<'_, Option<ProxyConfig>>;
+
+    /// Called when connecting through a proxy returned by get_proxy failed
+    fn report_failure(&self, _proxy_addr: &SocketAddr) {}
+}
+
+/// Implement AsyncProxyCallback for closures returning a future
+impl<F, Fut> AsyncProxyCallback for F
 where
-    T: NetStreamProvider,
+    F: Fn(SocketAddr) -> Fut + Send + Sync,
+    Fut: Future<Output = Option<ProxyConfig>> + Send + 'static,
 {
-    // Connect to proxy server
-    let mut stream = provider.connect(&proxy_addr).await?;
+    fn get_proxy(&self, target: SocketAddr) -> BoxFuture<'_, Option<ProxyConfig>> {
+        self(target).boxed()
+    }
+}
 
-    // Build HTTP CONNECT request
-    let mut request = format!(
-        "CONNECT {}:{} HTTP/1.1\r\nHost: {}:{}\r\n",
-        target_addr.ip(),
-        target_addr.port(),
-        target_addr.ip(),
-        target_addr.port()
-    );
+/// Supplies fresh credentials when a proxy rejects the ones it was given,
+/// e.g. by asking the user again or fetching rotated credentials
+///
+/// Register it with `ProxyTcpProvider::with_auth_refresh`. Called when a
+/// SOCKS5 proxy fails username/password authentication; the handshake is
+/// then retried once with what it returns. It is not told whether the retry
+/// worked, so store the new credentials for later connections yourself,
+/// e.g. by updating the proxy that `ProxyCallback::get_proxy` returns.
+pub trait AuthRefreshCallback: Send + Sync {
+    /// New credentials for `proxy_addr`, which rejected `rejected`
+    /// Resolves to None to give up, failing the connection as before
+    fn refresh_auth(&self, proxy_addr: SocketAddr, rejected: ProxyAuth) -> BoxFuture<'_, Option<ProxyAuth>>;
+}
 
-    if let Some(auth) = auth {
-        use base64::{engine::general_purpose::STANDARD, Engine as _};
-        let credentials = format!("{}:{}", auth.username, auth.password);
-        let encoded = STANDARD.encode(credentials.as_bytes());
-        request.push_str(&format!("Proxy-Authorization: Basic {}\r\n", encoded));
+/// Implement AuthRefreshCallback for closures returning a future
+impl<F, Fut> AuthRefreshCallback for F
+where
+    F: Fn(SocketAddr, ProxyAuth) -> Fut + Send + Sync,
+    Fut: Future<Output = Option<ProxyAuth>> + Send + 'static,
+{
+    fn refresh_auth(&self, proxy_addr: SocketAddr, rejected: ProxyAuth) -> BoxFuture<'_, Option<ProxyAuth>> {
+        self(proxy_addr, rejected).boxed()
     }
+}
 
-    request.push_str("\r\n");
+/// Observer of outgoing connection attempts, e.g. to time proxy handshakes
+///
+/// Register it with `ProxyTcpProvider::with_observer`. Every attempt gets
+/// `on_connect_start` and then either `on_connect_success` or
+/// `on_connect_error`. That is one attempt per proxy tried from a failover
+/// list and per retry; connections refused by the target policy make no
+/// attempt. `target` is redacted as in the logs and `via` names the proxy as
+/// in the connection log. Unlike the debug log these run in release builds.
+/// They are called on the connecting task, so keep them quick.
+pub trait ProxyObserver: Send + Sync {
+    fn on_connect_start(&self, _target: &str, _via: &str) {}
 
-    write_all(&mut stream, request.as_bytes()).await?;
+    /// The stream is ready for use, `elapsed` after the attempt started
+    fn on_connect_success(&self, _target: &str, _via: &str, _elapsed: Duration) {}
 
-    // Read response
-    let mut response = Vec::new();
-    let mut buf = [0u8; 1];
+    /// `error` carries a ProxyError if the proxy failed rather than the
+    /// connection to it, see `ProxyError::from_io`
+    fn on_connect_error(&self, _target: &str, _via: &str, _error: &IoError) {}
+}
 
-    // Read until \r\n\r\n
-    loop {
-        read_exact(&mut stream, &mut buf).await?;
-        response.push(buf[0]);
+/// Stream a SOCKS5 authentication method runs its sub-negotiation on
+pub trait Socks5Stream: AsyncRead + AsyncWrite + Unpin + Send {}
 
-        if response.len() >= 4
-            && &response[response.len() - 4..] == b"\r\n\r\n"
-        {
-            break;
-        }
+impl<S: AsyncRead + AsyncWrite + Unpin + Send> Socks5Stream for S {}
 
-        if response.len() > 8192 {
-            return Err(IoError::new(ErrorKind::Other, "HTTP response too large"));
-        }
-    }
+/// SOCKS5 method byte for "no authentication required"
+pub const SOCKS5_NO_AUTH: u8 = 0x00;
+/// SOCKS5 method byte for username/password authentication (RFC 1929)
+pub const SOCKS5_USERNAME_PASSWORD: u8 = 0x02;
+/// SOCKS5 method byte for GSSAPI authentication (RFC 1961)
+///
+/// Not built in, as it needs a GSS-API library (e.g. Kerberos) to produce
+/// the security context tokens. Embedders that have one can register a
+/// handler for it with `ProxyTcpProvider::with_socks5_auth_method`.
+pub const SOCKS5_GSSAPI: u8 = 0x01;
+/// SOCKS5 reply meaning none of the offered methods is acceptable
+const SOCKS5_NO_ACCEPTABLE_METHODS: u8 = 0xFF;
 
-    // Parse response
-    let response_str = String::from_utf8_lossy(&response);
-    if !response_str.starts_with("HTTP/1.1 200")
-        && !response_str.starts_with("HTTP/1.0 200")
-    {
-        return Err(IoError::new(
-            ErrorKind::Other,
-            format!("HTTP CONNECT failed: {}", response_str.lines().next().unwrap_or("")),
-        ));
+/// Handler for one SOCKS5 authentication method (RFC 1928 section 3)
+///
+/// Register handlers for vendor-specific methods (e.g. the private range
+/// 0x80-0xFE) with `ProxyTcpProvider::with_socks5_auth_method`.
+pub trait Socks5AuthMethod: Send + Sync {
+    /// Whether to offer this method to a proxy with these credentials
+    fn offer(&self, _auth: Option<&ProxyAuth>) -> bool {
+        true
     }
 
-    Ok(stream)
+    /// Run the method's sub-negotiation after the proxy selected it
+    fn authenticate<'a>(
+        &'a self,
+        stream: &'a mut dyn Socks5Stream,
+        auth: Option<&'a ProxyAuth>,
+    ) -> BoxFuture<'a, IoResult<()>>;
 }
 
-/// Helper to write all bytes
-async fn write_all<T>(stream: &mut T, buf: &[u8]) -> IoResult<()>
-where
-    T: AsyncWrite + Unpin,
-{
-    use futures::io::AsyncWriteExt;
-    stream.write_all(buf).await
-}
+/// Built-in method 0x00: nothing to negotiate
+struct NoAuth;
 
-/// Helper to read exact bytes
-async fn read_exact<T>(stream: &mut T, buf: &mut [u8]) -> IoResult<()>
-where
-    T: AsyncRead + Unpin,
-{
-    use futures::io::AsyncReadExt;
-    stream.read_exact(buf).await
+impl Socks5AuthMethod for NoAuth {
+    fn authenticate<'a>(
+        &'a self,
+        _stream: &'a mut dyn Socks5Stream,
+        _auth: Option<&'a ProxyAuth>,
+    ) -> BoxFuture<'a, IoResult<()>> {
+        futures::future::ok(()).boxed()
+    }
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+/// Built-in method 0x02: username and password, offered only when set
+struct UsernamePassword;
 
-    #[test]
-    fn test_proxy_config_creation() {
-        let config = ProxyConfig::Socks5 {
-            proxy_addr: "127.0.0.1:1080".parse().unwrap(),
-            auth: None,
-        };
+impl Socks5AuthMethod for UsernamePassword {
+    fn offer(&self, auth: Option<&ProxyAuth>) -> bool {
+        auth.is_some()
+    }
 
-        match config {
+    fn authenticate<'a>(
+        &'a self,
+        stream: &'a mut dyn Socks5Stream,
+        auth: Option<&'a ProxyAuth>,
+    ) -> BoxFuture<'a, IoResult<()>> {
+        async move {
+            let auth = auth.ok_or_else(|| {
+                ProxyError::new(
+                    ProxyErrorKind::HandshakeFailed,
+                    "SOCKS5 proxy chose password auth, but no credentials are set",
+                )
+            })?;
+            let auth_req = socks5_auth_request(auth)?;
+            write_all(stream, &auth_req).await?;
+
+            let mut auth_resp = [0u8; 2];
+            read_exact(stream, &mut auth_resp).await?;
+
+            if auth_resp[1] != 0x00 {
+                return Err(IoError::from(ProxyError::new(ProxyErrorKind::AuthFailed, "SOCKS5 auth failed")));
+            }
+            Ok(())
+        }
+        .boxed()
+    }
+}
+
+/// SOCKS5 authentication methods, keyed by method byte
+///
+/// The default holds the standard methods, no authentication and
+/// username/password, and offers every method whose handler wants to be
+/// offered, in order of method byte.
+#[derive(Clone)]
+pub struct Socks5AuthMethods {
+    handlers: BTreeMap<u8, Arc<dyn Socks5AuthMethod>>,
+    /// Methods to offer and in which order, if not all by method byte
+    order: Option<Vec<u8>>,
+}
+
+impl Default for Socks5AuthMethods {
+    fn default() -> Self {
+        let mut handlers: BTreeMap<u8, Arc<dyn Socks5AuthMethod>> = BTreeMap::new();
+        handlers.insert(SOCKS5_NO_AUTH, Arc::new(NoAuth));
+        handlers.insert(SOCKS5_USERNAME_PASSWORD, Arc::new(UsernamePassword));
+        Self { handlers, order: None }
+    }
+}
+
+impl Socks5AuthMethods {
+    /// Add a handler for `method`, replacing any handler it had
+    ///
+    /// # Panics
+    ///
+    /// If `method` is 0xFF, which means "no acceptable methods".
+    pub fn register(&mut self, method: u8, handler: Arc<dyn Socks5AuthMethod>) {
+        assert_ne!(method, SOCKS5_NO_ACCEPTABLE_METHODS, "0xFF is not a SOCKS5 method");
+        self.handlers.insert(method, handler);
+    }
+
+    /// Offer only `order`'s methods, most preferred first
+    ///
+    /// For proxies that reject clients offering a method they do not use,
+    /// e.g. `[SOCKS5_NO_AUTH]` never offers username/password even when
+    /// credentials are set. Each method is still only offered if its handler
+    /// agrees (username/password needs credentials); methods without a
+    /// handler and repeats are skipped.
+    pub fn set_order(&mut self, order: &[u8]) {
+        let mut methods = Vec::with_capacity(order.len());
+        for &method in order {
+            if !methods.contains(&method) {
+                methods.push(method);
+            }
+        }
+        self.order = Some(methods);
+    }
+
+    /// Methods to offer a proxy with these credentials, most preferred first
+    fn offered(&self, auth: Option<&ProxyAuth>) -> Vec<u8> {
+        let offer = |method: &u8| self.handlers.get(method).is_some_and(|handler| handler.offer(auth));
+        match &self.order {
+            Some(order) => order.iter().copied().filter(offer).collect(),
+            None => self.handlers.keys().copied().filter(offer).collect(),
+        }
+    }
+}
+
+/// Stream as the proxy provider hands it out: a plain TCP stream, or a TLS
+/// session over one for HTTPS proxies
+pub enum ProxyStream<S> {
+    Plain(S),
+    /// Boxed, as its type depends on the TLS provider, and in a mutex only so
+    /// that the stream is Sync; reads and writes go through get_mut()
+    #[cfg_attr(not(feature = "https-proxy"), allow(dead_code))]
+    Tls(Mutex<Box<dyn TlsTunnel>>),
+}
+
+/// TLS session to an HTTPS proxy, see `ProxyStream::Tls`
+pub trait TlsTunnel: AsyncRead + AsyncWrite + StreamOps + Unpin + Send {}
+
+impl<S: AsyncRead + AsyncWrite + StreamOps + Unpin + Send> TlsTunnel for S {}
+
+impl<S: AsyncRead + Unpin> AsyncRead for ProxyStream<S> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<IoResult<usize>> {
+        match self.get_mut() {
+            Self::Plain(stream) => Pin::new(stream).poll_read(cx, buf),
+            Self::Tls(tls) => Pin::new(tls.get_mut().unwrap()).poll_read(cx, buf),
+        }
+    }
+}
+
+impl<S: AsyncWrite + Unpin> AsyncWrite for ProxyStream<S> {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<IoResult<usize>> {
+        match self.get_mut() {
+            Self::Plain(stream) => Pin::new(stream).poll_write(cx, buf),
+            Self::Tls(tls) => Pin::new(tls.get_mut().unwrap()).poll_write(cx, buf),
+        }
+    }
+
+    fn poll_write_vectored(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        bufs: &[IoSlice<'_>],
+    ) -> Poll<IoResult<usize>> {
+        match self.get_mut() {
+            Self::Plain(stream) => Pin::new(stream).poll_write_vectored(cx, bufs),
+            Self::Tls(tls) => Pin::new(tls.get_mut().unwrap()).poll_write_vectored(cx, bufs),
+        }
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<IoResult<()>> {
+        match self.get_mut() {
+            Self::Plain(stream) => Pin::new(stream).poll_flush(cx),
+            Self::Tls(tls) => Pin::new(tls.get_mut().unwrap()).poll_flush(cx),
+        }
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<IoResult<()>> {
+        match self.get_mut() {
+            Self::Plain(stream) => Pin::new(stream).poll_close(cx),
+            Self::Tls(tls) => Pin::new(tls.get_mut().unwrap()).poll_close(cx),
+        }
+    }
+}
+
+impl<S: StreamOps> StreamOps for ProxyStream<S> {
+    fn set_tcp_notsent_lowat(&self, notsent_lowat: u32) -> IoResult<()> {
+        match self {
+            Self::Plain(stream) => stream.set_tcp_notsent_lowat(notsent_lowat),
+            Self::Tls(tls) => tls.lock().unwrap().set_tcp_notsent_lowat(notsent_lowat),
+        }
+    }
+
+    fn new_handle(&self) -> Box<dyn StreamOps + Send + Unpin> {
+        match self {
+            Self::Plain(stream) => stream.new_handle(),
+            Self::Tls(tls) => tls.lock().unwrap().new_handle(),
+        }
+    }
+}
+
+/// Hybrid TCP provider that supports proxy connections
+#[derive(Clone)]
+pub struct ProxyTcpProvider<T> {
+    inner: T,
+    proxy_config: Arc<ProxyConfig>,
+    target_policy: Arc<RwLock<TargetPolicy>>,
+    target_redaction: Arc<RwLock<TargetRedaction>>,
+    connection_log: Option<Arc<ConnectionLog>>,
+    failover_on_disconnect: bool,
+    listener_idle_timeout: Arc<RwLock<Option<Duration>>>,
+    listener_isolation_map: IsolationMap,
+    listener_streams: ActiveStreams,
+    socks5_auth_methods: Socks5AuthMethods,
+    auth_refresh: Option<Arc<dyn AuthRefreshCallback>>,
+    retry_policy: Option<RetryPolicy>,
+    handshake_deadline: Duration,
+    observer: Option<Arc<dyn ProxyObserver>>,
+}
+
+impl<T> ProxyTcpProvider<T> {
+    /// Create a new proxy TCP provider
+    pub fn new(inner: T, proxy_config: ProxyConfig) -> Self {
+        Self {
+            inner,
+            proxy_config: Arc::new(proxy_config),
+            target_policy: Arc::default(),
+            target_redaction: Arc::new(RwLock::new(DEFAULT_TARGET_REDACTION)),
+            connection_log: None,
+            failover_on_disconnect: false,
+            listener_idle_timeout: Arc::default(),
+            listener_isolation_map: IsolationMap::default(),
+            listener_streams: ActiveStreams::default(),
+            socks5_auth_methods: Socks5AuthMethods::default(),
+            auth_refresh: None,
+            retry_policy: None,
+            handshake_deadline: DEFAULT_HANDSHAKE_DEADLINE,
+            observer: None,
+        }
+    }
+
+    /// Check targets against a shared policy that can be updated at any time
+    pub fn with_target_policy(mut self, target_policy: Arc<RwLock<TargetPolicy>>) -> Self {
+        self.target_policy = target_policy;
+        self
+    }
+
+    /// Redact targets in logs as set in a shared setting that can be updated at any time
+    pub fn with_target_redaction(mut self, target_redaction: Arc<RwLock<TargetRedaction>>) -> Self {
+        self.target_redaction = target_redaction;
+        self
+    }
+
+    /// Record every outgoing connection in `connection_log`
+    pub fn with_connection_log(mut self, connection_log: Arc<ConnectionLog>) -> Self {
+        self.connection_log = Some(connection_log);
+        self
+    }
+
+    /// Report a proxy to its callback when an established stream through it
+    /// fails, not only when connecting fails, so later connections avoid it
+    ///
+    /// The failed stream itself is not retried: it carries a Tor channel,
+    /// which cannot be replayed, and arti opens a new channel as needed.
+    pub fn with_failover_on_disconnect(mut self) -> Self {
+        self.failover_on_disconnect = true;
+        self
+    }
+
+    /// Close accepted streams that carry no traffic for the timeout in a
+    /// shared setting, read when a listener is opened
+    pub fn with_listener_idle_timeout(mut self, idle_timeout: Arc<RwLock<Option<Duration>>>) -> Self {
+        self.listener_idle_timeout = idle_timeout;
+        self
+    }
+
+    /// Map the SOCKS5 usernames of accepted streams to isolation tokens
+    /// through a shared map that can be updated at any time
+    pub fn with_listener_isolation_map(mut self, isolation_map: IsolationMap) -> Self {
+        self.listener_isolation_map = isolation_map;
+        self
+    }
+
+    /// Count the streams accepted by listeners in `streams` while they are open
+    pub fn with_listener_streams(mut self, streams: ActiveStreams) -> Self {
+        self.listener_streams = streams;
+        self
+    }
+
+    /// Offer SOCKS5 proxies the authentication method `method`, run by `handler`
+    ///
+    /// Replaces the built-in handler if `method` is 0x00 or 0x02.
+    ///
+    /// # Panics
+    ///
+    /// If `method` is 0xFF, which means "no acceptable methods".
+    pub fn with_socks5_auth_method(mut self, method: u8, handler: Arc<dyn Socks5AuthMethod>) -> Self {
+        self.socks5_auth_methods.register(method, handler);
+        self
+    }
+
+    /// Offer SOCKS5 proxies only the methods in `order`, most preferred
+    /// first, instead of every known method by method byte
+    ///
+    /// See `Socks5AuthMethods::set_order`.
+    pub fn with_socks5_method_order(mut self, order: &[u8]) -> Self {
+        self.socks5_auth_methods.set_order(order);
+        self
+    }
+
+    /// Ask `auth_refresh` for new credentials when a SOCKS5 proxy rejects
+    /// ours, and retry the handshake once with them
+    pub fn with_auth_refresh(mut self, auth_refresh: Arc<dyn AuthRefreshCallback>) -> Self {
+        self.auth_refresh = Some(auth_refresh);
+        self
+    }
+
+    /// Try connections again as set in `retry_policy` when they fail in a
+    /// way that may be transient
+    ///
+    /// Each retry starts over, so a failover list is walked again.
+    pub fn with_retry_policy(mut self, retry_policy: RetryPolicy) -> Self {
+        self.retry_policy = Some(retry_policy);
+        self
+    }
+
+    /// Give up on a proxy that has not completed its handshake within
+    /// `deadline`, counted from dialling it
+    ///
+    /// This bounds the handshake as a whole, on top of the per-step timeout
+    /// of each config, so a proxy that answers just often enough to keep
+    /// every step alive still cannot stall a connection. Each hop of a chain
+    /// gets its own deadline.
+    pub fn with_handshake_deadline(mut self, deadline: Duration) -> Self {
+        self.handshake_deadline = deadline;
+        self
+    }
+
+    /// Tell `observer` about every connection attempt and how it went
+    pub fn with_observer(mut self, observer: Arc<dyn ProxyObserver>) -> Self {
+        self.observer = Some(observer);
+        self
+    }
+
+    /// Create a direct connection provider (no proxy)
+    pub fn direct(inner: T) -> Self {
+        Self::new(inner, ProxyConfig::Direct)
+    }
+}
+
+impl<T> ProxyTcpProvider<T>
+where
+    T: NetStreamProvider + TlsProvider<<T as NetStreamProvider>::Stream> + Clone + Send + Sync + 'static,
+    T::Stream: Send + Unpin + AsyncRead + AsyncWrite + StreamOps + 'static,
+{
+    /// Connect to `target`, which may be a domain name
+    ///
+    /// `connect`, which arti uses, goes through here with an address, so
+    /// domain targets only come from calling this directly. A domain target
+    /// is passed to the proxy unresolved, so it fails without a proxy rather
+    /// than being resolved locally.
+    pub fn connect_to(&self, proxy_target: ProxyTarget) -> BoxFuture<'static, IoResult<LoggedStream<ProxyStream<T::Stream>>>> {
+        let Some(retry_policy) = self.retry_policy else {
+            return self.connect_once(proxy_target);
+        };
+        let provider = self.clone();
+        retry(retry_policy, move || provider.connect_once(proxy_target.clone())).boxed()
+    }
+
+    /// Connect to `target` without retrying
+    fn connect_once(&self, proxy_target: ProxyTarget) -> BoxFuture<'static, IoResult<LoggedStream<ProxyStream<T::Stream>>>> {
+        let proxy_config = Arc::clone(&self.proxy_config);
+        let target_policy = Arc::clone(&self.target_policy);
+        let redaction = *self.target_redaction.read().unwrap();
+        let connection_log = self.connection_log.clone();
+        let failover_on_disconnect = self.failover_on_disconnect;
+        let socks5_auth_methods = self.socks5_auth_methods.clone();
+        let auth_refresh = self.auth_refresh.clone();
+        let handshake_deadline = self.handshake_deadline;
+        let observer = self.observer.clone();
+        let inner = self.inner.clone();
+
+        async move {
+            let opened_at = SystemTime::now();
+            let opened = Instant::now();
+            // Logged in place of the target itself
+            let target = redact_target(redaction, &proxy_target);
+
+            if !target_policy.read().unwrap().permits_target(&proxy_target) {
+                proxy_log!("✗ Connection to {} blocked by target policy", target);
+                return Err(IoError::new(
+                    ErrorKind::PermissionDenied,
+                    format!("Connection to {} blocked by policy", target),
+                ));
+            }
+
+            // Dynamic callbacks only take addresses; a domain target is shown
+            // to them as the unspecified address with its port
+            let addr = match &proxy_target {
+                ProxyTarget::Addr(addr) => *addr,
+                ProxyTarget::Domain { port, .. } => SocketAddr::new(Ipv4Addr::UNSPECIFIED.into(), *port),
+            };
+            let report_failure = |proxy_addr: &SocketAddr| report_proxy_failure(&proxy_config, proxy_addr);
+
+            // Resolve proxy configuration (handle dynamic case), keeping the
+            // reason whenever the connection ends up going direct
+            let resolved = match proxy_config.as_ref() {
+                _ if addr.ip().is_loopback() => Err(DirectReason::Loopback),
+                ProxyConfig::Dynamic(callback) => {
+                    proxy_log!("Resolving dynamic proxy for target: {}", target);
+                    callback.resolve(&addr)
+                }
+                ProxyConfig::DynamicAsync(callback) => {
+                    proxy_log!("Resolving async dynamic proxy for target: {}", target);
+                    callback.get_proxy(addr).await.ok_or(DirectReason::CallbackReturnedNone)
+                }
+                ProxyConfig::Direct => Err(DirectReason::ProxyDisabled),
+                config => Ok(config.clone()),
+            };
+            let effective_config = match resolved {
+                Ok(ProxyConfig::Direct) => Err(DirectReason::CallbackChoseDirect),
+                resolved => resolved,
+            };
+            match &effective_config {
+                Err(reason) => proxy_log!("Proxy resolved to: Direct connection ({})", reason),
+                Ok(ProxyConfig::Socks5 { proxy_addr, .. }) => proxy_log!("Proxy resolved to: SOCKS5 via {}", proxy_addr),
+                Ok(ProxyConfig::Socks4 { proxy_addr, .. }) => proxy_log!("Proxy resolved to: SOCKS4 via {}", proxy_addr),
+                Ok(ProxyConfig::HttpConnect { proxy_addr, .. }) => proxy_log!("Proxy resolved to: HTTP CONNECT via {}", proxy_addr),
+                Ok(ProxyConfig::HttpsConnect { proxy_addr, .. }) => proxy_log!("Proxy resolved to: HTTPS CONNECT via {}", proxy_addr),
+                Ok(config @ (ProxyConfig::Chain(_) | ProxyConfig::Failover(_))) => {
+                    proxy_log!("Proxy resolved to: {}", hop_via(config))
+                }
+                Ok(_) => {}
+            }
+
+            // A failover list is tried one proxy at a time, anything else
+            // in a single attempt
+            let (failover, attempts): (_, Vec<_>) = match effective_config {
+                Ok(ProxyConfig::Failover(failover)) => {
+                    let order = failover.order();
+                    let attempts = order.iter().map(|&i| Ok(failover.proxies()[i].clone())).collect();
+                    (Some((failover, order)), attempts)
+                }
+                effective_config => (None, vec![effective_config]),
+            };
+            let attempt_count = attempts.len();
+            let mut last_error = None;
+            for (attempt, effective_config) in attempts.into_iter().enumerate() {
+                let inner = inner.clone();
+                let proxy_addr = match &effective_config {
+                    Ok(
+                        ProxyConfig::Socks5 { proxy_addr, .. }
+                        | ProxyConfig::Socks4 { proxy_addr, .. }
+                        | ProxyConfig::HttpConnect { proxy_addr, .. }
+                        | ProxyConfig::HttpsConnect { proxy_addr, .. },
+                    ) => Some(*proxy_addr),
+                    // The stream's own connection goes to the first hop
+                    Ok(ProxyConfig::Chain(hops)) => chain_addrs(hops).ok().map(|addrs| addrs[0]),
+                    _ => None,
+                };
+                let via = match &effective_config {
+                    Err(reason) => format!("direct:{}", reason),
+                    Ok(config) => hop_via(config),
+                };
+                let started = Instant::now();
+                if let Some(observer) = &observer {
+                    observer.on_connect_start(&target, &via);
+                }
+
+                // Connect through proxy or directly
+                let result = match effective_config {
+                    Err(reason) => match &proxy_target {
+                        ProxyTarget::Addr(addr) => {
+                            // Direct connection
+                            proxy_log!("Connecting directly to {} ({})", target, reason);
+                            inner.connect(addr).await.map(ProxyStream::Plain)
+                        }
+                        ProxyTarget::Domain { .. } => {
+                            // Resolving the name here would leak it to the local
+                            // resolver, which is what the name target avoids
+                            proxy_log!("✗ Connection to {} needs a proxy to resolve it ({})", target, reason);
+                            Err(IoError::new(
+                                ErrorKind::Unsupported,
+                                format!("Connection to {} needs a proxy to resolve the name", target),
+                            ))
+                        }
+                    },
+                    Ok(ProxyConfig::Socks5 { proxy_addr, auth, handshake_timeout }) => {
+                        // Connect via SOCKS5
+                        proxy_log!("Connecting to {} via SOCKS5 proxy at {} (auth: {})", 
+                                  target, proxy_addr, auth.is_some());
+                        let mut result = within_deadline(
+                            handshake_deadline,
+                            connect_socks5(
+                                inner.clone(),
+                                proxy_addr,
+                                &proxy_target,
+                                auth.as_ref(),
+                                &socks5_auth_methods,
+                                handshake_timeout,
+                            ),
+                        )
+                        .await;
+                        let rejected = match (&result, auth) {
+                            (Err(e), Some(auth)) if e.kind == ProxyErrorKind::AuthFailed => Some(auth),
+                            _ => None,
+                        };
+                        if let (Some(rejected), Some(auth_refresh)) = (rejected, &auth_refresh) {
+                            proxy_log!("SOCKS5 proxy {} rejected our credentials, asking for new ones", proxy_addr);
+                            // The proxy closes the connection after a failed
+                            // login (RFC 1929), so the retry starts over
+                            if let Some(fresh) = auth_refresh.refresh_auth(proxy_addr, rejected).await {
+                                result = within_deadline(
+                                    handshake_deadline,
+                                    connect_socks5(
+                                        inner,
+                                        proxy_addr,
+                                        &proxy_target,
+                                        Some(&fresh),
+                                        &socks5_auth_methods,
+                                        handshake_timeout,
+                                    ),
+                                )
+                                .await;
+                            }
+                        }
+                        match &result {
+                            Ok(_) => proxy_log!("✓ Successfully connected to {} via SOCKS5 proxy {}", target, proxy_addr),
+                            Err(e) => {
+                                proxy_log!("✗ Failed to connect to {} via SOCKS5 proxy {} ({:?}): {}",
+                                          target, proxy_addr, e.kind, e);
+                                report_failure(&proxy_addr);
+                            }
+                        }
+                        result.map(ProxyStream::Plain).map_err(IoError::from)
+                    }
+                    Ok(ProxyConfig::Socks4 { proxy_addr, user_id, handshake_timeout }) => {
+                        // Connect via SOCKS4/4a
+                        proxy_log!("Connecting to {} via SOCKS4 proxy at {}", target, proxy_addr);
+                        let result = within_deadline(
+                            handshake_deadline,
+                            connect_socks4(inner, proxy_addr, &proxy_target, user_id.as_deref(), handshake_timeout),
+                        )
+                        .await;
+                        match &result {
+                            Ok(_) => proxy_log!("✓ Successfully connected to {} via SOCKS4 proxy {}", target, proxy_addr),
+                            Err(e) => {
+                                proxy_log!("✗ Failed to connect to {} via SOCKS4 proxy {} ({:?}): {}",
+                                          target, proxy_addr, e.kind, e);
+                                report_failure(&proxy_addr);
+                            }
+                        }
+                        result.map(ProxyStream::Plain).map_err(IoError::from)
+                    }
+                    Ok(ProxyConfig::HttpConnect { proxy_addr, auth, handshake_timeout, max_response_len }) => {
+                        // Connect via HTTP CONNECT
+                        proxy_log!("Connecting to {} via HTTP CONNECT proxy at {} (auth: {})", 
+                                  target, proxy_addr, auth.is_some());
+                        let result = within_deadline(
+                            handshake_deadline,
+                            connect_http(
+                                inner,
+                                proxy_addr,
+                                &proxy_target,
+                                auth.as_ref(),
+                                handshake_timeout,
+                                max_response_len,
+                            ),
+                        )
+                        .await;
+                        match &result {
+                            Ok((_, response)) => proxy_log!(
+                                "✓ Successfully connected to {} via HTTP CONNECT proxy {} (headers: {:?})",
+                                target, proxy_addr, response.headers
+                            ),
+                            Err(e) => {
+                                proxy_log!("✗ Failed to connect to {} via HTTP CONNECT proxy {} ({:?}): {}",
+                                          target, proxy_addr, e.kind, e);
+                                report_failure(&proxy_addr);
+                            }
+                        }
+                        result.map(|(stream, _)| ProxyStream::Plain(stream)).map_err(IoError::from)
+                    }
+                    Ok(ProxyConfig::HttpsConnect {
+                        proxy_addr,
+                        server_name,
+                        cert_sha256,
+                        auth,
+                        handshake_timeout,
+                        max_response_len,
+                    }) => {
+                        // Connect via HTTP CONNECT inside TLS
+                        proxy_log!("Connecting to {} via HTTPS CONNECT proxy at {} (auth: {}, pinned: {})",
+                                  target, proxy_addr, auth.is_some(), cert_sha256.is_some());
+                        let result = within_deadline(
+                            handshake_deadline,
+                            connect_https(
+                                inner,
+                                proxy_addr,
+                                &server_name,
+                                cert_sha256.as_ref(),
+                                &proxy_target,
+                                auth.as_ref(),
+                                handshake_timeout,
+                                max_response_len,
+                            ),
+                        )
+                        .await;
+                        match &result {
+                            Ok((_, response)) => proxy_log!(
+                                "✓ Successfully connected to {} via HTTPS CONNECT proxy {} (headers: {:?})",
+                                target, proxy_addr, response.headers
+                            ),
+                            Err(e) => {
+                                proxy_log!("✗ Failed to connect to {} via HTTPS CONNECT proxy {} ({:?}): {}",
+                                          target, proxy_addr, e.kind, e);
+                                report_failure(&proxy_addr);
+                            }
+                        }
+                        result.map(|(stream, _)| stream).map_err(IoError::from)
+                    }
+                    Ok(ProxyConfig::Chain(hops)) => {
+                        // Connect through each proxy in turn
+                        proxy_log!("Connecting to {} via {}", target, via);
+                        match chain_addrs(&hops) {
+                            Err(e) => {
+                                proxy_log!("✗ Invalid proxy chain: {}", e);
+                                Err(e)
+                            }
+                            Ok(addrs) => {
+                                let result = connect_chain(
+                                    inner,
+                                    &hops,
+                                    &addrs,
+                                    &proxy_target,
+                                    &socks5_auth_methods,
+                                    handshake_deadline,
+                                )
+                                .await;
+                                match &result {
+                                    Ok(_) => proxy_log!("✓ Successfully connected to {} via {}", target, via),
+                                    Err((hop_addr, e)) => {
+                                        proxy_log!("✗ Failed to connect to {} via {} at hop {} ({:?}): {}",
+                                                  target, via, hop_addr, e.kind, e);
+                                        report_failure(hop_addr);
+                                    }
+                                }
+                                result.map(ProxyStream::Plain).map_err(|(_, e)| IoError::from(e))
+                            }
+                        }
+                    }
+                    Ok(ProxyConfig::Direct | ProxyConfig::Dynamic(_) | ProxyConfig::DynamicAsync(_)) => {
+                        unreachable!("Direct and dynamic configs should have been resolved")
+                    }
+                    Ok(ProxyConfig::Failover(_)) => unreachable!("ProxyFailover::new() only accepts proxies"),
+                };
+                match (&observer, &result) {
+                    (Some(observer), Ok(_)) => observer.on_connect_success(&target, &via, started.elapsed()),
+                    (Some(observer), Err(e)) => observer.on_connect_error(&target, &via, e),
+                    (None, _) => {}
+                }
+                let stream = match result {
+                    Ok(stream) => stream,
+                    Err(e) => {
+                        if attempt + 1 < attempt_count {
+                            proxy_log!("Trying the next proxy for {} ({} of {})", target, attempt + 2, attempt_count);
+                        }
+                        last_error = Some(e);
+                        continue;
+                    }
+                };
+                if let Some((failover, order)) = &failover {
+                    proxy_log!("✓ Connected to {} through failover proxy {} of {} ({})",
+                              target, order[attempt] + 1, failover.proxies().len(), via);
+                    failover.succeeded(order[attempt]);
+                }
+
+                let stream = LoggedStream::new(stream, connection_log, target, via, opened_at, opened);
+                return Ok(match proxy_addr {
+                    Some(proxy_addr) => {
+                        let on_failure = failover_on_disconnect.then(|| {
+                            let proxy_config = Arc::clone(&proxy_config);
+                            Box::new(move || report_proxy_failure(&proxy_config, &proxy_addr))
+                                as Box<dyn FnOnce() + Send + Sync>
+                        });
+                        stream.with_proxy(ProxyLink::new(proxy_addr, on_failure))
+                    }
+                    None => stream,
+                });
+            }
+            Err(last_error.expect("every connection makes at least one attempt"))
+        }
+        .boxed()
+    }
+}
+
+impl<T> NetStreamProvider for ProxyTcpProvider<T>
+where
+    T: NetStreamProvider + TlsProvider<<T as NetStreamProvider>::Stream> + Clone + Send + Sync + 'static,
+    T::Stream: Send + Unpin + AsyncRead + AsyncWrite + StreamOps + 'static,
+{
+    type Stream = LoggedStream<ProxyStream<T::Stream>>;
+    type Listener = LoggedListener<T::Listener>;
+
+    fn connect<'a, 'b, 'c>(
+        &'a self,
+        addr: &'b SocketAddr,
+    ) -> Pin<Box<dyn Future<Output = IoResult<Self::Stream>> + Send + 'c>>
+    where
+        'a: 'c,
+        'b: 'c,
+        Self: 'c,
+    {
+        self.connect_to(ProxyTarget::Addr(*addr))
+    }
+
+    fn listen<'a, 'b, 'c>(
+        &'a self,
+        addr: &'b SocketAddr,
+    ) -> Pin<Box<dyn Future<Output = IoResult<Self::Listener>> + Send + 'c>>
+    where
+        'a: 'c,
+        'b: 'c,
+        Self: 'c,
+    {
+        let listener = self.inner.listen(addr);
+        let idle_timeout = *self.listener_idle_timeout.read().unwrap();
+        let isolation_map = Arc::clone(&self.listener_isolation_map);
+        let streams = self.listener_streams.clone();
+        async move { Ok(LoggedListener::new(listener.await?, idle_timeout, isolation_map, streams)) }.boxed()
+    }
+}
+
+/// How a proxy config appears in logs and the connection log, e.g.
+/// "socks5:127.0.0.1:1080"
+fn hop_via(config: &ProxyConfig) -> String {
+    match config {
+        ProxyConfig::Socks5 { proxy_addr, .. } => format!("socks5:{}", proxy_addr),
+        ProxyConfig::Socks4 { proxy_addr, .. } => format!("socks4:{}", proxy_addr),
+        ProxyConfig::HttpConnect { proxy_addr, .. } => format!("http:{}", proxy_addr),
+        ProxyConfig::HttpsConnect { proxy_addr, .. } => format!("https:{}", proxy_addr),
+        ProxyConfig::Chain(hops) => {
+            let hops: Vec<String> = hops.iter().map(hop_via).collect();
+            format!("chain:{}", hops.join(">"))
+        }
+        ProxyConfig::Failover(failover) => {
+            let proxies: Vec<String> = failover.proxies().iter().map(hop_via).collect();
+            format!("failover:{}", proxies.join("|"))
+        }
+        ProxyConfig::Direct | ProxyConfig::Dynamic(_) | ProxyConfig::DynamicAsync(_) => "direct".to_string(),
+    }
+}
+
+/// Proxy address of each hop of a chain
+///
+/// Fails for an empty chain and for hops that are not a single proxy, as
+/// there would be nothing to dial or no handshake to reach the next hop with.
+fn chain_addrs(hops: &[ProxyConfig]) -> IoResult<Vec<SocketAddr>> {
+    if hops.is_empty() {
+        return Err(IoError::new(ErrorKind::InvalidInput, "Proxy chain has no hops"));
+    }
+    hops.iter()
+        .map(|hop| match hop {
+            ProxyConfig::Socks5 { proxy_addr, .. }
+            | ProxyConfig::Socks4 { proxy_addr, .. }
+            | ProxyConfig::HttpConnect { proxy_addr, .. } => Ok(*proxy_addr),
+            hop => Err(IoError::new(
+                ErrorKind::InvalidInput,
+                format!("Proxy chain hops must be SOCKS5, SOCKS4 or HTTP CONNECT proxies, not {:?}", hop),
+            )),
+        })
+        .collect()
+}
+
+/// Run `attempt` until it succeeds, fails for good or runs out of attempts
+///
+/// Dropping the future cancels the wait between attempts.
+async fn retry<F, Fut, S>(policy: RetryPolicy, mut attempt: F) -> IoResult<S>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = IoResult<S>>,
+{
+    let mut attempts = 1;
+    loop {
+        match attempt().await {
+            Err(err) if attempts < policy.max_attempts && RetryPolicy::is_retriable(&err) => {
+                let delay = policy.delay(attempts);
+                proxy_log!("Attempt {} failed ({}), retrying in {:?}", attempts, err, delay);
+                tokio::time::sleep(delay).await;
+                attempts += 1;
+            }
+            result => return result,
+        }
+    }
+}
+
+/// Tell a dynamic proxy callback that `proxy_addr` failed
+fn report_proxy_failure(proxy_config: &ProxyConfig, proxy_addr: &SocketAddr) {
+    match proxy_config {
+        ProxyConfig::Dynamic(callback) => callback.report_failure(proxy_addr),
+        ProxyConfig::DynamicAsync(callback) => callback.report_failure(proxy_addr),
+        _ => {}
+    }
+}
+
+/// Connect to target via SOCKS5 proxy
+async fn connect_socks5<T>(
+    provider: T,
+    proxy_addr: SocketAddr,
+    target: &ProxyTarget,
+    auth: Option<&ProxyAuth>,
+    methods: &Socks5AuthMethods,
+    handshake_timeout: Option<Duration>,
+) -> Result<T::Stream, ProxyError>
+where
+    T: NetStreamProvider,
+{
+    // Connect to proxy server
+    let mut stream = provider
+        .connect(&proxy_addr)
+        .await
+        .map_err(|e| ProxyError::unreachable(proxy_addr, e))?;
+
+    let mut timed = StepTimeout::new(&mut stream, handshake_timeout);
+    socks5_handshake(&mut timed, target, auth, methods)
+        .await
+        .map_err(|e| ProxyError::handshake("SOCKS5", e))?;
+    Ok(stream)
+}
+
+/// Authenticate with a SOCKS5 proxy and ask it to connect to `target`
+async fn socks5_handshake<S: Socks5Stream>(
+    stream: &mut S,
+    target: &ProxyTarget,
+    auth: Option<&ProxyAuth>,
+    methods: &Socks5AuthMethods,
+) -> IoResult<()> {
+    // Build the request first, so a bad target fails before any traffic
+    let request = socks5_connect_request(target)?;
+    negotiate_socks5_method(stream, methods, auth).await?;
+
+    write_all(stream, &request).await?;
+    let bound = read_socks5_reply(stream).await?;
+    proxy_log!("SOCKS5 proxy bound {} for the connection", bound);
+    Ok(())
+}
+
+/// Read a SOCKS5 reply, however the proxy splits it up, and return the
+/// address it binds
+async fn read_socks5_reply<S: Socks5Stream>(stream: &mut S) -> IoResult<ProxyTarget> {
+    let mut reply = Socks5ReplyParser::default();
+    let mut buf = [0u8; SOCKS5_MAX_REPLY_FIELD];
+    while reply.needed() > 0 {
+        let field = &mut buf[..reply.needed()];
+        read_exact(stream, field).await?;
+        reply.feed(field)?;
+    }
+    Ok(reply.bound().expect("reply is complete").clone())
+}
+
+/// Longest field of a SOCKS5 reply: a 255-byte domain and its port
+const SOCKS5_MAX_REPLY_FIELD: usize = 255 + 2;
+
+/// Where a SOCKS5 reply is up to
+enum Socks5ReplyState {
+    /// VER REP RSV ATYP
+    Header,
+    /// Length of a domain name bound address
+    DomainLen,
+    /// BND.ADDR BND.PORT, `len` bytes in all
+    Address { atyp: u8, len: usize },
+    /// Reply complete
+    Done(ProxyTarget),
+}
+
+/// Parser for the proxy's reply to a SOCKS5 CONNECT or UDP ASSOCIATE request
+///
+/// Takes the reply in pieces of any size and says how many more bytes it
+/// needs, so the caller never reads past the reply into the tunnelled data.
+/// Each field is checked as soon as it is complete: a version other than 5,
+/// an unknown address type or an empty domain name is a protocol violation,
+/// and a reply code other than success fails with `ConnectRejected`.
+struct Socks5ReplyParser {
+    state: Socks5ReplyState,
+    /// Bytes of the current field received so far
+    field: Vec<u8>,
+}
+
+impl Default for Socks5ReplyParser {
+    fn default() -> Self {
+        Self {
+            state: Socks5ReplyState::Header,
+            field: Vec::with_capacity(4),
+        }
+    }
+}
+
+impl Socks5ReplyParser {
+    /// Bytes still missing from the current field, 0 once the reply is done
+    fn needed(&self) -> usize {
+        let len = match self.state {
+            Socks5ReplyState::Header => 4,
+            Socks5ReplyState::DomainLen => 1,
+            Socks5ReplyState::Address { len, .. } => len,
+            Socks5ReplyState::Done(_) => return 0,
+        };
+        len - self.field.len()
+    }
+
+    /// Take the next `bytes` of the reply
+    fn feed(&mut self, mut bytes: &[u8]) -> IoResult<()> {
+        while !bytes.is_empty() {
+            let needed = self.needed();
+            if needed == 0 {
+                return Err(ProxyError::new(ProxyErrorKind::ProtocolViolation, "SOCKS5 reply is too long").into());
+            }
+            let (now, rest) = bytes.split_at(needed.min(bytes.len()));
+            self.field.extend_from_slice(now);
+            bytes = rest;
+            if self.needed() == 0 {
+                self.finish_field()?;
+            }
+        }
+        Ok(())
+    }
+
+    /// The address the proxy connected from, once the reply is complete
+    fn bound(&self) -> Option<&ProxyTarget> {
+        match &self.state {
+            Socks5ReplyState::Done(bound) => Some(bound),
+            _ => None,
+        }
+    }
+
+    /// Check the field just completed and move on to the next one
+    fn finish_field(&mut self) -> IoResult<()> {
+        let violation = |message: &str| IoError::from(ProxyError::new(ProxyErrorKind::ProtocolViolation, message));
+        let field = std::mem::take(&mut self.field);
+        self.state = match self.state {
+            Socks5ReplyState::Header => {
+                if field[0] != 0x05 {
+                    return Err(violation("Invalid SOCKS5 response"));
+                }
+                if field[1] != 0x00 {
+                    let reply = Socks5Reply::from_code(field[1]);
+                    return Err(ProxyError::new(
+                        ProxyErrorKind::ConnectRejected(reply),
+                        format!("SOCKS5 connection failed: {}", reply),
+                    )
+                    .into());
+                }
+                match field[3] {
+                    0x01 => Socks5ReplyState::Address { atyp: 0x01, len: 4 + 2 },
+                    0x04 => Socks5ReplyState::Address { atyp: 0x04, len: 16 + 2 },
+                    0x03 => Socks5ReplyState::DomainLen,
+                    _ => return Err(violation("Unknown SOCKS5 address type")),
+                }
+            }
+            Socks5ReplyState::DomainLen => match field[0] {
+                0 => return Err(violation("SOCKS5 reply has an empty domain name")),
+                len => Socks5ReplyState::Address { atyp: 0x03, len: len as usize + 2 },
+            },
+            Socks5ReplyState::Address { atyp, len } => {
+                let port = u16::from_be_bytes([field[len - 2], field[len - 1]]);
+                let host = &field[..len - 2];
+                Socks5ReplyState::Done(match atyp {
+                    0x01 => ProxyTarget::Addr(SocketAddr::new(<[u8; 4]>::try_from(host).unwrap().into(), port)),
+                    0x04 => ProxyTarget::Addr(SocketAddr::new(<[u8; 16]>::try_from(host).unwrap().into(), port)),
+                    _ => ProxyTarget::Domain {
+                        host: String::from_utf8_lossy(host).into_owned(),
+                        port,
+                    },
+                })
+            }
+            Socks5ReplyState::Done(_) => unreachable!("needed() is 0 once done"),
+        };
+        Ok(())
+    }
+}
+
+/// Build the SOCKS5 CONNECT request for `target`
+fn socks5_connect_request(target: &ProxyTarget) -> IoResult<Vec<u8>> {
+    let mut request = vec![0x05, 0x01, 0x00]; // Version, CONNECT, reserved
+    socks5_push_address(&mut request, target)?;
+    Ok(request)
+}
+
+/// Append `target` as a SOCKS5 address: type, address and port
+///
+/// Domain names are length-prefixed with a single byte, so an empty name or
+/// one longer than 255 bytes cannot be encoded and is rejected.
+fn socks5_push_address(request: &mut Vec<u8>, target: &ProxyTarget) -> IoResult<()> {
+    let port = match target {
+        ProxyTarget::Addr(addr) => {
+            match addr.ip() {
+                IpAddr::V4(ip) => {
+                    request.push(0x01); // IPv4
+                    request.extend_from_slice(&ip.octets());
+                }
+                IpAddr::V6(ip) => {
+                    request.push(0x04); // IPv6
+                    request.extend_from_slice(&ip.octets());
+                }
+            }
+            addr.port()
+        }
+        ProxyTarget::Domain { host, port } => {
+            let host = host.as_bytes();
+            let host_len = u8::try_from(host.len())
+                .ok()
+                .filter(|&len| len > 0)
+                .ok_or_else(|| {
+                    IoError::new(
+                        ErrorKind::InvalidInput,
+                        "SOCKS5 domain name must be 1 to 255 bytes long",
+                    )
+                })?;
+            request.push(0x03); // Domain name
+            request.push(host_len);
+            request.extend_from_slice(host);
+            *port
+        }
+    };
+    request.extend_from_slice(&port.to_be_bytes());
+    Ok(())
+}
+
+/// Offer the proxy our authentication methods and run the one it selects
+async fn negotiate_socks5_method<S: Socks5Stream>(
+    stream: &mut S,
+    methods: &Socks5AuthMethods,
+    auth: Option<&ProxyAuth>,
+) -> IoResult<()> {
+    let offered = methods.offered(auth);
+    if offered.is_empty() {
+        return Err(ProxyError::new(
+            ProxyErrorKind::UnsupportedMethod,
+            "No SOCKS5 method to offer with the configured method order",
+        )
+        .into());
+    }
+    let mut greeting = vec![0x05, offered.len() as u8]; // Version 5, method count
+    greeting.extend_from_slice(&offered);
+    write_all(stream, &greeting).await?;
+
+    let mut response = [0u8; 2];
+    read_exact(stream, &mut response).await?;
+
+    if response[0] != 0x05 {
+        return Err(ProxyError::new(ProxyErrorKind::ProtocolViolation, "Invalid SOCKS5 version").into());
+    }
+    match response[1] {
+        SOCKS5_NO_ACCEPTABLE_METHODS => Err(no_acceptable_methods(&offered).into()),
+        method if offered.contains(&method) => methods.handlers[&method].authenticate(stream, auth).await,
+        method => Err(ProxyError::new(
+            ProxyErrorKind::ProtocolViolation,
+            format!("SOCKS5 proxy chose method {:#04x}, which was not offered", method),
+        )
+        .into()),
+    }
+}
+
+/// Error for a SOCKS5 proxy that accepts none of the `offered` methods
+///
+/// The proxy does not say which method it wanted. Enterprise proxies that
+/// reject everything else usually want GSSAPI, so say if it was not offered.
+fn no_acceptable_methods(offered: &[u8]) -> ProxyError {
+    let methods: Vec<String> = offered.iter().map(|method| format!("{:#04x}", method)).collect();
+    let mut message = format!("No acceptable SOCKS5 methods among {}", methods.join(", "));
+    if !offered.contains(&SOCKS5_GSSAPI) {
+        message.push_str(" (GSSAPI is not supported)");
+    }
+    ProxyError::new(ProxyErrorKind::UnsupportedMethod, message)
+}
+
+/// Build the RFC 1929 username/password sub-negotiation request
+///
+/// Both fields are length-prefixed with a single byte, so anything longer
+/// than 255 bytes cannot be encoded and is rejected rather than truncated.
+fn socks5_auth_request(auth: &ProxyAuth) -> IoResult<Vec<u8>> {
+    let username = auth.username.as_bytes();
+    let password = auth.password.as_bytes();
+
+    let username_len = u8::try_from(username.len()).map_err(|_| {
+        IoError::new(
+            ErrorKind::InvalidInput,
+            "SOCKS5 username longer than 255 bytes",
+        )
+    })?;
+    let password_len = u8::try_from(password.len()).map_err(|_| {
+        IoError::new(
+            ErrorKind::InvalidInput,
+            "SOCKS5 password longer than 255 bytes",
+        )
+    })?;
+
+    let mut auth_req = Vec::with_capacity(3 + username.len() + password.len());
+    auth_req.push(0x01); // Auth version
+    auth_req.push(username_len);
+    auth_req.extend_from_slice(username);
+    auth_req.push(password_len);
+    auth_req.extend_from_slice(password);
+    Ok(auth_req)
+}
+
+/// UDP association with a SOCKS5 proxy (RFC 1928 section 7)
+///
+/// Datagrams for any target are sent to the proxy's relay, each behind a
+/// header naming the target, and come back the same way. The association
+/// lasts as long as the TCP connection it was requested on, which is kept
+/// here, so dropping this ends it. Fragmentation is not supported: few
+/// proxies implement it, and fragments received are dropped.
+#[cfg(feature = "socks-udp")]
+pub struct Socks5UdpAssociation<S> {
+    _control: S,
+    relay: SocketAddr,
+    socket: tokio::net::UdpSocket,
+}
+
+#[cfg(feature = "socks-udp")]
+impl<S> Socks5UdpAssociation<S> {
+    /// Address of the proxy's UDP relay
+    pub fn relay_addr(&self) -> SocketAddr {
+        self.relay
+    }
+
+    /// Send `payload` to `target` through the relay
+    pub async fn send_to(&self, payload: &[u8], target: &ProxyTarget) -> IoResult<usize> {
+        let datagram = socks5_udp_datagram(target, payload)?;
+        self.socket.send_to(&datagram, self.relay).await?;
+        Ok(payload.len())
+    }
+
+    /// Receive a datagram through the relay, with the address it came from
+    ///
+    /// Datagrams from anywhere but the relay, fragments and malformed
+    /// headers are dropped. A payload longer than `buf` is truncated.
+    pub async fn recv_from(&self, buf: &mut [u8]) -> IoResult<(usize, ProxyTarget)> {
+        let mut datagram = vec![0u8; u16::MAX as usize];
+        loop {
+            let (len, from) = self.socket.recv_from(&mut datagram).await?;
+            if from != self.relay {
+                proxy_log!("Dropping datagram from {}, which is not the SOCKS5 relay", from);
+                continue;
+            }
+            match parse_socks5_udp_datagram(&datagram[..len]) {
+                Ok((source, payload)) => {
+                    let len = payload.len().min(buf.len());
+                    buf[..len].copy_from_slice(&payload[..len]);
+                    return Ok((len, source));
+                }
+                Err(e) => proxy_log!("Dropping datagram from the SOCKS5 relay: {}", e),
+            }
+        }
+    }
+}
+
+/// Ask the SOCKS5 proxy at `proxy_addr` to relay UDP
+///
+/// The TCP connection for the handshake is made with `provider`, like any
+/// proxied connection. The datagrams themselves go from a local UDP socket
+/// straight to the relay, since stream providers only make TCP connections.
+/// A relay address of 0.0.0.0 or :: stands for the proxy's own address.
+#[cfg(feature = "socks-udp")]
+pub async fn associate_socks5_udp<T>(
+    provider: T,
+    proxy_addr: SocketAddr,
+    auth: Option<&ProxyAuth>,
+    methods: &Socks5AuthMethods,
+    handshake_timeout: Option<Duration>,
+) -> IoResult<Socks5UdpAssociation<T::Stream>>
+where
+    T: NetStreamProvider,
+{
+    let mut stream = provider
+        .connect(&proxy_addr)
+        .await
+        .map_err(|e| ProxyError::unreachable(proxy_addr, e))?;
+
+    let mut timed = StepTimeout::new(&mut stream, handshake_timeout);
+    let relay = socks5_udp_associate_handshake(&mut timed, auth, methods)
+        .await
+        .and_then(|bound| udp_relay_addr(&bound, proxy_addr))
+        .map_err(|e| ProxyError::handshake("SOCKS5", e))?;
+
+    let local: IpAddr = match relay {
+        SocketAddr::V4(_) => Ipv4Addr::UNSPECIFIED.into(),
+        SocketAddr::V6(_) => std::net::Ipv6Addr::UNSPECIFIED.into(),
+    };
+    let socket = tokio::net::UdpSocket::bind((local, 0)).await?;
+    proxy_log!("SOCKS5 proxy {} relays UDP at {}", proxy_addr, relay);
+    Ok(Socks5UdpAssociation {
+        _control: stream,
+        relay,
+        socket,
+    })
+}
+
+/// Authenticate with a SOCKS5 proxy and request a UDP association,
+/// returning the relay address from the reply
+#[cfg(feature = "socks-udp")]
+async fn socks5_udp_associate_handshake<S: Socks5Stream>(
+    stream: &mut S,
+    auth: Option<&ProxyAuth>,
+    methods: &Socks5AuthMethods,
+) -> IoResult<ProxyTarget> {
+    negotiate_socks5_method(stream, methods, auth).await?;
+    // Version, UDP ASSOCIATE, reserved, then the address datagrams will
+    // come from; it is not known yet, which RFC 1928 says to send as zeros
+    let mut request = vec![0x05, 0x03, 0x00];
+    let unknown = ProxyTarget::Addr(SocketAddr::new(Ipv4Addr::UNSPECIFIED.into(), 0));
+    socks5_push_address(&mut request, &unknown)?;
+    write_all(stream, &request).await?;
+    // Same reply as for CONNECT, with the relay as the bound address
+    read_socks5_reply(stream).await
+}
+
+/// Where to send datagrams for a relay the proxy reported as `bound`
+#[cfg(feature = "socks-udp")]
+fn udp_relay_addr(bound: &ProxyTarget, proxy_addr: SocketAddr) -> IoResult<SocketAddr> {
+    match bound {
+        ProxyTarget::Addr(addr) if addr.ip().is_unspecified() => {
+            Ok(SocketAddr::new(proxy_addr.ip(), addr.port()))
+        }
+        ProxyTarget::Addr(addr) => Ok(*addr),
+        ProxyTarget::Domain { .. } => Err(IoError::new(
+            ErrorKind::Unsupported,
+            format!("SOCKS5 proxy named its UDP relay {}; only IP addresses are supported", bound),
+        )),
+    }
+}
+
+/// Wrap `payload` for `target` in a SOCKS5 UDP request header
+#[cfg(feature = "socks-udp")]
+fn socks5_udp_datagram(target: &ProxyTarget, payload: &[u8]) -> IoResult<Vec<u8>> {
+    let mut datagram = vec![0x00, 0x00, 0x00]; // Reserved, fragment 0 (whole datagram)
+    socks5_push_address(&mut datagram, target)?;
+    datagram.extend_from_slice(payload);
+    Ok(datagram)
+}
+
+/// Split a datagram from a SOCKS5 relay into its source and payload
+#[cfg(feature = "socks-udp")]
+fn parse_socks5_udp_datagram(datagram: &[u8]) -> IoResult<(ProxyTarget, &[u8])> {
+    let violation = |message: &str| IoError::from(ProxyError::new(ProxyErrorKind::ProtocolViolation, message));
+    let [_, _, frag, atyp, rest @ ..] = datagram else {
+        return Err(violation("SOCKS5 UDP header is truncated"));
+    };
+    if *frag != 0 {
+        return Err(IoError::new(ErrorKind::Unsupported, "Fragmented SOCKS5 datagrams are not supported"));
+    }
+    let (host_len, rest) = match (atyp, rest) {
+        (0x01, _) => (4, rest),
+        (0x04, _) => (16, rest),
+        (0x03, [len, rest @ ..]) if *len > 0 => (*len as usize, rest),
+        (0x03, _) => return Err(violation("SOCKS5 UDP header has an empty domain name")),
+        _ => return Err(violation("Unknown SOCKS5 address type")),
+    };
+    if rest.len() < host_len + 2 {
+        return Err(violation("SOCKS5 UDP header is truncated"));
+    }
+    let (host, rest) = rest.split_at(host_len);
+    let port = u16::from_be_bytes([rest[0], rest[1]]);
+    let source = match atyp {
+        0x01 => ProxyTarget::Addr(SocketAddr::new(<[u8; 4]>::try_from(host).unwrap().into(), port)),
+        0x04 => ProxyTarget::Addr(SocketAddr::new(<[u8; 16]>::try_from(host).unwrap().into(), port)),
+        _ => ProxyTarget::Domain {
+            host: String::from_utf8_lossy(host).into_owned(),
+            port,
+        },
+    };
+    Ok((source, &rest[2..]))
+}
+
+/// Connect to target via SOCKS4 proxy, or SOCKS4a for a domain name
+async fn connect_socks4<T>(
+    provider: T,
+    proxy_addr: SocketAddr,
+    target: &ProxyTarget,
+    user_id: Option<&str>,
+    handshake_timeout: Option<Duration>,
+) -> Result<T::Stream, ProxyError>
+where
+    T: NetStreamProvider,
+{
+    // Connect to proxy server
+    let mut stream = provider
+        .connect(&proxy_addr)
+        .await
+        .map_err(|e| ProxyError::unreachable(proxy_addr, e))?;
+
+    let mut timed = StepTimeout::new(&mut stream, handshake_timeout);
+    socks4_handshake(&mut timed, target, user_id)
+        .await
+        .map_err(|e| ProxyError::handshake("SOCKS4", e))?;
+    Ok(stream)
+}
+
+/// Ask a SOCKS4 proxy to connect to `target` and check its reply
+async fn socks4_handshake<S>(stream: &mut S, target: &ProxyTarget, user_id: Option<&str>) -> IoResult<()>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    let request = socks4_connect_request(target, user_id)?;
+    write_all(stream, &request).await?;
+
+    // VN CD DSTPORT DSTIP; only VN and CD mean anything in a CONNECT reply
+    let mut reply = [0u8; 8];
+    read_exact(stream, &mut reply).await?;
+
+    if reply[0] != 0x00 {
+        return Err(ProxyError::new(ProxyErrorKind::ProtocolViolation, "Invalid SOCKS4 reply version").into());
+    }
+    let (kind, message) = match reply[1] {
+        0x5A => return Ok(()),
+        0x5B => (ProxyErrorKind::HandshakeFailed, "SOCKS4 request rejected or failed"),
+        0x5C => (
+            ProxyErrorKind::AuthFailed,
+            "SOCKS4 request rejected: proxy cannot reach identd on our host",
+        ),
+        0x5D => (
+            ProxyErrorKind::AuthFailed,
+            "SOCKS4 request rejected: identd reports a different user id",
+        ),
+        code => {
+            return Err(ProxyError::new(
+                ProxyErrorKind::ProtocolViolation,
+                format!("Unknown SOCKS4 reply code: {:#04x}", code),
+            )
+            .into())
+        }
+    };
+    Err(ProxyError::new(kind, message).into())
+}
+
+/// Build the SOCKS4 CONNECT request for `target`
+///
+/// The user id and a SOCKS4a domain name are NUL-terminated, so they cannot
+/// contain NUL themselves. SOCKS4 has no IPv6 addresses.
+fn socks4_connect_request(target: &ProxyTarget, user_id: Option<&str>) -> IoResult<Vec<u8>> {
+    let user_id = user_id.unwrap_or_default();
+    if user_id.contains('\0') {
+        return Err(IoError::new(ErrorKind::InvalidInput, "SOCKS4 user id contains NUL"));
+    }
+
+    let mut request = vec![0x04, 0x01]; // Version, CONNECT
+    match target {
+        ProxyTarget::Addr(addr) => {
+            let IpAddr::V4(ip) = addr.ip() else {
+                return Err(IoError::new(ErrorKind::InvalidInput, "SOCKS4 cannot connect to IPv6 addresses"));
+            };
+            request.extend_from_slice(&addr.port().to_be_bytes());
+            request.extend_from_slice(&ip.octets());
+            request.extend_from_slice(user_id.as_bytes());
+            request.push(0x00);
+        }
+        ProxyTarget::Domain { host, port } => {
+            if host.is_empty() || host.contains('\0') {
+                return Err(IoError::new(ErrorKind::InvalidInput, "Invalid SOCKS4a domain name"));
+            }
+            request.extend_from_slice(&port.to_be_bytes());
+            request.extend_from_slice(&[0, 0, 0, 1]); // 0.0.0.x: the name follows
+            request.extend_from_slice(user_id.as_bytes());
+            request.push(0x00);
+            request.extend_from_slice(host.as_bytes());
+            request.push(0x00);
+        }
+    }
+    Ok(request)
+}
+
+/// Connect to target via HTTP CONNECT proxy
+///
+/// Returns the stream together with the proxy's answer to CONNECT. If the
+/// proxy asks for credentials and closes the connection, they are sent on a
+/// second one.
+async fn connect_http<T>(
+    provider: T,
+    proxy_addr: SocketAddr,
+    target: &ProxyTarget,
+    auth: Option<&ProxyAuth>,
+    handshake_timeout: Option<Duration>,
+    max_response_len: Option<usize>,
+) -> Result<(T::Stream, HttpConnectResponse), ProxyError>
+where
+    T: NetStreamProvider,
+{
+    let mut authorization = None;
+    loop {
+        // Connect to proxy server
+        let mut stream = provider
+            .connect(&proxy_addr)
+            .await
+            .map_err(|e| ProxyError::unreachable(proxy_addr, e))?;
+
+        let mut timed = StepTimeout::new(&mut stream, handshake_timeout);
+        match http_exchange(&mut timed, target, auth, authorization.take(), max_response_len)
+            .await
+            .map_err(|e| ProxyError::handshake("HTTP CONNECT", e))?
+        {
+            HttpExchange::Connected(response) => return Ok((stream, response)),
+            HttpExchange::Redial(answer) => {
+                proxy_log!("HTTP proxy {} closed the connection, sending credentials on a new one", proxy_addr);
+                authorization = Some(answer);
+            }
+        }
+    }
+}
+
+/// Connect to `target` through the HTTPS proxy at `proxy_addr`
+///
+/// Like connect_http(), with the CONNECT request sent inside a TLS session.
+/// The session is set up by native-tls rather than the runtime's TLS
+/// provider, which is built for relays and never validates certificates.
+/// See `ProxyConfig::HttpsConnect` for how the certificate is checked.
+#[cfg(feature = "https-proxy")]
+#[allow(clippy::too_many_arguments)]
+async fn connect_https<T>(
+    provider: T,
+    proxy_addr: SocketAddr,
+    server_name: &str,
+    cert_sha256: Option<&[u8; 32]>,
+    target: &ProxyTarget,
+    auth: Option<&ProxyAuth>,
+    handshake_timeout: Option<Duration>,
+    max_response_len: Option<usize>,
+) -> Result<(ProxyStream<T::Stream>, HttpConnectResponse), ProxyError>
+where
+    T: NetStreamProvider,
+    T::Stream: AsyncRead + AsyncWrite + StreamOps + Unpin + Send + 'static,
+{
+    let connector = match cert_sha256 {
+        // The pin replaces CA and name checks; SNI is only sent if a name
+        // was given
+        Some(_) => async_native_tls::TlsConnector::new()
+            .danger_accept_invalid_certs(true)
+            .danger_accept_invalid_hostnames(true)
+            .use_sni(!server_name.is_empty()),
+        None if server_name.is_empty() => {
+            return Err(ProxyError::new(
+                ProxyErrorKind::HandshakeFailed,
+                "HTTPS proxy needs a server name or a certificate pin",
+            ))
+        }
+        None => async_native_tls::TlsConnector::new(),
+    };
+    let domain = match server_name {
+        "" => proxy_addr.ip().to_string(),
+        name => name.to_string(),
+    };
+
+    let mut authorization = None;
+    loop {
+        // Connect to proxy server
+        let stream = provider
+            .connect(&proxy_addr)
+            .await
+            .map_err(|e| ProxyError::unreachable(proxy_addr, e))?;
+
+        let timeout = handshake_timeout.unwrap_or(DEFAULT_HANDSHAKE_TIMEOUT);
+        let negotiate = connector.connect(domain.as_str(), stream);
+        let tls = match tokio::time::timeout(timeout, negotiate).await {
+            Ok(result) => result.map_err(|e| IoError::new(ErrorKind::InvalidData, e)),
+            Err(_) => Err(IoError::new(
+                ErrorKind::TimedOut,
+                format!("Proxy did not finish TLS within {} seconds", timeout.as_secs()),
+            )),
+        }
+        .and_then(|tls| match cert_sha256 {
+            Some(pin) => {
+                let cert = tls
+                    .peer_certificate()
+                    .and_then(|cert| cert.map(|cert| cert.to_der()).transpose())
+                    .map_err(|e| IoError::new(ErrorKind::InvalidData, e))?;
+                check_cert_pin(cert.as_deref(), pin).map(|()| tls)
+            }
+            None => Ok(tls),
+        })
+        .map_err(|e| ProxyError::handshake("TLS", e))?;
+        let mut tls = HttpsTunnel(tls);
+
+        let mut timed = StepTimeout::new(&mut tls, handshake_timeout);
+        match http_exchange(&mut timed, target, auth, authorization.take(), max_response_len)
+            .await
+            .map_err(|e| ProxyError::handshake("HTTPS CONNECT", e))?
+        {
+            HttpExchange::Connected(response) => {
+                return Ok((ProxyStream::Tls(Mutex::new(Box::new(tls))), response))
+            }
+            HttpExchange::Redial(answer) => {
+                proxy_log!("HTTPS proxy {} closed the connection, sending credentials on a new one", proxy_addr);
+                authorization = Some(answer);
+            }
+        }
+    }
+}
+
+/// TLS session to an HTTPS proxy, with the socket options of the TCP
+/// stream underneath
+#[cfg(feature = "https-proxy")]
+struct HttpsTunnel<S>(async_native_tls::TlsStream<S>);
+
+#[cfg(feature = "https-proxy")]
+impl<S: AsyncRead + AsyncWrite + Unpin> AsyncRead for HttpsTunnel<S> {
+    fn poll_read(self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &mut [u8]) -> Poll<IoResult<usize>> {
+        Pin::new(&mut self.get_mut().0).poll_read(cx, buf)
+    }
+}
+
+#[cfg(feature = "https-proxy")]
+impl<S: AsyncRead + AsyncWrite + Unpin> AsyncWrite for HttpsTunnel<S> {
+    fn poll_write(self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &[u8]) -> Poll<IoResult<usize>> {
+        Pin::new(&mut self.get_mut().0).poll_write(cx, buf)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<IoResult<()>> {
+        Pin::new(&mut self.get_mut().0).poll_flush(cx)
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<IoResult<()>> {
+        Pin::new(&mut self.get_mut().0).poll_close(cx)
+    }
+}
+
+#[cfg(feature = "https-proxy")]
+impl<S: AsyncRead + AsyncWrite + StreamOps + Unpin> StreamOps for HttpsTunnel<S> {
+    fn set_tcp_notsent_lowat(&self, notsent_lowat: u32) -> IoResult<()> {
+        self.0.get_ref().set_tcp_notsent_lowat(notsent_lowat)
+    }
+
+    fn new_handle(&self) -> Box<dyn StreamOps + Send + Unpin> {
+        self.0.get_ref().new_handle()
+    }
+}
+
+/// Without the `https-proxy` feature, HTTPS proxies fail to connect
+#[cfg(not(feature = "https-proxy"))]
+#[allow(clippy::too_many_arguments)]
+async fn connect_https<T>(
+    _provider: T,
+    _proxy_addr: SocketAddr,
+    _server_name: &str,
+    _cert_sha256: Option<&[u8; 32]>,
+    _target: &ProxyTarget,
+    _auth: Option<&ProxyAuth>,
+    _handshake_timeout: Option<Duration>,
+    _max_response_len: Option<usize>,
+) -> Result<(ProxyStream<T::Stream>, HttpConnectResponse), ProxyError>
+where
+    T: NetStreamProvider,
+{
+    Err(ProxyError {
+        kind: ProxyErrorKind::HandshakeFailed,
+        message: "HTTPS proxies need the https-proxy feature".to_string(),
+        source: Some(IoError::from(ErrorKind::Unsupported)),
+    })
+}
+
+/// Check the certificate an HTTPS proxy presented against `pin`
+#[cfg(feature = "https-proxy")]
+fn check_cert_pin(cert: Option<&[u8]>, pin: &[u8; 32]) -> IoResult<()> {
+    let cert = cert.ok_or_else(|| IoError::new(ErrorKind::InvalidData, "Proxy presented no certificate"))?;
+    if Sha256::digest(cert).as_slice() != pin {
+        return Err(IoError::new(
+            ErrorKind::InvalidData,
+            "Proxy certificate does not match the pinned SHA-256",
+        ));
+    }
+    Ok(())
+}
+
+/// Connect to `target` through the proxies `hops`, at `addrs`
+///
+/// Dials the first hop, then runs each hop's handshake over the stream so
+/// far: every hop but the last is asked to connect to the next one, and the
+/// last one to `target`. `addrs` comes from chain_addrs(). Credentials are
+/// not refreshed on rejection as they are for a single SOCKS5 proxy. Each
+/// hop's handshake, and dialling the first, gets `deadline`. The first
+/// failure ends the chain; its error names the hop, and the address of that
+/// hop is returned with it.
+async fn connect_chain<T>(
+    provider: T,
+    hops: &[ProxyConfig],
+    addrs: &[SocketAddr],
+    target: &ProxyTarget,
+    methods: &Socks5AuthMethods,
+    deadline: Duration,
+) -> Result<T::Stream, (SocketAddr, ProxyError)>
+where
+    T: NetStreamProvider,
+    T::Stream: Send + Unpin,
+{
+    let failed = |i: usize, e: ProxyError| (addrs[i], e.at_hop(i, hops.len()));
+    let dial = async {
+        provider
+            .connect(&addrs[0])
+            .await
+            .map_err(|e| ProxyError::unreachable(addrs[0], e))
+    };
+    let mut stream = within_deadline(deadline, dial).await.map_err(|e| failed(0, e))?;
+
+    for (i, hop) in hops.iter().enumerate() {
+        let next = match addrs.get(i + 1) {
+            Some(addr) => ProxyTarget::Addr(*addr),
+            None => target.clone(),
+        };
+        within_deadline(deadline, hop_handshake(&mut stream, hop, &next, methods))
+            .await
+            .map_err(|e| failed(i, e))?;
+    }
+    Ok(stream)
+}
+
+/// Fail with a `Timeout` error if `handshake` takes longer than `deadline`
+async fn within_deadline<F, S>(deadline: Duration, handshake: F) -> Result<S, ProxyError>
+where
+    F: Future<Output = Result<S, ProxyError>>,
+{
+    tokio::time::timeout(deadline, handshake).await.unwrap_or_else(|_| {
+        Err(ProxyError::new(
+            ProxyErrorKind::Timeout,
+            format!("Proxy handshake did not finish within {} seconds", deadline.as_secs()),
+        ))
+    })
+}
+
+/// Run the handshake of the single proxy `hop` over `stream`, which already
+/// reaches it, asking it to connect to `next`
+async fn hop_handshake<S: Socks5Stream>(
+    stream: &mut S,
+    hop: &ProxyConfig,
+    next: &ProxyTarget,
+    methods: &Socks5AuthMethods,
+) -> Result<(), ProxyError> {
+    match hop {
+        ProxyConfig::Socks5 { auth, handshake_timeout, .. } => {
+            let mut timed = StepTimeout::new(stream, *handshake_timeout);
+            socks5_handshake(&mut timed, next, auth.as_ref(), methods)
+                .await
+                .map_err(|e| ProxyError::handshake("SOCKS5", e))
+        }
+        ProxyConfig::Socks4 { user_id, handshake_timeout, .. } => {
+            let mut timed = StepTimeout::new(stream, *handshake_timeout);
+            socks4_handshake(&mut timed, next, user_id.as_deref())
+                .await
+                .map_err(|e| ProxyError::handshake("SOCKS4", e))
+        }
+        ProxyConfig::HttpConnect { auth, handshake_timeout, max_response_len, .. } => {
+            let mut timed = StepTimeout::new(stream, *handshake_timeout);
+            http_handshake(&mut timed, next, auth.as_ref(), *max_response_len)
+                .await
+                .map(|_| ())
+                .map_err(|e| ProxyError::handshake("HTTP CONNECT", e))
+        }
+        _ => unreachable!("chain_addrs() only accepts single proxies"),
+    }
+}
+
+/// Status and header fields of an HTTP proxy's answer to CONNECT
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HttpConnectResponse {
+    pub status: u16,
+    /// Header fields by lowercase name; repeated fields are joined with ", "
+    pub headers: BTreeMap<String, String>,
+}
+
+impl HttpConnectResponse {
+    /// Parse a response head, the status line and header fields before the
+    /// blank line
+    ///
+    /// Header lines without a colon are skipped rather than rejected, as
+    /// they carry nothing we act on.
+    fn parse(head: &str) -> IoResult<Self> {
+        let mut lines = head.split("\r\n");
+        let status = lines
+            .next()
+            .and_then(|line| {
+                let mut parts = line.splitn(3, ' ');
+                let version = parts.next()?;
+                let status = parts.next()?;
+                (version.starts_with("HTTP/1.") && status.len() == 3).then_some(status)?.parse().ok()
+            })
+            .ok_or_else(|| ProxyError::new(ProxyErrorKind::ProtocolViolation, "Invalid HTTP status line"))?;
+
+        let mut headers = BTreeMap::<String, String>::new();
+        for line in lines.filter(|line| !line.is_empty()) {
+            let Some((name, value)) = line.split_once(':') else {
+                proxy_log!("Skipping malformed HTTP header line: {}", sanitize_status_line(line));
+                continue;
+            };
+            let (name, value) = (name.trim().to_ascii_lowercase(), value.trim());
+            headers
+                .entry(name)
+                .and_modify(|joined| {
+                    joined.push_str(", ");
+                    joined.push_str(value);
+                })
+                .or_insert_with(|| value.to_string());
+        }
+        Ok(Self { status, headers })
+    }
+
+    /// The challenges in `Proxy-Authenticate`, if any
+    pub fn auth_challenges(&self) -> Vec<AuthChallenge> {
+        self.headers
+            .get("proxy-authenticate")
+            .map_or_else(Vec::new, |value| http_auth::parse_challenges(value))
+    }
+
+    /// Whether the proxy said it closes the connection after this response,
+    /// in `Connection` or the older `Proxy-Connection`
+    pub fn closes_connection(&self) -> bool {
+        ["connection", "proxy-connection"].iter().any(|name| {
+            self.headers
+                .get(*name)
+                .is_some_and(|value| value.split(',').any(|token| token.trim().eq_ignore_ascii_case("close")))
+        })
+    }
+}
+
+/// How a CONNECT exchange with an HTTP proxy ended
+enum HttpExchange {
+    Connected(HttpConnectResponse),
+    /// The proxy asked for credentials but will not take them on this
+    /// connection, so they have to be sent on a new one: this
+    /// `Proxy-Authorization` value
+    Redial(String),
+}
+
+/// Ask an HTTP proxy to connect to `target` and wait for its answer
+///
+/// `authorization` is sent right away as `Proxy-Authorization` if given.
+/// Otherwise credentials are only sent once the proxy asks for them with a
+/// 407, answering its Digest or Basic challenge (see `http_auth`). The
+/// retry goes on the same connection when the proxy keeps it open;
+/// otherwise the result is `Redial`. A 407 that cannot be answered, or one
+/// to the credentials, fails with `AuthFailed`, and a 407 offering only
+/// other schemes or Digest algorithms with `UnsupportedMethod`.
+///
+/// Fails unless the proxy finally answers 200. A 200 with `Connection:
+/// close` fails too: the proxy is about to close the connection the tunnel
+/// would run on. A response longer than `max_response_len` (or
+/// DEFAULT_MAX_HTTP_RESPONSE_LEN) fails with `ResponseTooLarge`.
+async fn http_exchange<S>(
+    stream: &mut S,
+    target: &ProxyTarget,
+    auth: Option<&ProxyAuth>,
+    mut authorization: Option<String>,
+    max_response_len: Option<usize>,
+) -> IoResult<HttpExchange>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    let max_response_len = max_response_len.unwrap_or(DEFAULT_MAX_HTTP_RESPONSE_LEN);
+    loop {
+        write_all(stream, connect_request(target, authorization.as_deref()).as_bytes()).await?;
+        let head = read_http_head(stream, max_response_len).await?;
+        let response = HttpConnectResponse::parse(&head)?;
+        let status_line = sanitize_status_line(head.lines().next().unwrap_or(""));
+
+        if response.status == 407 {
+            if authorization.is_some() {
+                return Err(ProxyError::new(
+                    ProxyErrorKind::AuthFailed,
+                    format!("HTTP proxy rejected the credentials: {}", status_line),
+                )
+                .into());
+            }
+            let Some(auth) = auth else {
+                return Err(ProxyError::new(
+                    ProxyErrorKind::AuthFailed,
+                    format!("HTTP proxy requires authentication: {}", status_line),
+                )
+                .into());
+            };
+            let answer = http_auth::authorization(&response.auth_challenges(), auth, "CONNECT", &authority(target))?;
+            if !skip_http_body(stream, &response, max_response_len).await? {
+                return Ok(HttpExchange::Redial(answer));
+            }
+            proxy_log!("HTTP proxy asked for credentials, retrying CONNECT with them");
+            authorization = Some(answer);
+            continue;
+        }
+
+        if response.status != 200 {
+            return Err(ProxyError::new(
+                ProxyErrorKind::HttpRejected(response.status),
+                format!("{}: {}", http_status_meaning(response.status), status_line),
+            )
+            .into());
+        }
+        if response.closes_connection() {
+            return Err(ProxyError::new(
+                ProxyErrorKind::HandshakeFailed,
+                "HTTP proxy accepted CONNECT but is closing the connection",
+            )
+            .into());
+        }
+        return Ok(HttpExchange::Connected(response));
+    }
+}
+
+/// http_exchange() on a connection that cannot be replaced, as for a hop of
+/// a chain: a `Redial` fails with `AuthFailed`
+async fn http_handshake<S>(
+    stream: &mut S,
+    target: &ProxyTarget,
+    auth: Option<&ProxyAuth>,
+    max_response_len: Option<usize>,
+) -> IoResult<HttpConnectResponse>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    match http_exchange(stream, target, auth, None, max_response_len).await? {
+        HttpExchange::Connected(response) => Ok(response),
+        HttpExchange::Redial(_) => Err(ProxyError::new(
+            ProxyErrorKind::AuthFailed,
+            "HTTP proxy closed the connection after asking for credentials",
+        )
+        .into()),
+    }
+}
+
+/// `target` as the authority of a CONNECT request, "host:port"
+fn authority(target: &ProxyTarget) -> String {
+    // IPv6 literals need brackets (RFC 3986)
+    match target {
+        ProxyTarget::Addr(addr) => match addr.ip() {
+            IpAddr::V4(ip) => format!("{}:{}", ip, addr.port()),
+            IpAddr::V6(ip) => format!("[{}]:{}", ip, addr.port()),
+        },
+        ProxyTarget::Domain { host, port } => format!("{}:{}", host, port),
+    }
+}
+
+/// CONNECT request for `target`, with a `Proxy-Authorization` value if given
+fn connect_request(target: &ProxyTarget, authorization: Option<&str>) -> String {
+    let authority = authority(target);
+    let mut request = format!("CONNECT {} HTTP/1.1\r\nHost: {}\r\n", authority, authority);
+    if let Some(authorization) = authorization {
+        request.push_str(&format!("Proxy-Authorization: {}\r\n", authorization));
+    }
+    request.push_str("\r\n");
+    request
+}
+
+/// Read a response head, up to and including the blank line
+///
+/// Fails with `ResponseTooLarge` once it gets to `max_len` bytes without
+/// ending.
+async fn read_http_head<S>(stream: &mut S, max_len: usize) -> IoResult<String>
+where
+    S: AsyncRead + Unpin,
+{
+    let mut response = Vec::new();
+    let mut buf = [0u8; 1];
+
+    // Read until \r\n\r\n
+    loop {
+        read_exact(stream, &mut buf).await?;
+        response.push(buf[0]);
+
+        if response.len() >= 4
+            && &response[response.len() - 4..] == b"\r\n\r\n"
+        {
+            return Ok(String::from_utf8_lossy(&response).into_owned());
+        }
+
+        if response.len() >= max_len {
+            return Err(ProxyError::new(
+                ProxyErrorKind::ResponseTooLarge,
+                format!("HTTP response is longer than {} bytes", max_len),
+            )
+            .into());
+        }
+    }
+}
+
+/// Read past the body of `response`, so the connection can carry another
+/// request
+///
+/// Returns false, having read nothing, if the connection cannot be reused:
+/// the proxy closes it, the body is chunked or runs until the close, or it
+/// is longer than `max_len`.
+async fn skip_http_body<S>(stream: &mut S, response: &HttpConnectResponse, max_len: usize) -> IoResult<bool>
+where
+    S: AsyncRead + Unpin,
+{
+    if response.closes_connection() || response.headers.contains_key("transfer-encoding") {
+        return Ok(false);
+    }
+    let Some(len) = response.headers.get("content-length").and_then(|len| len.parse::<usize>().ok()) else {
+        return Ok(false);
+    };
+    if len > max_len {
+        return Ok(false);
+    }
+    let mut body = vec![0u8; len];
+    read_exact(stream, &mut body).await?;
+    Ok(true)
+}
+
+/// Longest proxy status line quoted in errors, in characters
+const MAX_STATUS_LINE_LEN: usize = 256;
+
+/// Make a status line from an untrusted proxy safe to put in errors and logs:
+/// control characters are replaced and the line is truncated
+fn sanitize_status_line(line: &str) -> String {
+    let mut sanitized: String = line
+        .chars()
+        .take(MAX_STATUS_LINE_LEN)
+        .map(|c| if c.is_control() { '?' } else { c })
+        .collect();
+    if line.chars().nth(MAX_STATUS_LINE_LEN).is_some() {
+        sanitized.push_str("...");
+    }
+    sanitized
+}
+
+/// Stream wrapper that fails a handshake step the proxy leaves unanswered
+///
+/// Each read or write that has to wait gets the timeout; one that completes
+/// starts the next step afresh. The timer only starts when a step waits.
+struct StepTimeout<'a, S: ?Sized> {
+    inner: &'a mut S,
+    timeout: Duration,
+    sleep: Option<Pin<Box<tokio::time::Sleep>>>,
+}
+
+impl<'a, S: ?Sized> StepTimeout<'a, S> {
+    fn new(inner: &'a mut S, timeout: Option<Duration>) -> Self {
+        Self {
+            inner,
+            timeout: timeout.unwrap_or(DEFAULT_HANDSHAKE_TIMEOUT),
+            sleep: None,
+        }
+    }
+
+    /// Pass on a finished step, or time out a waiting one
+    fn poll_step<R>(&mut self, cx: &mut Context<'_>, result: Poll<IoResult<R>>) -> Poll<IoResult<R>> {
+        if result.is_ready() {
+            self.sleep = None;
+            return result;
+        }
+        let timeout = self.timeout;
+        let sleep = self.sleep.get_or_insert_with(|| Box::pin(tokio::time::sleep(timeout)));
+        ready!(sleep.as_mut().poll(cx));
+        self.sleep = None;
+        Poll::Ready(Err(IoError::new(
+            ErrorKind::TimedOut,
+            format!("Proxy did not answer within {} seconds", timeout.as_secs()),
+        )))
+    }
+}
+
+impl<S: AsyncRead + Unpin + ?Sized> AsyncRead for StepTimeout<'_, S> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<IoResult<usize>> {
+        let this = self.get_mut();
+        let result = Pin::new(&mut *this.inner).poll_read(cx, buf);
+        this.poll_step(cx, result)
+    }
+}
+
+impl<S: AsyncWrite + Unpin + ?Sized> AsyncWrite for StepTimeout<'_, S> {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<IoResult<usize>> {
+        let this = self.get_mut();
+        let result = Pin::new(&mut *this.inner).poll_write(cx, buf);
+        this.poll_step(cx, result)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<IoResult<()>> {
+        let this = self.get_mut();
+        let result = Pin::new(&mut *this.inner).poll_flush(cx);
+        this.poll_step(cx, result)
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<IoResult<()>> {
+        let this = self.get_mut();
+        let result = Pin::new(&mut *this.inner).poll_close(cx);
+        this.poll_step(cx, result)
+    }
+}
+
+/// Helper to write all bytes
+async fn write_all<T>(stream: &mut T, buf: &[u8]) -> IoResult<()>
+where
+    T: AsyncWrite + Unpin + ?Sized,
+{
+    use futures::io::AsyncWriteExt;
+    stream.write_all(buf).await
+}
+
+/// Helper to read exact bytes
+async fn read_exact<T>(stream: &mut T, buf: &mut [u8]) -> IoResult<()>
+where
+    T: AsyncRead + Unpin + ?Sized,
+{
+    use futures::io::AsyncReadExt;
+    stream.read_exact(buf).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_proxy_config_creation() {
+        let config = ProxyConfig::Socks5 {
+            proxy_addr: "127.0.0.1:1080".parse().unwrap(),
+            auth: None,
+            handshake_timeout: None,
+        };
+
+        match config {
             ProxyConfig::Socks5 { proxy_addr, .. } => {
                 assert_eq!(proxy_addr.port(), 1080);
             }
-            _ => panic!("Wrong config type"),
+            _ => panic!("Wrong config type"),
+        }
+    }
+    #[test]
+    fn test_socks5_auth_request_max_length() {
+        let auth = ProxyAuth {
+            username: "u".repeat(255),
+            password: "p".repeat(255),
+        };
+        let req = socks5_auth_request(&auth).unwrap();
+
+        assert_eq!(req.len(), 1 + 1 + 255 + 1 + 255);
+        assert_eq!(req[0], 0x01);
+        assert_eq!(req[1], 255);
+        assert!(req[2..257].iter().all(|&b| b == b'u'));
+        assert_eq!(req[257], 255);
+        assert!(req[258..].iter().all(|&b| b == b'p'));
+    }
+
+    #[test]
+    fn test_socks5_auth_request_too_long() {
+        let auth = ProxyAuth {
+            username: "u".repeat(256),
+            password: "p".to_string(),
+        };
+        let err = socks5_auth_request(&auth).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidInput);
+
+        let auth = ProxyAuth {
+            username: "u".to_string(),
+            password: "p".repeat(256),
+        };
+        let err = socks5_auth_request(&auth).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidInput);
+    }
+
+    #[test]
+    fn test_socks5_connect_request() {
+        let target = ProxyTarget::Addr("203.0.113.5:9001".parse().unwrap());
+        let req = socks5_connect_request(&target).unwrap();
+        assert_eq!(req, [0x05, 0x01, 0x00, 0x01, 203, 0, 113, 5, 0x23, 0x29]);
+
+        let target = ProxyTarget::Domain {
+            host: "example.com".to_string(),
+            port: 443,
+        };
+        let req = socks5_connect_request(&target).unwrap();
+        assert_eq!(req[..5], [0x05, 0x01, 0x00, 0x03, 11]);
+        assert_eq!(&req[5..16], b"example.com");
+        assert_eq!(req[16..], [0x01, 0xBB]);
+
+        let target = ProxyTarget::Domain {
+            host: "h".repeat(255),
+            port: 443,
+        };
+        assert_eq!(socks5_connect_request(&target).unwrap().len(), 5 + 255 + 2);
+    }
+
+    #[test]
+    fn test_socks4_connect_request() {
+        let target = ProxyTarget::Addr("203.0.113.5:9001".parse().unwrap());
+        let req = socks4_connect_request(&target, Some("bob")).unwrap();
+        assert_eq!(req, [0x04, 0x01, 0x23, 0x29, 203, 0, 113, 5, b'b', b'o', b'b', 0x00]);
+
+        // SOCKS4a
+        let target = ProxyTarget::Domain {
+            host: "example.com".to_string(),
+            port: 443,
+        };
+        let req = socks4_connect_request(&target, None).unwrap();
+        assert_eq!(req[..9], [0x04, 0x01, 0x01, 0xBB, 0, 0, 0, 1, 0x00]);
+        assert_eq!(&req[9..], b"example.com\0");
+
+        let target = ProxyTarget::Addr("[2001:db8::1]:443".parse().unwrap());
+        let err = socks4_connect_request(&target, None).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidInput);
+        let target = ProxyTarget::Addr("203.0.113.5:9001".parse().unwrap());
+        let err = socks4_connect_request(&target, Some("b\0b")).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidInput);
+    }
+
+    #[test]
+    fn test_socks4_handshake_replies() {
+        let target = ProxyTarget::Addr("203.0.113.5:9001".parse().unwrap());
+        let handshake = |reply: &[u8]| {
+            let mut stream = MockStream::new(reply);
+            let result = futures::executor::block_on(socks4_handshake(&mut stream, &target, None));
+            (result, stream.output)
+        };
+
+        let (result, written) = handshake(&[0x00, 0x5A, 0, 0, 0, 0, 0, 0]);
+        result.unwrap();
+        assert_eq!(written, socks4_connect_request(&target, None).unwrap());
+
+        let (result, _) = handshake(&[0x00, 0x5B, 0, 0, 0, 0, 0, 0]);
+        assert_eq!(ProxyError::from_io(&result.unwrap_err()).unwrap().kind, ProxyErrorKind::HandshakeFailed);
+        let (result, _) = handshake(&[0x00, 0x5C, 0, 0, 0, 0, 0, 0]);
+        let err = result.unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::PermissionDenied);
+        assert_eq!(err.to_string(), "SOCKS4 request rejected: proxy cannot reach identd on our host");
+        let (result, _) = handshake(&[0x00, 0x5D, 0, 0, 0, 0, 0, 0]);
+        assert_eq!(ProxyError::from_io(&result.unwrap_err()).unwrap().kind, ProxyErrorKind::AuthFailed);
+        let (result, _) = handshake(&[0x00, 0x5E, 0, 0, 0, 0, 0, 0]);
+        let err = result.unwrap_err();
+        assert_eq!(ProxyError::from_io(&err).unwrap().kind, ProxyErrorKind::ProtocolViolation);
+        assert_eq!(err.to_string(), "Unknown SOCKS4 reply code: 0x5e");
+        let (result, _) = handshake(&[0x05, 0x5A, 0, 0, 0, 0, 0, 0]);
+        assert_eq!(ProxyError::from_io(&result.unwrap_err()).unwrap().kind, ProxyErrorKind::ProtocolViolation);
+    }
+
+    #[test]
+    fn test_socks5_connect_request_bad_domain() {
+        for host in ["".to_string(), "h".repeat(256)] {
+            let target = ProxyTarget::Domain { host, port: 443 };
+            let err = socks5_connect_request(&target).unwrap_err();
+            assert_eq!(err.kind(), ErrorKind::InvalidInput);
+        }
+    }
+
+    /// In-memory stream that replays `input` and records what is written
+    struct MockStream {
+        input: futures::io::Cursor<Vec<u8>>,
+        output: Vec<u8>,
+    }
+
+    impl MockStream {
+        fn new(input: &[u8]) -> Self {
+            Self {
+                input: futures::io::Cursor::new(input.to_vec()),
+                output: Vec::new(),
+            }
+        }
+    }
+
+    impl AsyncRead for MockStream {
+        fn poll_read(
+            mut self: Pin<&mut Self>,
+            cx: &mut std::task::Context<'_>,
+            buf: &mut [u8],
+        ) -> std::task::Poll<IoResult<usize>> {
+            Pin::new(&mut self.input).poll_read(cx, buf)
+        }
+    }
+
+    impl AsyncWrite for MockStream {
+        fn poll_write(
+            mut self: Pin<&mut Self>,
+            _cx: &mut std::task::Context<'_>,
+            buf: &[u8],
+        ) -> std::task::Poll<IoResult<usize>> {
+            self.output.extend_from_slice(buf);
+            std::task::Poll::Ready(Ok(buf.len()))
+        }
+
+        fn poll_flush(self: Pin<&mut Self>, _cx: &mut std::task::Context<'_>) -> std::task::Poll<IoResult<()>> {
+            std::task::Poll::Ready(Ok(()))
+        }
+
+        fn poll_close(self: Pin<&mut Self>, _cx: &mut std::task::Context<'_>) -> std::task::Poll<IoResult<()>> {
+            std::task::Poll::Ready(Ok(()))
+        }
+    }
+
+    /// Stream whose peer never answers
+    struct SilentStream;
+
+    impl AsyncRead for SilentStream {
+        fn poll_read(
+            self: Pin<&mut Self>,
+            _cx: &mut std::task::Context<'_>,
+            _buf: &mut [u8],
+        ) -> std::task::Poll<IoResult<usize>> {
+            std::task::Poll::Pending
+        }
+    }
+
+    impl AsyncWrite for SilentStream {
+        fn poll_write(
+            self: Pin<&mut Self>,
+            _cx: &mut std::task::Context<'_>,
+            buf: &[u8],
+        ) -> std::task::Poll<IoResult<usize>> {
+            std::task::Poll::Ready(Ok(buf.len()))
+        }
+
+        fn poll_flush(self: Pin<&mut Self>, _cx: &mut std::task::Context<'_>) -> std::task::Poll<IoResult<()>> {
+            std::task::Poll::Ready(Ok(()))
+        }
+
+        fn poll_close(self: Pin<&mut Self>, _cx: &mut std::task::Context<'_>) -> std::task::Poll<IoResult<()>> {
+            std::task::Poll::Ready(Ok(()))
+        }
+    }
+
+    #[test]
+    fn test_handshake_step_timeout() {
+        let runtime = tokio::runtime::Builder::new_current_thread().enable_time().build().unwrap();
+        let target = ProxyTarget::Addr("203.0.113.5:9001".parse().unwrap());
+        let mut stream = SilentStream;
+        let mut timed = StepTimeout::new(&mut stream, Some(Duration::from_millis(20)));
+
+        let err = runtime
+            .block_on(socks5_handshake(&mut timed, &target, None, &Socks5AuthMethods::default()))
+            .unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::TimedOut);
+        let err = ProxyError::handshake("SOCKS5", err);
+        assert_eq!(err.kind, ProxyErrorKind::Timeout);
+    }
+
+    /// Vendor method that sends a fixed token and expects 0x00 back
+    struct TokenAuth;
+
+    impl Socks5AuthMethod for TokenAuth {
+        fn authenticate<'a>(
+            &'a self,
+            stream: &'a mut dyn Socks5Stream,
+            _auth: Option<&'a ProxyAuth>,
+        ) -> BoxFuture<'a, IoResult<()>> {
+            async move {
+                write_all(stream, b"token").await?;
+                let mut status = [0u8; 1];
+                read_exact(stream, &mut status).await?;
+                if status[0] != 0x00 {
+                    return Err(IoError::new(ErrorKind::PermissionDenied, "token rejected"));
+                }
+                Ok(())
+            }
+            .boxed()
         }
     }
+
+    #[test]
+    fn test_handshake_deadline() {
+        let runtime = tokio::runtime::Builder::new_current_thread().enable_time().build().unwrap();
+
+        // A handshake that never finishes, however lively each step is
+        let stalled = futures::future::pending::<Result<(), ProxyError>>();
+        let err = runtime.block_on(within_deadline(Duration::from_millis(30), stalled)).unwrap_err();
+        assert_eq!(err.kind, ProxyErrorKind::Timeout);
+        assert_eq!(IoError::from(err).kind(), ErrorKind::TimedOut);
+
+        let done = runtime.block_on(within_deadline(Duration::from_millis(30), async { Ok(7) }));
+        assert_eq!(done.unwrap(), 7);
+    }
+
+    #[test]
+    fn test_socks5_offered_methods() {
+        let auth = ProxyAuth {
+            username: "u".to_string(),
+            password: "p".to_string(),
+        };
+        let mut methods = Socks5AuthMethods::default();
+        assert_eq!(methods.offered(None), vec![SOCKS5_NO_AUTH]);
+        assert_eq!(methods.offered(Some(&auth)), vec![SOCKS5_NO_AUTH, SOCKS5_USERNAME_PASSWORD]);
+
+        methods.register(0x80, Arc::new(TokenAuth));
+        assert_eq!(methods.offered(None), vec![SOCKS5_NO_AUTH, 0x80]);
+    }
+
+    #[test]
+    fn test_socks5_method_order() {
+        let auth = ProxyAuth {
+            username: "u".to_string(),
+            password: "p".to_string(),
+        };
+        let mut methods = Socks5AuthMethods::default();
+        methods.register(0x80, Arc::new(TokenAuth));
+        methods.set_order(&[SOCKS5_USERNAME_PASSWORD, 0x80, SOCKS5_USERNAME_PASSWORD, 0x42]);
+        assert_eq!(methods.offered(Some(&auth)), vec![SOCKS5_USERNAME_PASSWORD, 0x80]);
+        assert_eq!(methods.offered(None), vec![0x80]);
+
+        methods.set_order(&[SOCKS5_NO_AUTH]);
+        assert_eq!(methods.offered(Some(&auth)), vec![SOCKS5_NO_AUTH]);
+        methods.set_order(&[]);
+        let err = futures::executor::block_on(negotiate_socks5_method(&mut MockStream::new(&[]), &methods, None))
+            .unwrap_err();
+        assert_eq!(ProxyError::from_io(&err).unwrap().kind, ProxyErrorKind::UnsupportedMethod);
+
+        // Credentials are set, but the proxy picks no authentication: they
+        // are not sent
+        let methods = Socks5AuthMethods::default();
+        let mut stream = MockStream::new(&[0x05, SOCKS5_NO_AUTH]);
+        futures::executor::block_on(negotiate_socks5_method(&mut stream, &methods, Some(&auth))).unwrap();
+        assert_eq!(stream.output, [0x05, 0x02, SOCKS5_NO_AUTH, SOCKS5_USERNAME_PASSWORD]);
+    }
+
+    #[test]
+    fn test_socks5_custom_method_negotiation() {
+        let mut methods = Socks5AuthMethods::default();
+        methods.register(0x80, Arc::new(TokenAuth));
+
+        let mut stream = MockStream::new(&[0x05, 0x80, 0x00]);
+        futures::executor::block_on(negotiate_socks5_method(&mut stream, &methods, None)).unwrap();
+        assert_eq!(stream.output, b"\x05\x02\x00\x80token");
+
+        let mut stream = MockStream::new(&[0x05, 0x80, 0x01]);
+        let err = futures::executor::block_on(negotiate_socks5_method(&mut stream, &methods, None)).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::PermissionDenied);
+    }
+
+    #[test]
+    fn test_socks5_method_not_offered() {
+        let methods = Socks5AuthMethods::default();
+        // Password auth chosen although no credentials were offered
+        let mut stream = MockStream::new(&[0x05, 0x02]);
+        assert!(futures::executor::block_on(negotiate_socks5_method(&mut stream, &methods, None)).is_err());
+
+        let mut stream = MockStream::new(&[0x05, 0xFF]);
+        let err = futures::executor::block_on(negotiate_socks5_method(&mut stream, &methods, None)).unwrap_err();
+        assert_eq!(err.to_string(), "No acceptable SOCKS5 methods among 0x00 (GSSAPI is not supported)");
+        assert_eq!(err.kind(), ErrorKind::Unsupported);
+        assert_eq!(ProxyError::from_io(&err).unwrap().kind, ProxyErrorKind::UnsupportedMethod);
+
+        // No hint once a GSSAPI handler is registered
+        let mut methods = Socks5AuthMethods::default();
+        methods.register(SOCKS5_GSSAPI, Arc::new(TokenAuth));
+        let mut stream = MockStream::new(&[0x05, 0xFF]);
+        let err = futures::executor::block_on(negotiate_socks5_method(&mut stream, &methods, None)).unwrap_err();
+        assert_eq!(err.to_string(), "No acceptable SOCKS5 methods among 0x00, 0x01");
+    }
+
+    #[test]
+    fn test_socks5_connect_replies() {
+        let target = ProxyTarget::Addr("203.0.113.5:9001".parse().unwrap());
+        let handshake = |rep: u8| {
+            let mut stream = MockStream::new(&[0x05, 0x00, 0x05, rep, 0x00, 0x01, 0, 0, 0, 0, 0, 0]);
+            let methods = Socks5AuthMethods::default();
+            futures::executor::block_on(socks5_handshake(&mut stream, &target, None, &methods))
+        };
+
+        handshake(0x00).unwrap();
+
+        let err = handshake(0x05).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::ConnectionRefused);
+        assert_eq!(err.to_string(), "SOCKS5 connection failed: connection refused");
+        assert_eq!(
+            ProxyError::from_io(&err).unwrap().kind,
+            ProxyErrorKind::ConnectRejected(Socks5Reply::ConnectionRefused)
+        );
+
+        let err = handshake(0x04).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::HostUnreachable);
+        let err = handshake(0x02).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::PermissionDenied);
+        assert_ne!(ProxyError::from_io(&err).unwrap().kind, ProxyErrorKind::AuthFailed);
+        let err = handshake(0x08).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::Unsupported);
+
+        let err = handshake(0x42).unwrap_err();
+        assert_eq!(
+            ProxyError::from_io(&err).unwrap().kind,
+            ProxyErrorKind::ConnectRejected(Socks5Reply::Other(0x42))
+        );
+        assert_eq!(err.to_string(), "SOCKS5 connection failed: unknown reply 0x42");
+    }
+
+    #[cfg(feature = "socks-udp")]
+    #[test]
+    fn test_socks5_udp_associate() {
+        let proxy_addr: SocketAddr = "192.0.2.1:1080".parse().unwrap();
+        let associate = |reply: &[u8]| {
+            let mut input = vec![0x05, 0x00];
+            input.extend_from_slice(reply);
+            let mut stream = MockStream::new(&input);
+            let methods = Socks5AuthMethods::default();
+            let bound = futures::executor::block_on(socks5_udp_associate_handshake(&mut stream, None, &methods));
+            assert_eq!(&stream.output[3..], &[0x05, 0x03, 0x00, 0x01, 0, 0, 0, 0, 0, 0]);
+            bound.and_then(|bound| udp_relay_addr(&bound, proxy_addr))
+        };
+
+        let relay = associate(&[0x05, 0x00, 0x00, 0x01, 198, 51, 100, 7, 0x1F, 0x90]).unwrap();
+        assert_eq!(relay, "198.51.100.7:8080".parse().unwrap());
+        // All zeros stands for the proxy's own address
+        let relay = associate(&[0x05, 0x00, 0x00, 0x01, 0, 0, 0, 0, 0x1F, 0x90]).unwrap();
+        assert_eq!(relay, "192.0.2.1:8080".parse().unwrap());
+
+        let err = associate(&[0x05, 0x07, 0x00, 0x01, 0, 0, 0, 0, 0, 0]).unwrap_err();
+        assert_eq!(
+            ProxyError::from_io(&err).unwrap().kind,
+            ProxyErrorKind::ConnectRejected(Socks5Reply::CommandNotSupported)
+        );
+        let err = associate(&[0x05, 0x00, 0x00, 0x03, 5, b'r', b'e', b'l', b'a', b'y', 0x1F, 0x90]).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::Unsupported);
+    }
+
+    #[cfg(feature = "socks-udp")]
+    #[test]
+    fn test_socks5_udp_datagram() {
+        let target = ProxyTarget::Addr("203.0.113.5:53".parse().unwrap());
+        let datagram = socks5_udp_datagram(&target, b"query").unwrap();
+        assert_eq!(datagram, b"\x00\x00\x00\x01\xCB\x00\x71\x05\x00\x35query");
+        let (source, payload) = parse_socks5_udp_datagram(&datagram).unwrap();
+        assert_eq!(source, target);
+        assert_eq!(payload, b"query");
+
+        let target = ProxyTarget::Domain {
+            host: "example.com".to_string(),
+            port: 443,
+        };
+        let datagram = socks5_udp_datagram(&target, b"").unwrap();
+        assert_eq!(parse_socks5_udp_datagram(&datagram).unwrap(), (target, &b""[..]));
+
+        let err = parse_socks5_udp_datagram(&[0x00, 0x00, 0x01, 0x01, 1, 2, 3, 4, 0, 53]).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::Unsupported);
+        let err = parse_socks5_udp_datagram(&[0x00, 0x00, 0x00, 0x04, 1, 2, 3, 4, 0, 53]).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+        let err = parse_socks5_udp_datagram(&[0x00, 0x00]).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn test_socks5_reply_parser() {
+        let parse = |chunks: &[&[u8]]| {
+            let mut reply = Socks5ReplyParser::default();
+            for chunk in chunks {
+                reply.feed(chunk)?;
+            }
+            assert_eq!(reply.needed(), 0);
+            Ok::<_, IoError>(reply.bound().unwrap().clone())
+        };
+
+        let ipv4 = [0x05, 0x00, 0x00, 0x01, 198, 51, 100, 7, 0x1f, 0x90];
+        assert_eq!(parse(&[&ipv4]).unwrap(), ProxyTarget::Addr("198.51.100.7:8080".parse().unwrap()));
+        // Split inside the header and inside the address
+        assert_eq!(
+            parse(&[&ipv4[..2], &ipv4[2..6], &ipv4[6..]]).unwrap(),
+            ProxyTarget::Addr("198.51.100.7:8080".parse().unwrap())
+        );
+
+        let mut ipv6 = vec![0x05, 0x00, 0x00, 0x04];
+        ipv6.extend_from_slice(&"2001:db8::1".parse::<std::net::Ipv6Addr>().unwrap().octets());
+        ipv6.extend_from_slice(&443u16.to_be_bytes());
+        assert_eq!(parse(&[&ipv6]).unwrap(), ProxyTarget::Addr("[2001:db8::1]:443".parse().unwrap()));
+
+        let mut domain = vec![0x05, 0x00, 0x00, 0x03, 11];
+        domain.extend_from_slice(b"example.com");
+        domain.extend_from_slice(&80u16.to_be_bytes());
+        let expected = ProxyTarget::Domain { host: "example.com".to_string(), port: 80 };
+        assert_eq!(parse(&[&domain]).unwrap(), expected);
+        assert_eq!(parse(&[&domain[..5], &domain[5..]]).unwrap(), expected);
+
+        // Needs exactly the reply, one field at a time
+        let mut reply = Socks5ReplyParser::default();
+        assert_eq!(reply.needed(), 4);
+        reply.feed(&domain[..4]).unwrap();
+        assert_eq!(reply.needed(), 1);
+        reply.feed(&domain[4..5]).unwrap();
+        assert_eq!(reply.needed(), 13);
+        reply.feed(&domain[5..]).unwrap();
+        assert_eq!(reply.feed(&[0]).unwrap_err().kind(), ErrorKind::InvalidData);
+
+        let violation = |bytes: &[u8]| {
+            let err = parse(&[bytes]).unwrap_err();
+            ProxyError::from_io(&err).unwrap().kind
+        };
+        assert_eq!(violation(&[0x04, 0x00, 0x00, 0x01]), ProxyErrorKind::ProtocolViolation);
+        assert_eq!(violation(&[0x05, 0x00, 0x00, 0x02]), ProxyErrorKind::ProtocolViolation);
+        assert_eq!(violation(&[0x05, 0x00, 0x00, 0x03, 0]), ProxyErrorKind::ProtocolViolation);
+        assert_eq!(
+            violation(&[0x05, 0x05, 0x00, 0x01]),
+            ProxyErrorKind::ConnectRejected(Socks5Reply::ConnectionRefused)
+        );
+    }
+
+    #[test]
+    fn test_proxy_error_keeps_category() {
+        let error: IoError = ProxyError::new(ProxyErrorKind::AuthFailed, "SOCKS5 auth failed").into();
+        assert_eq!(error.kind(), ErrorKind::PermissionDenied);
+        assert_eq!(ProxyError::from_io(&error).unwrap().kind, ProxyErrorKind::AuthFailed);
+
+        // Classifying the handshake error again keeps the original
+        let proxy_error = ProxyError::handshake("SOCKS5", error);
+        assert_eq!(proxy_error.kind, ProxyErrorKind::AuthFailed);
+        assert_eq!(proxy_error.to_string(), "SOCKS5 auth failed");
+
+        let eof = IoError::new(ErrorKind::UnexpectedEof, "eof");
+        let proxy_error = ProxyError::handshake("HTTP CONNECT", eof);
+        assert_eq!(proxy_error.kind, ProxyErrorKind::HandshakeFailed);
+        assert_eq!(proxy_error.to_string(), "HTTP CONNECT handshake failed: eof");
+        assert!(std::error::Error::source(&proxy_error).is_some());
+        assert_eq!(IoError::from(proxy_error).kind(), ErrorKind::UnexpectedEof);
+
+        let refused = IoError::new(ErrorKind::ConnectionRefused, "refused");
+        let error = IoError::from(ProxyError::unreachable("127.0.0.1:1080".parse().unwrap(), refused));
+        assert_eq!(error.kind(), ErrorKind::ConnectionRefused);
+        assert_eq!(ProxyError::from_io(&error).unwrap().kind, ProxyErrorKind::Unreachable);
+        assert!(ProxyError::from_io(&IoError::other("plain")).is_none());
+
+        let tor_error = TorError::from_proxy_io(&error).unwrap();
+        assert!(matches!(tor_error, TorError::ProxyFailed { kind: ProxyErrorKind::Unreachable, .. }));
+        assert_eq!(tor_error.to_string(), "Proxy failed: Could not connect to proxy 127.0.0.1:1080: refused");
+        assert!(TorError::from_proxy_io(&IoError::other("plain")).is_none());
+    }
+
+    #[test]
+    fn test_socks5_method_errors_are_classified() {
+        let methods = Socks5AuthMethods::default();
+        let mut stream = MockStream::new(&[0x04, 0x00]);
+        let err = futures::executor::block_on(negotiate_socks5_method(&mut stream, &methods, None)).unwrap_err();
+        assert_eq!(ProxyError::from_io(&err).unwrap().kind, ProxyErrorKind::ProtocolViolation);
+
+        let mut stream = MockStream::new(&[0x05, 0x02]);
+        let err = futures::executor::block_on(negotiate_socks5_method(&mut stream, &methods, None)).unwrap_err();
+        assert_eq!(ProxyError::from_io(&err).unwrap().kind, ProxyErrorKind::ProtocolViolation);
+    }
+
+    #[test]
+    fn test_async_proxy_callback_closure() {
+        let proxy_addr: SocketAddr = "127.0.0.1:1080".parse().unwrap();
+        let callback: Arc<dyn AsyncProxyCallback> = Arc::new(move |target: SocketAddr| async move {
+            (target.port() == 443).then_some(ProxyConfig::Socks5 {
+                proxy_addr,
+                auth: None,
+                handshake_timeout: None,
+            })
+        });
+
+        let resolved = futures::executor::block_on(callback.get_proxy("1.2.3.4:443".parse().unwrap()));
+        assert!(matches!(resolved, Some(ProxyConfig::Socks5 { proxy_addr: addr, .. }) if addr == proxy_addr));
+        let resolved = futures::executor::block_on(callback.get_proxy("1.2.3.4:80".parse().unwrap()));
+        assert!(resolved.is_none());
+    }
+
+    #[test]
+    fn test_auth_refresh_closure() {
+        let callback: Arc<dyn AuthRefreshCallback> = Arc::new(|_: SocketAddr, rejected: ProxyAuth| async move {
+            (rejected.password == "old").then(|| ProxyAuth {
+                username: rejected.username,
+                password: "new".to_string(),
+            })
+        });
+        let proxy_addr: SocketAddr = "127.0.0.1:1080".parse().unwrap();
+        let rejected = |password: &str| ProxyAuth {
+            username: "u".to_string(),
+            password: password.to_string(),
+        };
+
+        let fresh = futures::executor::block_on(callback.refresh_auth(proxy_addr, rejected("old"))).unwrap();
+        assert_eq!((fresh.username.as_str(), fresh.password.as_str()), ("u", "new"));
+        assert!(futures::executor::block_on(callback.refresh_auth(proxy_addr, rejected("new"))).is_none());
+    }
+
+    #[test]
+    fn test_proxy_callback_default_resolve() {
+        let none = |_: &SocketAddr| -> Option<ProxyConfig> { None };
+        let target: SocketAddr = "1.2.3.4:443".parse().unwrap();
+        let reason = none.resolve(&target).unwrap_err();
+        assert_eq!(reason, DirectReason::CallbackReturnedNone);
+        assert_eq!(reason.to_string(), "callback-returned-none");
+
+        let socks = |_: &SocketAddr| {
+            Some(ProxyConfig::Socks5 {
+                proxy_addr: "127.0.0.1:1080".parse().unwrap(),
+                auth: None,
+                handshake_timeout: None,
+            })
+        };
+        assert!(matches!(socks.resolve(&target), Ok(ProxyConfig::Socks5 { .. })));
+    }
+
+    #[test]
+    fn test_proxy_link_tags_errors() {
+        let proxy_addr: SocketAddr = "127.0.0.1:1080".parse().unwrap();
+        let failures = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let counter = Arc::clone(&failures);
+        let mut link = ProxyLink::new(
+            proxy_addr,
+            Some(Box::new(move || {
+                counter.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            })),
+        );
+
+        let error = link.tag(IoError::new(ErrorKind::ConnectionReset, "reset"));
+        assert_eq!(error.kind(), ErrorKind::ConnectionReset);
+        assert_eq!(ProxyStreamError::from_io(&error).unwrap().proxy_addr, proxy_addr);
+        assert!(ProxyStreamError::from_io(&IoError::other("plain")).is_none());
+
+        // Failover runs only once per stream
+        link.tag(IoError::new(ErrorKind::BrokenPipe, "pipe"));
+        assert_eq!(failures.load(std::sync::atomic::Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn test_chain_hops() {
+        let socks5 = ProxyConfig::Socks5 {
+            proxy_addr: "127.0.0.1:1080".parse().unwrap(),
+            auth: None,
+            handshake_timeout: None,
+        };
+        let http = ProxyConfig::HttpConnect {
+            proxy_addr: "127.0.0.1:8080".parse().unwrap(),
+            auth: None,
+            handshake_timeout: None,
+            max_response_len: None,
+        };
+        let chain = ProxyConfig::Chain(vec![socks5.clone(), http.clone()]);
+
+        assert_eq!(hop_via(&chain), "chain:socks5:127.0.0.1:1080>http:127.0.0.1:8080");
+        let addrs: Vec<String> = chain_addrs(&[socks5.clone(), http]).unwrap().iter().map(|a| a.to_string()).collect();
+        assert_eq!(addrs, ["127.0.0.1:1080", "127.0.0.1:8080"]);
+
+        assert_eq!(chain_addrs(&[]).unwrap_err().kind(), ErrorKind::InvalidInput);
+        assert_eq!(chain_addrs(&[socks5, ProxyConfig::Direct]).unwrap_err().kind(), ErrorKind::InvalidInput);
+    }
+
+    #[test]
+    fn test_chain_handshakes_share_stream() {
+        let socks5 = ProxyConfig::Socks5 {
+            proxy_addr: "127.0.0.1:1080".parse().unwrap(),
+            auth: None,
+            handshake_timeout: None,
+        };
+        let http = ProxyConfig::HttpConnect {
+            proxy_addr: "192.0.2.8:8080".parse().unwrap(),
+            auth: None,
+            handshake_timeout: None,
+            max_response_len: None,
+        };
+        let methods = Socks5AuthMethods::default();
+        let runtime = tokio::runtime::Builder::new_current_thread().enable_time().build().unwrap();
+        let target = ProxyTarget::Addr("203.0.113.5:9001".parse().unwrap());
+
+        // The SOCKS5 proxy's replies, then the HTTP proxy's behind it
+        let mut input = vec![0x05, 0x00, 0x05, 0x00, 0x00, 0x01, 0, 0, 0, 0, 0, 0];
+        input.extend_from_slice(b"HTTP/1.1 200 Connection established\r\n\r\n");
+        let mut stream = MockStream::new(&input);
+        runtime.block_on(async {
+            let next = ProxyTarget::Addr("192.0.2.8:8080".parse().unwrap());
+            hop_handshake(&mut stream, &socks5, &next, &methods).await.unwrap();
+            hop_handshake(&mut stream, &http, &target, &methods).await.unwrap();
+        });
+        let sent = String::from_utf8_lossy(&stream.output);
+        assert!(sent.contains("CONNECT 203.0.113.5:9001 HTTP/1.1\r\n"));
+
+        // The HTTP proxy refuses: the error names the hop
+        let mut input = vec![0x05, 0x00, 0x05, 0x00, 0x00, 0x01, 0, 0, 0, 0, 0, 0];
+        input.extend_from_slice(b"HTTP/1.1 403 Forbidden\r\n\r\n");
+        let mut stream = MockStream::new(&input);
+        let err = runtime.block_on(async {
+            let next = ProxyTarget::Addr("192.0.2.8:8080".parse().unwrap());
+            hop_handshake(&mut stream, &socks5, &next, &methods).await.unwrap();
+            hop_handshake(&mut stream, &http, &target, &methods).await.unwrap_err()
+        });
+        let err = err.at_hop(1, 2);
+        assert!(err.message.starts_with("Proxy chain hop 2 of 2: "), "{}", err.message);
+    }
+
+    #[test]
+    fn test_failover_order() {
+        let proxy = |port: u16| ProxyConfig::Socks5 {
+            proxy_addr: SocketAddr::from(([127, 0, 0, 1], port)),
+            auth: None,
+            handshake_timeout: None,
+        };
+        let proxies = vec![proxy(1080), proxy(1081), proxy(1082)];
+
+        assert!(ProxyFailover::new(Vec::new()).is_err());
+        assert!(ProxyFailover::new(vec![ProxyConfig::Direct]).is_err());
+
+        let failover = ProxyFailover::new(proxies.clone()).unwrap();
+        assert_eq!(failover.order(), [0, 1, 2]);
+        failover.succeeded(2);
+        assert_eq!(failover.order(), [0, 1, 2]);
+        assert_eq!(
+            hop_via(&ProxyConfig::Failover(Arc::new(failover))),
+            "failover:socks5:127.0.0.1:1080|socks5:127.0.0.1:1081|socks5:127.0.0.1:1082"
+        );
+
+        let sticky = ProxyFailover::new(proxies).unwrap().with_max_attempts(2).sticky(true);
+        assert_eq!(sticky.order(), [0, 1]);
+        sticky.succeeded(2);
+        assert_eq!(sticky.order(), [2, 0]);
+    }
+
+    #[test]
+    fn test_retry_transient_failures() {
+        let runtime = tokio::runtime::Builder::new_current_thread().enable_time().build().unwrap();
+        let policy = RetryPolicy {
+            max_attempts: 4,
+            base_delay: Duration::from_millis(1),
+            multiplier: 2.0,
+            jitter: 0.5,
+        };
+        let fail_then_succeed = |failures: u32, kind: ErrorKind| {
+            let mut calls = 0;
+            runtime.block_on(retry(policy, || {
+                calls += 1;
+                let result = if calls <= failures { Err(IoError::from(kind)) } else { Ok(calls) };
+                async move { result }
+            }))
+        };
+
+        assert_eq!(fail_then_succeed(3, ErrorKind::ConnectionRefused).unwrap(), 4);
+        assert_eq!(fail_then_succeed(4, ErrorKind::TimedOut).unwrap_err().kind(), ErrorKind::TimedOut);
+        let err = fail_then_succeed(1, ErrorKind::PermissionDenied).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::PermissionDenied);
+
+        let delay = RetryPolicy { base_delay: Duration::from_secs(1), ..policy }.delay(3);
+        assert!(delay >= Duration::from_secs(4) && delay <= Duration::from_secs(6));
+    }
+
+    #[cfg(feature = "https-proxy")]
+    #[test]
+    fn test_https_cert_pin() {
+        let cert = b"not really DER, but hashed all the same";
+        let pin: [u8; 32] = Sha256::digest(cert).as_slice().try_into().unwrap();
+
+        assert!(check_cert_pin(Some(cert), &pin).is_ok());
+        assert_eq!(check_cert_pin(Some(b"other"), &pin).unwrap_err().kind(), ErrorKind::InvalidData);
+        assert_eq!(check_cert_pin(None, &pin).unwrap_err().kind(), ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn test_http_connect_response_parse() {
+        let response = HttpConnectResponse::parse(
+            "HTTP/1.1 200 Connection established\r\nProxy-Connection: keep-alive\r\nVia: a\r\nvia: b\r\nbogus\r\n\r\n",
+        )
+        .unwrap();
+        assert_eq!(response.status, 200);
+        assert_eq!(response.headers["proxy-connection"], "keep-alive");
+        assert_eq!(response.headers["via"], "a, b");
+        assert!(!response.closes_connection());
+
+        let response = HttpConnectResponse::parse("HTTP/1.0 200 OK\r\nConnection: Keep-Alive, Close\r\n\r\n").unwrap();
+        assert!(response.closes_connection());
+
+        let err = HttpConnectResponse::parse("SSH-2.0-OpenSSH\r\n\r\n").unwrap_err();
+        assert_eq!(ProxyError::from_io(&err).unwrap().kind, ProxyErrorKind::ProtocolViolation);
+    }
+
+    #[test]
+    fn test_http_handshake_honors_connection_close() {
+        let target = ProxyTarget::Addr("203.0.113.5:9001".parse().unwrap());
+        let mut stream = MockStream::new(b"HTTP/1.1 200 OK\r\nProxy-Agent: test\r\n\r\n");
+        let response = futures::executor::block_on(http_handshake(&mut stream, &target, None, None)).unwrap();
+        assert_eq!(response.headers["proxy-agent"], "test");
+        assert!(stream.output.starts_with(b"CONNECT 203.0.113.5:9001 HTTP/1.1\r\n"));
+
+        let mut stream = MockStream::new(b"HTTP/1.1 200 OK\r\nConnection: close\r\n\r\n");
+        let err = futures::executor::block_on(http_handshake(&mut stream, &target, None, None)).unwrap_err();
+        assert_eq!(ProxyError::from_io(&err).unwrap().kind, ProxyErrorKind::HandshakeFailed);
+
+        let mut stream = MockStream::new(b"HTTP/1.1 407 Proxy Authentication Required\r\n\r\n");
+        let err = futures::executor::block_on(http_handshake(&mut stream, &target, None, None)).unwrap_err();
+        assert_eq!(err.to_string(), "HTTP proxy requires authentication: HTTP/1.1 407 Proxy Authentication Required");
+        assert_eq!(err.kind(), std::io::ErrorKind::PermissionDenied);
+    }
+
+    #[test]
+    fn test_http_handshake_status_errors() {
+        let target = ProxyTarget::Addr("203.0.113.5:9001".parse().unwrap());
+        let refuse = |reply: &str| {
+            let mut stream = MockStream::new(reply.as_bytes());
+            futures::executor::block_on(http_handshake(&mut stream, &target, None, None)).unwrap_err()
+        };
+
+        let err = refuse("HTTP/1.1 403 Forbidden\r\n\r\n");
+        assert_eq!(ProxyError::from_io(&err).unwrap().kind, ProxyErrorKind::HttpRejected(403));
+        assert_eq!(err.kind(), std::io::ErrorKind::PermissionDenied);
+        assert_eq!(err.to_string(), "HTTP proxy forbids connecting to this target: HTTP/1.1 403 Forbidden");
+
+        let err = refuse("HTTP/1.1 502 Bad Gateway\r\n\r\n");
+        assert_eq!(err.kind(), std::io::ErrorKind::HostUnreachable);
+        assert_eq!(err.to_string(), "HTTP proxy could not reach the target: HTTP/1.1 502 Bad Gateway");
+
+        let err = refuse("HTTP/1.0 504 Gateway Timeout\r\n\r\n");
+        assert_eq!(err.kind(), std::io::ErrorKind::TimedOut);
+        assert_eq!(err.to_string(), "HTTP proxy timed out reaching the target: HTTP/1.0 504 Gateway Timeout");
+
+        let err = refuse("HTTP/1.1 500 Internal Server Error\r\n\r\n");
+        assert_eq!(ProxyError::from_io(&err).unwrap().kind, ProxyErrorKind::HttpRejected(500));
+        assert_eq!(err.kind(), std::io::ErrorKind::Other);
+        assert_eq!(err.to_string(), "HTTP CONNECT failed: HTTP/1.1 500 Internal Server Error");
+    }
+
+    #[test]
+    fn test_http_handshake_brackets_ipv6() {
+        let target = ProxyTarget::Addr("[2001:db8::1]:443".parse().unwrap());
+        let mut stream = MockStream::new(b"HTTP/1.1 200 OK\r\n\r\n");
+        futures::executor::block_on(http_handshake(&mut stream, &target, None, None)).unwrap();
+        let request = String::from_utf8(stream.output).unwrap();
+        assert!(request.starts_with("CONNECT [2001:db8::1]:443 HTTP/1.1\r\nHost: [2001:db8::1]:443\r\n"));
+    }
+
+    #[test]
+    fn test_http_connect_request() {
+        let target = ProxyTarget::Addr("[fe80::1]:443".parse().unwrap());
+        assert_eq!(
+            connect_request(&target, None),
+            "CONNECT [fe80::1]:443 HTTP/1.1\r\nHost: [fe80::1]:443\r\n\r\n"
+        );
+
+        let target = ProxyTarget::Addr("203.0.113.5:9001".parse().unwrap());
+        assert_eq!(
+            connect_request(&target, None),
+            "CONNECT 203.0.113.5:9001 HTTP/1.1\r\nHost: 203.0.113.5:9001\r\n\r\n"
+        );
+
+        let auth = ProxyAuth {
+            username: "user".to_string(),
+            password: "pass".to_string(),
+        };
+        let target = ProxyTarget::Addr("[2001:db8::1]:80".parse().unwrap());
+        assert_eq!(
+            connect_request(&target, Some(&http_auth::basic_authorization(&auth))),
+            "CONNECT [2001:db8::1]:80 HTTP/1.1\r\nHost: [2001:db8::1]:80\r\n\
+             Proxy-Authorization: Basic dXNlcjpwYXNz\r\n\r\n"
+        );
+    }
+
+    #[test]
+    fn test_http_response_length_limit() {
+        let target = ProxyTarget::Addr("203.0.113.5:9001".parse().unwrap());
+        let reply = b"HTTP/1.1 200 OK\r\nProxy-Agent: test\r\n\r\n";
+
+        let mut stream = MockStream::new(reply);
+        let limit = Some(reply.len());
+        futures::executor::block_on(http_handshake(&mut stream, &target, None, limit)).unwrap();
+
+        let mut stream = MockStream::new(reply);
+        let limit = Some(reply.len() - 1);
+        let err = futures::executor::block_on(http_handshake(&mut stream, &target, None, limit)).unwrap_err();
+        assert_eq!(ProxyError::from_io(&err).unwrap().kind, ProxyErrorKind::ResponseTooLarge);
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn test_http_auth_challenge() {
+        let target = ProxyTarget::Addr("203.0.113.5:9001".parse().unwrap());
+        let auth = ProxyAuth {
+            username: "user".to_string(),
+            password: "pass".to_string(),
+        };
+        let challenge = "HTTP/1.1 407 Proxy Authentication Required\r\n\
+                         Proxy-Authenticate: Negotiate, Basic realm=\"proxy\"\r\n\
+                         Content-Length: 4\r\n\r\ndeny";
+        let authorization = "Proxy-Authorization: Basic dXNlcjpwYXNz\r\n";
+
+        // Retried with credentials on the same connection
+        let mut stream = MockStream::new(format!("{}HTTP/1.1 200 OK\r\n\r\n", challenge).as_bytes());
+        futures::executor::block_on(http_handshake(&mut stream, &target, Some(&auth), None)).unwrap();
+        let requests = String::from_utf8(stream.output).unwrap();
+        let (first, second) = requests.split_at(requests.find("\r\n\r\n").unwrap() + 4);
+        assert!(!first.contains("Proxy-Authorization"));
+        assert!(second.starts_with("CONNECT 203.0.113.5:9001 HTTP/1.1\r\n"));
+        assert!(second.contains(authorization));
+
+        // Digest is answered in preference to Basic
+        let digest = "HTTP/1.1 407 Proxy Authentication Required\r\n\
+                      Proxy-Authenticate: Basic realm=\"proxy\"\r\n\
+                      Proxy-Authenticate: Digest realm=\"proxy\", nonce=\"abc\", qop=\"auth\"\r\n\
+                      Content-Length: 0\r\n\r\n";
+        let mut stream = MockStream::new(format!("{}HTTP/1.1 200 OK\r\n\r\n", digest).as_bytes());
+        futures::executor::block_on(http_handshake(&mut stream, &target, Some(&auth), None)).unwrap();
+        let requests = String::from_utf8(stream.output).unwrap();
+        let (_, second) = requests.split_at(requests.find("\r\n\r\n").unwrap() + 4);
+        assert!(second.contains("Proxy-Authorization: Digest username=\"user\", realm=\"proxy\", nonce=\"abc\", \
+                                 uri=\"203.0.113.5:9001\", algorithm=MD5, response=\""));
+        assert!(second.contains(", qop=auth, nc=00000001, cnonce=\""));
+
+        // Rejected credentials
+        let mut stream = MockStream::new(format!("{}{}", challenge, challenge).as_bytes());
+        let err = futures::executor::block_on(http_handshake(&mut stream, &target, Some(&auth), None)).unwrap_err();
+        assert_eq!(ProxyError::from_io(&err).unwrap().kind, ProxyErrorKind::AuthFailed);
+
+        // No credentials to answer with
+        let mut stream = MockStream::new(challenge.as_bytes());
+        let err = futures::executor::block_on(http_handshake(&mut stream, &target, None, None)).unwrap_err();
+        assert_eq!(ProxyError::from_io(&err).unwrap().kind, ProxyErrorKind::AuthFailed);
+
+        // Only schemes we cannot answer
+        let mut stream = MockStream::new(b"HTTP/1.1 407 Nope\r\nProxy-Authenticate: Negotiate\r\n\r\n");
+        let err = futures::executor::block_on(http_handshake(&mut stream, &target, Some(&auth), None)).unwrap_err();
+        assert_eq!(ProxyError::from_io(&err).unwrap().kind, ProxyErrorKind::UnsupportedMethod);
+
+        // Connection closed after the challenge: credentials go on a new one
+        let closing = "HTTP/1.1 407 Proxy Authentication Required\r\n\
+                       Proxy-Authenticate: Basic realm=\"proxy\"\r\nConnection: close\r\n\r\n";
+        let mut stream = MockStream::new(closing.as_bytes());
+        let exchange = futures::executor::block_on(http_exchange(&mut stream, &target, Some(&auth), None, None));
+        let HttpExchange::Redial(answer) = exchange.unwrap() else { panic!("expected a redial") };
+        assert_eq!(answer, "Basic dXNlcjpwYXNz");
+
+        let mut stream = MockStream::new(b"HTTP/1.1 200 OK\r\n\r\n");
+        let exchange = futures::executor::block_on(http_exchange(&mut stream, &target, Some(&auth), Some(answer), None));
+        assert!(matches!(exchange.unwrap(), HttpExchange::Connected(_)));
+        assert!(String::from_utf8(stream.output).unwrap().contains(authorization));
+    }
+
+    #[test]
+    fn test_sanitize_status_line() {
+        assert_eq!(
+            sanitize_status_line("HTTP/1.1 407 Proxy Authentication Required"),
+            "HTTP/1.1 407 Proxy Authentication Required"
+        );
+        assert_eq!(sanitize_status_line("HTTP/1.1 502 \x1b[31mBad\0"), "HTTP/1.1 502 ?[31mBad?");
+
+        let long = format!("HTTP/1.1 500 {}", "x".repeat(1000));
+        let sanitized = sanitize_status_line(&long);
+        assert_eq!(sanitized.chars().count(), MAX_STATUS_LINE_LEN + 3);
+        assert!(sanitized.ends_with("..."));
+    }
+
+    #[test]
+    fn test_ip_cidr_parse_and_contains() {
+        let net = IpCidr::parse("10.0.0.0/8").unwrap();
+        assert!(net.contains("10.1.2.3".parse().unwrap()));
+        assert!(net.contains("::ffff:10.1.2.3".parse().unwrap()));
+        assert!(!net.contains("11.0.0.1".parse().unwrap()));
+
+        let host = IpCidr::parse("192.168.1.1").unwrap();
+        assert!(host.contains("192.168.1.1".parse().unwrap()));
+        assert!(!host.contains("192.168.1.2".parse().unwrap()));
+
+        let net6 = IpCidr::parse("fe80::/10").unwrap();
+        assert!(net6.contains("fe80::1".parse().unwrap()));
+        assert!(!net6.contains("10.1.2.3".parse().unwrap()));
+        assert!(IpCidr::parse("::/0").unwrap().contains("2001:db8::1".parse().unwrap()));
+
+        assert!(IpCidr::parse("10.0.0.0/33").is_none());
+        assert!(IpCidr::parse("not-an-ip/8").is_none());
+    }
+
+    #[test]
+    fn test_target_policy_permits() {
+        let mut policy = TargetPolicy::default();
+        assert!(policy.permits("8.8.8.8".parse().unwrap()));
+
+        policy.deny.push(IpCidr::parse("10.0.0.0/8").unwrap());
+        assert!(!policy.permits("10.0.0.1".parse().unwrap()));
+        assert!(policy.permits("8.8.8.8".parse().unwrap()));
+
+        policy.allow.push(IpCidr::parse("8.8.0.0/16").unwrap());
+        policy.allow.push(IpCidr::parse("10.0.0.0/16").unwrap());
+        assert!(policy.permits("8.8.8.8".parse().unwrap()));
+        assert!(!policy.permits("1.1.1.1".parse().unwrap()));
+        // Deny wins over allow
+        assert!(!policy.permits("10.0.0.1".parse().unwrap()));
+
+        let domain = ProxyTarget::Domain {
+            host: "example.com".to_string(),
+            port: 443,
+        };
+        assert!(!policy.permits_target(&domain));
+        policy.allow.clear();
+        assert!(policy.permits_target(&domain));
+    }
+    #[test]
+    fn test_redact_target() {
+        let target = ProxyTarget::Addr("203.0.113.5:9001".parse().unwrap());
+        let other = ProxyTarget::Addr("203.0.113.5:443".parse().unwrap());
+        assert_eq!(redact_target(TargetRedaction::Full, &target), "203.0.113.5:9001");
+        assert_eq!(redact_target(TargetRedaction::HostOnly, &target), "203.0.113.5");
+        assert_eq!(redact_target(TargetRedaction::None, &target), "redacted");
+
+        let hashed = redact_target(TargetRedaction::Hashed, &target);
+        assert!(hashed.starts_with("hash:"));
+        assert!(!hashed.contains("203.0.113.5"));
+        assert_eq!(hashed, redact_target(TargetRedaction::Hashed, &target));
+        assert_ne!(hashed, redact_target(TargetRedaction::Hashed, &other));
+
+        let domain = ProxyTarget::Domain {
+            host: "example.com".to_string(),
+            port: 443,
+        };
+        assert_eq!(redact_target(TargetRedaction::Full, &domain), "example.com:443");
+        assert_eq!(redact_target(TargetRedaction::HostOnly, &domain), "example.com");
+        assert!(!redact_target(TargetRedaction::Hashed, &domain).contains("example.com"));
+    }
 }
\ No newline at end of file
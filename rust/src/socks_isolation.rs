@@ -0,0 +1,236 @@
+// SPDX-FileCopyrightText: 2025 0xChat
+//
+// SPDX-License-Identifier: MIT
+
+//! Per-contact isolation for the local SOCKS listener
+//!
+//! Arti's SOCKS listener puts streams on separate circuits when their SOCKS
+//! username or password differ. Apps pass a stable contact id as username;
+//! contact ids registered in the isolation map are replaced by their
+//! isolation token before arti reads the username. Contacts that share a
+//! token then share circuits, and registering a new token for a contact
+//! moves its new streams to new circuits. Unregistered usernames reach arti
+//! unchanged, so they keep getting one circuit per username.
+//!
+//! Only SOCKS5 username/password authentication (RFC 1929) is rewritten;
+//! SOCKS4 user ids pass through as they are.
+
+use std::collections::HashMap;
+use std::io::Result as IoResult;
+use std::pin::Pin;
+use std::sync::{Arc, RwLock};
+use std::task::{ready, Context, Poll};
+
+use futures::AsyncRead;
+
+/// Contact id to isolation token, shared with whoever registers mappings
+pub type IsolationMap = Arc<RwLock<HashMap<String, String>>>;
+
+/// Prefix of the username arti sees for a mapped contact, so that a token
+/// cannot collide with an unmapped username
+pub const TOKEN_PREFIX: &str = "isolation-token:";
+
+/// SOCKS5 method code for username/password authentication
+const USERNAME_PASSWORD: u8 = 0x02;
+
+enum Phase {
+    /// Waiting for the client's greeting (version and offered methods)
+    Greeting,
+    /// Waiting for the username/password request
+    Auth,
+    /// Handshake rewritten or not rewritable: pass everything through
+    Done,
+}
+
+/// Read side of an accepted SOCKS connection that maps the SOCKS5 username
+///
+/// Buffers the client's first messages until they are complete, rewrites the
+/// username if it is a registered contact id and then gets out of the way.
+pub struct SocksUsernameRewrite {
+    map: IsolationMap,
+    phase: Phase,
+    /// Bytes from the client that do not form a whole message yet
+    input: Vec<u8>,
+    /// Bytes ready for the reader, from `output_pos` on
+    output: Vec<u8>,
+    output_pos: usize,
+}
+
+impl SocksUsernameRewrite {
+    pub fn new(map: IsolationMap) -> Self {
+        Self {
+            map,
+            phase: Phase::Greeting,
+            input: Vec::new(),
+            output: Vec::new(),
+            output_pos: 0,
+        }
+    }
+
+    /// Read from `inner` into `buf`, rewriting the handshake on the way
+    pub fn poll_read<S: AsyncRead + Unpin>(
+        &mut self,
+        inner: &mut S,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<IoResult<usize>> {
+        loop {
+            if self.output_pos < self.output.len() {
+                let n = (self.output.len() - self.output_pos).min(buf.len());
+                buf[..n].copy_from_slice(&self.output[self.output_pos..self.output_pos + n]);
+                self.output_pos += n;
+                if self.output_pos == self.output.len() {
+                    self.output.clear();
+                    self.output_pos = 0;
+                }
+                return Poll::Ready(Ok(n));
+            }
+            if let Phase::Done = self.phase {
+                return Pin::new(inner).poll_read(cx, buf);
+            }
+
+            let mut chunk = [0u8; 512];
+            let n = ready!(Pin::new(&mut *inner).poll_read(cx, &mut chunk))?;
+            if n == 0 {
+                // Closed mid-handshake: hand over what there is and let the
+                // SOCKS server report it
+                self.finish();
+                if self.output.is_empty() {
+                    return Poll::Ready(Ok(0));
+                }
+                continue;
+            }
+            self.input.extend_from_slice(&chunk[..n]);
+            self.advance();
+        }
+    }
+
+    /// Move whole handshake messages from `input` to `output`
+    fn advance(&mut self) {
+        loop {
+            match self.phase {
+                Phase::Greeting => {
+                    // VER NMETHODS METHODS
+                    match self.input.first() {
+                        None => return,
+                        Some(5) => {}
+                        Some(_) => return self.finish(),
+                    }
+                    let Some(&n_methods) = self.input.get(1) else { return };
+                    let len = 2 + n_methods as usize;
+                    if self.input.len() < len {
+                        return;
+                    }
+                    let offers_auth = self.input[2..len].contains(&USERNAME_PASSWORD);
+                    self.output.extend(self.input.drain(..len));
+                    if !offers_auth {
+                        return self.finish();
+                    }
+                    // Arti picks username/password whenever it is offered
+                    self.phase = Phase::Auth;
+                }
+                Phase::Auth => {
+                    // VER ULEN UNAME PLEN PASSWD
+                    let Some(&ulen) = self.input.get(1) else { return };
+                    let ulen = ulen as usize;
+                    let Some(&plen) = self.input.get(2 + ulen) else { return };
+                    let len = 3 + ulen + plen as usize;
+                    if self.input.len() < len {
+                        return;
+                    }
+                    match self.mapped(&self.input[2..2 + ulen]) {
+                        Some(username) => {
+                            self.output.push(self.input[0]);
+                            self.output.push(username.len() as u8);
+                            self.output.extend_from_slice(&username);
+                            self.output.extend_from_slice(&self.input[2 + ulen..len]);
+                        }
+                        None => self.output.extend_from_slice(&self.input[..len]),
+                    }
+                    self.input.drain(..len);
+                    return self.finish();
+                }
+                Phase::Done => return,
+            }
+        }
+    }
+
+    /// The username arti should see for `username`, if it is a mapped contact
+    fn mapped(&self, username: &[u8]) -> Option<Vec<u8>> {
+        let contact_id = std::str::from_utf8(username).ok()?;
+        let map = self.map.read().unwrap();
+        let token = map.get(contact_id)?;
+        Some(format!("{}{}", TOKEN_PREFIX, token).into_bytes())
+    }
+
+    /// Stop rewriting and pass on anything buffered
+    fn finish(&mut self) {
+        self.phase = Phase::Done;
+        self.output.append(&mut self.input);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::io::Cursor;
+
+    /// Read all of `client` through a rewrite with `contact` mapped to `token`
+    fn rewrite(client: Vec<u8>, contact: &str, token: &str) -> Vec<u8> {
+        let map = IsolationMap::default();
+        map.write().unwrap().insert(contact.to_string(), token.to_string());
+        let mut rewrite = SocksUsernameRewrite::new(map);
+        let mut inner = Cursor::new(client);
+        let mut out = Vec::new();
+        let mut buf = [0u8; 7];
+        loop {
+            let read = futures::future::poll_fn(|cx| rewrite.poll_read(&mut inner, cx, &mut buf));
+            let n = futures::executor::block_on(read).unwrap();
+            if n == 0 {
+                return out;
+            }
+            out.extend_from_slice(&buf[..n]);
+        }
+    }
+
+    #[test]
+    fn test_maps_registered_username() {
+        let mut client = vec![5, 2, 0x00, 0x02, 1, 5];
+        client.extend_from_slice(b"alice");
+        client.extend_from_slice(&[2]);
+        client.extend_from_slice(b"pw");
+        client.extend_from_slice(&[5, 1, 0, 3]);
+
+        let out = rewrite(client, "alice", "group-1");
+
+        let username = format!("{}group-1", TOKEN_PREFIX);
+        let mut expected = vec![5, 2, 0x00, 0x02, 1, username.len() as u8];
+        expected.extend_from_slice(username.as_bytes());
+        expected.extend_from_slice(&[2]);
+        expected.extend_from_slice(b"pw");
+        expected.extend_from_slice(&[5, 1, 0, 3]);
+        assert_eq!(out, expected);
+    }
+
+    #[test]
+    fn test_passes_other_handshakes_through() {
+        // Unmapped username
+        let mut client = vec![5, 1, 0x02, 1, 3];
+        client.extend_from_slice(b"bob");
+        client.extend_from_slice(&[0]);
+        assert_eq!(rewrite(client.clone(), "alice", "group-1"), client);
+
+        // No username offered
+        let client = vec![5, 1, 0x00, 5, 1, 0, 1, 127, 0, 0, 1, 0, 80];
+        assert_eq!(rewrite(client.clone(), "alice", "group-1"), client);
+
+        // SOCKS4
+        let mut client = vec![4, 1, 0, 80, 127, 0, 0, 1];
+        client.extend_from_slice(b"alice\0");
+        assert_eq!(rewrite(client.clone(), "alice", "group-1"), client);
+
+        // Cut off mid-handshake
+        let client = vec![5, 1, 0x02, 1, 5, b'a'];
+        assert_eq!(rewrite(client.clone(), "alice", "group-1"), client);
+    }
+}
@@ -2,8 +2,27 @@
 //
 // SPDX-License-Identifier: MIT
 
-use crate::error::update_last_error;
+use std::net::{IpAddr, SocketAddr};
+use std::str::FromStr;
 
+#[cfg(feature = "stderr-log")]
+use once_cell::sync::Lazy;
+use tor_hscrypto::pk::{HsId, HsIdParseError};
+
+use crate::api::{FdUsage, ProxyInfo, ProxyType};
+use crate::error::{update_last_error, TorError};
+
+/// Whether the stderr-log feature prints the debug log: in debug builds or
+/// when TOR_PROXY_DEBUG is set
+#[cfg(feature = "stderr-log")]
+pub static DEBUG_OUTPUT: Lazy<bool> =
+    Lazy::new(|| cfg!(debug_assertions) || std::env::var_os("TOR_PROXY_DEBUG").is_some());
+
+/// Get the soft NOFILE limit, or 0 on failure
+///
+/// # Safety
+///
+/// Takes no pointers; exported over the C ABI only.
 #[no_mangle]
 #[cfg(not(target_os = "windows"))]
 pub unsafe extern "C" fn tor_get_nofile_limit() -> u64 {
@@ -11,8 +30,301 @@ pub unsafe extern "C" fn tor_get_nofile_limit() -> u64 {
     nofile_limit.0
 }
 
+/// Raise the soft NOFILE limit, returning the new value or 0 on failure
+///
+/// # Safety
+///
+/// Takes no pointers; exported over the C ABI only.
 #[no_mangle]
 #[cfg(not(target_os = "windows"))]
 pub unsafe extern "C" fn tor_set_nofile_limit(limit: u64) -> u64 {
     unwrap_or_return!(rlimit::increase_nofile_limit(limit), 0)
 }
+
+/// Report the number of open file descriptors along with the NOFILE limits
+#[cfg(not(target_os = "windows"))]
+pub fn fd_usage() -> anyhow::Result<FdUsage> {
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    const FD_DIR: &str = "/proc/self/fd";
+    #[cfg(not(any(target_os = "linux", target_os = "android")))]
+    const FD_DIR: &str = "/dev/fd";
+
+    let (soft_limit, hard_limit) = rlimit::getrlimit(rlimit::Resource::NOFILE)?;
+
+    // The directory handle used for listing is itself an open descriptor
+    let open = std::fs::read_dir(FD_DIR)?.count().saturating_sub(1) as u64;
+
+    Ok(FdUsage {
+        open,
+        soft_limit,
+        hard_limit,
+    })
+}
+
+#[cfg(target_os = "windows")]
+pub fn fd_usage() -> anyhow::Result<FdUsage> {
+    anyhow::bail!("File descriptor usage is not supported on Windows")
+}
+
+/// Length of the label of a v3 onion address (before `.onion`)
+const ONION_V3_LEN: usize = 56;
+/// Length of the label of a v2 onion address, which Tor no longer supports
+const ONION_V2_LEN: usize = 16;
+
+/// Check that `address` is a well-formed v3 onion address
+///
+/// Subdomains (`www.<id>.onion`) are accepted. The error reason is meant to
+/// be shown to a user who pasted the address.
+pub fn validate_onion_address(address: &str) -> Result<(), TorError> {
+    let invalid = |reason: String| TorError::InvalidOnionAddress { reason };
+
+    let address = address.trim().to_ascii_lowercase();
+    let host = address
+        .strip_suffix(".onion")
+        .ok_or_else(|| invalid("address must end in .onion".to_string()))?;
+    let label = host.rsplit('.').next().unwrap_or(host);
+
+    match label.len() {
+        ONION_V3_LEN => {}
+        ONION_V2_LEN => {
+            return Err(invalid(
+                "this is a v2 address (16 characters), which Tor no longer supports; \
+                 ask the site for its v3 address (56 characters)"
+                    .to_string(),
+            ))
+        }
+        len => {
+            return Err(invalid(format!(
+                "expected {} characters before .onion, found {}",
+                ONION_V3_LEN, len
+            )))
+        }
+    }
+    if let Some(c) = label.chars().find(|c| !matches!(c, 'a'..='z' | '2'..='7')) {
+        return Err(invalid(format!(
+            "invalid character '{}' (onion addresses use only a-z and 2-7)",
+            c
+        )));
+    }
+
+    HsId::from_str(&format!("{}.onion", label)).map_err(|e| {
+        invalid(match e {
+            HsIdParseError::WrongChecksum => {
+                "checksum mismatch, the address is mistyped or incomplete".to_string()
+            }
+            HsIdParseError::UnsupportedVersion(version) => {
+                format!("unknown onion address version {}, the address may be mistyped", version)
+            }
+            other => other.to_string(),
+        })
+    })?;
+    Ok(())
+}
+
+/// Socket address of a proxy, None if its address is not an IP literal
+///
+/// IPv6 addresses may come with or without brackets.
+pub fn proxy_socket_addr(proxy: &ProxyInfo) -> Option<SocketAddr> {
+    let address = proxy.address.trim();
+    let address = address
+        .strip_prefix('[')
+        .and_then(|address| address.strip_suffix(']'))
+        .unwrap_or(address);
+    let ip: IpAddr = address.parse().ok()?;
+    Some(SocketAddr::new(ip, proxy.port))
+}
+
+/// SHA-256 certificate pin of an HTTPS proxy, None if it is not 64 hex
+/// digits
+///
+/// Pairs of digits may be separated by colons, as openssl prints them.
+pub fn parse_cert_sha256(hex: &str) -> Option<[u8; 32]> {
+    let digits: Vec<u32> = hex
+        .trim()
+        .chars()
+        .filter(|c| *c != ':')
+        .map(|c| c.to_digit(16))
+        .collect::<Option<_>>()?;
+    if digits.len() != 64 {
+        return None;
+    }
+    let mut pin = [0u8; 32];
+    for (byte, pair) in pin.iter_mut().zip(digits.chunks(2)) {
+        *byte = (pair[0] * 16 + pair[1]) as u8;
+    }
+    Some(pin)
+}
+
+/// Check that a proxy could ever be connected to
+///
+/// The address must be an IP literal that names a single host, so the
+/// unspecified address (0.0.0.0, ::), broadcast (255.255.255.255) and
+/// multicast addresses are refused, as are an empty address and port 0.
+/// HTTPS proxies need a server name to check the certificate against or a
+/// valid certificate pin.
+pub fn validate_proxy(proxy: &ProxyInfo) -> Result<(), TorError> {
+    let invalid = |reason: String| TorError::InvalidProxy { reason };
+
+    if proxy.address.trim().is_empty() {
+        return Err(invalid("the address is empty".to_string()));
+    }
+    // Parsed the same way as when connecting
+    let addr = proxy_socket_addr(proxy)
+        .ok_or_else(|| invalid(format!("'{}' is not an IP address", proxy.address)))?;
+    let ip = addr.ip();
+    let reserved = match ip {
+        IpAddr::V4(v4) if v4.is_unspecified() => Some("unspecified"),
+        IpAddr::V4(v4) if v4.is_broadcast() => Some("broadcast"),
+        IpAddr::V4(v4) if v4.is_multicast() => Some("multicast"),
+        IpAddr::V6(v6) if v6.is_unspecified() => Some("unspecified"),
+        IpAddr::V6(v6) if v6.is_multicast() => Some("multicast"),
+        _ => None,
+    };
+    if let Some(kind) = reserved {
+        return Err(invalid(format!("{} is a {} address", ip, kind)));
+    }
+    if proxy.port == 0 {
+        return Err(invalid("port 0 is not a valid proxy port".to_string()));
+    }
+    if proxy.proxy_type == ProxyType::HttpsConnect {
+        let has_name = proxy
+            .tls_server_name
+            .as_deref()
+            .is_some_and(|name| !name.trim().is_empty());
+        match proxy.tls_cert_sha256.as_deref() {
+            Some(pin) if parse_cert_sha256(pin).is_none() => {
+                return Err(invalid(format!("'{}' is not a SHA-256 certificate pin", pin)));
+            }
+            None if !has_name => {
+                return Err(invalid(
+                    "an HTTPS proxy needs a TLS server name or a certificate pin".to_string(),
+                ));
+            }
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+/// Optional cargo features compiled into this build
+pub fn enabled_features() -> Vec<&'static str> {
+    let features = [
+        ("socks", cfg!(feature = "socks")),
+        ("onion-service", cfg!(feature = "onion-service")),
+        ("socks-udp", cfg!(feature = "socks-udp")),
+        ("https-proxy", cfg!(feature = "https-proxy")),
+        ("bridges", cfg!(feature = "bridges")),
+        ("exit-country", cfg!(feature = "exit-country")),
+        ("stderr-log", cfg!(feature = "stderr-log")),
+    ];
+    features
+        .into_iter()
+        .filter(|(_, enabled)| *enabled)
+        .map(|(name, _)| name)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn https_proxy(server_name: Option<&str>, pin: Option<&str>) -> ProxyInfo {
+        ProxyInfo {
+            address: "192.0.2.1".to_string(),
+            port: 443,
+            proxy_type: ProxyType::HttpsConnect,
+            username: None,
+            password: None,
+            tls_server_name: server_name.map(str::to_string),
+            tls_cert_sha256: pin.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn test_validate_onion_address() {
+        let valid = "duckduckgogg42xjoc72x3sjasowoarfbgcmvfimaftt6twagswzczad.onion";
+        assert!(validate_onion_address(valid).is_ok());
+        assert!(validate_onion_address(&valid.to_uppercase()).is_ok());
+        assert!(validate_onion_address(&format!("www.{}", valid)).is_ok());
+
+        let reason = |address: &str| match validate_onion_address(address) {
+            Err(TorError::InvalidOnionAddress { reason }) => reason,
+            other => panic!("{} gave {:?}", address, other),
+        };
+        assert!(reason("example.com").contains("must end in .onion"));
+        assert!(reason("3g2upl4pq6kufc4m.onion").contains("v2 address"));
+        assert!(reason("duckduckgo.onion").contains("expected 56 characters"));
+        assert!(reason(&valid.replacen('d', "1", 1)).contains("invalid character '1'"));
+        assert!(reason(&valid.replacen("duck", "dock", 1)).contains("checksum"));
+    }
+
+    #[test]
+    fn test_parse_cert_sha256() {
+        let hex = "00112233445566778899aabbccddeeff00112233445566778899AABBCCDDEEFF";
+        let pin = parse_cert_sha256(hex).unwrap();
+        assert_eq!(pin[..4], [0x00, 0x11, 0x22, 0x33]);
+        assert_eq!(pin[31], 0xFF);
+
+        let pairs: Vec<&str> = (0..64).step_by(2).map(|i| &hex[i..i + 2]).collect();
+        assert_eq!(parse_cert_sha256(&pairs.join(":")), Some(pin));
+
+        assert_eq!(parse_cert_sha256(&hex[2..]), None);
+        assert_eq!(parse_cert_sha256(&hex.replace('a', "g")), None);
+        assert_eq!(parse_cert_sha256(""), None);
+    }
+
+    #[test]
+    fn test_validate_https_proxy() {
+        let pin = "00112233445566778899aabbccddeeff00112233445566778899aabbccddeeff";
+        assert!(validate_proxy(&https_proxy(Some("proxy.example.com"), None)).is_ok());
+        assert!(validate_proxy(&https_proxy(None, Some(pin))).is_ok());
+        assert!(validate_proxy(&https_proxy(None, None)).is_err());
+        assert!(validate_proxy(&https_proxy(Some(" "), None)).is_err());
+        assert!(validate_proxy(&https_proxy(Some("proxy.example.com"), Some("abc"))).is_err());
+    }
+
+    #[test]
+    fn test_validate_proxy() {
+        let proxy = ProxyInfo {
+            address: "192.0.2.1".to_string(),
+            port: 1080,
+            proxy_type: ProxyType::Socks5,
+            username: None,
+            password: None,
+            tls_server_name: None,
+            tls_cert_sha256: None,
+        };
+        for address in ["192.0.2.1", "2001:db8::1", " 2001:db8::1 ", "[2001:db8::1]"] {
+            let valid = ProxyInfo {
+                address: address.to_string(),
+                ..proxy.clone()
+            };
+            assert!(validate_proxy(&valid).is_ok(), "{}", address);
+        }
+
+        for (address, port, expected) in [
+            ("", 1080, "the address is empty"),
+            ("  ", 1080, "the address is empty"),
+            ("proxy.example.com", 1080, "is not an IP address"),
+            ("192.0.2.1:1080", 1080, "is not an IP address"),
+            ("0.0.0.0", 1080, "unspecified"),
+            ("::", 1080, "unspecified"),
+            ("255.255.255.255", 1080, "broadcast"),
+            ("224.0.0.1", 1080, "multicast"),
+            ("ff02::1", 1080, "multicast"),
+            ("192.0.2.1", 0, "port 0"),
+        ] {
+            let invalid = ProxyInfo {
+                address: address.to_string(),
+                port,
+                ..proxy.clone()
+            };
+            match validate_proxy(&invalid) {
+                Err(TorError::InvalidProxy { reason }) => {
+                    assert!(reason.contains(expected), "{}", reason)
+                }
+                other => panic!("{}:{} gave {:?}", address, port, other),
+            }
+        }
+    }
+}